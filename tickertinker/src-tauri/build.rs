@@ -28,6 +28,7 @@ pub enum Protocol {
 pub struct ServiceQuery(pub u16, pub Protocol);
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
@@ -35,23 +36,53 @@ use std::path::Path;
 
 
 const SERVICES_LIST_PATH: &str = "./services.txt"; // Note: The path might need adjustment
+/// Minimum number of entries of the services list, to catch a truncated file
+const MIN_SERVICES_ENTRIES: usize = 10_000;
+/// MAC address prefixes assigned to each vendor, in the format published by the IEEE
+/// (a subset of <https://standards-oui.ieee.org/oui/oui.txt>, used if the full one isn't found)
+const OUI_LIST_PATH: &str = "./oui.txt";
+/// Environment variable with the path of the full IEEE registry, preferred over the others
+const OUI_LIST_ENV: &str = "TICKERTINKER_OUI_LIST";
+/// Path of the full IEEE registry installed by the `ieee-data` package (e.g., on Debian)
+const SYSTEM_OUI_LIST_PATH: &str = "/usr/share/ieee-data/oui.txt";
+/// Minimum number of entries of the full IEEE registry, to tell it from the bundled subset
+const MIN_FULL_OUI_ENTRIES: usize = 10_000;
 
 fn main() {
     println!("cargo:rerun-if-changed={SERVICES_LIST_PATH}");
+    println!("cargo:rerun-if-env-changed={OUI_LIST_ENV}");
 
     build_services_phf();
+    build_oui_phf();
 
     // Keep the original tauri_build::build() call
     tauri_build::build();
 }
 
 fn build_services_phf() {
-    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("services.rs");
-    let mut output = BufWriter::new(File::create(out_path).unwrap());
+    let out_dir = env::var("OUT_DIR").unwrap();
 
-    let mut services_map = phf_codegen::Map::new();
+    // without the list the crate still builds, just without recognizing services
+    let services_map = match File::open(SERVICES_LIST_PATH) {
+        Ok(file) => read_services_list(BufReader::new(file)),
+        Err(e) => {
+            println!(
+                "cargo:warning=Cannot open {SERVICES_LIST_PATH} ({e}): building an empty services map"
+            );
+            phf_codegen::Map::new()
+        }
+    };
+    write_services_map(&Path::new(&out_dir).join("services.rs"), &services_map);
 
-    let input = BufReader::new(File::open(SERVICES_LIST_PATH).unwrap());
+    // the map built when the list is missing, compiled by the tests to make sure it's valid
+    write_services_map(
+        &Path::new(&out_dir).join("services_empty.rs"),
+        &phf_codegen::Map::new(),
+    );
+}
+
+fn read_services_list(input: impl BufRead) -> phf_codegen::Map<ServiceQuery> {
+    let mut services_map = phf_codegen::Map::new();
     let mut num_entries = 0;
     for line_res in input.lines() {
         // we want to panic if one of the lines is err...
@@ -65,9 +96,15 @@ fn build_services_phf() {
         services_map.entry(key, val);
         num_entries += 1;
     }
-    // You might need to adjust this assertion based on your services.txt file
-    assert_eq!(num_entries, 12084);
+    assert!(
+        num_entries >= MIN_SERVICES_ENTRIES,
+        "{SERVICES_LIST_PATH} only has {num_entries} entries"
+    );
+    services_map
+}
 
+fn write_services_map(out_path: &Path, services_map: &phf_codegen::Map<ServiceQuery>) {
+    let mut output = BufWriter::new(File::create(out_path).unwrap());
     writeln!(
         &mut output,
         "#[allow(clippy::unreadable_literal)]
@@ -78,6 +115,75 @@ fn build_services_phf() {
     .unwrap();
 }
 
+fn build_oui_phf() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    let oui_list_path = oui_list_path();
+    println!("cargo:rerun-if-changed={oui_list_path}");
+    // without the list the crate still builds, just without resolving vendors
+    let (oui_map, num_entries) = match File::open(&oui_list_path) {
+        Ok(file) => read_oui_list(BufReader::new(file)),
+        Err(e) => {
+            println!("cargo:warning=Cannot open {oui_list_path} ({e}): building an empty OUI map");
+            (phf_codegen::Map::new(), 0)
+        }
+    };
+    let mut output = BufWriter::new(File::create(Path::new(&out_dir).join("oui.rs")).unwrap());
+    writeln!(
+        &mut output,
+        "#[allow(clippy::unreadable_literal)]
+\
+        static OUI_VENDORS: phf::Map<u32, &'static str> = {};
+\
+        #[cfg(test)]
+\
+        const OUI_REGISTRY_COMPLETE: bool = {};",
+        oui_map.build(),
+        num_entries >= MIN_FULL_OUI_ENTRIES
+    )
+    .unwrap();
+}
+
+/// Returns the path of the full IEEE registry if available, or the one of the bundled subset
+fn oui_list_path() -> String {
+    if let Ok(path) = env::var(OUI_LIST_ENV) {
+        return path;
+    }
+    if Path::new(SYSTEM_OUI_LIST_PATH).is_file() {
+        return SYSTEM_OUI_LIST_PATH.to_string();
+    }
+    println!(
+        "cargo:warning=IEEE registry not found (set {OUI_LIST_ENV} to its path): \
+        only the vendors of {OUI_LIST_PATH} are resolved"
+    );
+    OUI_LIST_PATH.to_string()
+}
+
+/// Reads the assignments from lines like `00-50-56   (hex)\t\tVMware, Inc.`, ignoring the others
+/// (headers, addresses, and the same assignments in base 16), returning them with their number
+fn read_oui_list(input: impl BufRead) -> (phf_codegen::Map<u32>, usize) {
+    let mut oui_map = phf_codegen::Map::new();
+    // a few prefixes were assigned more than once: the first assignment is kept
+    let mut seen = HashSet::new();
+    for line_res in input.lines() {
+        // we want to panic if one of the lines is err...
+        let line = line_res.unwrap();
+        let Some((prefix, vendor)) = line.split_once("(hex)") else {
+            continue;
+        };
+        // we want to panic if the prefix isn't made of three hexadecimal octets
+        let prefix = prefix.trim().replace('-', "");
+        assert_eq!(prefix.len(), 6, "Invalid OUI found: {prefix}");
+        let oui = u32::from_str_radix(&prefix, 16).unwrap();
+        let vendor = vendor.trim();
+        assert!(!vendor.is_empty(), "Missing vendor of OUI {prefix}");
+        if seen.insert(oui) {
+            oui_map.entry(oui, Cow::Owned(format!("{vendor:?}")));
+        }
+    }
+    (oui_map, seen.len())
+}
+
 fn get_valid_service_fmt_const(s: &str) -> String {
     match s.trim() {
         invalid
@@ -1,4 +1,5 @@
 pub mod networking;
+pub mod network_monitor;
 pub mod mmdb;
 pub mod countries;
 pub mod report;
@@ -9,23 +10,38 @@ use tauri::State;
 use crate::network_monitor::NetworkMonitorState;
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
-fn greet(name: &str) -> String { 
+fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
 #[tauri::command]
-
-#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn start_capture(
     app_handle: tauri::AppHandle,
     interface_name: String,
+    options: Option<crate::networking::types::capture_options::CaptureOptions>,
+    capture_filter: Option<crate::networking::types::capture_filter::CaptureFilter>,
+    protocol_filter: Option<Vec<crate::networking::types::protocol::Protocol>>,
+    binary_updates: Option<tauri::ipc::Channel>,
+    pcap_out_path: Option<String>,
+    bpf_filter: Option<String>,
+    promiscuous: Option<bool>,
     state: State<'_, NetworkMonitorState>,
-) -> Result<(), String> {
-    crate::network_monitor::start_capture(interface_name, state, app_handle)
+) -> Result<usize, String> {
+    crate::network_monitor::start_capture(
+        interface_name,
+        options,
+        capture_filter,
+        protocol_filter,
+        binary_updates,
+        pcap_out_path,
+        bpf_filter,
+        promiscuous,
+        state,
+        app_handle,
+    )
 }
 
-#[tauri::command]
-
 #[tauri::command]
 fn stop_capture(
     state: State<NetworkMonitorState>,
@@ -33,20 +49,18 @@ fn stop_capture(
     crate::network_monitor::stop_capture(state)
 }
 
-#[tauri::command]
-
 #[tauri::command]
 fn get_traffic_data(
     state: State<NetworkMonitorState>,
-) -> Result<crate::network_monitor::traffic_data::TrafficData, String> {
+) -> Result<crate::network_monitor::traffic_data::InfoTrafficData, String> {
     crate::network_monitor::get_traffic_data(state)
 }
 
 #[tauri::command]
 fn list_interfaces(
     state: State<NetworkMonitorState>,
-) -> Result<Vec<String>, String> {
-    crate::network_monitor::list_interfaces(state).map(|devices| devices.into_iter().map(|d| d.name).collect())
+) -> Result<Vec<crate::networking::types::interface_info::InterfaceInfo>, String> {
+    crate::network_monitor::list_interfaces(state)
 }
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 
@@ -54,7 +68,78 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
     .manage(crate::network_monitor::NetworkMonitorState::default())
-        .invoke_handler(tauri::generate_handler![greet, start_capture, stop_capture, get_traffic_data])
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            start_capture,
+            crate::network_monitor::start_capture_from_file,
+            crate::network_monitor::start_capture_from_files,
+            crate::network_monitor::capture_n_packets,
+            stop_capture,
+            crate::network_monitor::load_mmdb,
+            crate::network_monitor::asn_lookup,
+            crate::network_monitor::country_lookup,
+            crate::network_monitor::classify_bogon,
+            crate::network_monitor::start_csv_logging,
+            crate::network_monitor::stop_csv_logging,
+            crate::network_monitor::export_report_csv,
+            crate::network_monitor::export_report_json,
+            crate::network_monitor::pause_capture,
+            crate::network_monitor::resume_capture,
+            crate::network_monitor::subscribe_events,
+            crate::network_monitor::unsubscribe_events,
+            get_traffic_data,
+            list_interfaces,
+            crate::network_monitor::list_interfaces_with_activity,
+            crate::network_monitor::get_capture_status,
+            crate::network_monitor::get_session_info,
+            crate::network_monitor::get_capture_stats,
+            crate::network_monitor::take_traffic_snapshot,
+            crate::network_monitor::take_drop_delta,
+            crate::network_monitor::update_filter,
+            crate::network_monitor::set_rdns_enabled,
+            crate::network_monitor::ack_traffic_update,
+            crate::network_monitor::get_traffic_history,
+            crate::network_monitor::focus_host,
+            crate::network_monitor::clear_focus,
+            crate::network_monitor::add_favorite_host,
+            crate::network_monitor::remove_favorite_host,
+            crate::network_monitor::get_favorite_hosts,
+            crate::network_monitor::set_retention,
+            crate::network_monitor::get_retention,
+            crate::network_monitor::set_baseline,
+            crate::network_monitor::get_new_flows_since_baseline,
+            crate::network_monitor::get_idle_connections,
+            crate::network_monitor::get_connection_details,
+            crate::network_monitor::get_packet_sizes,
+            crate::network_monitor::get_connection_rtts,
+            crate::network_monitor::get_connection_profiles,
+            crate::network_monitor::get_asymmetric_flows,
+            crate::network_monitor::get_recent_hosts,
+            crate::network_monitor::get_top_hosts,
+            crate::network_monitor::get_top_services,
+            crate::network_monitor::get_top_ports,
+            crate::network_monitor::get_observed_local_addresses,
+            crate::network_monitor::get_external_connections,
+            crate::network_monitor::get_gateway_traffic,
+            crate::network_monitor::get_local_listening_ports,
+            crate::network_monitor::get_stats_for_cidr,
+            crate::network_monitor::get_parse_failure_samples,
+            crate::network_monitor::get_directionality,
+            crate::network_monitor::get_host_classifications,
+            crate::network_monitor::get_app_protocol_breakdown,
+            crate::network_monitor::get_traffic_by_process,
+            crate::network_monitor::get_qos_breakdown,
+            crate::network_monitor::get_bandwidth_percentiles,
+            crate::network_monitor::get_hourly_profile,
+            crate::network_monitor::estimate_data_cost,
+            crate::network_monitor::estimate_data_footprint,
+            crate::network_monitor::get_services_map_info,
+            crate::network_monitor::service_lookup,
+            crate::network_monitor::get_resolution_stats,
+            crate::network_monitor::get_notification_summary,
+            crate::network_monitor::debug_dump_state,
+            crate::network_monitor::get_platform_capabilities,
+        ])
         .run(tauri::generate_context!())
  .expect("error while running tauri application");
 }
@@ -2,6 +2,8 @@ use std::net::IpAddr;
 use std::sync::Arc;
 
 use crate::location;
+use crate::mmdb::asn::ASN_MMDB;
+use crate::mmdb::country::COUNTRY_MMDB;
 use crate::utils::error_logger::{ErrorLogger, Location};
 use maxminddb::{MaxMindDbError, Reader};
 use serde::Deserialize;
@@ -12,6 +14,33 @@ pub struct MmdbReaders {
     pub asn: Arc<MmdbReader>,
 }
 
+impl Default for MmdbReaders {
+    fn default() -> Self {
+        Self {
+            country: Arc::new(MmdbReader::Empty),
+            asn: Arc::new(MmdbReader::Empty),
+        }
+    }
+}
+
+impl MmdbReaders {
+    /// Opens the country and ASN databases at the given paths,
+    /// falling back to the bundled ones for the paths that are `None`
+    pub fn load(country_path: Option<&str>, asn_path: Option<&str>) -> Result<Self, String> {
+        Ok(Self {
+            country: Arc::new(MmdbReader::open(country_path, COUNTRY_MMDB)?),
+            asn: Arc::new(MmdbReader::open(asn_path, ASN_MMDB)?),
+        })
+    }
+
+    /// Opens the bundled country and ASN databases (empty if they can't be read)
+    pub fn bundled() -> Self {
+        Self::load(None, None)
+            .log_err(location!())
+            .unwrap_or_default()
+    }
+}
+
 pub enum MmdbReader {
     Default(Reader<&'static [u8]>),
     Custom(Reader<Vec<u8>>),
@@ -32,6 +61,22 @@ impl MmdbReader {
         }
     }
 
+    /// Like `from`, but fails if the file at `mmdb_path` isn't a valid database
+    /// instead of silently falling back to the bundled one
+    pub fn open(
+        mmdb_path: Option<&str>,
+        default_mmdb: &'static [u8],
+    ) -> Result<MmdbReader, String> {
+        match mmdb_path {
+            Some(path) => maxminddb::Reader::open_readfile(path)
+                .map(MmdbReader::Custom)
+                .map_err(|e| format!("Invalid MMDB file \"{path}\": {e}")),
+            None => maxminddb::Reader::from_source(default_mmdb)
+                .map(MmdbReader::Default)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
     pub fn lookup<'a, T: Deserialize<'a>>(
         &'a self,
         ip: IpAddr,
@@ -43,3 +88,37 @@ impl MmdbReader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mmdb::types::mmdb_reader::{MmdbReader, MmdbReaders};
+
+    #[test]
+    fn test_load_mmdb_readers() {
+        // bundled databases
+        let readers = MmdbReaders::load(None, None).unwrap();
+        assert!(matches!(*readers.country, MmdbReader::Default(_)));
+        assert!(matches!(*readers.asn, MmdbReader::Default(_)));
+        let readers = MmdbReaders::bundled();
+        assert!(matches!(*readers.country, MmdbReader::Default(_)));
+        assert!(matches!(*readers.asn, MmdbReader::Default(_)));
+
+        // custom databases
+        let readers = MmdbReaders::load(
+            Some("resources/DB/GeoLite2-Country.mmdb"),
+            Some("resources/DB/GeoLite2-ASN.mmdb"),
+        )
+        .unwrap();
+        assert!(matches!(*readers.country, MmdbReader::Custom(_)));
+        assert!(matches!(*readers.asn, MmdbReader::Custom(_)));
+
+        // invalid paths aren't replaced by the bundled databases
+        for (country_path, asn_path) in [
+            (Some("unknown path"), None),
+            (None, Some("resources/repository/hr.png")),
+        ] {
+            let err = MmdbReaders::load(country_path, asn_path).err().unwrap();
+            assert!(err.starts_with("Invalid MMDB file"));
+        }
+    }
+}
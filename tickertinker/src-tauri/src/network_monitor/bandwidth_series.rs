@@ -0,0 +1,80 @@
+//! Module defining the `BandwidthSeries` struct, which keeps the bytes exchanged in each second
+//! of a capture with a bounded amount of memory.
+
+use std::collections::VecDeque;
+
+use super::traffic_data::BandwidthPercentiles;
+
+/// Maximum number of runs kept in a `BandwidthSeries` (a day of live capture, at worst)
+pub const MAX_BANDWIDTH_RUNS: usize = 86_400;
+
+/// Bytes exchanged in each second, stored as runs of consecutive seconds with the same value:
+/// long periods without traffic take a single run, and the oldest runs are evicted past
+/// `MAX_BANDWIDTH_RUNS`
+#[derive(Clone, Debug, Default)]
+pub struct BandwidthSeries {
+    /// Bytes per second, with the number of consecutive seconds they were observed in
+    runs: VecDeque<(u128, u64)>,
+}
+
+impl BandwidthSeries {
+    /// Adds a second in which the given bytes were exchanged
+    pub fn push(&mut self, bytes: u128) {
+        self.push_run(bytes, 1);
+    }
+
+    /// Adds the given number of seconds without traffic
+    pub fn push_idle(&mut self, secs: u64) {
+        self.push_run(0, secs);
+    }
+
+    fn push_run(&mut self, bytes: u128, secs: u64) {
+        if secs == 0 {
+            return;
+        }
+        if let Some((last_bytes, last_secs)) = self.runs.back_mut()
+            && *last_bytes == bytes
+        {
+            *last_secs = last_secs.saturating_add(secs);
+            return;
+        }
+        if self.runs.len() >= MAX_BANDWIDTH_RUNS {
+            self.runs.pop_front();
+        }
+        self.runs.push_back((bytes, secs));
+    }
+
+    /// Returns the percentiles of the bytes exchanged per second
+    pub fn percentiles(&self) -> BandwidthPercentiles {
+        BandwidthPercentiles::from_runs(self.runs.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_seconds_take_a_single_run() {
+        let mut series = BandwidthSeries::default();
+        series.push(0);
+        // a gap of years doesn't allocate a value per second
+        series.push_idle(u64::from(u32::MAX) * 10);
+        series.push_idle(0);
+        series.push(100);
+        assert_eq!(series.runs, [(0, u64::from(u32::MAX) * 10 + 1), (100, 1)]);
+        let percentiles = series.percentiles();
+        assert_eq!(percentiles.p99, 0);
+    }
+
+    #[test]
+    fn test_oldest_runs_are_evicted() {
+        let mut series = BandwidthSeries::default();
+        for bytes in 1..=u128::try_from(MAX_BANDWIDTH_RUNS).unwrap() + 2 {
+            series.push(bytes);
+        }
+        assert_eq!(series.runs.len(), MAX_BANDWIDTH_RUNS);
+        assert_eq!(series.runs.front(), Some(&(3, 1)));
+        assert_eq!(series.percentiles().seconds, MAX_BANDWIDTH_RUNS);
+    }
+}
@@ -0,0 +1,421 @@
+//! Module defining the `CaptureThread` struct, which drives a capture running in background.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use async_channel::{Receiver, Sender};
+use pcap::Address;
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+use crate::location;
+use crate::mmdb::types::mmdb_reader::MmdbReaders;
+use crate::networking::parse_packets::{
+    AddressesResolutionState, BackendTrafficMessage, CaptureControlMessage, ResolutionStateDump,
+    ResolutionStats, parse_packets,
+};
+use crate::networking::types::capture_context::{CaptureContext, CaptureSource};
+use crate::networking::types::capture_filter::CaptureFilter;
+use crate::networking::types::capture_options::CaptureOptions;
+use crate::networking::types::host::{Host, HostMessage};
+use crate::utils::error_logger::{ErrorLogger, Location};
+
+use super::event_subscriptions::{EventEmitter, EventKind};
+use super::traffic_analyzer::TrafficAnalyzer;
+use super::traffic_data::{
+    CaptureErrorMessage, CaptureStatus, HostResolvedMessage, HostsEvictedMessage,
+    HostsResolvedBatchMessage, LocalAddressChangedMessage, OfflineGapMessage, TrafficUpdateMessage,
+    UserspaceFilteringMessage,
+};
+use super::update_coalescer::UpdateCoalescer;
+
+/// Maximum time waited for the capture thread to act on a request
+const CONTROL_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A running capture.
+///
+/// One thread parses the packets, while another one collects the resulting messages
+/// into the shared `TrafficAnalyzer` and notifies the frontend.
+pub struct CaptureThread {
+    cap_id: usize,
+    source: CaptureSource,
+    /// Maximum number of bytes stored per packet (`None` for files)
+    snaplen: Option<i32>,
+    /// Whether the device was opened in promiscuous mode (`false` for files)
+    promiscuous: bool,
+    /// Closed to make the parsing thread return
+    traffic_rx: Receiver<BackendTrafficMessage>,
+    /// Set to make the parsing thread return, even if it's waiting for pending resolutions
+    stop_signal: Arc<AtomicBool>,
+    /// Set while the capture is paused: packets are discarded, and no traffic update is sent
+    pause_signal: Arc<AtomicBool>,
+    control_tx: Sender<CaptureControlMessage>,
+    /// Addresses resolved or waiting for a resolution, shared with the parsing thread
+    resolutions_state: Arc<Mutex<AddressesResolutionState>>,
+    /// Traffic updates waiting for the frontend to acknowledge the previous one
+    /// (only if updates are coalesced)
+    update_coalescer: Option<Arc<Mutex<UpdateCoalescer>>>,
+    emitter: EventEmitter,
+    binary_updates: Option<Channel>,
+    handles: Vec<JoinHandle<()>>,
+    /// When the capture started, to measure its duration on the wall clock
+    started_at: Instant,
+}
+
+impl CaptureThread {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        cap_id: usize,
+        cs: CaptureSource,
+        capture_context: CaptureContext,
+        queued_files: VecDeque<(CaptureSource, CaptureContext)>,
+        options: CaptureOptions,
+        capture_filter: CaptureFilter,
+        mmdb_readers: MmdbReaders,
+        favorite_hosts: HashSet<Host>,
+        analyzer: Arc<Mutex<TrafficAnalyzer>>,
+        emitter: EventEmitter,
+        binary_updates: Option<Channel>,
+    ) -> Self {
+        let (traffic_tx, traffic_rx) = async_channel::unbounded();
+        let (control_tx, control_rx) = async_channel::unbounded();
+        let mut handles = Vec::new();
+        let source = cs.clone();
+        let snaplen = capture_context.snaplen();
+        let promiscuous = capture_context.promiscuous();
+        let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::new(favorite_hosts)));
+        let resolutions_state_2 = resolutions_state.clone();
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_2 = stop_signal.clone();
+        let pause_signal = Arc::new(AtomicBool::new(false));
+        let pause_signal_2 = pause_signal.clone();
+        let pause_signal_3 = pause_signal.clone();
+        let update_coalescer = options
+            .coalesce_updates
+            .then(|| Arc::new(Mutex::new(UpdateCoalescer::default())));
+        let update_coalescer_2 = update_coalescer.clone();
+        let single_host_events = options.single_host_events;
+        let emitter_2 = emitter.clone();
+        let binary_updates_2 = binary_updates.clone();
+
+        if let Ok(handle) = thread::Builder::new()
+            .name("thread_parse_packets".to_string())
+            .spawn(move || {
+                parse_packets(
+                    cap_id,
+                    cs,
+                    &mmdb_readers,
+                    capture_context,
+                    queued_files,
+                    options,
+                    capture_filter,
+                    &resolutions_state_2,
+                    &traffic_tx,
+                    &control_rx,
+                    &stop_signal_2,
+                    &pause_signal_2,
+                );
+            })
+            .log_err(location!())
+        {
+            handles.push(handle);
+        }
+
+        let traffic_rx_2 = traffic_rx.clone();
+        if let Ok(handle) = thread::Builder::new()
+            .name("thread_collect_traffic".to_string())
+            .spawn(move || {
+                collect_traffic(
+                    cap_id,
+                    &traffic_rx_2,
+                    &analyzer,
+                    &emitter_2,
+                    binary_updates_2.as_ref(),
+                    update_coalescer_2.as_deref(),
+                    &pause_signal_3,
+                    single_host_events,
+                );
+            })
+            .log_err(location!())
+        {
+            handles.push(handle);
+        }
+
+        Self {
+            cap_id,
+            source,
+            snaplen,
+            promiscuous,
+            traffic_rx,
+            stop_signal,
+            pause_signal,
+            control_tx,
+            resolutions_state,
+            update_coalescer,
+            emitter,
+            binary_updates,
+            handles,
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn cap_id(&self) -> usize {
+        self.cap_id
+    }
+
+    /// Returns the state of the capture, querying whether the device is really in promiscuous mode
+    pub fn status(&self) -> CaptureStatus {
+        let (promiscuous_requested, promiscuous_effective) = match &self.source {
+            CaptureSource::Device(device) => (self.promiscuous, device.is_promiscuous()),
+            CaptureSource::File(_) => (false, None),
+        };
+        CaptureStatus {
+            cap_id: self.cap_id,
+            source: self.source.get_name(),
+            promiscuous_requested,
+            promiscuous_effective,
+        }
+    }
+
+    /// Whether the capture stopped on its own, because of a capture error
+    pub fn is_stopped(&self) -> bool {
+        self.stop_signal.load(Ordering::Relaxed)
+    }
+
+    /// Returns the name of the captured device (or file)
+    pub fn source_name(&self) -> String {
+        self.source.get_name()
+    }
+
+    /// Returns the time elapsed since the capture started
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Returns the maximum number of bytes stored per packet (`None` for files)
+    pub fn snaplen(&self) -> Option<i32> {
+        self.snaplen
+    }
+
+    /// Returns the addresses of the captured device (empty for files)
+    pub fn interface_addresses(&self) -> Vec<Address> {
+        self.source.get_addresses().clone()
+    }
+
+    /// Returns the number of addresses resolved or waiting for a resolution, with a sample of each
+    pub fn dump_resolutions(&self, sample_size: usize) -> ResolutionStateDump {
+        self.resolutions_state.lock().unwrap().dump(sample_size)
+    }
+
+    /// Acknowledges the latest traffic update, sending the one produced in the meantime if any
+    /// (only meaningful if updates are coalesced)
+    pub fn ack_traffic_update(&self) {
+        let Some(update_coalescer) = &self.update_coalescer else {
+            return;
+        };
+        let pending = update_coalescer.lock().unwrap().ack();
+        if let Some(update) = pending {
+            send_traffic_update(&self.emitter, self.binary_updates.as_ref(), update);
+        }
+    }
+
+    /// Returns how many hosts were resolved to a domain, and how long it took on average
+    pub fn resolution_stats(&self) -> ResolutionStats {
+        self.resolutions_state.lock().unwrap().stats()
+    }
+
+    /// Marks or unmarks a host as favorite in the data collected from now on
+    pub fn set_favorite_host(&self, host: &Host, is_favorite: bool) {
+        let favorite_hosts = &mut self.resolutions_state.lock().unwrap().favorite_hosts;
+        if is_favorite {
+            favorite_hosts.insert(host.clone());
+        } else {
+            favorite_hosts.remove(host);
+        }
+    }
+
+    /// Replaces the BPF filter of the running capture, without losing the collected traffic,
+    /// warning the frontend if the new filter is applied in userspace
+    pub fn update_filter(&self, bpf: &str) -> Result<(), String> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.control_tx
+            .send_blocking(CaptureControlMessage::UpdateFilter(
+                bpf.to_string(),
+                reply_tx,
+            ))
+            .map_err(|_| "Capture is not running anymore".to_string())?;
+        let userspace_reason = reply_rx
+            .recv_timeout(CONTROL_REPLY_TIMEOUT)
+            .map_err(|_| "Capture is not running anymore".to_string())??;
+        if let Some(reason) = userspace_reason {
+            self.emitter.emit(
+                EventKind::UserspaceFiltering,
+                UserspaceFilteringMessage {
+                    cap_id: self.cap_id,
+                    bpf_filter: bpf.to_string(),
+                    reason,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Enables or disables the rDNS resolution of the addresses seen from now on
+    pub fn set_rdns_enabled(&self, enabled: bool) -> Result<(), String> {
+        self.control_tx
+            .send_blocking(CaptureControlMessage::SetRdnsEnabled(enabled))
+            .map_err(|_| "Capture is not running anymore".to_string())
+    }
+
+    /// Pauses or resumes the capture; the traffic collected so far is kept either way
+    pub fn set_paused(&self, paused: bool) {
+        self.pause_signal.store(paused, Ordering::Relaxed);
+    }
+
+    /// Stops the capture, waiting for its threads to return,
+    /// and sends the latest traffic update if it was held back waiting for an acknowledgement
+    pub fn stop(self) {
+        // the parsing thread returns as soon as it notices the signal or the closed channel
+        self.stop_signal.store(true, Ordering::Relaxed);
+        self.traffic_rx.close();
+        for handle in self.handles {
+            let _ = handle.join();
+        }
+        if let Some(update_coalescer) = &self.update_coalescer
+            && let Some(update) = update_coalescer.lock().unwrap().flush()
+        {
+            send_traffic_update(&self.emitter, self.binary_updates.as_ref(), update);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_traffic(
+    cap_id: usize,
+    traffic_rx: &Receiver<BackendTrafficMessage>,
+    analyzer: &Mutex<TrafficAnalyzer>,
+    emitter: &EventEmitter,
+    binary_updates: Option<&Channel>,
+    update_coalescer: Option<&Mutex<UpdateCoalescer>>,
+    pause_signal: &AtomicBool,
+    single_host_events: bool,
+) {
+    while let Ok(msg) = traffic_rx.recv_blocking() {
+        match msg {
+            BackendTrafficMessage::TickRun(_, info_traffic, new_hosts, is_final) => {
+                emit_hosts_resolved(emitter, cap_id, &new_hosts, single_host_events);
+                let (update, hosts_evicted) = {
+                    let mut analyzer = analyzer.lock().unwrap();
+                    analyzer.refresh(info_traffic, new_hosts);
+                    let hosts_evicted = apply_retention(cap_id, &mut analyzer);
+                    (analyzer.traffic_update(cap_id, is_final), hosts_evicted)
+                };
+                emit_hosts_evicted(emitter, hosts_evicted);
+                // the frontend keeps showing the traffic as it was when paused
+                if pause_signal.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let update = match update_coalescer {
+                    Some(update_coalescer) => update_coalescer.lock().unwrap().submit(update),
+                    None => Some(update),
+                };
+                if let Some(update) = update {
+                    send_traffic_update(emitter, binary_updates, update);
+                }
+            }
+            BackendTrafficMessage::PendingHosts(_, new_hosts) => {
+                emit_hosts_resolved(emitter, cap_id, &new_hosts, single_host_events);
+                let hosts_evicted = {
+                    let mut analyzer = analyzer.lock().unwrap();
+                    analyzer.add_hosts(new_hosts);
+                    apply_retention(cap_id, &mut analyzer)
+                };
+                emit_hosts_evicted(emitter, hosts_evicted);
+            }
+            BackendTrafficMessage::OfflineGap(_, gap_secs) => {
+                analyzer.lock().unwrap().add_idle_seconds(gap_secs);
+                emitter.emit(
+                    EventKind::OfflineGap,
+                    OfflineGapMessage { cap_id, gap_secs },
+                );
+            }
+            BackendTrafficMessage::ParseFailure(_, sample) => {
+                analyzer.lock().unwrap().add_parse_failure_sample(sample);
+            }
+            BackendTrafficMessage::CaptureError(_, error) => {
+                emitter.emit(
+                    EventKind::CaptureError,
+                    CaptureErrorMessage { cap_id, error },
+                );
+            }
+            BackendTrafficMessage::LocalAddressChanged(_, old_addresses, new_addresses) => {
+                emitter.emit(
+                    EventKind::LocalAddressChanged,
+                    LocalAddressChangedMessage {
+                        cap_id,
+                        old_addresses,
+                        new_addresses,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Sends a traffic update through the binary channel if provided, otherwise as an event
+fn send_traffic_update(
+    emitter: &EventEmitter,
+    binary_updates: Option<&Channel>,
+    update: TrafficUpdateMessage,
+) {
+    if let Some(channel) = binary_updates {
+        if let Ok(bytes) = update.to_msgpack().log_err(location!()) {
+            let _ = channel
+                .send(InvokeResponseBody::Raw(bytes))
+                .log_err(location!());
+        }
+    } else {
+        emitter.emit(EventKind::TrafficUpdate, update);
+    }
+}
+
+/// Applies the retention policy, returning the event to emit if any host was evicted
+fn apply_retention(cap_id: usize, analyzer: &mut TrafficAnalyzer) -> Option<HostsEvictedMessage> {
+    let evicted = analyzer.apply_retention();
+    (!evicted.is_empty()).then(|| HostsEvictedMessage {
+        cap_id,
+        evicted,
+        other_hosts: analyzer.get_other_hosts(),
+    })
+}
+
+/// Notifies the frontend of the resolved hosts, either one by one or as a single batch
+fn emit_hosts_resolved(
+    emitter: &EventEmitter,
+    cap_id: usize,
+    new_hosts: &[HostMessage],
+    single_host_events: bool,
+) {
+    let Some(batch) = HostsResolvedBatchMessage::new(cap_id, new_hosts) else {
+        return;
+    };
+    if single_host_events {
+        for host in batch.hosts {
+            emitter.emit(
+                EventKind::NewHostResolved,
+                HostResolvedMessage { cap_id, host },
+            );
+        }
+    } else {
+        emitter.emit(EventKind::HostsResolvedBatch, batch);
+    }
+}
+
+fn emit_hosts_evicted(emitter: &EventEmitter, hosts_evicted: Option<HostsEvictedMessage>) {
+    if let Some(msg) = hosts_evicted {
+        emitter.emit(EventKind::HostsEvicted, msg);
+    }
+}
@@ -0,0 +1,123 @@
+//! Module defining the one-shot export of the connections of the current capture to a CSV file,
+//! to be opened in a spreadsheet.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::networking::types::traffic_direction::TrafficDirection;
+use crate::utils::formatted_strings::get_formatted_timestamp;
+
+use super::csv_logger::csv_field;
+use super::traffic_data::ConnectionData;
+
+const CSV_HEADER: &str = "source_ip,source_port,destination_ip,destination_port,protocol,\
+service,bytes,packets,direction,first_seen,last_seen";
+
+/// Writes the connections to the CSV file at `path`, one per row, replacing the file if it exists
+pub fn export_connections(path: &Path, connections: &[ConnectionData]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{CSV_HEADER}")?;
+    for connection in connections {
+        writer.write_all(csv_record(connection).as_bytes())?;
+    }
+    writer.flush()
+}
+
+fn csv_record(connection: &ConnectionData) -> String {
+    let key = &connection.key;
+    let info = &connection.info;
+    let port = |port: Option<u16>| port.map(|p| p.to_string()).unwrap_or_default();
+    let direction = match info.traffic_direction {
+        TrafficDirection::Incoming => "incoming",
+        TrafficDirection::Outgoing => "outgoing",
+    };
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{}\n",
+        key.address1,
+        port(key.port1),
+        key.address2,
+        port(key.port2),
+        key.protocol,
+        csv_field(&info.service.to_string()),
+        info.transmitted_bytes,
+        info.transmitted_packets,
+        direction,
+        get_formatted_timestamp(info.initial_timestamp),
+        get_formatted_timestamp(info.final_timestamp),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::networking::types::address_port_pair::AddressPortPair;
+    use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+    use crate::networking::types::protocol::Protocol;
+    use crate::networking::types::service::Service;
+    use crate::utils::temp_files::temp_path;
+    use crate::utils::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_export_connections() {
+        let path = temp_path("tickertinker_csv_export.csv");
+        let connection = |key, service, direction| {
+            let info = InfoAddressPortPair {
+                transmitted_bytes: 1_500,
+                transmitted_packets: 3,
+                initial_timestamp: Timestamp::new(1_700_000_000, 0),
+                final_timestamp: Timestamp::new(1_700_000_010, 0),
+                service,
+                traffic_direction: direction,
+                ..Default::default()
+            };
+            ConnectionData::new(&key, &info)
+        };
+        let connections = [
+            connection(
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(50000),
+                    IpAddr::from([8, 8, 8, 8]),
+                    Some(53),
+                    Protocol::UDP,
+                ),
+                Service::Name("domain"),
+                TrafficDirection::Outgoing,
+            ),
+            connection(
+                AddressPortPair::new(
+                    IpAddr::from([8, 8, 4, 4]),
+                    None,
+                    IpAddr::from([192, 168, 1, 2]),
+                    None,
+                    Protocol::ICMP,
+                ),
+                Service::NotApplicable,
+                TrafficDirection::Incoming,
+            ),
+        ];
+
+        export_connections(&path, &connections).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("192.168.1.2,50000,8.8.8.8,53,UDP,domain,1500,3,outgoing,"));
+        assert!(lines[2].starts_with("8.8.4.4,,192.168.1.2,,ICMP,-,1500,3,incoming,"));
+        assert_eq!(lines[1].split(',').count(), 11);
+
+        // the file is replaced by the next export
+        export_connections(&path, &connections[..1]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_export_connections_write_error() {
+        let path = temp_path("tickertinker_missing_dir").join("export.csv");
+        assert!(export_connections(&path, &[]).is_err());
+    }
+}
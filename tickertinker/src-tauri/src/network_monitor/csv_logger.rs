@@ -0,0 +1,332 @@
+//! Module defining the `CsvLogger` struct, which periodically appends the connections
+//! updated by the current capture to a CSV file, as a continuous audit log.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::location;
+use crate::utils::error_logger::{ErrorLogger, Location};
+use crate::utils::formatted_strings::get_formatted_timestamp;
+use crate::utils::types::timestamp::Timestamp;
+
+use super::traffic_analyzer::TrafficAnalyzer;
+use super::traffic_data::ConnectionData;
+
+/// Size in bytes a CSV log can reach before being rotated, if not specified
+pub const DEFAULT_CSV_LOG_MAX_BYTES: u64 = 50_000_000;
+
+const CSV_HEADER: &str = "first_seen,last_seen,protocol,address1,port1,address2,port2,service,\
+packets_sent,packets_received,bytes_sent,bytes_received,process";
+const HEADER_BYTES: u64 = CSV_HEADER.len() as u64 + 1;
+
+/// Appends the connections updated since the previous interval to a CSV file.
+///
+/// Records are written as soon as they're collected, so nothing is kept in memory but the time
+/// of the latest logged packet. When a record would make the file exceed `max_bytes`,
+/// the file is renamed with a `.1` suffix (replacing the previous one) and a new one is started.
+pub struct CsvLog {
+    path: PathBuf,
+    max_bytes: u64,
+    writer: BufWriter<File>,
+    /// Size in bytes of the current file
+    written_bytes: u64,
+    /// Timestamp of the latest packet accounted in the log
+    logged_until: Timestamp,
+}
+
+impl CsvLog {
+    pub fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let (writer, written_bytes) = open_log_file(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            writer,
+            written_bytes,
+            logged_until: Timestamp::default(),
+        })
+    }
+
+    /// Returns the connections that exchanged packets since the previous call,
+    /// to be written with `write_records` (without holding the analyzer)
+    pub fn take_updates(&mut self, analyzer: &TrafficAnalyzer) -> Vec<ConnectionData> {
+        let connections = analyzer.get_updated_connections(self.logged_until);
+        self.logged_until = analyzer.last_packet_timestamp();
+        connections
+    }
+
+    /// Logs the connections of a new capture from its start
+    pub fn restart(&mut self) {
+        self.logged_until = Timestamp::default();
+    }
+
+    /// Appends the connections to the log, returning the number of records written
+    pub fn write_records(&mut self, connections: &[ConnectionData]) -> io::Result<usize> {
+        for connection in connections {
+            let record = csv_record(connection);
+            // a file is never left with the header alone
+            if self.written_bytes + record.len() as u64 > self.max_bytes
+                && self.written_bytes > HEADER_BYTES
+            {
+                self.rotate()?;
+            }
+            self.writer.write_all(record.as_bytes())?;
+            self.written_bytes += record.len() as u64;
+        }
+        self.writer.flush()?;
+        Ok(connections.len())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        (self.writer, self.written_bytes) = open_log_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Opens the log file for appending, writing the header if it's new or empty
+fn open_log_file(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut written_bytes = file.metadata()?.len();
+    let mut writer = BufWriter::new(file);
+    if written_bytes == 0 {
+        writeln!(writer, "{CSV_HEADER}")?;
+        writer.flush()?;
+        written_bytes = HEADER_BYTES;
+    }
+    Ok((writer, written_bytes))
+}
+
+fn csv_record(connection: &ConnectionData) -> String {
+    let key = &connection.key;
+    let info = &connection.info;
+    let port = |port: Option<u16>| port.map(|p| p.to_string()).unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+        get_formatted_timestamp(info.initial_timestamp),
+        get_formatted_timestamp(info.final_timestamp),
+        key.protocol,
+        key.address1,
+        port(key.port1),
+        key.address2,
+        port(key.port2),
+        csv_field(&info.service.to_string()),
+        info.packets_sent,
+        info.packets_received,
+        info.bytes_sent,
+        info.bytes_received,
+        csv_field(info.process.as_deref().unwrap_or_default()),
+    )
+}
+
+/// Quotes the field if it contains characters with a special meaning in CSV
+pub(super) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Thread appending the updated connections to a CSV log at each interval
+pub struct CsvLogger {
+    /// Dropped to make the thread log a last time and return
+    stop_tx: Sender<()>,
+    /// Set when a new capture starts, whose connections are logged from its start
+    restarted: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl CsvLogger {
+    pub fn start(
+        path: &Path,
+        interval: Duration,
+        max_bytes: u64,
+        analyzer: Arc<Mutex<TrafficAnalyzer>>,
+    ) -> Result<Self, String> {
+        let mut log = CsvLog::open(path, max_bytes)
+            .map_err(|e| format!("Cannot open CSV log {}: {e}", path.display()))?;
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let restarted = Arc::new(AtomicBool::new(false));
+        let restarted_2 = restarted.clone();
+        let handle = thread::Builder::new()
+            .name("thread_csv_logging".to_string())
+            .spawn(move || {
+                loop {
+                    let stopped = !matches!(
+                        stop_rx.recv_timeout(interval),
+                        Err(RecvTimeoutError::Timeout)
+                    );
+                    // the file is written after releasing the analyzer, not to hold the capture
+                    let analyzer = analyzer.lock().unwrap();
+                    if restarted_2.swap(false, Ordering::Relaxed) {
+                        log.restart();
+                    }
+                    let connections = log.take_updates(&analyzer);
+                    drop(analyzer);
+                    let _ = log.write_records(&connections).log_err(location!());
+                    if stopped {
+                        return;
+                    }
+                }
+            })
+            .map_err(|e| format!("Cannot start CSV logging: {e}"))?;
+        Ok(Self {
+            stop_tx,
+            restarted,
+            handle,
+        })
+    }
+
+    /// Logs the connections of the new capture from its start
+    /// (to be called while the analyzer of the new capture is being installed)
+    pub fn restart(&self) {
+        self.restarted.store(true, Ordering::Relaxed);
+    }
+
+    /// Logs the connections updated since the last interval, and stops the logging thread
+    pub fn stop(self) {
+        drop(self.stop_tx);
+        let _ = self.handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::networking::types::address_port_pair::AddressPortPair;
+    use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+    use crate::networking::types::info_traffic::InfoTraffic;
+    use crate::networking::types::protocol::Protocol;
+    use crate::utils::temp_files::temp_path;
+
+    fn interval(port: u16, secs: i64) -> InfoTraffic {
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(port),
+            IpAddr::from([8, 8, 8, 8]),
+            Some(53),
+            Protocol::UDP,
+        );
+        let info = InfoAddressPortPair {
+            transmitted_packets: 1,
+            packets_sent: 1,
+            transmitted_bytes: 80,
+            bytes_sent: 80,
+            initial_timestamp: Timestamp::new(secs, 0),
+            final_timestamp: Timestamp::new(secs, 0),
+            ..Default::default()
+        };
+        InfoTraffic {
+            last_packet_timestamp: Timestamp::new(secs, 0),
+            map: HashMap::from([(key, info)]),
+            ..Default::default()
+        }
+    }
+
+    fn log_updates(log: &mut CsvLog, analyzer: &TrafficAnalyzer) -> io::Result<usize> {
+        let connections = log.take_updates(analyzer);
+        log.write_records(&connections)
+    }
+
+    fn log_lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_records_appended_across_intervals() {
+        let path = temp_path("tickertinker_csv_log_intervals.csv");
+        let _ = fs::remove_file(&path);
+        let mut analyzer = TrafficAnalyzer::default();
+        let mut log = CsvLog::open(&path, DEFAULT_CSV_LOG_MAX_BYTES).unwrap();
+
+        analyzer.refresh(interval(50000, 1_000_000), Vec::new());
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 1);
+
+        // only the connection updated in the second interval is appended
+        analyzer.refresh(interval(50001, 1_000_010), Vec::new());
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 1);
+
+        // nothing new to log
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 0);
+
+        let lines = log_lines(&path);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].contains(",UDP,192.168.1.2,50000,8.8.8.8,53,"));
+        assert!(lines[2].contains(",UDP,192.168.1.2,50001,8.8.8.8,53,"));
+        assert!(lines[2].ends_with(",1,0,80,0,"));
+
+        // reopening the log appends to it, without repeating the header
+        drop(log);
+        let mut log = CsvLog::open(&path, DEFAULT_CSV_LOG_MAX_BYTES).unwrap();
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 2);
+        assert_eq!(log_lines(&path).len(), 5);
+    }
+
+    #[test]
+    fn test_log_rotated_by_size() {
+        let path = temp_path("tickertinker_csv_log_rotation.csv");
+        let rotated = temp_path("tickertinker_csv_log_rotation.csv.1");
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+        let mut analyzer = TrafficAnalyzer::default();
+        // only fits the header and a record
+        let mut log = CsvLog::open(&path, HEADER_BYTES + 150).unwrap();
+
+        analyzer.refresh(interval(50000, 1_000_000), Vec::new());
+        log_updates(&mut log, &analyzer).unwrap();
+        assert!(!rotated.exists());
+
+        analyzer.refresh(interval(50001, 1_000_010), Vec::new());
+        log_updates(&mut log, &analyzer).unwrap();
+
+        let rotated_lines = log_lines(&rotated);
+        assert_eq!(rotated_lines.len(), 2);
+        assert!(rotated_lines[1].contains(",50000,"));
+        let lines = log_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].contains(",50001,"));
+    }
+
+    #[test]
+    fn test_log_restarted_with_new_capture() {
+        let path = temp_path("tickertinker_csv_log_restart.csv");
+        let _ = fs::remove_file(&path);
+        let mut log = CsvLog::open(&path, DEFAULT_CSV_LOG_MAX_BYTES).unwrap();
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(interval(50000, 1_000_010), Vec::new());
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 1);
+
+        // a new capture (e.g., of an older file) has packets preceding the logged ones
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(interval(50001, 1_000_000), Vec::new());
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 0);
+        log.restart();
+        assert_eq!(log_updates(&mut log, &analyzer).unwrap(), 1);
+        assert!(log_lines(&path)[2].contains(",50001,"));
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("firefox"), "firefox");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,160 @@
+//! Module defining the `EventSubscriptions` struct, which lets each window of the frontend
+//! receive only the events it's interested in.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, EventTarget};
+
+use crate::location;
+use crate::utils::error_logger::{ErrorLogger, Location};
+
+/// Events emitted to the frontend while capturing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    TrafficUpdate,
+    OfflineGap,
+    HostsEvicted,
+    NewHostResolved,
+    HostsResolvedBatch,
+    UserspaceFiltering,
+    CaptureError,
+    LocalAddressChanged,
+}
+
+impl EventKind {
+    /// Name of the event, as listened to by the frontend
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TrafficUpdate => "traffic_update",
+            Self::OfflineGap => "offline_gap",
+            Self::HostsEvicted => "hosts_evicted",
+            Self::NewHostResolved => "new_host_resolved",
+            Self::HostsResolvedBatch => "hosts_resolved_batch",
+            Self::UserspaceFiltering => "userspace_filtering",
+            Self::CaptureError => "capture_error",
+            Self::LocalAddressChanged => "local_address_changed",
+        }
+    }
+}
+
+/// Events each window opted into, by window label.
+///
+/// Windows that never subscribed receive all the events.
+#[derive(Default)]
+pub struct EventSubscriptions {
+    interests: HashMap<String, HashSet<EventKind>>,
+}
+
+impl EventSubscriptions {
+    /// Restricts the events delivered to the window to the given ones
+    pub fn subscribe(&mut self, label: &str, events: impl IntoIterator<Item = EventKind>) {
+        self.interests
+            .insert(label.to_string(), events.into_iter().collect());
+    }
+
+    /// Delivers all the events to the window again
+    pub fn unsubscribe(&mut self, label: &str) {
+        self.interests.remove(label);
+    }
+
+    /// Returns the events the window subscribed to (`None` if it receives all of them)
+    pub fn get(&self, label: &str) -> Option<&HashSet<EventKind>> {
+        self.interests.get(label)
+    }
+
+    /// Whether the event must be delivered to the target
+    pub fn is_interested(&self, target: &EventTarget, event: EventKind) -> bool {
+        let label = match target {
+            EventTarget::AnyLabel { label }
+            | EventTarget::Window { label }
+            | EventTarget::Webview { label }
+            | EventTarget::WebviewWindow { label } => label,
+            _ => return true,
+        };
+        self.get(label).is_none_or(|events| events.contains(&event))
+    }
+}
+
+/// Emits events to the windows interested in them
+#[derive(Clone)]
+pub struct EventEmitter {
+    app_handle: AppHandle,
+    subscriptions: Arc<Mutex<EventSubscriptions>>,
+}
+
+impl EventEmitter {
+    pub fn new(app_handle: AppHandle, subscriptions: Arc<Mutex<EventSubscriptions>>) -> Self {
+        Self {
+            app_handle,
+            subscriptions,
+        }
+    }
+
+    pub fn emit<S: Serialize + Clone>(&self, event: EventKind, payload: S) {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let _ = self
+            .app_handle
+            .emit_filter(event.name(), payload, |target| {
+                subscriptions.is_interested(target, event)
+            })
+            .log_err(location!());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(label: &str) -> EventTarget {
+        EventTarget::WebviewWindow {
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_narrow_subscriber_only_gets_its_events() {
+        let mut subscriptions = EventSubscriptions::default();
+        subscriptions.subscribe("tray", [EventKind::HostsEvicted]);
+
+        // the subscriber only gets the events it opted into
+        assert!(subscriptions.is_interested(&window("tray"), EventKind::HostsEvicted));
+        assert!(!subscriptions.is_interested(&window("tray"), EventKind::TrafficUpdate));
+        assert!(!subscriptions.is_interested(&window("tray"), EventKind::OfflineGap));
+
+        // other windows still get everything
+        for event in [
+            EventKind::TrafficUpdate,
+            EventKind::OfflineGap,
+            EventKind::HostsEvicted,
+        ] {
+            assert!(subscriptions.is_interested(&window("main"), event));
+            assert!(subscriptions.is_interested(&EventTarget::App, event));
+        }
+
+        // an empty subscription silences the window
+        subscriptions.subscribe("tray", []);
+        assert!(!subscriptions.is_interested(&window("tray"), EventKind::HostsEvicted));
+
+        subscriptions.unsubscribe("tray");
+        assert!(subscriptions.is_interested(&window("tray"), EventKind::TrafficUpdate));
+    }
+
+    #[test]
+    fn test_event_kind_names() {
+        for event in [
+            EventKind::TrafficUpdate,
+            EventKind::OfflineGap,
+            EventKind::HostsEvicted,
+            EventKind::NewHostResolved,
+            EventKind::HostsResolvedBatch,
+            EventKind::UserspaceFiltering,
+            EventKind::CaptureError,
+            EventKind::LocalAddressChanged,
+        ] {
+            assert_eq!(serde_json::to_value(event).unwrap(), event.name());
+        }
+    }
+}
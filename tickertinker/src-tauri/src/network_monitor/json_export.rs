@@ -0,0 +1,103 @@
+//! Module defining the one-shot export of the whole traffic of the current capture to a JSON file,
+//! to be post-processed by other tools (e.g., pandas).
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::traffic_data::TrafficReport;
+
+/// Writes the report to the JSON file at `path`, pretty-printed, replacing the file if it exists
+pub fn export_report(path: &Path, report: &TrafficReport) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, report)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::countries::types::country::Country;
+    use crate::network_monitor::traffic_analyzer::TrafficAnalyzer;
+    use crate::networking::types::address_port_pair::AddressPortPair;
+    use crate::networking::types::asn::Asn;
+    use crate::networking::types::data_info::DataInfo;
+    use crate::networking::types::data_info_host::DataInfoHost;
+    use crate::networking::types::host::Host;
+    use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+    use crate::networking::types::info_traffic::InfoTraffic;
+    use crate::networking::types::protocol::Protocol;
+    use crate::utils::temp_files::temp_path;
+    use crate::utils::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_export_report() {
+        let path = temp_path("tickertinker_json_export.json");
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from([8, 8, 8, 8]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let info = InfoAddressPortPair {
+            transmitted_bytes: 1_500,
+            transmitted_packets: 3,
+            initial_timestamp: Timestamp::new(1_700_000_000, 0),
+            final_timestamp: Timestamp::new(1_700_000_010, 0),
+            ..Default::default()
+        };
+        let host = Host {
+            domain: "dns.google".to_string(),
+            asn: Asn {
+                code: "15169".to_string(),
+                name: "GOOGLE".to_string(),
+            },
+            country: Country::US,
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(
+            InfoTraffic {
+                tot_data_info: DataInfo::new_for_tests(0, 3, 0, 1_500),
+                dropped_packets: 2,
+                map: HashMap::from([(key, info)]),
+                hosts: HashMap::from([(host, DataInfoHost::default())]),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+
+        let report = analyzer.get_traffic_report(Some("eth0".to_string()));
+        export_report(&path, &report).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["interface_name"], "eth0");
+        assert_eq!(json["capture_started_at"]["secs"], 1_700_000_000);
+        assert_eq!(json["total_bytes"], 1_500);
+        assert_eq!(json["dropped_packets"], 2);
+        assert_eq!(json["connections"][0]["port2"], 443);
+        assert_eq!(json["connections"][0]["transmitted_packets"], 3);
+        // the nested structure of the hosts is preserved
+        assert_eq!(json["hosts"][0]["domain"], "dns.google");
+        assert_eq!(json["hosts"][0]["asn"]["name"], "GOOGLE");
+        assert_eq!(json["hosts"][0]["country"], "US");
+
+        // nothing observed yet
+        let report = TrafficAnalyzer::default().get_traffic_report(None);
+        let json = serde_json::to_value(report).unwrap();
+        assert!(json["capture_started_at"].is_null());
+        assert!(json["interface_name"].is_null());
+        assert!(json["connections"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export_report_write_error() {
+        let path = temp_path("tickertinker_missing_dir").join("export.json");
+        let report = TrafficAnalyzer::default().get_traffic_report(None);
+        assert!(export_report(&path, &report).is_err());
+    }
+}
@@ -1,51 +1,335 @@
 #![allow(dead_code, clippy::enum_variant_names, clippy::module_inception)]
 
-use std::{collections::BTreeMap, sync::{Arc, Mutex}};
+use std::collections::{HashSet, VecDeque};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use pcap::{Device, Packet};
-use tauri::{AppHandle, Manager, State};
-use std::time::Instant;
+use pcap::Device;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State, Webview};
 
+use crate::mmdb::asn::get_asn;
+use crate::mmdb::country::get_country;
+use crate::mmdb::types::mmdb_reader::MmdbReaders;
+use crate::networking::parse_packets::{ResolutionStats, capture_n_packets};
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::asn::Asn;
+use crate::networking::types::bogon::is_bogon;
+use crate::networking::types::capture_context::{
+    CaptureContext, CaptureSource, MyPcapImport, PROMISCUOUS_MODE,
+};
+use crate::networking::types::capture_filter::CaptureFilter;
+use crate::networking::types::capture_options::CaptureOptions;
+use crate::networking::types::data_representation::{DataRepr, Precision};
+use crate::networking::types::host::Host;
+use crate::networking::types::interface_activity::{InterfaceActivity, SAMPLE_DURATION};
+use crate::networking::types::interface_info::InterfaceInfo;
+use crate::networking::types::my_device::MyDevice;
+use crate::networking::types::parse_failure_sample::ParseFailureSample;
+use crate::networking::types::platform_capabilities::PlatformCapabilities;
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::retention_policy::RetentionPolicy;
+use crate::networking::types::savefile_queue::MAX_SAVEFILE_QUEUE_SIZE;
+use crate::networking::types::service::Service;
+use crate::networking::types::services_map_info::ServicesMapInfo;
+use crate::report::types::sort_type::SortType;
+use crate::translations::types::language::Language;
+use crate::utils::types::timestamp::TimestampPrecision;
+
+mod bandwidth_series;
 mod capture;
+mod csv_export;
+mod csv_logger;
+pub mod event_subscriptions;
+mod json_export;
 mod traffic_analyzer;
-mod traffic_data;
+pub mod traffic_data;
+mod traffic_history;
+mod update_coalescer;
 
+use csv_logger::{CsvLogger, DEFAULT_CSV_LOG_MAX_BYTES};
+use event_subscriptions::{EventEmitter, EventKind, EventSubscriptions};
 use traffic_analyzer::TrafficAnalyzer;
-use traffic_data::{TrafficChartData, TrafficData};
+use traffic_data::{
+    AppProtocolData, AsymmetricFlowData, BandwidthPercentiles, CaptureStats, CaptureStatus,
+    CidrStats, ConnectionData, ConnectionProfileData, ConnectionRttData, CountryLookup,
+    DataEstimate, DebugStateDump, DropDelta, GatewayTraffic, HostClassification, HostData,
+    HostDirectionality, HourlyTrafficData, InfoTrafficData, ListeningPortData, PacketSizeData,
+    PortData, ProcessTrafficData, QosClassData, RecentHostData, ServiceData, SessionInfo,
+    TrafficSample, TrafficSnapshot, UserspaceFilteringMessage,
+};
+use traffic_history::{DEFAULT_HISTORY_WINDOW, MAX_HISTORY_WINDOW};
+
+/// Maximum number of addresses sampled by `debug_dump_state`
+const DEBUG_DUMP_SAMPLE_SIZE: usize = 20;
+/// How long `capture_n_packets` waits for the packets, unless specified
+const CAPTURE_N_PACKETS_TIMEOUT: Duration = Duration::from_secs(10);
 
-#[derive(Default)]
 pub struct NetworkMonitorState {
     capture_thread: Arc<Mutex<Option<capture::CaptureThread>>>,
     traffic_analyzer: Arc<Mutex<TrafficAnalyzer>>,
+    /// Identifier to assign to the next capture
+    next_cap_id: AtomicUsize,
+    /// Limits to the traffic data kept by the captures
+    retention: Mutex<RetentionPolicy>,
+    /// Events each window of the frontend opted into
+    event_subscriptions: Arc<Mutex<EventSubscriptions>>,
+    /// Country and ASN databases used by the next captures
+    mmdb_readers: Mutex<MmdbReaders>,
+    /// Thread appending the updated connections to a CSV file, if logging
+    csv_logger: Mutex<Option<CsvLogger>>,
+    /// Hosts marked as favorites, kept across captures
+    favorite_hosts: Mutex<HashSet<Host>>,
+}
+
+impl Default for NetworkMonitorState {
+    fn default() -> Self {
+        Self {
+            capture_thread: Arc::default(),
+            traffic_analyzer: Arc::default(),
+            next_cap_id: AtomicUsize::default(),
+            retention: Mutex::default(),
+            event_subscriptions: Arc::default(),
+            // the bundled databases are used until others are loaded
+            mmdb_readers: Mutex::new(MmdbReaders::bundled()),
+            csv_logger: Mutex::default(),
+            favorite_hosts: Mutex::default(),
+        }
+    }
 }
 
 impl NetworkMonitorState {
-    pub fn start_capture(&self, device_name: &str, app_handle: AppHandle) -> Result<(), String> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_capture(
+        &self,
+        device_name: &str,
+        options: CaptureOptions,
+        capture_filter: CaptureFilter,
+        protocol_filter: Option<Vec<Protocol>>,
+        app_handle: AppHandle,
+        binary_updates: Option<Channel>,
+        pcap_out_path: Option<String>,
+        bpf_filter: Option<String>,
+        promiscuous: bool,
+    ) -> Result<usize, String> {
+        validate_capture_options(&options)?;
         let mut capture_thread = self.capture_thread.lock().unwrap();
-        if capture_thread.is_some() {
-            return Err("Capture already in progress".into());
-        }
+        Self::ensure_no_capture(&mut capture_thread)?;
 
-        let device = Device::list().unwrap().into_iter()
+        let device = Device::list()
+            .map_err(|e| e.to_string())?
+            .into_iter()
             .find(|d| d.name == device_name)
             .ok_or_else(|| format!("Device not found: {}", device_name))?;
 
-        let (sender, receiver) = std::sync::mpsc::channel::<Packet>();
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(device));
+        if options.nanosecond_timestamps {
+            cs.set_timestamp_precision(TimestampPrecision::Nano);
+        }
+        let mut capture_context = CaptureContext::new(
+            &cs,
+            pcap_out_path.as_ref(),
+            None,
+            options.split_savefile_by_protocol,
+            options.verbose_save,
+            options.buffer_size,
+            options.snaplen,
+            promiscuous,
+        );
+        if let Some(e) = capture_context.error() {
+            return Err(e.to_string());
+        }
+        let userspace_bpf = apply_bpf_filter(&mut capture_context, bpf_filter.as_deref())?;
+        cs.set_link_type(capture_context.my_link_type());
+
+        let thread = self.new_capture_thread(
+            cs,
+            capture_context,
+            VecDeque::new(),
+            options,
+            capture_filter.with_protocol_filter(protocol_filter),
+            userspace_bpf,
+            app_handle,
+            binary_updates,
+        );
+        let cap_id = thread.cap_id();
+        *capture_thread = Some(thread);
+        Ok(cap_id)
+    }
+
+    /// Replays the packets of a PCAP or pcapng file, as if they were captured live
+    pub fn start_capture_from_file(
+        &self,
+        path: &str,
+        options: CaptureOptions,
+        capture_filter: CaptureFilter,
+        app_handle: AppHandle,
+        binary_updates: Option<Channel>,
+        bpf_filter: Option<String>,
+    ) -> Result<usize, String> {
+        validate_capture_options(&options)?;
+        let mut capture_thread = self.capture_thread.lock().unwrap();
+        Self::ensure_no_capture(&mut capture_thread)?;
 
-        let analyzer = self.traffic_analyzer.clone();
-        let app_handle_clone = app_handle.clone();
+        let (cs, mut capture_context) = open_capture_file(path)?;
+        let userspace_bpf = apply_bpf_filter(&mut capture_context, bpf_filter.as_deref())?;
 
-        let thread = capture::CaptureThread::new(
-            device,
-            sender,
-            analyzer,
-            app_handle_clone,
+        let thread = self.new_capture_thread(
+            cs,
+            capture_context,
+            VecDeque::new(),
+            options,
+            capture_filter,
+            userspace_bpf,
+            app_handle,
+            binary_updates,
         );
+        let cap_id = thread.cap_id();
+        *capture_thread = Some(thread);
+        Ok(cap_id)
+    }
 
+    /// Replays the packets of several PCAP or pcapng files as a single capture,
+    /// starting from the file whose first packet is the oldest
+    pub fn start_capture_from_files(
+        &self,
+        paths: &[String],
+        options: CaptureOptions,
+        capture_filter: CaptureFilter,
+        app_handle: AppHandle,
+        binary_updates: Option<Channel>,
+        bpf_filter: Option<String>,
+    ) -> Result<usize, String> {
+        validate_capture_options(&options)?;
+        let mut capture_thread = self.capture_thread.lock().unwrap();
+        Self::ensure_no_capture(&mut capture_thread)?;
+
+        let (mut files, userspace_bpf) = open_capture_files(paths, bpf_filter.as_deref())?;
+        let Some((cs, capture_context)) = files.pop_front() else {
+            return Err("No capture files provided".into());
+        };
+
+        let thread = self.new_capture_thread(
+            cs,
+            capture_context,
+            files,
+            options,
+            capture_filter,
+            userspace_bpf,
+            app_handle,
+            binary_updates,
+        );
+        let cap_id = thread.cap_id();
         *capture_thread = Some(thread);
+        Ok(cap_id)
+    }
+
+    /// Fails if a capture is in progress, cleaning up the previous one if it stopped on its own
+    fn ensure_no_capture(
+        capture_thread: &mut Option<capture::CaptureThread>,
+    ) -> Result<(), String> {
+        if capture_thread
+            .as_ref()
+            .is_some_and(capture::CaptureThread::is_stopped)
+            && let Some(thread) = capture_thread.take()
+        {
+            thread.stop();
+        }
+        if capture_thread.is_some() {
+            return Err("Capture already in progress".into());
+        }
+        Ok(())
+    }
+
+    /// Resets the collected traffic and starts the threads of a new capture,
+    /// warning the frontend if the BPF filter is applied in userspace
+    #[allow(clippy::too_many_arguments)]
+    fn new_capture_thread(
+        &self,
+        cs: CaptureSource,
+        capture_context: CaptureContext,
+        queued_files: VecDeque<(CaptureSource, CaptureContext)>,
+        options: CaptureOptions,
+        capture_filter: CaptureFilter,
+        userspace_bpf: Option<UserspaceBpf>,
+        app_handle: AppHandle,
+        binary_updates: Option<Channel>,
+    ) -> capture::CaptureThread {
+        let cap_id = self.next_cap_id.fetch_add(1, Ordering::Relaxed);
+        let retention = *self.retention.lock().unwrap();
+        let mut analyzer = TrafficAnalyzer::new(RetentionPolicy {
+            max_hosts: options.max_hosts.or(retention.max_hosts),
+            ..retention
+        });
+        analyzer.set_history_window(options.history_window.unwrap_or(DEFAULT_HISTORY_WINDOW));
+        let mut traffic_analyzer = self.traffic_analyzer.lock().unwrap();
+        *traffic_analyzer = analyzer;
+        // the CSV log (if any) starts over with the connections of the new capture
+        if let Some(csv_logger) = self.csv_logger.lock().unwrap().as_ref() {
+            csv_logger.restart();
+        }
+        drop(traffic_analyzer);
+        let emitter = EventEmitter::new(app_handle, self.event_subscriptions.clone());
+
+        let capture_filter = match userspace_bpf {
+            Some(userspace_bpf) => {
+                emitter.emit(
+                    EventKind::UserspaceFiltering,
+                    UserspaceFilteringMessage {
+                        cap_id,
+                        bpf_filter: userspace_bpf.bpf,
+                        reason: userspace_bpf.reason,
+                    },
+                );
+                capture_filter.with_userspace_bpf(userspace_bpf.filter)
+            }
+            None => capture_filter,
+        };
+
+        capture::CaptureThread::new(
+            cap_id,
+            cs,
+            capture_context,
+            queued_files,
+            options,
+            capture_filter,
+            self.mmdb_readers.lock().unwrap().clone(),
+            self.favorite_hosts.lock().unwrap().clone(),
+            self.traffic_analyzer.clone(),
+            emitter,
+            binary_updates,
+        )
+    }
+
+    /// Replaces the country and ASN databases used by the next captures
+    pub fn load_mmdb(
+        &self,
+        country_path: Option<&str>,
+        asn_path: Option<&str>,
+    ) -> Result<(), String> {
+        *self.mmdb_readers.lock().unwrap() = MmdbReaders::load(country_path, asn_path)?;
         Ok(())
     }
 
+    /// Looks up the ASN of the address in the loaded database
+    /// (an empty ASN if the address isn't in the database)
+    pub fn asn_lookup(&self, ip: &str) -> Result<Asn, String> {
+        let address = parse_ip_address(ip)?;
+        let mmdb_readers = self.mmdb_readers.lock().unwrap();
+        Ok(get_asn(&address, &mmdb_readers.asn))
+    }
+
+    /// Looks up the country of the address in the loaded database
+    pub fn country_lookup(&self, ip: &str) -> Result<CountryLookup, String> {
+        let address = parse_ip_address(ip)?;
+        let mmdb_readers = self.mmdb_readers.lock().unwrap();
+        let country = get_country(&address, &mmdb_readers.country);
+        Ok(CountryLookup::new(country))
+    }
+
     pub fn stop_capture(&self) -> Result<(), String> {
         let mut capture_thread = self.capture_thread.lock().unwrap();
         if let Some(thread) = capture_thread.take() {
@@ -56,34 +340,2205 @@ impl NetworkMonitorState {
         }
     }
 
-    pub fn get_traffic_data(&self) -> Result<TrafficData, String> {
+    /// Starts appending the connections updated at each interval to the CSV file at `path`,
+    /// rotating it when it reaches `max_file_bytes`
+    pub fn start_csv_logging(
+        &self,
+        path: &str,
+        interval_secs: u64,
+        max_file_bytes: Option<u64>,
+    ) -> Result<(), String> {
+        if interval_secs == 0 {
+            return Err("The logging interval must be at least 1 second".into());
+        }
+        let mut csv_logger = self.csv_logger.lock().unwrap();
+        if csv_logger.is_some() {
+            return Err("CSV logging already in progress".into());
+        }
+        *csv_logger = Some(CsvLogger::start(
+            Path::new(path),
+            Duration::from_secs(interval_secs),
+            max_file_bytes.unwrap_or(DEFAULT_CSV_LOG_MAX_BYTES),
+            self.traffic_analyzer.clone(),
+        )?);
+        Ok(())
+    }
+
+    pub fn stop_csv_logging(&self) -> Result<(), String> {
+        // the logger is stopped without holding the lock, since its thread locks the analyzer
+        let csv_logger = self.csv_logger.lock().unwrap().take();
+        match csv_logger {
+            Some(csv_logger) => {
+                csv_logger.stop();
+                Ok(())
+            }
+            None => Err("No CSV logging in progress".into()),
+        }
+    }
+
+    /// Writes the connections of the current capture to the CSV file at `path`
+    pub fn export_report_csv(&self, path: &str) -> Result<(), String> {
+        let connections = self.traffic_analyzer.lock().unwrap().get_connections();
+        csv_export::export_connections(Path::new(path), &connections).map_err(|e| e.to_string())
+    }
+
+    /// Writes the whole traffic of the current capture to the JSON file at `path`
+    pub fn export_report_json(&self, path: &str) -> Result<(), String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let interface_name = capture_thread
+            .as_ref()
+            .map(capture::CaptureThread::source_name);
+        drop(capture_thread);
+        let report = self
+            .traffic_analyzer
+            .lock()
+            .unwrap()
+            .get_traffic_report(interface_name);
+        json_export::export_report(Path::new(path), &report).map_err(|e| e.to_string())
+    }
+
+    /// Restricts the events delivered to the window with the given label
+    pub fn subscribe_events(&self, label: &str, events: Vec<EventKind>) {
+        self.event_subscriptions
+            .lock()
+            .unwrap()
+            .subscribe(label, events);
+    }
+
+    /// Delivers all the events to the window with the given label again
+    pub fn unsubscribe_events(&self, label: &str) {
+        self.event_subscriptions.lock().unwrap().unsubscribe(label);
+    }
+
+    /// Pauses or resumes the running capture, without resetting the collected traffic
+    pub fn set_capture_paused(&self, paused: bool) -> Result<(), String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) => {
+                thread.set_paused(paused);
+                Ok(())
+            }
+            None => Err("No capture in progress".into()),
+        }
+    }
+
+    /// Replaces the BPF filter of the running capture without restarting it
+    pub fn update_filter(&self, cap_id: usize, filter: &str) -> Result<(), String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) if thread.cap_id() == cap_id => thread.update_filter(filter),
+            _ => Err(format!("No capture in progress with id {cap_id}")),
+        }
+    }
+
+    pub fn set_rdns_enabled(&self, enabled: bool) -> Result<(), String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) => thread.set_rdns_enabled(enabled),
+            None => Err("No capture in progress".into()),
+        }
+    }
+
+    /// Streams only the connections involving `address` with the traffic updates (all if `None`)
+    pub fn set_focused_host(&self, address: Option<IpAddr>) {
+        let mut analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.set_focused_host(address);
+    }
+
+    /// Marks or unmarks a host as favorite, in the running capture and in the next ones
+    pub fn set_favorite_host(&self, host: Host, is_favorite: bool) {
+        // held until the end, so that a capture starting meanwhile gets the updated favorites
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let mut favorite_hosts = self.favorite_hosts.lock().unwrap();
+        if let Some(thread) = capture_thread.as_ref() {
+            thread.set_favorite_host(&host, is_favorite);
+        }
+        let mut analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.set_favorite_host(&host, is_favorite);
+        if is_favorite {
+            favorite_hosts.insert(host);
+        } else {
+            favorite_hosts.remove(&host);
+        }
+    }
+
+    pub fn get_favorite_hosts(&self) -> Vec<HostData> {
+        let favorite_hosts = self.favorite_hosts.lock().unwrap();
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.get_favorite_hosts(&favorite_hosts)
+    }
+
+    /// Replaces the retention policy, also applying it to the running capture
+    pub fn set_retention(&self, retention: RetentionPolicy) {
+        *self.retention.lock().unwrap() = retention;
+        self.traffic_analyzer
+            .lock()
+            .unwrap()
+            .set_retention(retention);
+    }
+
+    pub fn get_retention(&self) -> RetentionPolicy {
+        *self.retention.lock().unwrap()
+    }
+
+    /// Returns the data exchanged in each of the latest intervals of the capture, oldest first
+    pub fn get_traffic_history(&self, cap_id: usize) -> Result<Vec<TrafficSample>, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) if thread.cap_id() == cap_id => {
+                Ok(self.traffic_analyzer.lock().unwrap().get_traffic_history())
+            }
+            _ => Err(format!("No capture in progress with id {cap_id}")),
+        }
+    }
+
+    pub fn ack_traffic_update(&self, cap_id: usize) -> Result<(), String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) if thread.cap_id() == cap_id => {
+                thread.ack_traffic_update();
+                Ok(())
+            }
+            _ => Err(format!("No capture in progress with id {cap_id}")),
+        }
+    }
+
+    /// Returns the state of the running capture, if any
+    pub fn get_capture_status(&self) -> Option<CaptureStatus> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        capture_thread
+            .as_ref()
+            .filter(|thread| !thread.is_stopped())
+            .map(capture::CaptureThread::status)
+    }
+
+    /// Returns the snapshot length of the running capture with the number of packets it cut,
+    /// and the bytes analyzed compared to the ones that crossed the interface
+    pub fn get_session_info(&self) -> Result<SessionInfo, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let thread = capture_thread.as_ref().ok_or("No capture in progress")?;
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_session_info(thread.cap_id(), thread.snaplen()))
+    }
+
+    pub fn get_capture_stats(&self, cap_id: usize) -> Result<CaptureStats, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        match capture_thread.as_ref() {
+            Some(thread) if thread.cap_id() == cap_id => {
+                let analyzer = self.traffic_analyzer.lock().unwrap();
+                Ok(analyzer.get_capture_stats(cap_id, thread.elapsed()))
+            }
+            _ => Err(format!("No capture in progress with id {cap_id}")),
+        }
+    }
+
+    pub fn get_traffic_data(&self) -> Result<InfoTrafficData, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_info_traffic_data())
+    }
+
+    pub fn take_traffic_snapshot(&self) -> Result<TrafficSnapshot, String> {
+        let mut analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.take_traffic_snapshot())
+    }
+
+    pub fn take_drop_delta(&self) -> DropDelta {
+        self.traffic_analyzer.lock().unwrap().take_drop_delta()
+    }
+
+    pub fn set_baseline(&self) -> usize {
+        self.traffic_analyzer.lock().unwrap().set_baseline()
+    }
+
+    pub fn get_new_flows_since_baseline(&self) -> Result<Vec<ConnectionData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.get_new_flows_since_baseline()
+    }
+
+    pub fn get_idle_connections(&self, idle_secs: u64) -> Result<Vec<ConnectionData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_idle_connections(idle_secs))
+    }
+
+    /// Looks up a connection by its endpoints and protocol
+    pub fn get_connection_details(
+        &self,
+        source: &str,
+        sport: Option<u16>,
+        dest: &str,
+        dport: Option<u16>,
+        protocol: &str,
+    ) -> Result<Option<ConnectionData>, String> {
+        let key = AddressPortPair::new(
+            parse_ip_address(source)?,
+            sport,
+            parse_ip_address(dest)?,
+            dport,
+            parse_protocol(protocol)?,
+        );
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_connection_details(&key))
+    }
+
+    pub fn get_packet_sizes(&self) -> Result<Vec<PacketSizeData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_packet_sizes())
+    }
+
+    pub fn get_connection_rtts(&self) -> Result<Vec<ConnectionRttData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_connection_rtts())
+    }
+
+    pub fn get_connection_profiles(&self) -> Result<Vec<ConnectionProfileData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_connection_profiles())
+    }
+
+    pub fn get_parse_failure_samples(&self) -> Result<Vec<ParseFailureSample>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_parse_failure_samples())
+    }
+
+    pub fn get_asymmetric_flows(&self) -> Result<Vec<AsymmetricFlowData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_asymmetric_flows())
+    }
+
+    pub fn get_recent_hosts(&self, since_secs: u64) -> Result<Vec<RecentHostData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_recent_hosts(since_secs))
+    }
+
+    pub fn get_top_hosts(
+        &self,
+        n: usize,
+        sort_type: SortType,
+        data_repr: DataRepr,
+    ) -> Result<Vec<HostData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_top_hosts(n, sort_type, data_repr))
+    }
+
+    pub fn get_top_services(
+        &self,
+        n: usize,
+        sort_type: SortType,
+        data_repr: DataRepr,
+    ) -> Result<Vec<ServiceData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_top_services(n, sort_type, data_repr))
+    }
+
+    pub fn get_top_ports(&self, n: usize, data_repr: DataRepr) -> Result<Vec<PortData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_top_ports(n, data_repr))
+    }
+
+    pub fn get_external_connections(&self) -> Result<Vec<ConnectionData>, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let my_interface_addresses = capture_thread
+            .as_ref()
+            .map(capture::CaptureThread::interface_addresses)
+            .unwrap_or_default();
+        drop(capture_thread);
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_external_connections(&my_interface_addresses))
+    }
+
+    pub fn get_gateway_traffic(&self) -> Result<GatewayTraffic, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let my_interface_addresses = capture_thread
+            .as_ref()
+            .map(capture::CaptureThread::interface_addresses)
+            .unwrap_or_default();
+        drop(capture_thread);
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_gateway_traffic(&my_interface_addresses))
+    }
+
+    pub fn get_local_listening_ports(&self) -> Vec<ListeningPortData> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.get_local_listening_ports()
+    }
+
+    pub fn get_stats_for_cidr(&self, cidr: &str) -> Result<CidrStats, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.get_stats_for_cidr(cidr)
+    }
+
+    pub fn get_observed_local_addresses(&self) -> Result<Vec<IpAddr>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_observed_local_addresses())
+    }
+
+    pub fn get_directionality(&self) -> Result<Vec<HostDirectionality>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_directionality())
+    }
+
+    pub fn get_host_classifications(&self) -> Result<Vec<HostClassification>, String> {
         let analyzer = self.traffic_analyzer.lock().unwrap();
-        Ok(analyzer.get_traffic_data())
+        Ok(analyzer.get_host_classifications())
+    }
+
+    pub fn get_app_protocol_breakdown(&self) -> Result<Vec<AppProtocolData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_app_protocol_breakdown())
+    }
+
+    pub fn get_traffic_by_process(&self) -> Result<Vec<ProcessTrafficData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_traffic_by_process())
+    }
+
+    pub fn get_qos_breakdown(&self) -> Result<Vec<QosClassData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_qos_breakdown())
+    }
+
+    pub fn get_bandwidth_percentiles(&self) -> Result<BandwidthPercentiles, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_bandwidth_percentiles())
+    }
+
+    pub fn get_hourly_profile(&self) -> Result<Vec<HourlyTrafficData>, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_hourly_profile())
+    }
+
+    pub fn estimate_data_cost(&self, price_per_gb: f64) -> Result<DataEstimate, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.estimate_data_cost(price_per_gb)
+    }
+
+    pub fn estimate_data_footprint(&self, grams_co2_per_gb: f64) -> Result<DataEstimate, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.estimate_data_footprint(grams_co2_per_gb)
+    }
+
+    pub fn get_resolution_stats(&self) -> Result<ResolutionStats, String> {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        capture_thread
+            .as_ref()
+            .map(capture::CaptureThread::resolution_stats)
+            .ok_or_else(|| "No capture in progress".into())
+    }
+
+    pub fn get_notification_summary(
+        &self,
+        language: Language,
+        precision: Precision,
+    ) -> Result<String, String> {
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        Ok(analyzer.get_notification_summary(language, precision))
+    }
+
+    pub fn debug_dump_state(&self) -> DebugStateDump {
+        let capture_thread = self.capture_thread.lock().unwrap();
+        let resolutions = capture_thread
+            .as_ref()
+            .map(|thread| thread.dump_resolutions(DEBUG_DUMP_SAMPLE_SIZE));
+        let analyzer = self.traffic_analyzer.lock().unwrap();
+        analyzer.debug_dump(resolutions)
     }
 
     pub fn list_interfaces(&self) -> Result<Vec<Device>, String> {
         Device::list().map_err(|e| e.to_string())
     }
+
+    /// Briefly samples all the devices, returning them from the busiest to the idlest
+    pub fn list_interfaces_with_activity(&self) -> Result<Vec<InterfaceActivity>, String> {
+        let devices = self.list_interfaces()?;
+        Ok(InterfaceActivity::sample_all(devices, SAMPLE_DURATION))
+    }
+
+    /// Captures `n` packets on the device apart from the running capture (if any),
+    /// returning the connections they make up
+    pub fn capture_n_packets(
+        &self,
+        device_name: &str,
+        n: usize,
+        bpf_filter: Option<&str>,
+        timeout: Duration,
+    ) -> Result<Vec<ConnectionData>, String> {
+        let device = self
+            .list_interfaces()?
+            .into_iter()
+            .find(|d| d.name == device_name)
+            .ok_or_else(|| format!("Device not found: {device_name}"))?;
+
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(device));
+        let mut capture_context =
+            CaptureContext::new(&cs, None, None, false, false, None, None, PROMISCUOUS_MODE);
+        if let Some(e) = capture_context.error() {
+            return Err(e.to_string());
+        }
+        let capture_filter = match apply_bpf_filter(&mut capture_context, bpf_filter)? {
+            Some(userspace_bpf) => {
+                CaptureFilter::default().with_userspace_bpf(userspace_bpf.filter)
+            }
+            None => CaptureFilter::default(),
+        };
+        cs.set_link_type(capture_context.my_link_type());
+
+        let info_traffic = capture_n_packets(&cs, capture_context, n, &capture_filter, timeout)?;
+        Ok(InfoTrafficData::new(&info_traffic).connections)
+    }
+}
+
+/// Parses an address typed in by the user, ignoring the surrounding whitespace
+fn parse_ip_address(ip: &str) -> Result<IpAddr, String> {
+    ip.trim()
+        .parse()
+        .map_err(|e| format!("Invalid IP address \"{ip}\": {e}"))
+}
+
+/// Parses the name of a protocol (e.g., `tcp`, case-insensitive)
+fn parse_protocol(protocol: &str) -> Result<Protocol, String> {
+    [Protocol::TCP, Protocol::UDP, Protocol::ICMP, Protocol::ARP]
+        .into_iter()
+        .find(|p| p.to_string().eq_ignore_ascii_case(protocol.trim()))
+        .ok_or_else(|| format!("Invalid protocol \"{protocol}\""))
+}
+
+/// Checks the capture options that aren't validated when opening the capture handle
+fn validate_capture_options(options: &CaptureOptions) -> Result<(), String> {
+    if let Some(history_window) = options.history_window
+        && !(1..=MAX_HISTORY_WINDOW).contains(&history_window)
+    {
+        return Err(format!(
+            "Invalid history window {history_window}: it must be between 1 and {MAX_HISTORY_WINDOW}"
+        ));
+    }
+    if let Some(queue_size) = options.savefile_queue_size
+        && !(1..=MAX_SAVEFILE_QUEUE_SIZE).contains(&queue_size)
+    {
+        return Err(format!(
+            "Invalid savefile queue size {queue_size}: \
+            it must be between 1 and {MAX_SAVEFILE_QUEUE_SIZE}"
+        ));
+    }
+    Ok(())
+}
+
+/// A BPF filter that couldn't be compiled, applied in userspace via its structured equivalent
+struct UserspaceBpf {
+    bpf: String,
+    filter: CaptureFilter,
+    /// Error returned by libpcap when compiling the filter
+    reason: String,
+}
+
+/// Applies the BPF filter (if any) to the capture handle.
+///
+/// If libpcap can't compile it (e.g., because the platform doesn't support it),
+/// its structured equivalent is returned, to be applied in userspace instead;
+/// the compilation error is returned if the filter can't be translated.
+fn apply_bpf_filter(
+    capture_context: &mut CaptureContext,
+    bpf_filter: Option<&str>,
+) -> Result<Option<UserspaceBpf>, String> {
+    let Some(bpf) = bpf_filter else {
+        return Ok(None);
+    };
+    let Err(reason) = capture_context.set_bpf(bpf) else {
+        return Ok(None);
+    };
+    userspace_bpf(bpf, reason).map(Some)
+}
+
+fn userspace_bpf(bpf: &str, reason: String) -> Result<UserspaceBpf, String> {
+    match CaptureFilter::from_bpf(bpf) {
+        Some(filter) => Ok(UserspaceBpf {
+            bpf: bpf.to_string(),
+            filter,
+            reason,
+        }),
+        None => Err(reason),
+    }
+}
+
+/// Opens a capture file, checking that it exists and that it's a valid PCAP or pcapng file
+fn open_capture_file(path: &str) -> Result<(CaptureSource, CaptureContext), String> {
+    if !Path::new(path).is_file() {
+        return Err(format!("Capture file not found: {path}"));
+    }
+    let mut cs = CaptureSource::File(MyPcapImport::new(path.to_string()));
+    let capture_context = CaptureContext::new(&cs, None, None, false, false, None, None, false);
+    if let Some(e) = capture_context.error() {
+        return Err(format!("Invalid capture file {path}: {e}"));
+    }
+    cs.set_link_type(capture_context.my_link_type());
+    Ok((cs, capture_context))
+}
+
+/// Capture files to be parsed in order, with their handles
+type CaptureFiles = VecDeque<(CaptureSource, CaptureContext)>;
+
+/// Opens the capture files (see `open_capture_file`) sorted by the timestamp of their first packet,
+/// and applies the BPF filter (if any) to them (see `apply_bpf_filter`)
+fn open_capture_files(
+    paths: &[String],
+    bpf_filter: Option<&str>,
+) -> Result<(CaptureFiles, Option<UserspaceBpf>), String> {
+    let mut files = Vec::new();
+    let mut userspace_bpf = None;
+    for path in paths {
+        let (cs, mut capture_context) = open_capture_file(path)?;
+        // the filter is applied in userspace to all the files if any of them can't compile it
+        if let Some(file_userspace_bpf) = apply_bpf_filter(&mut capture_context, bpf_filter)? {
+            userspace_bpf.get_or_insert(file_userspace_bpf);
+        }
+        files.push((cs.first_packet_timestamp(), cs, capture_context));
+    }
+    // files without packets come first, harmlessly
+    files.sort_by_key(|(first_packet_timestamp, _, _)| *first_packet_timestamp);
+    let files = files
+        .into_iter()
+        .map(|(_, cs, capture_context)| (cs, capture_context))
+        .collect();
+    Ok((files, userspace_bpf))
 }
 
 // Tauri commands
 
+/// Lists the devices available for capturing, with their description, addresses, and flags.
+#[tauri::command]
+pub fn list_interfaces(state: State<NetworkMonitorState>) -> Result<Vec<InterfaceInfo>, String> {
+    state
+        .list_interfaces()
+        .map(|devices| devices.iter().map(InterfaceInfo::new).collect())
+}
+
+/// Lists the devices sorted by the packet rate observed while capturing on each of them for
+/// half a second (all at once), so that the one carrying the traffic comes first.
+///
+/// Devices that can't be opened are reported as inactive.
 #[tauri::command]
-fn list_interfaces(state: State<NetworkMonitorState>) -> Result<Vec<Device>, String> {
-    state.list_interfaces()
+pub async fn list_interfaces_with_activity(
+    app_handle: AppHandle,
+) -> Result<Vec<InterfaceActivity>, String> {
+    // sampling blocks for a while: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle
+            .state::<NetworkMonitorState>()
+            .list_interfaces_with_activity()
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
+/// Starts capturing on the given device; `options` enables optional features
+/// (e.g., labelling connections with their process) and defaults to none of them.
+///
+/// If `binary_updates` is provided, traffic updates are sent through it encoded as MessagePack
+/// instead of being emitted as JSON `traffic_update` events.
+///
+/// If `pcap_out_path` is provided, the captured packets are also saved to that PCAP file.
+///
+/// If `capture_filter` is provided, only the packets satisfying all its conditions
+/// (IP ranges, ports, protocols, directions, and traffic types) are aggregated.
+///
+/// If `protocol_filter` is provided (e.g., `["TCP", "UDP"]` to leave out ARP and ICMP),
+/// the packets of the other protocols are counted in the totals but not aggregated
+/// into connections and hosts; `null` or an empty list means all the protocols.
+///
+/// If `bpf_filter` is provided (e.g., "tcp port 443"), it's applied to the capture handle
+/// so that the other packets aren't even captured; an invalid filter is reported as an error.
+/// If libpcap can't compile a simple filter (e.g., conjunctions of protocols, ports, and hosts),
+/// its equivalent is applied in userspace instead, and a `userspace_filtering` event is emitted.
+///
+/// The device is put in promiscuous mode unless `promiscuous` is `false`, which limits the capture
+/// to the traffic of this host (e.g., on restricted networks or in VMs), reducing its load.
 #[tauri::command]
-fn start_capture(device_name: String, state: State<NetworkMonitorState>, app_handle: AppHandle) -> Result<(), String> {
-    state.start_capture(&device_name, app_handle)
+#[allow(clippy::too_many_arguments)]
+pub fn start_capture(
+    device_name: String,
+    options: Option<CaptureOptions>,
+    capture_filter: Option<CaptureFilter>,
+    protocol_filter: Option<Vec<Protocol>>,
+    binary_updates: Option<Channel>,
+    pcap_out_path: Option<String>,
+    bpf_filter: Option<String>,
+    promiscuous: Option<bool>,
+    state: State<NetworkMonitorState>,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    state.start_capture(
+        &device_name,
+        options.unwrap_or_default(),
+        capture_filter.unwrap_or_default(),
+        protocol_filter,
+        app_handle,
+        binary_updates,
+        pcap_out_path,
+        bpf_filter,
+        promiscuous.unwrap_or(PROMISCUOUS_MODE),
+    )
 }
 
+/// Replays the packets of a PCAP or pcapng file, with the same options of `start_capture`.
+///
+/// The usual `traffic_update` events are emitted following the timestamps of the packets,
+/// with `offline_gap` events for the periods without packets;
+/// the update following the last packet has `is_final` set.
 #[tauri::command]
-fn stop_capture(state: State<NetworkMonitorState>) -> Result<(), String> {
+pub fn start_capture_from_file(
+    path: String,
+    options: Option<CaptureOptions>,
+    capture_filter: Option<CaptureFilter>,
+    binary_updates: Option<Channel>,
+    bpf_filter: Option<String>,
+    state: State<NetworkMonitorState>,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    state.start_capture_from_file(
+        &path,
+        options.unwrap_or_default(),
+        capture_filter.unwrap_or_default(),
+        app_handle,
+        binary_updates,
+        bpf_filter,
+    )
+}
+
+/// Replays the packets of several PCAP or pcapng files as a single capture,
+/// with the same options of `start_capture_from_file`.
+///
+/// Files are parsed one after the other, ordered by the timestamp of their first packet,
+/// and can have different link types; the periods between them produce `offline_gap` events.
+#[tauri::command]
+pub fn start_capture_from_files(
+    paths: Vec<String>,
+    options: Option<CaptureOptions>,
+    capture_filter: Option<CaptureFilter>,
+    binary_updates: Option<Channel>,
+    bpf_filter: Option<String>,
+    state: State<NetworkMonitorState>,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    state.start_capture_from_files(
+        &paths,
+        options.unwrap_or_default(),
+        capture_filter.unwrap_or_default(),
+        app_handle,
+        binary_updates,
+        bpf_filter,
+    )
+}
+
+/// Captures `n` packets on the given device satisfying the BPF filter (if any),
+/// and returns the connections they make up, sorted by the amount of bytes exchanged.
+///
+/// No event is emitted and hosts aren't resolved: this is meant for quick diagnostics,
+/// and can run alongside a capture. It returns what was captured after `timeout_secs`
+/// (10 by default) even if fewer packets were seen, so that it can't hang on a quiet device.
+#[tauri::command]
+pub async fn capture_n_packets(
+    interface: String,
+    n: usize,
+    filter: Option<String>,
+    timeout_secs: Option<u64>,
+    app_handle: AppHandle,
+) -> Result<Vec<ConnectionData>, String> {
+    let timeout = timeout_secs.map_or(CAPTURE_N_PACKETS_TIMEOUT, Duration::from_secs);
+    // the capture blocks until `n` packets or the timeout: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle.state::<NetworkMonitorState>().capture_n_packets(
+            &interface,
+            n,
+            filter.as_deref(),
+            timeout,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Loads the MaxMind databases used to resolve the country and ASN of the hosts,
+/// from the given paths or from the bundled ones if `null`.
+///
+/// Fails if a path isn't a valid MMDB file. Applies from the next capture.
+#[tauri::command]
+pub fn load_mmdb(
+    country_path: Option<String>,
+    asn_path: Option<String>,
+    state: State<NetworkMonitorState>,
+) -> Result<(), String> {
+    state.load_mmdb(country_path.as_deref(), asn_path.as_deref())
+}
+
+/// Returns the Autonomous System of an arbitrary IP address (e.g., typed in by the user),
+/// as found in the database loaded with `load_mmdb`.
+///
+/// Fails if `ip` isn't a valid IPv4 or IPv6 address;
+/// addresses not in the database get an empty code and name.
+#[tauri::command]
+pub fn asn_lookup(ip: String, state: State<NetworkMonitorState>) -> Result<Asn, String> {
+    state.asn_lookup(&ip)
+}
+
+/// Returns the country of an arbitrary IP address (e.g., copied from another tool)
+/// as its ISO 3166-1 alpha-2 code, as found in the database loaded with `load_mmdb`.
+///
+/// Fails if `ip` isn't a valid IPv4 or IPv6 address;
+/// addresses not in the database get the `ZZ` code, with `resolved` set to false.
+#[tauri::command]
+pub fn country_lookup(
+    ip: String,
+    state: State<NetworkMonitorState>,
+) -> Result<CountryLookup, String> {
+    state.country_lookup(&ip)
+}
+
+/// Returns why an arbitrary IP address is a bogon, i.e., the description of the reserved range
+/// it belongs to (e.g., "private-use", "carrier-grade NAT", or "ULA"), or `null` if it's public.
+///
+/// This tells why a captured address was flagged as bogon in the host table.
+/// Fails if `ip` isn't a valid IPv4 or IPv6 address.
+#[tauri::command]
+pub fn classify_bogon(ip: String) -> Result<Option<String>, String> {
+    let address = parse_ip_address(&ip)?;
+    Ok(is_bogon(&address).map(str::to_string))
+}
+
+#[tauri::command]
+pub fn stop_capture(state: State<NetworkMonitorState>) -> Result<(), String> {
     state.stop_capture()
 }
 
+/// Starts appending the connections that exchanged packets in each interval of `interval_secs`
+/// to the CSV file at `path`, as a continuous audit log (unlike a one-shot export).
+///
+/// Records are flushed at each interval; when the file would exceed `max_file_bytes`
+/// (50 MB if `null`), it's renamed with a `.1` suffix, replacing the previous one.
 #[tauri::command]
-fn get_traffic_data(state: State<NetworkMonitorState>) -> Result<TrafficData, String> {
-    state.get_traffic_data()
+pub fn start_csv_logging(
+    path: String,
+    interval_secs: u64,
+    max_file_bytes: Option<u64>,
+    state: State<NetworkMonitorState>,
+) -> Result<(), String> {
+    state.start_csv_logging(&path, interval_secs, max_file_bytes)
+}
+
+/// Stops the CSV logging, after appending the connections updated since the last interval.
+#[tauri::command]
+pub fn stop_csv_logging(state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.stop_csv_logging()
+}
+
+/// Writes the connections of the current capture to the CSV file at `path` (replacing it),
+/// one per row, with their addresses, ports, protocol, service, bytes, packets, direction,
+/// and first and last timestamps, to be opened in a spreadsheet.
+#[tauri::command]
+pub fn export_report_csv(path: String, state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.export_report_csv(&path)
+}
+
+/// Writes the whole traffic of the current capture to the JSON file at `path` (replacing it),
+/// pretty-printed: totals, connections, services, and hosts with their ASN and country,
+/// along with the timestamp of the first packet (`capture_started_at`)
+/// and the captured device or file (`interface_name`).
+#[tauri::command]
+pub fn export_report_json(path: String, state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.export_report_json(&path)
+}
+
+/// Restricts the events delivered to the calling window to the given ones
+/// (e.g., only `hosts_evicted` for a tray view), to spare the IPC of the others.
+///
+/// Windows that never subscribed receive all the events.
+#[tauri::command]
+pub fn subscribe_events(
+    events: Vec<EventKind>,
+    webview: Webview,
+    state: State<NetworkMonitorState>,
+) {
+    state.subscribe_events(webview.label(), events);
+}
+
+/// Delivers all the events to the calling window again.
+#[tauri::command]
+pub fn unsubscribe_events(webview: Webview, state: State<NetworkMonitorState>) {
+    state.unsubscribe_events(webview.label());
+}
+
+/// Pauses the running capture, to freeze the view of the traffic while inspecting it:
+/// live packets keep being read but are discarded, file replays are held,
+/// and no `traffic_update` event is emitted until `resume_capture` is called.
+#[tauri::command]
+pub fn pause_capture(state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.set_capture_paused(true)
+}
+
+/// Resumes a paused capture, which keeps accumulating on top of the traffic collected so far.
+#[tauri::command]
+pub fn resume_capture(state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.set_capture_paused(false)
+}
+
+/// Applies a new BPF filter to a running capture.
+///
+/// A filter that libpcap can't compile is applied in userspace if possible
+/// (emitting a `userspace_filtering` event); otherwise an error is returned
+/// and the previous filter stays in place.
+#[tauri::command]
+pub fn update_filter(
+    cap_id: usize,
+    filter: String,
+    state: State<NetworkMonitorState>,
+) -> Result<(), String> {
+    state.update_filter(cap_id, &filter)
+}
+
+/// Pauses or resumes the rDNS resolution of new addresses without stopping the capture;
+/// while paused, new hosts are named after their IP address.
+#[tauri::command]
+pub fn set_rdns_enabled(enabled: bool, state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.set_rdns_enabled(enabled)
+}
+
+/// Focuses the current capture on a single host: from the next interval, `traffic_update` events
+/// also carry the connections involving `address` (in `focused_connections`), still in real time.
+#[tauri::command]
+pub fn focus_host(address: IpAddr, state: State<NetworkMonitorState>) {
+    state.set_focused_host(Some(address));
+}
+
+/// Stops streaming the connections of the focused host with `traffic_update` events.
+#[tauri::command]
+pub fn clear_focus(state: State<NetworkMonitorState>) {
+    state.set_focused_host(None);
+}
+
+/// Marks a host as favorite: its data is flagged with `is_favorite`, in the running capture
+/// and in the next ones, until the app is closed.
+#[tauri::command]
+pub fn add_favorite_host(host: Host, state: State<NetworkMonitorState>) {
+    state.set_favorite_host(host, true);
+}
+
+/// Unmarks a host previously marked as favorite with `add_favorite_host`.
+#[tauri::command]
+pub fn remove_favorite_host(host: Host, state: State<NetworkMonitorState>) {
+    state.set_favorite_host(host, false);
+}
+
+/// Returns the favorite hosts with the data exchanged with them by the current capture,
+/// sorted by the amount of bytes exchanged.
+#[tauri::command]
+pub fn get_favorite_hosts(state: State<NetworkMonitorState>) -> Vec<HostData> {
+    state.get_favorite_hosts()
+}
+
+/// Configures how much traffic data captures keep: connections and hosts inactive for longer than
+/// `max_age_secs` are forgotten, as well as the least recently active ones exceeding `max_hosts`
+/// and `max_connections` (`null` disables a limit).
+/// Evicted hosts are aggregated into the "other hosts" entry.
+///
+/// The policy is applied at each interval, to the running capture too.
+#[tauri::command]
+pub fn set_retention(
+    max_age_secs: Option<u64>,
+    max_hosts: Option<usize>,
+    max_connections: Option<usize>,
+    state: State<NetworkMonitorState>,
+) {
+    state.set_retention(RetentionPolicy {
+        max_age_secs,
+        max_hosts,
+        max_connections,
+    });
+}
+
+/// Returns the retention policy currently configured (see `set_retention`).
+#[tauri::command]
+pub fn get_retention(state: State<NetworkMonitorState>) -> RetentionPolicy {
+    state.get_retention()
+}
+
+/// Acknowledges the latest `traffic_update` of the capture; when the `coalesce_updates` option
+/// is enabled, the next update is only sent after this call (merging the ones produced meanwhile).
+#[tauri::command]
+pub fn ack_traffic_update(cap_id: usize, state: State<NetworkMonitorState>) -> Result<(), String> {
+    state.ack_traffic_update(cap_id)
+}
+
+/// Returns the data exchanged in each second of the capture (incoming and outgoing bytes,
+/// and packets), oldest first, up to the `history_window` set in the capture options:
+/// a consistent source for the throughput graphs, even if some `traffic_update` was missed.
+#[tauri::command]
+pub fn get_traffic_history(
+    cap_id: usize,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<TrafficSample>, String> {
+    state.get_traffic_history(cap_id)
+}
+
+/// Returns the state of the running capture (`null` if none is running),
+/// including whether the OS really put the device in promiscuous mode.
+#[tauri::command]
+pub fn get_capture_status(state: State<NetworkMonitorState>) -> Option<CaptureStatus> {
+    state.get_capture_status()
+}
+
+/// Returns the snapshot length of the running capture (the maximum number of bytes stored
+/// per packet) and the number of packets it cut, telling whether it's too small to inspect
+/// the payloads.
+///
+/// It also compares the bytes actually analyzed with the ones that crossed the interface,
+/// estimated accounting for the dropped packets: under load, the statistics underestimate
+/// the real traffic.
+#[tauri::command]
+pub fn get_session_info(state: State<NetworkMonitorState>) -> Result<SessionInfo, String> {
+    state.get_session_info()
+}
+
+/// Returns the packets received and dropped by the capture handle, refreshed every second,
+/// with the time elapsed since the capture started.
+///
+/// Gaps in the traffic data come with `dropped` packets if the capture buffer was full
+/// (i.e., packets arrived faster than they could be parsed),
+/// or with `if_dropped` packets if they were lost by the interface itself.
+#[tauri::command]
+pub fn get_capture_stats(
+    cap_id: usize,
+    state: State<NetworkMonitorState>,
+) -> Result<CaptureStats, String> {
+    state.get_capture_stats(cap_id)
+}
+
+/// Returns the whole traffic observed by the current capture:
+/// its totals, together with the connections, services, and hosts it involves.
+#[tauri::command]
+pub fn get_traffic_data(state: State<NetworkMonitorState>) -> Result<InfoTrafficData, String> {
+    state.get_traffic_data()
+}
+
+/// Returns the traffic observed since the previous call and resets it in one operation,
+/// so that pollers draining the counters never miss or double-count a packet.
+///
+/// Only the counters drained by this command are reset: the traffic returned by the other
+/// commands (e.g., `get_traffic_data`) keeps accumulating.
+#[tauri::command]
+pub fn take_traffic_snapshot(state: State<NetworkMonitorState>) -> Result<TrafficSnapshot, String> {
+    state.take_traffic_snapshot()
+}
+
+/// Returns the packets dropped since the previous call (or since the start of the capture),
+/// so that drops happening now can be told from the ones that happened earlier.
+///
+/// `kernel_dropped` counts the packets dropped by the capture because they weren't read fast
+/// enough, while `interface_dropped` counts the ones dropped by the network interface or its driver
+/// (not tracked by all platforms).
+#[tauri::command]
+pub fn take_drop_delta(state: State<NetworkMonitorState>) -> DropDelta {
+    state.take_drop_delta()
+}
+
+/// Takes a baseline of the connections observed so far in the current capture
+/// (replacing the previous one), returning their number.
+#[tauri::command]
+pub fn set_baseline(state: State<NetworkMonitorState>) -> usize {
+    state.set_baseline()
+}
+
+/// Returns the connections first observed after the baseline was taken with `set_baseline`,
+/// in order of their first packet, e.g., to tell what an application connected to
+/// after an action in it.
+///
+/// Fails if no baseline was taken in the current capture.
+#[tauri::command]
+pub fn get_new_flows_since_baseline(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ConnectionData>, String> {
+    state.get_new_flows_since_baseline()
+}
+
+/// Returns the connections that exchanged packets, but not in the last `idle_secs` seconds
+/// (e.g., keep-alives and stalled transfers).
+#[tauri::command]
+pub fn get_idle_connections(
+    idle_secs: u64,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ConnectionData>, String> {
+    state.get_idle_connections(idle_secs)
+}
+
+/// Returns the full record of the connection with the given endpoints and protocol
+/// (e.g., `tcp`), including its MAC addresses, ICMP/ARP breakdowns, and timestamps,
+/// or `None` if it wasn't captured.
+///
+/// Ports are omitted for the protocols without them (ICMP and ARP).
+#[tauri::command]
+pub fn get_connection_details(
+    source: String,
+    sport: Option<u16>,
+    dest: String,
+    dport: Option<u16>,
+    protocol: String,
+    state: State<NetworkMonitorState>,
+) -> Result<Option<ConnectionData>, String> {
+    state.get_connection_details(&source, sport, &dest, dport, &protocol)
+}
+
+/// Returns the min/avg/max size of the packets exchanged by each connection.
+#[tauri::command]
+pub fn get_packet_sizes(state: State<NetworkMonitorState>) -> Result<Vec<PacketSizeData>, String> {
+    state.get_packet_sizes()
+}
+
+/// Returns the round-trip time of the TCP connections whose handshake was captured,
+/// estimated from the delay between the SYN and the SYN-ACK (the slowest first).
+#[tauri::command]
+pub fn get_connection_rtts(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ConnectionRttData>, String> {
+    state.get_connection_rtts()
+}
+
+/// Classifies each connection as background (large packets at a steady pace, e.g., downloads)
+/// or interactive (small packets in bursts, e.g., SSH sessions),
+/// based on the size of its packets, the interval between them, and its burstiness.
+#[tauri::command]
+pub fn get_connection_profiles(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ConnectionProfileData>, String> {
+    state.get_connection_profiles()
+}
+
+/// Returns the TCP flows only observed in one direction (e.g., SYNs never answered),
+/// sorted by the bytes exchanged: many of them hint at asymmetric routing,
+/// or at a capture point missing the return traffic.
+#[tauri::command]
+pub fn get_asymmetric_flows(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<AsymmetricFlowData>, String> {
+    state.get_asymmetric_flows()
+}
+
+/// Returns the hex dump of the first packets whose headers couldn't be parsed
+/// (only recorded when the `sample_parse_failures` option is enabled).
+#[tauri::command]
+pub fn get_parse_failure_samples(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ParseFailureSample>, String> {
+    state.get_parse_failure_samples()
+}
+
+/// Returns the hosts that exchanged packets in the `since_secs` seconds before the latest packet,
+/// the most recently active first.
+#[tauri::command]
+pub fn get_recent_hosts(
+    since_secs: u64,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<RecentHostData>, String> {
+    state.get_recent_hosts(since_secs)
+}
+
+/// Returns the first `n` hosts sorted by the data exchanged with them in the given representation
+/// (e.g., the top talkers by bytes, with `Descending` and `Bytes`),
+/// without transferring the whole list of hosts.
+#[tauri::command]
+pub fn get_top_hosts(
+    n: usize,
+    sort_type: SortType,
+    data_repr: DataRepr,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<HostData>, String> {
+    state.get_top_hosts(n, sort_type, data_repr)
+}
+
+/// Returns the first `n` services sorted by the data exchanged through them
+/// in the given representation (e.g., to feed a protocol breakdown chart),
+/// leaving out the traffic without a service.
+#[tauri::command]
+pub fn get_top_services(
+    n: usize,
+    sort_type: SortType,
+    data_repr: DataRepr,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ServiceData>, String> {
+    state.get_top_services(n, sort_type, data_repr)
+}
+
+/// Returns the `n` remote ports this host exchanged the most data with in the given representation
+/// (e.g., 443 and 53 for a host mostly browsing the web), aggregated across hosts and protocols,
+/// with the associated service; the traffic without ports (ICMP and ARP) is left out.
+#[tauri::command]
+pub fn get_top_ports(
+    n: usize,
+    data_repr: DataRepr,
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<PortData>, String> {
+    state.get_top_ports(n, data_repr)
+}
+
+/// Returns only the connections with public hosts on the internet,
+/// leaving out the ones with bogon addresses (e.g., private or multicast) or in the local network.
+#[tauri::command]
+pub fn get_external_connections(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ConnectionData>, String> {
+    state.get_external_connections()
+}
+
+/// Returns the traffic forwarded by the default gateway and the one delivered directly
+/// to other devices of the local network, telling internet-bound traffic from LAN traffic
+/// at the link layer.
+///
+/// The gateway is the MAC address most of the remote hosts outside the local network
+/// are reached through.
+#[tauri::command]
+pub fn get_gateway_traffic(state: State<NetworkMonitorState>) -> Result<GatewayTraffic, String> {
+    state.get_gateway_traffic()
+}
+
+/// Returns the local TCP ports services are listening on, with the inferred service names.
+///
+/// A port is considered listening when it received a connection request (SYN)
+/// or accepted one (SYN-ACK) during the capture.
+#[tauri::command]
+pub fn get_local_listening_ports(state: State<NetworkMonitorState>) -> Vec<ListeningPortData> {
+    state.get_local_listening_ports()
+}
+
+/// Returns the bytes, packets, and distinct hosts of the traffic whose remote endpoint
+/// belongs to the given CIDR block (e.g., "2600:1400::/32" to measure the traffic with a CDN).
+#[tauri::command]
+pub fn get_stats_for_cidr(
+    cidr: String,
+    state: State<NetworkMonitorState>,
+) -> Result<CidrStats, String> {
+    state.get_stats_for_cidr(&cidr)
+}
+
+/// Returns the distinct addresses of this host seen in the captured connections,
+/// which can be more than the ones configured on the device (e.g., aliases on multi-homed hosts).
+#[tauri::command]
+pub fn get_observed_local_addresses(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<IpAddr>, String> {
+    state.get_observed_local_addresses()
+}
+
+/// Returns, for each host, the ratio between the bytes received from and sent to it,
+/// flagging the strongly asymmetric ones (e.g., pure uploads could hint at data exfiltration).
+#[tauri::command]
+pub fn get_directionality(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<HostDirectionality>, String> {
+    state.get_directionality()
+}
+
+/// Returns the loopback, local, bogon, and traffic type flags of every host in one call.
+#[tauri::command]
+pub fn get_host_classifications(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<HostClassification>, String> {
+    state.get_host_classifications()
+}
+
+/// Returns the connections, packets, bytes, and average packet size of each application protocol
+/// (e.g., HTTP or TLS).
+///
+/// Protocols are detected from the payloads when `inspect_payloads` is enabled,
+/// otherwise they're inferred from the service associated to the ports.
+#[tauri::command]
+pub fn get_app_protocol_breakdown(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<AppProtocolData>, String> {
+    state.get_app_protocol_breakdown()
+}
+
+/// Returns the bytes and packets exchanged by each local process ("which app is using my
+/// bandwidth"), the most active first.
+///
+/// Processes are only known in live captures with `label_processes` enabled, on platforms
+/// exposing their socket table (Linux): the rest of the traffic, including the one exchanged
+/// after a process exited, is attributed to "unknown".
+#[tauri::command]
+pub fn get_traffic_by_process(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<ProcessTrafficData>, String> {
+    state.get_traffic_by_process()
+}
+
+/// Returns the traffic of each QoS class, after the DSCP value of the IP header
+/// (e.g., "EF" for expedited forwarding, "AF41", "CS5", or "BE" for best effort),
+/// sorted by the amount of bytes exchanged.
+///
+/// Values not assigned to a standard class are reported with a `null` class.
+#[tauri::command]
+pub fn get_qos_breakdown(state: State<NetworkMonitorState>) -> Result<Vec<QosClassData>, String> {
+    state.get_qos_breakdown()
+}
+
+/// Returns the 50th, 90th, 95th, and 99th percentiles of the bytes exchanged per second
+/// since the start of the capture, which tell bursty traffic from steady traffic
+/// better than the average does.
+#[tauri::command]
+pub fn get_bandwidth_percentiles(
+    state: State<NetworkMonitorState>,
+) -> Result<BandwidthPercentiles, String> {
+    state.get_bandwidth_percentiles()
+}
+
+/// Returns the packets and bytes exchanged in each hour of the day (UTC, by packet timestamps),
+/// summed over all the days of the capture, to reveal daily patterns in long offline captures
+/// (e.g., nightly backups).
+#[tauri::command]
+pub fn get_hourly_profile(
+    state: State<NetworkMonitorState>,
+) -> Result<Vec<HourlyTrafficData>, String> {
+    state.get_hourly_profile()
+}
+
+/// Estimates the cost of the traffic exchanged so far, given the price per (decimal) gigabyte
+/// of a metered connection.
+#[tauri::command]
+pub fn estimate_data_cost(
+    price_per_gb: f64,
+    state: State<NetworkMonitorState>,
+) -> Result<DataEstimate, String> {
+    state.estimate_data_cost(price_per_gb)
+}
+
+/// Estimates the carbon footprint of the traffic exchanged so far,
+/// given the grams of CO2 emitted per (decimal) gigabyte transferred.
+#[tauri::command]
+pub fn estimate_data_footprint(
+    grams_co2_per_gb: f64,
+    state: State<NetworkMonitorState>,
+) -> Result<DataEstimate, String> {
+    state.estimate_data_footprint(grams_co2_per_gb)
+}
+
+/// Returns a one-line summary of the current capture for OS notifications or tray tooltips,
+/// e.g., "Up 2.3 MB / Down 45 MB to 37 hosts in 5 countries", in `language` (English by default).
+///
+/// Amounts have one decimal if lower than 10 of their multiple, unless a fixed `precision`
+/// is requested (e.g., `{"Fixed": 2}` for "Up 2.30 MB / Down 45.00 MB").
+#[tauri::command]
+pub fn get_notification_summary(
+    language: Option<Language>,
+    precision: Option<Precision>,
+    state: State<NetworkMonitorState>,
+) -> Result<String, String> {
+    state.get_notification_summary(language.unwrap_or_default(), precision.unwrap_or_default())
+}
+
+/// Returns the sizes of the resolution, connection, host, and service maps of the current capture,
+/// with a bounded sample of the addresses being resolved (useful for support bundles).
+#[tauri::command]
+pub fn debug_dump_state(state: State<NetworkMonitorState>) -> DebugStateDump {
+    state.debug_dump_state()
+}
+
+/// Returns how many hosts of the running capture were resolved to a domain
+/// rather than named after their address, and the average time taken to resolve them.
+#[tauri::command]
+pub fn get_resolution_stats(state: State<NetworkMonitorState>) -> Result<ResolutionStats, String> {
+    state.get_resolution_stats()
+}
+
+/// Returns the number of entries of the services map generated at build time,
+/// with a few sample lookups (useful to check that the map was loaded).
+#[tauri::command]
+pub fn get_services_map_info() -> ServicesMapInfo {
+    crate::networking::manage_packets::get_services_map_info()
+}
+
+/// Returns the service associated with a port (e.g., `https` for 443 over TCP),
+/// to label ports outside of a capture, such as those of a manually entered connection.
+///
+/// `protocol` is either `tcp` or `udp` (case-insensitive): services don't apply to other protocols
+/// (`-`), while unknown ports get `?`.
+#[tauri::command]
+pub fn service_lookup(port: u16, protocol: String) -> Service {
+    crate::networking::manage_packets::lookup_service(port, &protocol)
+}
+
+/// Returns which capture features are available on this platform (e.g., whether libpcap or Npcap
+/// is installed, and whether this process is allowed to capture),
+/// so that the UI can disable unsupported options up front.
+#[tauri::command]
+pub fn get_platform_capabilities() -> PlatformCapabilities {
+    PlatformCapabilities::detect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use etherparse::{IpHeaders, Ipv4Dscp, Ipv4Header, PacketBuilder};
+    use pcap::{Capture, Linktype, Packet, PacketHeader};
+
+    use super::*;
+    use crate::countries::types::country::Country;
+    use crate::networking::parse_packets::{
+        AddressesResolutionState, BackendTrafficMessage, CaptureControlMessage, parse_packets,
+    };
+    use crate::networking::parse_tls::client_hello_for_tests;
+    use crate::networking::types::capture_context::NANOSECOND_TIMESTAMPS_SUPPORTED;
+    use crate::networking::types::data_representation::DataRepr;
+    use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+    use crate::networking::types::info_traffic::InfoTraffic;
+    use crate::utils::temp_files::temp_path;
+    use crate::utils::types::timestamp::Timestamp;
+
+    /// Writes a capture file with the given packets (timestamp in seconds and data),
+    /// cut at `snaplen` bytes if set
+    fn write_packets(
+        file_name: &str,
+        link_type: Linktype,
+        packets: impl IntoIterator<Item = (i64, Vec<u8>)>,
+        snaplen: Option<usize>,
+    ) -> String {
+        let path = temp_path(file_name);
+        let mut savefile = Capture::dead(link_type).unwrap().savefile(&path).unwrap();
+        for (secs, data) in packets {
+            let caplen = snaplen.map_or(data.len(), |snaplen| snaplen.min(data.len()));
+            let header = PacketHeader {
+                ts: libc::timeval {
+                    tv_sec: secs,
+                    tv_usec: 0,
+                },
+                caplen: u32::try_from(caplen).unwrap(),
+                len: u32::try_from(data.len()).unwrap(),
+            };
+            savefile.write(&Packet::new(&header, &data[..caplen]));
+        }
+        savefile.flush().unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// Writes a capture file with a UDP packet per timestamp, framed according to the link type
+    fn write_capture_file(file_name: &str, link_type: Linktype, timestamps: &[i64]) -> String {
+        let packets = timestamps.iter().map(|&secs| {
+            let builder = if link_type == Linktype::ETHERNET {
+                PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+                    .ipv4([127, 0, 0, 1], [127, 0, 0, 1], 64)
+                    .udp(50000, 53)
+            } else {
+                PacketBuilder::ipv4([127, 0, 0, 1], [127, 0, 0, 1], 64).udp(50000, 53)
+            };
+            let mut data = Vec::new();
+            builder.write(&mut data, &[0; 10]).unwrap();
+            (secs, data)
+        });
+        write_packets(file_name, link_type, packets, None)
+    }
+
+    /// Parses a capture file to the end, returning the messages sent to the capture thread
+    fn run_offline_capture(path: &str, options: CaptureOptions) -> Vec<BackendTrafficMessage> {
+        let (cs, capture_context) = open_capture_file(path).unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (_control_tx, control_rx) = async_channel::unbounded();
+        parse_packets(
+            0,
+            cs,
+            &MmdbReaders::default(),
+            capture_context,
+            VecDeque::new(),
+            options,
+            CaptureFilter::default(),
+            &Arc::new(Mutex::new(AddressesResolutionState::default())),
+            &tx,
+            &control_rx,
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+        );
+        let mut messages = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            messages.push(msg);
+        }
+        messages
+    }
+
+    #[test]
+    fn test_open_capture_file() {
+        let missing = temp_path("tickertinker_missing.pcap");
+        let err = open_capture_file(missing.to_str().unwrap()).err().unwrap();
+        assert!(err.starts_with("Capture file not found"));
+
+        let malformed = temp_path("tickertinker_malformed.pcap");
+        std::fs::write(&malformed, b"definitely not a capture file").unwrap();
+        let err = open_capture_file(malformed.to_str().unwrap())
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Invalid capture file"));
+
+        let valid = temp_path("tickertinker_valid.pcap");
+        let mut savefile = Capture::dead(Linktype::ETHERNET)
+            .unwrap()
+            .savefile(&valid)
+            .unwrap();
+        savefile.flush().unwrap();
+        drop(savefile);
+        let (cs, mut capture_context) = open_capture_file(valid.to_str().unwrap()).unwrap();
+        assert!(matches!(cs, CaptureSource::File(_)));
+        assert_eq!(cs.get_name(), valid.to_str().unwrap());
+
+        let userspace_bpf = apply_bpf_filter(&mut capture_context, Some("tcp port 443")).unwrap();
+        assert!(userspace_bpf.is_none());
+        let err = apply_bpf_filter(&mut capture_context, Some("tcp port nope"))
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Invalid BPF filter \"tcp port nope\""));
+    }
+
+    #[test]
+    fn test_invalid_snaplen() {
+        // validated before opening the source
+        let cs = CaptureSource::File(MyPcapImport::new("tickertinker_missing.pcap".to_string()));
+        for snaplen in [0, -1] {
+            let capture_context =
+                CaptureContext::new(&cs, None, None, false, false, None, Some(snaplen), false);
+            assert_eq!(
+                capture_context.error(),
+                Some(format!("Invalid snaplen {snaplen}: it must be positive").as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn test_nanosecond_timestamps_of_devices() {
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(Device::from("lo")));
+        assert_eq!(cs.timestamp_precision(), TimestampPrecision::Micro);
+        cs.set_timestamp_precision(TimestampPrecision::Nano);
+        assert_eq!(cs.timestamp_precision(), TimestampPrecision::Nano);
+        if !NANOSECOND_TIMESTAMPS_SUPPORTED {
+            // rejected before opening the device
+            let capture_context =
+                CaptureContext::new(&cs, None, None, false, false, None, None, false);
+            assert_eq!(
+                capture_context.error(),
+                Some("Device lo doesn't support nanosecond timestamps")
+            );
+        }
+
+        // files keep the precision of their header
+        let mut cs =
+            CaptureSource::File(MyPcapImport::new("tickertinker_missing.pcap".to_string()));
+        cs.set_timestamp_precision(TimestampPrecision::Nano);
+        assert_eq!(cs.timestamp_precision(), TimestampPrecision::Micro);
+    }
+
+    #[test]
+    fn test_invalid_history_window() {
+        for history_window in [0, MAX_HISTORY_WINDOW + 1, usize::MAX] {
+            let options = CaptureOptions {
+                history_window: Some(history_window),
+                ..CaptureOptions::default()
+            };
+            let err = validate_capture_options(&options).unwrap_err();
+            assert!(err.starts_with(&format!("Invalid history window {history_window}")));
+        }
+        let options = CaptureOptions {
+            history_window: Some(MAX_HISTORY_WINDOW),
+            ..CaptureOptions::default()
+        };
+        assert_eq!(validate_capture_options(&options), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_savefile_queue_size() {
+        for queue_size in [0, MAX_SAVEFILE_QUEUE_SIZE + 1] {
+            let options = CaptureOptions {
+                savefile_queue_size: Some(queue_size),
+                ..CaptureOptions::default()
+            };
+            let err = validate_capture_options(&options).unwrap_err();
+            assert!(err.starts_with(&format!("Invalid savefile queue size {queue_size}")));
+        }
+        let options = CaptureOptions {
+            savefile_queue_size: Some(MAX_SAVEFILE_QUEUE_SIZE),
+            ..CaptureOptions::default()
+        };
+        assert_eq!(validate_capture_options(&options), Ok(()));
+    }
+
+    #[test]
+    fn test_merge_capture_files() {
+        let later = write_capture_file(
+            "tickertinker_merge_later.pcap",
+            Linktype::ETHERNET,
+            &[1_000_010, 1_000_011],
+        );
+        let earlier = write_capture_file(
+            "tickertinker_merge_earlier.pcap",
+            Linktype::IPV4,
+            &[1_000_000],
+        );
+
+        let (mut files, userspace_bpf) =
+            open_capture_files(&[later.clone(), earlier.clone()], None).unwrap();
+        assert!(userspace_bpf.is_none());
+        let names: Vec<_> = files.iter().map(|(cs, _)| cs.get_name()).collect();
+        assert_eq!(names, vec![earlier, later]);
+
+        let (cs, capture_context) = files.pop_front().unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (_control_tx, control_rx) = async_channel::unbounded();
+        parse_packets(
+            0,
+            cs,
+            &MmdbReaders::default(),
+            capture_context,
+            files,
+            CaptureOptions::default(),
+            CaptureFilter::default(),
+            &Arc::new(Mutex::new(AddressesResolutionState::default())),
+            &tx,
+            &control_rx,
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+        );
+
+        let mut total_packets = 0;
+        let mut gaps = Vec::new();
+        let mut final_ticks = 0;
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                BackendTrafficMessage::TickRun(_, info_traffic, _, is_final) => {
+                    total_packets += info_traffic.tot_data_info.tot_data(DataRepr::Packets);
+                    final_ticks += usize::from(is_final);
+                }
+                BackendTrafficMessage::OfflineGap(_, gap_secs) => gaps.push(gap_secs),
+                _ => {}
+            }
+        }
+        // packets of both files are accounted, regardless of their link type
+        assert_eq!(total_packets, 3);
+        // the period between the files is reported as a gap, and only the last one ends the capture
+        assert_eq!(gaps, vec![9]);
+        assert_eq!(final_ticks, 1);
+    }
+
+    #[test]
+    fn test_paused_file_replay_is_held() {
+        let path = write_capture_file("tickertinker_paused.pcap", Linktype::ETHERNET, &[1_000_000]);
+        let (cs, capture_context) = open_capture_file(&path).unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (_control_tx, control_rx) = async_channel::unbounded();
+        let pause_signal = Arc::new(AtomicBool::new(true));
+        let pause_signal_2 = pause_signal.clone();
+        let handle = std::thread::spawn(move || {
+            parse_packets(
+                0,
+                cs,
+                &MmdbReaders::default(),
+                capture_context,
+                VecDeque::new(),
+                CaptureOptions::default(),
+                CaptureFilter::default(),
+                &Arc::new(Mutex::new(AddressesResolutionState::default())),
+                &tx,
+                &control_rx,
+                &AtomicBool::new(false),
+                &pause_signal_2,
+            );
+        });
+
+        // nothing is parsed while paused
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert!(rx.is_empty());
+        assert!(!handle.is_finished());
+
+        pause_signal.store(false, std::sync::atomic::Ordering::Relaxed);
+        handle.join().unwrap();
+        let mut total_packets = 0;
+        while let Ok(msg) = rx.try_recv() {
+            if let BackendTrafficMessage::TickRun(_, info_traffic, _, _) = msg {
+                total_packets += info_traffic.tot_data_info.tot_data(DataRepr::Packets);
+            }
+        }
+        assert_eq!(total_packets, 1);
+    }
+
+    #[test]
+    fn test_hourly_profile_of_offline_capture() {
+        // 2023-11-14 02:00:00 UTC
+        let two_am = 1_699_927_200;
+        let path = write_capture_file(
+            "tickertinker_hourly_profile.pcap",
+            Linktype::ETHERNET,
+            &[10, 11, 3_599, 12 * 3_600].map(|secs| two_am + secs),
+        );
+
+        let mut analyzer = TrafficAnalyzer::default();
+        for msg in run_offline_capture(&path, CaptureOptions::default()) {
+            match msg {
+                BackendTrafficMessage::TickRun(_, info_traffic, new_hosts, _) => {
+                    analyzer.refresh(info_traffic, new_hosts);
+                }
+                BackendTrafficMessage::OfflineGap(_, gap_secs) => {
+                    analyzer.add_idle_seconds(gap_secs);
+                }
+                _ => {}
+            }
+        }
+
+        let profile = analyzer.get_hourly_profile();
+        assert_eq!(profile.len(), 24);
+        let packet_bytes = profile[2].bytes / 3;
+        assert!(packet_bytes > 0);
+        for data in &profile {
+            let expected_packets = match data.hour {
+                2 => 3,
+                14 => 1,
+                _ => 0,
+            };
+            assert_eq!(data.packets, expected_packets);
+            assert_eq!(data.bytes, expected_packets * packet_bytes);
+        }
+    }
+
+    /// Writes two DNS packets (UDP port 53) around an HTTPS one (TCP port 443)
+    fn write_dns_and_https_packets(file_name: &str) -> String {
+        let dns_packets = [(1_000_000, true), (1_000_000, false), (1_000_001, true)];
+        let packets = dns_packets.map(|(secs, dns)| {
+            let ethernet = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]);
+            let ipv4 = ethernet.ipv4([127, 0, 0, 1], [127, 0, 0, 1], 64);
+            let mut data = Vec::new();
+            let written = if dns {
+                ipv4.udp(50000, 53).write(&mut data, &[0; 10])
+            } else {
+                ipv4.tcp(50000, 443, 1, 1024).write(&mut data, &[0; 10])
+            };
+            written.unwrap();
+            (secs, data)
+        });
+        write_packets(file_name, Linktype::ETHERNET, packets, None)
+    }
+
+    #[test]
+    fn test_userspace_bpf_fallback_filters_packets() {
+        let path = write_dns_and_https_packets("tickertinker_userspace_bpf.pcap");
+
+        // filters that can't be translated keep the compilation error
+        let err = userspace_bpf("udp or tcp", "BPF unavailable".to_string())
+            .err()
+            .unwrap();
+        assert_eq!(err, "BPF unavailable");
+
+        // force the fallback, as if libpcap couldn't compile the filter
+        let userspace_bpf = userspace_bpf("udp port 53", "BPF unavailable".to_string()).unwrap();
+        assert_eq!(userspace_bpf.bpf, "udp port 53");
+        assert_eq!(userspace_bpf.reason, "BPF unavailable");
+        let capture_filter = CaptureFilter::default().with_userspace_bpf(userspace_bpf.filter);
+
+        let (cs, capture_context) = open_capture_file(&path).unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (_control_tx, control_rx) = async_channel::unbounded();
+        parse_packets(
+            0,
+            cs,
+            &MmdbReaders::default(),
+            capture_context,
+            VecDeque::new(),
+            CaptureOptions::default(),
+            capture_filter,
+            &Arc::new(Mutex::new(AddressesResolutionState::default())),
+            &tx,
+            &control_rx,
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+        );
+
+        let mut info_traffic = InfoTraffic::default();
+        while let Ok(msg) = rx.try_recv() {
+            if let BackendTrafficMessage::TickRun(_, mut msg_traffic, _, _) = msg {
+                info_traffic.refresh(&mut msg_traffic);
+            }
+        }
+        // the TCP packet was filtered out in userspace
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 2);
+        assert_eq!(info_traffic.map.len(), 1);
+        assert!(info_traffic.map.keys().all(|key| key.port2 == Some(53)));
+    }
+
+    #[test]
+    fn test_update_filter_replaces_userspace_bpf() {
+        let path = write_dns_and_https_packets("tickertinker_update_userspace_bpf.pcap");
+        let userspace_bpf = userspace_bpf("udp port 53", "BPF unavailable".to_string()).unwrap();
+        let capture_filter = CaptureFilter::default().with_userspace_bpf(userspace_bpf.filter);
+
+        // the running capture is asked to replace its filter before reading any packet
+        let (cs, capture_context) = open_capture_file(&path).unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (control_tx, control_rx) = async_channel::unbounded();
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        control_tx
+            .send_blocking(CaptureControlMessage::UpdateFilter(
+                "tcp port 443".to_string(),
+                reply_tx,
+            ))
+            .unwrap();
+        parse_packets(
+            0,
+            cs,
+            &MmdbReaders::default(),
+            capture_context,
+            VecDeque::new(),
+            CaptureOptions::default(),
+            capture_filter,
+            &Arc::new(Mutex::new(AddressesResolutionState::default())),
+            &tx,
+            &control_rx,
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+        );
+        assert_eq!(reply_rx.recv().unwrap(), Ok(None));
+
+        let mut info_traffic = InfoTraffic::default();
+        while let Ok(msg) = rx.try_recv() {
+            if let BackendTrafficMessage::TickRun(_, mut msg_traffic, _, _) = msg {
+                info_traffic.refresh(&mut msg_traffic);
+            }
+        }
+        // the new filter is compiled by libpcap, and the one in userspace doesn't apply anymore
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 1);
+        assert_eq!(info_traffic.map.len(), 1);
+        assert!(info_traffic.map.keys().all(|key| key.port2 == Some(443)));
+    }
+
+    #[test]
+    fn test_local_listening_ports_from_inbound_syn() {
+        let (local, client, server) = ([192, 168, 1, 10], [203, 0, 113, 5], [8, 8, 8, 8]);
+        // inbound SYN to port 22 with its SYN-ACK, and an outbound SYN to a remote port 443
+        let segments = [
+            (client, 51000, local, 22, false),
+            (local, 22, client, 51000, true),
+            (local, 50000, server, 443, false),
+        ];
+        let packets = segments.map(|(source, source_port, destination, dest_port, ack)| {
+            let ethernet = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]);
+            let tcp = ethernet
+                .ipv4(source, destination, 64)
+                .tcp(source_port, dest_port, 1, 1024)
+                .syn();
+            let tcp = if ack { tcp.ack(2) } else { tcp };
+            let mut data = Vec::new();
+            tcp.write(&mut data, &[]).unwrap();
+            (1_000_000, data)
+        });
+        let path = write_packets(
+            "tickertinker_listening_ports.pcap",
+            Linktype::ETHERNET,
+            packets,
+            None,
+        );
+
+        let mut analyzer = TrafficAnalyzer::default();
+        for msg in run_offline_capture(&path, CaptureOptions::default()) {
+            if let BackendTrafficMessage::TickRun(_, msg_traffic, _, _) = msg {
+                analyzer.refresh(msg_traffic, Vec::new());
+            }
+        }
+        // the outbound SYN doesn't make its source port a listening one
+        let listening_ports = analyzer.get_local_listening_ports();
+        assert_eq!(listening_ports.len(), 1);
+        let listening_port = &listening_ports[0];
+        assert_eq!(listening_port.address, IpAddr::from(local));
+        assert_eq!(listening_port.port, 22);
+        assert_eq!(listening_port.service, Service::Name("ssh"));
+        assert_eq!(listening_port.connections, 1);
+        assert_eq!(listening_port.clients, 1);
+    }
+
+    #[test]
+    fn test_tcp_handshake_only_skips_data_packets() {
+        let (client, server) = ([192, 168, 1, 2], [93, 184, 216, 34]);
+        let ethernet = || PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]);
+        let tcp = |source, destination, source_port, destination_port| {
+            ethernet()
+                .ipv4(source, destination, 64)
+                .tcp(source_port, destination_port, 1, 1024)
+        };
+        // a TCP connection opened, used to send data, and closed, next to a DNS query
+        let segments = [
+            tcp(client, server, 50000, 443).syn(),
+            tcp(server, client, 443, 50000).syn().ack(2),
+            tcp(client, server, 50000, 443).ack(2).psh(),
+            tcp(client, server, 50000, 443).ack(2).fin(),
+        ];
+        let mut packets: Vec<_> = segments
+            .into_iter()
+            .map(|segment| {
+                let mut data = Vec::new();
+                segment.write(&mut data, &[0; 100]).unwrap();
+                (1_000_000, data)
+            })
+            .collect();
+        let mut data = Vec::new();
+        ethernet()
+            .ipv4(client, [8, 8, 8, 8], 64)
+            .udp(50001, 53)
+            .write(&mut data, &[0; 10])
+            .unwrap();
+        packets.push((1_000_000, data));
+        let path = write_packets(
+            "tickertinker_tcp_handshake_only.pcap",
+            Linktype::ETHERNET,
+            packets,
+            None,
+        );
+
+        let analyze = |options| {
+            let mut analyzer = TrafficAnalyzer::default();
+            for msg in run_offline_capture(&path, options) {
+                if let BackendTrafficMessage::TickRun(_, msg_traffic, _, _) = msg {
+                    analyzer.refresh(msg_traffic, Vec::new());
+                }
+            }
+            analyzer.get_info_traffic_data()
+        };
+        let all_packets = analyze(CaptureOptions::default());
+        assert_eq!(all_packets.traffic_data.total_packets, 5);
+
+        // the data packet and the DNS query are skipped
+        let handshake_only = analyze(CaptureOptions {
+            tcp_handshake_only: true,
+            ..CaptureOptions::default()
+        });
+        assert_eq!(handshake_only.traffic_data.total_packets, 3);
+        assert!(!handshake_only.connections.is_empty());
+        assert!(
+            handshake_only
+                .connections
+                .iter()
+                .all(|connection| connection.key.protocol == Protocol::TCP)
+        );
+    }
+
+    #[test]
+    fn test_capture_n_packets_from_file() {
+        // a DNS query from a different port each second
+        let packets = (0..5).map(|i| {
+            let mut data = Vec::new();
+            PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+                .ipv4([192, 168, 1, 2], [8, 8, 8, 8], 64)
+                .udp(50000 + i, 53)
+                .write(&mut data, &[0; 10])
+                .unwrap();
+            (1_000_000 + i64::from(i), data)
+        });
+        let path = write_packets(
+            "tickertinker_capture_n_packets.pcap",
+            Linktype::ETHERNET,
+            packets,
+            None,
+        );
+        let path = path.as_str();
+        let (no_filter, timeout) = (CaptureFilter::default(), Duration::from_secs(5));
+
+        let (cs, capture_context) = open_capture_file(path).unwrap();
+        let info_traffic = capture_n_packets(&cs, capture_context, 3, &no_filter, timeout).unwrap();
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 3);
+        assert_eq!(info_traffic.map.len(), 3);
+        assert!(info_traffic.map.keys().all(|key| key.port1 < Some(50003)));
+        assert_eq!(info_traffic.last_packet_timestamp.secs(), 1_000_002);
+
+        // the file ends before enough packets are seen
+        let (cs, capture_context) = open_capture_file(path).unwrap();
+        let info_traffic =
+            capture_n_packets(&cs, capture_context, 100, &no_filter, timeout).unwrap();
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 5);
+
+        // only the packets satisfying the filter are accounted
+        let (cs, capture_context) = open_capture_file(path).unwrap();
+        let capture_filter = CaptureFilter::from_bpf("udp port 50004").unwrap();
+        let info_traffic =
+            capture_n_packets(&cs, capture_context, 3, &capture_filter, timeout).unwrap();
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 1);
+        assert!(info_traffic.map.keys().all(|key| key.port1 == Some(50004)));
+    }
+
+    #[test]
+    fn test_qos_breakdown_aggregates_classes() {
+        // two voice flows marked EF, and a flow marked BE
+        let packets = [(50000, 46), (50002, 46), (50004, 0)].map(|(source_port, dscp)| {
+            let ipv4 = Ipv4Header {
+                dscp: Ipv4Dscp::try_new(dscp).unwrap(),
+                time_to_live: 64,
+                source: [192, 168, 1, 2],
+                destination: [203, 0, 113, 7],
+                ..Default::default()
+            };
+            let mut data = Vec::new();
+            PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+                .ip(IpHeaders::Ipv4(ipv4, Default::default()))
+                .udp(source_port, 5004)
+                .write(&mut data, &[0; 100])
+                .unwrap();
+            (1_000_000, data)
+        });
+        let path = write_packets(
+            "tickertinker_qos_breakdown.pcap",
+            Linktype::ETHERNET,
+            packets,
+            None,
+        );
+
+        let mut analyzer = TrafficAnalyzer::default();
+        for msg in run_offline_capture(&path, CaptureOptions::default()) {
+            if let BackendTrafficMessage::TickRun(_, msg_traffic, _, _) = msg {
+                analyzer.refresh(msg_traffic, Vec::new());
+            }
+        }
+        let breakdown = analyzer.get_qos_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        let (ef, be) = (&breakdown[0], &breakdown[1]);
+        assert_eq!((ef.class, ef.dscp), (Some("EF"), 46));
+        assert_eq!((be.class, be.dscp), (Some("BE"), 0));
+        // the packets of both EF flows are aggregated into the class
+        assert_eq!(ef.data.outgoing_packets, 2);
+        assert_eq!(be.data.outgoing_packets, 1);
+        assert_eq!(ef.data.outgoing_bytes, 2 * be.data.outgoing_bytes);
+    }
+
+    #[test]
+    fn test_snaplen_truncated_packets_are_counted() {
+        // the packets are cut right after their UDP header: only the one with a payload loses bytes
+        let packets = [0, 100].map(|payload_len| {
+            let mut data = Vec::new();
+            PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+                .ipv4([127, 0, 0, 1], [127, 0, 0, 1], 64)
+                .udp(50000, 53)
+                .write(&mut data, &vec![0; payload_len])
+                .unwrap();
+            (1_000_000, data)
+        });
+        let path = write_packets(
+            "tickertinker_truncated.pcap",
+            Linktype::ETHERNET,
+            packets,
+            Some(42),
+        );
+        let (_, capture_context) = open_capture_file(&path).unwrap();
+        assert_eq!(capture_context.snaplen(), None);
+
+        let mut info_traffic = InfoTraffic::default();
+        for msg in run_offline_capture(&path, CaptureOptions::default()) {
+            if let BackendTrafficMessage::TickRun(_, mut msg_traffic, _, _) = msg {
+                info_traffic.refresh(&mut msg_traffic);
+            }
+        }
+        assert_eq!(info_traffic.capture_volume.packets, 2);
+        assert_eq!(info_traffic.capture_volume.snaplen_truncated_packets, 1);
+    }
+
+    #[test]
+    fn test_hosts_named_after_ip_with_rdns_disabled() {
+        let path = write_capture_file(
+            "tickertinker_no_rdns.pcap",
+            Linktype::ETHERNET,
+            &[1_000_000],
+        );
+        let (cs, capture_context) = open_capture_file(&path).unwrap();
+        let (tx, rx) = async_channel::unbounded();
+        let (_control_tx, control_rx) = async_channel::unbounded();
+        let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::default()));
+        parse_packets(
+            0,
+            cs,
+            &MmdbReaders::default(),
+            capture_context,
+            VecDeque::new(),
+            CaptureOptions {
+                disable_rdns: true,
+                ..CaptureOptions::default()
+            },
+            CaptureFilter::default(),
+            &resolutions_state,
+            &tx,
+            &control_rx,
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+        );
+
+        let mut hosts = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                BackendTrafficMessage::TickRun(_, _, new_hosts, _)
+                | BackendTrafficMessage::PendingHosts(_, new_hosts) => hosts.extend(new_hosts),
+                _ => {}
+            }
+        }
+        // the host is still reported, named after its address rather than "localhost"
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].rdns, "127.0.0.1");
+        assert_eq!(hosts[0].host.domain, "127.0.0.1");
+        assert_eq!(resolutions_state.lock().unwrap().stats().resolved_hosts, 0);
+    }
+
+    #[test]
+    fn test_hosts_named_after_tls_server_name() {
+        let (local, server) = ([192, 168, 1, 2], [203, 0, 113, 80]);
+        let mut data = Vec::new();
+        PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4(local, server, 64)
+            .tcp(50000, 443, 1, 1024)
+            .write(&mut data, &client_hello_for_tests(b"www.tickertinker.org"))
+            .unwrap();
+        let path = write_packets(
+            "tickertinker_tls_server_name.pcap",
+            Linktype::ETHERNET,
+            [(1_000_000, data)],
+            None,
+        );
+        let options = CaptureOptions {
+            inspect_payloads: true,
+            disable_rdns: true,
+            ..CaptureOptions::default()
+        };
+
+        let mut info_traffic = InfoTraffic::default();
+        let mut hosts = Vec::new();
+        for msg in run_offline_capture(&path, options) {
+            match msg {
+                BackendTrafficMessage::TickRun(_, mut msg_traffic, new_hosts, _) => {
+                    info_traffic.refresh(&mut msg_traffic);
+                    hosts.extend(new_hosts);
+                }
+                BackendTrafficMessage::PendingHosts(_, new_hosts) => hosts.extend(new_hosts),
+                _ => {}
+            }
+        }
+        // the server is named after the requested domain, even without rDNS
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].address_to_lookup, IpAddr::from(server));
+        assert_eq!(hosts[0].rdns, "www.tickertinker.org");
+        assert_eq!(hosts[0].host.domain, "tickertinker.org");
+        let info = info_traffic.map.values().next().unwrap();
+        assert_eq!(info.server_name.as_deref(), Some("www.tickertinker.org"));
+    }
+
+    #[test]
+    fn test_asn_lookup() {
+        let state = NetworkMonitorState::default();
+        state.load_mmdb(None, None).unwrap();
+
+        let asn = state.asn_lookup("8.8.8.8").unwrap();
+        assert_eq!(asn.code, "15169");
+        assert_eq!(asn.name, "GOOGLE");
+        let asn = state.asn_lookup(" 2806:230:2057:: ").unwrap();
+        assert_eq!(asn.code, "11888");
+
+        // addresses not in the database
+        assert_eq!(state.asn_lookup("127.0.0.1").unwrap(), Asn::default());
+
+        let err = state.asn_lookup("8.8.8").err().unwrap();
+        assert!(err.starts_with("Invalid IP address \"8.8.8\""));
+    }
+
+    #[test]
+    fn test_country_lookup() {
+        let state = NetworkMonitorState::default();
+        state.load_mmdb(None, None).unwrap();
+
+        let lookup = state.country_lookup("8.8.8.8").unwrap();
+        assert_eq!(lookup.country, Country::US);
+        assert!(lookup.resolved);
+        assert_eq!(
+            serde_json::to_value(&lookup).unwrap(),
+            serde_json::json!({"country": "US", "resolved": true})
+        );
+        assert_eq!(
+            state.country_lookup("2806:230:2057::").unwrap().country,
+            Country::MX
+        );
+
+        // addresses not in the database
+        let lookup = state.country_lookup("127.0.0.1").unwrap();
+        assert_eq!(lookup.country, Country::ZZ);
+        assert!(!lookup.resolved);
+
+        let err = state.country_lookup("not an address").err().unwrap();
+        assert!(err.starts_with("Invalid IP address"));
+    }
+
+    #[test]
+    fn test_bundled_mmdb_used_by_default() {
+        // no need to load the databases before the first capture
+        let state = NetworkMonitorState::default();
+        let lookup = state.country_lookup("78.35.248.93").unwrap();
+        assert_eq!(lookup.country, Country::DE);
+        assert!(lookup.resolved);
+        assert_eq!(state.asn_lookup("8.8.8.8").unwrap().code, "15169");
+    }
+
+    #[test]
+    fn test_get_connection_details() {
+        let state = NetworkMonitorState::default();
+        let info_timestamp = Timestamp::new(1_700_000_000, 0);
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from([93, 184, 216, 34]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let info = InfoAddressPortPair {
+            mac_address1: Some("aa:bb:cc:dd:ee:ff".to_string()),
+            transmitted_bytes: 1500,
+            transmitted_packets: 3,
+            initial_timestamp: info_timestamp,
+            final_timestamp: Timestamp::new(1_700_000_002, 0),
+            ..Default::default()
+        };
+        state.traffic_analyzer.lock().unwrap().refresh(
+            InfoTraffic {
+                map: [(key, info)].into_iter().collect(),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+
+        let (local, remote) = ("192.168.1.2", "93.184.216.34");
+        let details = state
+            .get_connection_details(local, Some(50000), " 93.184.216.34", Some(443), "Tcp")
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.key, key);
+        let mac_address = details.info.mac_address1.as_deref();
+        assert_eq!(mac_address, Some("aa:bb:cc:dd:ee:ff"));
+        assert_eq!(details.info.transmitted_bytes, 1500);
+        assert_eq!(details.info.initial_timestamp, info_timestamp);
+
+        // the direction of the pair is part of the key
+        let details = state.get_connection_details(remote, Some(443), local, Some(50000), "tcp");
+        assert!(details.unwrap().is_none());
+        let details = state.get_connection_details(local, Some(50000), remote, Some(443), "udp");
+        assert!(details.unwrap().is_none());
+
+        let err = state.get_connection_details(local, None, remote, None, "sctp");
+        assert_eq!(err.err().unwrap(), "Invalid protocol \"sctp\"");
+        let err = state
+            .get_connection_details("192.168.1", None, remote, None, "icmp")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Invalid IP address \"192.168.1\""));
+    }
+
+    #[test]
+    fn test_classify_bogon() {
+        let classify = |ip: &str| classify_bogon(ip.to_string()).unwrap();
+        assert_eq!(classify("192.168.1.10").as_deref(), Some("private-use"));
+        assert_eq!(
+            classify(" 100.64.3.1 ").as_deref(),
+            Some("carrier-grade NAT")
+        );
+        assert_eq!(classify("fd12:3456::1").as_deref(), Some("ULA"));
+        assert_eq!(classify("ff02::1").as_deref(), Some("multicast v6"));
+        assert_eq!(classify("8.8.8.8"), None);
+        assert_eq!(classify("2001:4860:4860::8888"), None);
+
+        let err = classify_bogon("10.0.0".to_string()).err().unwrap();
+        assert!(err.starts_with("Invalid IP address \"10.0.0\""));
+    }
 }
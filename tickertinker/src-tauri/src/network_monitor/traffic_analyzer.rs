@@ -0,0 +1,2525 @@
+//! Module defining the `TrafficAnalyzer` struct, which accumulates the traffic observed by a capture.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use pcap::Address;
+
+use crate::countries::types::country::Country;
+use crate::networking::manage_packets::{
+    get_address_to_lookup, get_local_address, is_local_connection,
+};
+use crate::networking::parse_packets::ResolutionStateDump;
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::app_protocol::AppProtocol;
+use crate::networking::types::bogon::is_bogon;
+use crate::networking::types::data_info::DataInfo;
+use crate::networking::types::data_info_host::DataInfoHost;
+use crate::networking::types::data_representation::{DataRepr, Precision};
+use crate::networking::types::host::{Host, HostMessage};
+use crate::networking::types::info_traffic::InfoTraffic;
+use crate::networking::types::ip_collection::IpCollection;
+use crate::networking::types::parse_failure_sample::{
+    MAX_PARSE_FAILURE_SAMPLES, ParseFailureSample,
+};
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::qos_class::qos_class;
+use crate::networking::types::retention_policy::RetentionPolicy;
+use crate::networking::types::service::Service;
+use crate::networking::types::socket_table::LocalProcess;
+use crate::networking::types::traffic_direction::TrafficDirection;
+use crate::report::types::sort_type::SortType;
+use crate::translations::translations_5::notification_summary_translation;
+use crate::translations::types::language::Language;
+use crate::utils::types::timestamp::Timestamp;
+
+use super::bandwidth_series::BandwidthSeries;
+use super::traffic_data::{
+    AppProtocolData, AsymmetricFlowData, BandwidthPercentiles, CaptureStats, CidrStats,
+    ConnectionData, ConnectionProfileData, ConnectionRttData, DataEstimate, DebugStateDump,
+    DropDelta, ExchangedData, GatewayTraffic, HostClassification, HostData, HostDirectionality,
+    HourlyTrafficData, InfoTrafficData, ListeningPortData, OtherHostsData, PacketSizeData,
+    PortData, ProcessTrafficData, QosClassData, RecentHostData, ServiceData, SessionInfo,
+    TrafficData, TrafficReport, TrafficSample, TrafficSnapshot, TrafficUpdateMessage,
+};
+use super::traffic_history::TrafficHistory;
+
+/// Traffic collected since the start of the current capture
+#[derive(Default)]
+pub struct TrafficAnalyzer {
+    info_traffic: InfoTraffic,
+    /// Limits to the collected connections and hosts
+    retention: RetentionPolicy,
+    /// Number of hosts evicted to respect the retention policy
+    evicted_hosts: usize,
+    /// Data exchanged with the evicted hosts
+    other_hosts: DataInfo,
+    /// First packets whose headers couldn't be parsed (only recorded if requested)
+    parse_failure_samples: Vec<ParseFailureSample>,
+    /// Address whose connections are streamed with the traffic updates, if any
+    focused_host: Option<IpAddr>,
+    /// Bytes exchanged in each second of the capture, to compute the bandwidth percentiles
+    bytes_per_second: BandwidthSeries,
+    /// Data exchanged in each hour of the day (UTC), according to the timestamp of the packets
+    hourly_traffic: [DataInfo; 24],
+    /// Timestamp of the first packet of the capture
+    first_packet_timestamp: Option<Timestamp>,
+    /// Connections observed when the baseline was taken, if any
+    baseline: Option<HashSet<AddressPortPair>>,
+    /// Cumulative dropped packets as of the latest call to `take_drop_delta`
+    reported_drops: DropDelta,
+    /// Data exchanged in the latest intervals, for the graphs of the frontend
+    history: TrafficHistory,
+    /// Data exchanged by each local process, attributed interval by interval
+    /// (`None` for the data of connections whose process wasn't known at the time)
+    process_traffic: HashMap<Option<LocalProcess>, DataInfo>,
+    /// Traffic collected since the latest call to `take_traffic_snapshot`,
+    /// only accumulated once it has been called
+    snapshot_traffic: Option<InfoTraffic>,
+}
+
+impl TrafficAnalyzer {
+    pub fn new(retention: RetentionPolicy) -> Self {
+        Self {
+            retention,
+            ..Self::default()
+        }
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
+
+    /// Sets the number of intervals kept in the traffic history, clearing it
+    pub fn set_history_window(&mut self, window: usize) {
+        self.history = TrafficHistory::new(window);
+    }
+
+    /// Replaces the retention policy, effective from the next interval
+    pub fn set_retention(&mut self, retention: RetentionPolicy) {
+        self.retention = retention;
+    }
+
+    /// Merges the data of the latest interval (one second) into the collected traffic
+    pub fn refresh(&mut self, mut msg: InfoTraffic, new_hosts: Vec<HostMessage>) {
+        self.bytes_per_second
+            .push(msg.tot_data_info.tot_data(DataRepr::Bytes));
+        self.hourly_traffic[msg.last_packet_timestamp.hour_of_day()].refresh(msg.tot_data_info);
+        self.history
+            .push(msg.last_packet_timestamp, &msg.tot_data_info);
+        if self.first_packet_timestamp.is_none() {
+            self.first_packet_timestamp = msg.map.values().map(|info| info.initial_timestamp).min();
+        }
+        for info in msg.map.values() {
+            // the data of a connection outliving its process (e.g., after a crash) becomes unknown
+            let process = info
+                .pid
+                .zip(info.process.clone())
+                .map(|(pid, name)| LocalProcess { pid, name });
+            let data_info = self.process_traffic.entry(process).or_default();
+            data_info.add_packets(
+                info.packets_sent,
+                info.bytes_sent,
+                TrafficDirection::Outgoing,
+            );
+            data_info.add_packets(
+                info.packets_received,
+                info.bytes_received,
+                TrafficDirection::Incoming,
+            );
+        }
+        if let Some(snapshot_traffic) = self.snapshot_traffic.as_mut() {
+            snapshot_traffic.refresh(&mut msg);
+        }
+        self.info_traffic.refresh(&mut msg);
+        self.add_hosts(new_hosts);
+    }
+
+    /// Adds the newly resolved hosts, with the data they exchanged while waiting for a resolution
+    pub fn add_hosts(&mut self, new_hosts: Vec<HostMessage>) {
+        for host_msg in new_hosts {
+            let data_info_host = host_msg.data_info_host;
+            self.info_traffic
+                .hosts
+                .entry(host_msg.host)
+                .and_modify(|x| x.refresh(&data_info_host))
+                .or_insert(data_info_host);
+        }
+    }
+
+    /// Accounts the seconds without packets of an offline capture
+    pub fn add_idle_seconds(&mut self, secs: u64) {
+        self.bytes_per_second.push_idle(secs);
+    }
+
+    /// Returns the percentiles of the bytes exchanged per second since the start of the capture
+    /// (or, in very long captures, in their latest part)
+    pub fn get_bandwidth_percentiles(&self) -> BandwidthPercentiles {
+        self.bytes_per_second.percentiles()
+    }
+
+    /// Returns the data exchanged in each of the latest intervals, oldest first
+    pub fn get_traffic_history(&self) -> Vec<TrafficSample> {
+        self.history.samples()
+    }
+
+    /// Returns the data exchanged in each hour of the day (UTC), summed over all the days
+    pub fn get_hourly_profile(&self) -> Vec<HourlyTrafficData> {
+        self.hourly_traffic
+            .iter()
+            .enumerate()
+            .map(|(hour, data_info)| HourlyTrafficData {
+                hour,
+                packets: data_info.tot_data(DataRepr::Packets),
+                bytes: data_info.tot_data(DataRepr::Bytes),
+            })
+            .collect()
+    }
+
+    pub fn add_parse_failure_sample(&mut self, sample: ParseFailureSample) {
+        if self.parse_failure_samples.len() < MAX_PARSE_FAILURE_SAMPLES {
+            self.parse_failure_samples.push(sample);
+        }
+    }
+
+    /// Forgets the connections and evicts the hosts exceeding the retention policy,
+    /// returning the evicted hosts.
+    ///
+    /// Ages are measured up to the timestamp of the latest packet, so that replaying a file
+    /// gives the same result as the live capture it was recorded from.
+    pub fn apply_retention(&mut self) -> Vec<Host> {
+        self.forget_connections();
+        let mut evicted = self.evict_inactive_hosts();
+        evicted.extend(self.evict_hosts());
+        evicted
+    }
+
+    /// Forgets the connections inactive for longer than `max_age_secs`,
+    /// and then the least recently active ones exceeding `max_connections`;
+    /// their data is still part of the totals
+    fn forget_connections(&mut self) {
+        if let Some(max_age_secs) = self.retention.max_age_secs {
+            let expired: Vec<_> = self
+                .info_traffic
+                .idle_connections(max_age_secs)
+                .into_iter()
+                .map(|(key, _)| *key)
+                .collect();
+            for key in &expired {
+                self.info_traffic.map.remove(key);
+            }
+        }
+
+        let Some(max_connections) = self.retention.max_connections else {
+            return;
+        };
+        if self.info_traffic.map.len() <= max_connections {
+            return;
+        }
+        let mut connections: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .map(|(key, info)| (*key, info.final_timestamp))
+            .collect();
+        // most recently active first
+        connections.sort_by(|(_, a), (_, b)| b.cmp(a));
+        for (key, _) in connections.split_off(max_connections) {
+            self.info_traffic.map.remove(&key);
+        }
+    }
+
+    /// Evicts the hosts inactive for longer than `max_age_secs`,
+    /// accumulating their data into the "other hosts" aggregate
+    fn evict_inactive_hosts(&mut self) -> Vec<Host> {
+        let Some(max_age_secs) = self.retention.max_age_secs else {
+            return Vec::new();
+        };
+        let max_age = Duration::from_secs(max_age_secs);
+        let now = self.info_traffic.last_packet_timestamp;
+        let evicted: Vec<Host> = self
+            .info_traffic
+            .hosts
+            .iter()
+            .filter(|(_, data_info_host)| {
+                elapsed_since(data_info_host.data_info.final_timestamp(), now) > max_age
+            })
+            .map(|(host, _)| host.clone())
+            .collect();
+        self.evict(&evicted);
+        evicted
+    }
+
+    /// Evicts the least recently active hosts exceeding `max_hosts`,
+    /// accumulating their data into the "other hosts" aggregate
+    pub fn evict_hosts(&mut self) -> Vec<Host> {
+        let Some(max_hosts) = self.retention.max_hosts else {
+            return Vec::new();
+        };
+        if self.info_traffic.hosts.len() <= max_hosts {
+            return Vec::new();
+        }
+
+        let mut hosts: Vec<_> = self.info_traffic.hosts.iter().collect();
+        // most recently active first
+        hosts.sort_by_key(|(_, info)| Reverse(info.data_info.final_timestamp()));
+        let evicted: Vec<Host> = hosts
+            .split_off(max_hosts)
+            .into_iter()
+            .map(|(host, _)| host.clone())
+            .collect();
+        self.evict(&evicted);
+        evicted
+    }
+
+    fn evict(&mut self, hosts: &[Host]) {
+        for host in hosts {
+            if let Some(data_info_host) = self.info_traffic.hosts.remove(host) {
+                self.other_hosts.refresh(data_info_host.data_info);
+            }
+        }
+        self.evicted_hosts += hosts.len();
+    }
+
+    pub fn get_other_hosts(&self) -> OtherHostsData {
+        OtherHostsData {
+            hosts: self.evicted_hosts,
+            incoming_packets: self.other_hosts.incoming_data(DataRepr::Packets),
+            outgoing_packets: self.other_hosts.outgoing_data(DataRepr::Packets),
+            incoming_bytes: self.other_hosts.incoming_data(DataRepr::Bytes),
+            outgoing_bytes: self.other_hosts.outgoing_data(DataRepr::Bytes),
+        }
+    }
+
+    pub fn get_parse_failure_samples(&self) -> Vec<ParseFailureSample> {
+        self.parse_failure_samples.clone()
+    }
+
+    /// Returns the details of the capture with the given id and snapshot length,
+    /// comparing the traffic on the wire with the part of it analyzed
+    pub fn get_session_info(&self, cap_id: usize, snaplen: Option<i32>) -> SessionInfo {
+        SessionInfo::new(cap_id, snaplen, &self.info_traffic)
+    }
+
+    /// Returns the packet counters of the capture handle, as of the latest interval
+    pub fn get_capture_stats(&self, cap_id: usize, duration: Duration) -> CaptureStats {
+        CaptureStats {
+            cap_id,
+            received: self.info_traffic.received_packets,
+            dropped: self.info_traffic.dropped_packets,
+            if_dropped: self.info_traffic.interface_dropped_packets,
+            duration_secs: duration.as_secs_f64(),
+        }
+    }
+
+    /// Returns a one-line summary of the capture (data sent and received, hosts, and countries),
+    /// suitable for OS notifications or tooltips
+    pub fn get_notification_summary(&self, language: Language, precision: Precision) -> String {
+        let tot_data_info = &self.info_traffic.tot_data_info;
+        let formatted_bytes =
+            |bytes| DataRepr::Bytes.formatted_string_with_precision(bytes, precision);
+        let upload = formatted_bytes(tot_data_info.outgoing_data(DataRepr::Bytes));
+        let download = formatted_bytes(tot_data_info.incoming_data(DataRepr::Bytes));
+        let hosts = self.info_traffic.hosts.len() + self.evicted_hosts;
+        let countries = self
+            .info_traffic
+            .hosts
+            .keys()
+            .map(|host| host.country)
+            .filter(|country| *country != Country::ZZ)
+            .collect::<HashSet<_>>()
+            .len();
+        notification_summary_translation(language, &upload, &download, hosts, countries)
+    }
+
+    pub fn debug_dump(&self, resolutions: Option<ResolutionStateDump>) -> DebugStateDump {
+        DebugStateDump {
+            resolutions,
+            connections: self.info_traffic.map.len(),
+            hosts: self.info_traffic.hosts.len(),
+            services: self.info_traffic.services.len(),
+        }
+    }
+
+    pub fn get_traffic_data(&self) -> TrafficData {
+        TrafficData::new(&self.info_traffic)
+    }
+
+    /// Returns the whole traffic collected, with its connections, services, and hosts
+    pub fn get_info_traffic_data(&self) -> InfoTrafficData {
+        InfoTrafficData::new(&self.info_traffic)
+    }
+
+    /// Returns the whole traffic of the capture, with the time it started and the captured source
+    pub fn get_traffic_report(&self, interface_name: Option<String>) -> TrafficReport {
+        TrafficReport {
+            capture_started_at: self.first_packet_timestamp,
+            interface_name,
+            traffic: self.get_info_traffic_data(),
+        }
+    }
+
+    /// Restricts the connections streamed with the traffic updates to the ones involving
+    /// the given address, or stops streaming connections if `None`
+    pub fn set_focused_host(&mut self, address: Option<IpAddr>) {
+        self.focused_host = address;
+    }
+
+    /// Marks or unmarks a host as favorite in the data collected so far
+    pub fn set_favorite_host(&mut self, host: &Host, is_favorite: bool) {
+        if let Some(data_info_host) = self.info_traffic.hosts.get_mut(host) {
+            data_info_host.is_favorite = is_favorite;
+        }
+    }
+
+    /// Returns the given favorite hosts with the data exchanged with them,
+    /// sorted by the amount of bytes exchanged (the ones never observed have no data)
+    pub fn get_favorite_hosts(&self, favorite_hosts: &HashSet<Host>) -> Vec<HostData> {
+        let never_observed = DataInfoHost {
+            is_favorite: true,
+            ..Default::default()
+        };
+        let mut favorites: Vec<_> = favorite_hosts
+            .iter()
+            .map(|host| {
+                let data_info_host = self.info_traffic.hosts.get(host);
+                (host, data_info_host.copied().unwrap_or(never_observed))
+            })
+            .collect();
+        favorites.sort_by(|(host_a, a), (host_b, b)| {
+            b.data_info
+                .tot_data(DataRepr::Bytes)
+                .cmp(&a.data_info.tot_data(DataRepr::Bytes))
+                .then_with(|| host_a.domain.cmp(&host_b.domain))
+        });
+        favorites
+            .into_iter()
+            .map(|(host, data_info_host)| HostData::new(host, &data_info_host))
+            .collect()
+    }
+
+    /// Returns the connections involving the focused host, sorted by the amount of bytes exchanged
+    /// (`None` if no host is focused)
+    pub fn get_focused_connections(&self) -> Option<Vec<ConnectionData>> {
+        let focused_host = self.focused_host?;
+        let mut connections: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .filter(|(key, _)| key.address1 == focused_host || key.address2 == focused_host)
+            .collect();
+        connections.sort_by(|(_, a), (_, b)| b.transmitted_bytes.cmp(&a.transmitted_bytes));
+        Some(
+            connections
+                .into_iter()
+                .map(|(key, info)| ConnectionData::new(key, info))
+                .collect(),
+        )
+    }
+
+    /// Builds the payload of the `traffic_update` event for the collected traffic
+    pub fn traffic_update(&self, cap_id: usize, is_final: bool) -> TrafficUpdateMessage {
+        TrafficUpdateMessage {
+            cap_id,
+            traffic_data: self.get_traffic_data(),
+            focused_connections: self.get_focused_connections(),
+            is_final,
+        }
+    }
+
+    /// Returns the traffic collected since the previous snapshot and resets it, atomically:
+    /// consecutive snapshots partition the observed packets, with no overlap or loss.
+    ///
+    /// Snapshots are drained from a dedicated accumulator, leaving the traffic of the capture
+    /// (shown by the other commands) untouched; the first one covers the whole capture.
+    pub fn take_traffic_snapshot(&mut self) -> TrafficSnapshot {
+        let info_traffic = match self.snapshot_traffic.as_mut() {
+            Some(snapshot_traffic) => snapshot_traffic.take_but_leave_something(),
+            None => {
+                let mut snapshot_traffic = self.info_traffic.clone();
+                let info_traffic = snapshot_traffic.take_but_leave_something();
+                self.snapshot_traffic = Some(snapshot_traffic);
+                info_traffic
+            }
+        };
+        TrafficSnapshot {
+            traffic_data: TrafficData::new(&info_traffic),
+            connections: info_traffic
+                .map
+                .iter()
+                .map(|(key, info)| ConnectionData::new(key, info))
+                .collect(),
+        }
+    }
+
+    /// Returns the timestamp of the latest parsed packet
+    pub fn last_packet_timestamp(&self) -> Timestamp {
+        self.info_traffic.last_packet_timestamp
+    }
+
+    /// Returns all the connections, in order of their first packet
+    pub fn get_connections(&self) -> Vec<ConnectionData> {
+        let mut connections: Vec<_> = self.info_traffic.map.iter().collect();
+        connections.sort_by_key(|(_, info)| info.initial_timestamp);
+        connections
+            .into_iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect()
+    }
+
+    /// Returns the packets dropped since the previous call (or since the start of the capture),
+    /// telling whether packets are being dropped now or were dropped earlier
+    pub fn take_drop_delta(&mut self) -> DropDelta {
+        let drops = DropDelta {
+            kernel_dropped: self.info_traffic.dropped_packets,
+            interface_dropped: self.info_traffic.interface_dropped_packets,
+        };
+        // the counters of libpcap are 32 bits long, and can wrap around in long captures
+        let delta = DropDelta {
+            kernel_dropped: drops
+                .kernel_dropped
+                .wrapping_sub(self.reported_drops.kernel_dropped),
+            interface_dropped: drops
+                .interface_dropped
+                .wrapping_sub(self.reported_drops.interface_dropped),
+        };
+        self.reported_drops = drops;
+        delta
+    }
+
+    /// Takes a baseline of the connections observed so far, returning their number
+    pub fn set_baseline(&mut self) -> usize {
+        let baseline: HashSet<_> = self.info_traffic.map.keys().copied().collect();
+        let connections = baseline.len();
+        self.baseline = Some(baseline);
+        connections
+    }
+
+    /// Returns the connections first observed after the baseline was taken,
+    /// in order of their first packet
+    pub fn get_new_flows_since_baseline(&self) -> Result<Vec<ConnectionData>, String> {
+        let baseline = self.baseline.as_ref().ok_or("No baseline taken")?;
+        let mut connections: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .filter(|(key, _)| !baseline.contains(key))
+            .collect();
+        connections.sort_by_key(|(_, info)| info.initial_timestamp);
+        Ok(connections
+            .into_iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect())
+    }
+
+    /// Returns the connections that exchanged packets after `since`, the least recent first
+    pub fn get_updated_connections(&self, since: Timestamp) -> Vec<ConnectionData> {
+        let mut connections: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .filter(|(_, info)| info.final_timestamp > since)
+            .collect();
+        connections.sort_by_key(|(_, info)| info.final_timestamp);
+        connections
+            .into_iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect()
+    }
+
+    pub fn get_idle_connections(&self, idle_secs: u64) -> Vec<ConnectionData> {
+        self.info_traffic
+            .idle_connections(idle_secs)
+            .into_iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect()
+    }
+
+    /// Returns the full record of a connection, or `None` if it wasn't captured
+    pub fn get_connection_details(&self, key: &AddressPortPair) -> Option<ConnectionData> {
+        self.info_traffic
+            .map
+            .get(key)
+            .map(|info| ConnectionData::new(key, info))
+    }
+
+    pub fn get_packet_sizes(&self) -> Vec<PacketSizeData> {
+        self.info_traffic
+            .map
+            .iter()
+            .map(|(key, info)| PacketSizeData::new(key, info))
+            .collect()
+    }
+
+    /// Returns the round-trip time of the connections it could be estimated for, the slowest first
+    pub fn get_connection_rtts(&self) -> Vec<ConnectionRttData> {
+        let mut rtts: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .filter_map(|(key, info)| {
+                Some(ConnectionRttData {
+                    key: *key,
+                    estimated_rtt_ms: info.estimated_rtt_ms?,
+                })
+            })
+            .collect();
+        rtts.sort_by(|a, b| b.estimated_rtt_ms.total_cmp(&a.estimated_rtt_ms));
+        rtts
+    }
+
+    /// Returns the profile of each connection (background or interactive),
+    /// sorted by the amount of bytes exchanged
+    pub fn get_connection_profiles(&self) -> Vec<ConnectionProfileData> {
+        let mut connections: Vec<_> = self.info_traffic.map.iter().collect();
+        connections.sort_by(|(_, a), (_, b)| b.transmitted_bytes.cmp(&a.transmitted_bytes));
+        connections
+            .into_iter()
+            .map(|(key, info)| ConnectionProfileData::new(key, info))
+            .collect()
+    }
+
+    /// Returns the TCP flows whose packets were only observed in one direction
+    /// (e.g., a SYN never answered by a SYN-ACK, or data never acknowledged),
+    /// sorted by the amount of bytes exchanged
+    pub fn get_asymmetric_flows(&self) -> Vec<AsymmetricFlowData> {
+        let map = &self.info_traffic.map;
+        let mut flows: Vec<_> = map
+            .iter()
+            .filter(|(key, info)| {
+                key.protocol == Protocol::TCP
+                    && info.transmitted_packets > 0
+                    && (info.packets_sent == 0 || info.packets_received == 0)
+                    && map
+                        .get(&key.reversed())
+                        .is_none_or(|reverse| reverse.transmitted_packets == 0)
+            })
+            .collect();
+        flows.sort_by(|(_, a), (_, b)| b.transmitted_bytes.cmp(&a.transmitted_bytes));
+        flows
+            .into_iter()
+            .map(|(key, info)| AsymmetricFlowData::new(key, info))
+            .collect()
+    }
+
+    /// Returns the hosts that exchanged packets in the last `since_secs` seconds,
+    /// the most recently active first
+    pub fn get_recent_hosts(&self, since_secs: u64) -> Vec<RecentHostData> {
+        self.recent_hosts(
+            self.info_traffic.last_packet_timestamp,
+            Duration::from_secs(since_secs),
+        )
+    }
+
+    /// Returns the first `n` hosts in the given order of the data exchanged with them
+    /// (the most recently active first, if the sort type is neutral)
+    pub fn get_top_hosts(
+        &self,
+        n: usize,
+        sort_type: SortType,
+        data_repr: DataRepr,
+    ) -> Vec<HostData> {
+        let mut hosts: Vec<_> = self.info_traffic.hosts.iter().collect();
+        hosts.sort_by(|(_, a), (_, b)| a.data_info.compare(&b.data_info, sort_type, data_repr));
+        hosts
+            .into_iter()
+            .take(n)
+            .map(|(host, data_info_host)| HostData::new(host, data_info_host))
+            .collect()
+    }
+
+    /// Returns the first `n` services in the given order of the data exchanged through them
+    /// (the most recently used first, if the sort type is neutral),
+    /// leaving out the traffic without a service (e.g., ICMP)
+    pub fn get_top_services(
+        &self,
+        n: usize,
+        sort_type: SortType,
+        data_repr: DataRepr,
+    ) -> Vec<ServiceData> {
+        let mut services: Vec<_> = self
+            .info_traffic
+            .services
+            .iter()
+            .filter(|(service, _)| **service != Service::NotApplicable)
+            .collect();
+        services.sort_by(|(_, a), (_, b)| a.compare(b, sort_type, data_repr));
+        services
+            .into_iter()
+            .take(n)
+            .map(|(service, data_info)| ServiceData::new(*service, data_info))
+            .collect()
+    }
+
+    /// Returns the `n` remote ports exchanging the most data in the given representation,
+    /// aggregated across hosts and protocols; traffic without ports (ICMP and ARP) is left out
+    pub fn get_top_ports(&self, n: usize, data_repr: DataRepr) -> Vec<PortData> {
+        // service, number of connections, and data exchanged of each port
+        let mut ports: HashMap<u16, (Service, usize, DataInfo)> = HashMap::new();
+        for (key, info) in &self.info_traffic.map {
+            let remote_port = match info.traffic_direction {
+                TrafficDirection::Outgoing => key.port2,
+                TrafficDirection::Incoming => key.port1,
+            };
+            let Some(port) = remote_port else {
+                continue;
+            };
+            let (service, connections, data_info) = ports
+                .entry(port)
+                .or_insert_with(|| (info.service, 0, DataInfo::default()));
+            if *service == Service::Unknown {
+                *service = info.service;
+            }
+            // both directions of a connection can have an entry, but it's counted once
+            if self.info_traffic.is_canonical_entry(key) {
+                *connections += 1;
+            }
+            data_info.add_packets(
+                info.packets_sent,
+                info.bytes_sent,
+                TrafficDirection::Outgoing,
+            );
+            data_info.add_packets(
+                info.packets_received,
+                info.bytes_received,
+                TrafficDirection::Incoming,
+            );
+        }
+        let mut ports: Vec<_> = ports.into_iter().collect();
+        ports.sort_by_key(|(port, (_, _, data_info))| {
+            (Reverse(data_info.tot_data(data_repr)), *port)
+        });
+        ports
+            .into_iter()
+            .take(n)
+            .map(|(port, (service, connections, data_info))| PortData {
+                port,
+                service,
+                connections,
+                data: ExchangedData::new(&data_info),
+            })
+            .collect()
+    }
+
+    /// Returns the hosts that exchanged packets in the `window` before the packet timestamp `now`
+    fn recent_hosts(&self, now: Timestamp, window: Duration) -> Vec<RecentHostData> {
+        let mut hosts: Vec<_> = self
+            .info_traffic
+            .hosts
+            .iter()
+            .map(|(host, data_info_host)| {
+                let elapsed = elapsed_since(data_info_host.data_info.final_timestamp(), now);
+                (host, elapsed)
+            })
+            .filter(|(_, elapsed)| *elapsed <= window)
+            .collect();
+        hosts.sort_by_key(|(_, elapsed)| *elapsed);
+        hosts
+            .into_iter()
+            .map(|(host, elapsed)| RecentHostData {
+                host: host.clone(),
+                last_seen_ms_ago: elapsed.as_millis(),
+            })
+            .collect()
+    }
+
+    /// Returns the connections with public remote hosts
+    /// (i.e., neither bogons nor in the local network), sorted by the amount of bytes exchanged
+    pub fn get_external_connections(
+        &self,
+        my_interface_addresses: &[Address],
+    ) -> Vec<ConnectionData> {
+        let mut connections: Vec<_> = self
+            .info_traffic
+            .map
+            .iter()
+            .filter(|(key, info)| {
+                let remote_address = get_address_to_lookup(key, info.traffic_direction);
+                is_bogon(&remote_address).is_none()
+                    && !is_local_connection(&remote_address, my_interface_addresses)
+            })
+            .collect();
+        connections.sort_by(|(_, a), (_, b)| b.transmitted_bytes.cmp(&a.transmitted_bytes));
+        connections
+            .into_iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect()
+    }
+
+    /// Identifies the default gateway as the unicast MAC address shared by most of the remote
+    /// hosts outside the local network, and returns the traffic going through it compared
+    /// with the one delivered directly (connections without MAC addresses are left out)
+    pub fn get_gateway_traffic(&self, my_interface_addresses: &[Address]) -> GatewayTraffic {
+        let mut remote_hosts_by_mac: HashMap<&str, HashSet<IpAddr>> = HashMap::new();
+        for (key, info) in &self.info_traffic.map {
+            let Some((mac_address, mac_flags)) = info.remote_mac() else {
+                continue;
+            };
+            let remote_address = get_address_to_lookup(key, info.traffic_direction);
+            if mac_flags.is_none_or(|flags| !flags.multicast)
+                && !is_local_connection(&remote_address, my_interface_addresses)
+            {
+                remote_hosts_by_mac
+                    .entry(mac_address)
+                    .or_default()
+                    .insert(remote_address);
+            }
+        }
+        // ties are broken by the lowest MAC address, to always pick the same one
+        let gateway = remote_hosts_by_mac
+            .into_iter()
+            .max_by(|(mac_a, a), (mac_b, b)| a.len().cmp(&b.len()).then(mac_b.cmp(mac_a)));
+
+        let mut gateway_traffic = GatewayTraffic {
+            gateway_mac: gateway.as_ref().map(|(mac, _)| (*mac).to_string()),
+            remote_hosts: gateway.as_ref().map_or(0, |(_, hosts)| hosts.len()),
+            ..Default::default()
+        };
+        for info in self.info_traffic.map.values() {
+            let Some((mac_address, _)) = info.remote_mac() else {
+                continue;
+            };
+            if gateway_traffic.gateway_mac.as_deref() == Some(mac_address) {
+                gateway_traffic.through_gateway.add(info);
+            } else {
+                gateway_traffic.direct.add(info);
+            }
+        }
+        gateway_traffic
+    }
+
+    /// Returns the traffic exchanged with the remote hosts belonging to a CIDR block
+    pub fn get_stats_for_cidr(&self, cidr: &str) -> Result<CidrStats, String> {
+        let cidr = cidr.trim();
+        let collection = IpCollection::new(cidr)
+            .filter(|_| !cidr.is_empty())
+            .ok_or_else(|| format!("Invalid CIDR: {cidr}"))?;
+        let mut stats = CidrStats {
+            cidr: cidr.to_string(),
+            bytes: 0,
+            packets: 0,
+            hosts: 0,
+            connections: 0,
+        };
+        let mut hosts = HashSet::new();
+        for (key, info) in &self.info_traffic.map {
+            let remote_address = get_address_to_lookup(key, info.traffic_direction);
+            if collection.contains(&remote_address) {
+                stats.bytes = stats.bytes.saturating_add(info.transmitted_bytes);
+                stats.packets = stats.packets.saturating_add(info.transmitted_packets);
+                stats.connections += 1;
+                hosts.insert(remote_address);
+            }
+        }
+        stats.hosts = hosts.len();
+        Ok(stats)
+    }
+
+    /// Returns the local TCP ports accepting connections, i.e., the ones receiving connection
+    /// requests (SYN) or accepting them (SYN-ACK), sorted by port and address
+    pub fn get_local_listening_ports(&self) -> Vec<ListeningPortData> {
+        // remote endpoints connecting to each local port, with the service of the port
+        let mut listening: BTreeMap<(u16, IpAddr), (Service, HashSet<(IpAddr, Option<u16>)>)> =
+            BTreeMap::new();
+        for (key, info) in &self.info_traffic.map {
+            let (local, remote) = match info.traffic_direction {
+                TrafficDirection::Incoming if info.syn_segments > 0 => {
+                    ((key.address2, key.port2), (key.address1, key.port1))
+                }
+                TrafficDirection::Outgoing if info.syn_ack_segments > 0 => {
+                    ((key.address1, key.port1), (key.address2, key.port2))
+                }
+                _ => continue,
+            };
+            let (local_address, Some(local_port)) = local else {
+                continue;
+            };
+            listening
+                .entry((local_port, local_address))
+                .or_insert_with(|| (info.service, HashSet::new()))
+                .1
+                .insert(remote);
+        }
+        listening
+            .into_iter()
+            .map(|((port, address), (service, remote_endpoints))| {
+                let clients: HashSet<_> = remote_endpoints.iter().map(|(ip, _)| ip).collect();
+                ListeningPortData {
+                    address,
+                    port,
+                    service,
+                    connections: remote_endpoints.len(),
+                    clients: clients.len(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the distinct addresses of this host observed in the connections (e.g., aliases),
+    /// sorted; multicast, broadcast, and unspecified destinations are not considered local
+    pub fn get_observed_local_addresses(&self) -> Vec<IpAddr> {
+        self.info_traffic
+            .map
+            .iter()
+            .map(|(key, info)| get_local_address(key, info.traffic_direction))
+            .filter(|address| {
+                !address.is_unspecified()
+                    && !address.is_multicast()
+                    && !matches!(address, IpAddr::V4(v4) if v4.is_broadcast())
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the balance between incoming and outgoing bytes of each host,
+    /// sorted by the amount of bytes exchanged
+    pub fn get_directionality(&self) -> Vec<HostDirectionality> {
+        self.hosts_by_bytes()
+            .into_iter()
+            .map(|(host, data_info_host)| {
+                let data_info = &data_info_host.data_info;
+                HostDirectionality::new(
+                    host,
+                    data_info.incoming_data(DataRepr::Bytes),
+                    data_info.outgoing_data(DataRepr::Bytes),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the loopback, local, bogon, and traffic type flags of each host,
+    /// sorted by the amount of bytes exchanged
+    pub fn get_host_classifications(&self) -> Vec<HostClassification> {
+        self.hosts_by_bytes()
+            .into_iter()
+            .map(|(host, data_info_host)| HostClassification::new(host, data_info_host))
+            .collect()
+    }
+
+    /// Returns the traffic of each application protocol, sorted by the amount of bytes exchanged
+    pub fn get_app_protocol_breakdown(&self) -> Vec<AppProtocolData> {
+        let mut breakdown: HashMap<AppProtocol, AppProtocolData> = HashMap::new();
+        for info in self.info_traffic.map.values() {
+            let app_protocol = info.app_protocol();
+            let data = breakdown.entry(app_protocol).or_insert(AppProtocolData {
+                app_protocol,
+                connections: 0,
+                packets: 0,
+                bytes: 0,
+                avg_packet_size: 0,
+            });
+            data.connections += 1;
+            data.packets = data.packets.saturating_add(info.transmitted_packets);
+            data.bytes = data.bytes.saturating_add(info.transmitted_bytes);
+        }
+        for data in breakdown.values_mut() {
+            data.avg_packet_size = data.bytes.checked_div(data.packets).unwrap_or_default();
+        }
+        let mut breakdown: Vec<_> = breakdown.into_values().collect();
+        breakdown.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        breakdown
+    }
+
+    /// Returns the traffic of each local process, sorted by the amount of bytes exchanged
+    pub fn get_traffic_by_process(&self) -> Vec<ProcessTrafficData> {
+        let mut process_traffic: Vec<_> = self.process_traffic.iter().collect();
+        process_traffic.sort_by_key(|(process, data_info)| {
+            (
+                Reverse(data_info.tot_data(DataRepr::Bytes)),
+                process.as_ref().map(|p| p.pid),
+            )
+        });
+        process_traffic
+            .into_iter()
+            .map(|(process, data_info)| ProcessTrafficData {
+                name: process
+                    .as_ref()
+                    .map_or_else(|| "unknown".to_string(), |p| p.name.clone()),
+                pid: process.as_ref().map(|p| p.pid),
+                data: ExchangedData::new(data_info),
+            })
+            .collect()
+    }
+
+    /// Returns the traffic of each QoS class (DSCP value), sorted by the amount of bytes exchanged
+    pub fn get_qos_breakdown(&self) -> Vec<QosClassData> {
+        let mut dscp_data: Vec<_> = self.info_traffic.dscp_data.iter().collect();
+        dscp_data.sort_by_key(|(dscp, data_info)| {
+            (Reverse(data_info.tot_data(DataRepr::Bytes)), **dscp)
+        });
+        dscp_data
+            .into_iter()
+            .map(|(dscp, data_info)| QosClassData {
+                class: qos_class(*dscp),
+                dscp: *dscp,
+                data: ExchangedData::new(data_info),
+            })
+            .collect()
+    }
+
+    /// Estimates the cost of the bytes exchanged, given the price per gigabyte
+    pub fn estimate_data_cost(&self, price_per_gb: f64) -> Result<DataEstimate, String> {
+        DataEstimate::cost(self.total_bytes(), price_per_gb)
+    }
+
+    /// Estimates the carbon footprint of the bytes exchanged, given the grams of CO2 per gigabyte
+    pub fn estimate_data_footprint(&self, grams_co2_per_gb: f64) -> Result<DataEstimate, String> {
+        DataEstimate::footprint(self.total_bytes(), grams_co2_per_gb)
+    }
+
+    fn total_bytes(&self) -> u128 {
+        self.info_traffic.tot_data_info.tot_data(DataRepr::Bytes)
+    }
+
+    fn hosts_by_bytes(&self) -> Vec<(&Host, &DataInfoHost)> {
+        let mut hosts: Vec<_> = self.info_traffic.hosts.iter().collect();
+        hosts.sort_by(|(_, a), (_, b)| {
+            b.data_info
+                .tot_data(DataRepr::Bytes)
+                .cmp(&a.data_info.tot_data(DataRepr::Bytes))
+        });
+        hosts
+    }
+}
+
+/// Time elapsed between two packet timestamps (zero if `now` isn't later than `then`)
+fn elapsed_since(then: Timestamp, now: Timestamp) -> Duration {
+    let usecs = now
+        .to_usecs()
+        .zip(then.to_usecs())
+        .and_then(|(now, then)| u64::try_from(now.checked_sub(then)?).ok())
+        .unwrap_or_default();
+    Duration::from_micros(usecs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_monitor::traffic_data::{ConnectionProfile, Directionality};
+    use crate::networking::types::address_port_pair::AddressPortPair;
+    use crate::networking::types::capture_volume::CaptureVolume;
+    use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+    use crate::networking::types::mac_flags::MacFlags;
+    use crate::networking::types::my_link_type::MyLinkType;
+    use crate::networking::types::protocol::Protocol;
+    use crate::networking::types::service::Service;
+    use crate::networking::types::socket_table::SocketTable;
+    use crate::networking::types::traffic_direction::TrafficDirection;
+    use crate::networking::types::traffic_type::TrafficType;
+    use crate::utils::types::timestamp::Timestamp;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_traffic_data_exposes_timestamp_and_totals() {
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(
+            InfoTraffic {
+                tot_data_info: DataInfo::new_for_tests(3, 2, 1500, 400),
+                dropped_packets: 1,
+                last_packet_timestamp: Timestamp::new(1_700_000_000, 250),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+
+        let traffic_data = analyzer.get_traffic_data();
+        assert_eq!(traffic_data.total_packets, 5);
+        assert_eq!(traffic_data.total_bytes, 1900);
+        assert_eq!(traffic_data.incoming_bytes, 1500);
+        assert_eq!(traffic_data.outgoing_bytes, 400);
+        assert_eq!(traffic_data.dropped_packets, 1);
+
+        let json = serde_json::to_value(&traffic_data).unwrap();
+        assert_eq!(json["total_packets"], 5);
+        assert_eq!(json["total_bytes"], 1900);
+        assert_eq!(json["incoming_packets"], 3);
+        assert_eq!(json["outgoing_packets"], 2);
+        assert_eq!(json["last_packet_timestamp"]["secs"], 1_700_000_000);
+        assert_eq!(json["last_packet_timestamp"]["usecs"], 250);
+    }
+
+    #[test]
+    fn test_info_traffic_data_exposes_connections_services_and_hosts() {
+        let connection = |port: u16, bytes: u128| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(port),
+                    IpAddr::from([8, 8, 8, 8]),
+                    Some(53),
+                    Protocol::UDP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_bytes: bytes,
+                    transmitted_packets: 1,
+                    service: Service::Name("domain"),
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(
+            InfoTraffic {
+                tot_data_info: DataInfo::new_for_tests(2, 2, 300, 200),
+                dropped_packets: 4,
+                map: [connection(50000, 100), connection(50001, 400)]
+                    .into_iter()
+                    .collect(),
+                services: [
+                    (
+                        Service::Name("domain"),
+                        DataInfo::new_for_tests(2, 2, 300, 200),
+                    ),
+                    (Service::Unknown, DataInfo::default()),
+                ]
+                .into_iter()
+                .collect(),
+                hosts: [host("a.com", 10, 10), host("dns.google", 300, 200)]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+
+        let data = analyzer.get_info_traffic_data();
+        assert_eq!(data.traffic_data.total_bytes, 500);
+        assert_eq!(data.traffic_data.dropped_packets, 4);
+        let ports: Vec<_> = data.connections.iter().map(|c| c.key.port1).collect();
+        assert_eq!(ports, vec![Some(50001), Some(50000)]);
+
+        let json = serde_json::to_value(&data).unwrap();
+        assert_eq!(json["total_packets"], 4);
+        assert_eq!(json["dropped_packets"], 4);
+        assert_eq!(json["connections"][0]["address2"], "8.8.8.8");
+        assert_eq!(json["connections"][0]["port1"], 50001);
+        assert_eq!(json["connections"][0]["transmitted_bytes"], 400);
+        assert_eq!(json["services"][0]["service"], "domain");
+        assert_eq!(json["services"][0]["incoming_bytes"], 300);
+        assert_eq!(json["services"][1]["service"], "?");
+        assert_eq!(json["hosts"][0]["domain"], "dns.google");
+        assert_eq!(json["hosts"][0]["outgoing_bytes"], 200);
+        assert_eq!(json["hosts"][0]["is_local"], false);
+        assert_eq!(json["hosts"][1]["domain"], "a.com");
+    }
+
+    fn host(domain: &str, incoming_bytes: u128, outgoing_bytes: u128) -> (Host, DataInfoHost) {
+        (
+            Host {
+                domain: domain.to_string(),
+                ..Default::default()
+            },
+            DataInfoHost {
+                data_info: DataInfo::new_for_tests(1, 1, incoming_bytes, outgoing_bytes),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// A host whose latest packet was sent at `secs`
+    fn host_seen_at(domain: &str, secs: i64) -> (Host, DataInfoHost) {
+        let (host, mut data_info_host) = host(domain, 100, 10);
+        data_info_host.data_info = data_info_host
+            .data_info
+            .with_final_timestamp(Timestamp::new(secs, 0));
+        (host, data_info_host)
+    }
+
+    /// A connection between `local` and `remote` (address and port) that transmitted
+    /// `packets` packets of `bytes` bytes in the given direction
+    fn connection(
+        (local, local_port): ([u8; 4], Option<u16>),
+        (remote, remote_port): ([u8; 4], Option<u16>),
+        protocol: Protocol,
+        traffic_direction: TrafficDirection,
+        (packets, bytes): (u128, u128),
+    ) -> (AddressPortPair, InfoAddressPortPair) {
+        let (local, remote) = (IpAddr::from(local), IpAddr::from(remote));
+        let (key, sent, received) = match traffic_direction {
+            TrafficDirection::Outgoing => (
+                AddressPortPair::new(local, local_port, remote, remote_port, protocol),
+                (packets, bytes),
+                (0, 0),
+            ),
+            TrafficDirection::Incoming => (
+                AddressPortPair::new(remote, remote_port, local, local_port, protocol),
+                (0, 0),
+                (packets, bytes),
+            ),
+        };
+        let info = InfoAddressPortPair {
+            transmitted_packets: packets,
+            transmitted_bytes: bytes,
+            packets_sent: sent.0,
+            bytes_sent: sent.1,
+            packets_received: received.0,
+            bytes_received: received.1,
+            traffic_direction,
+            ..Default::default()
+        };
+        (key, info)
+    }
+
+    #[test]
+    fn test_directionality_classification() {
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.hosts = [
+            host("upload.example.com", 1_000, 250_000),
+            host("download.example.com", 900_000, 3_000),
+            host("chat.example.com", 4_000, 2_000),
+        ]
+        .into_iter()
+        .collect();
+
+        let directionality = analyzer.get_directionality();
+        let domains: Vec<&str> = directionality
+            .iter()
+            .map(|h| h.host.domain.as_str())
+            .collect();
+        assert_eq!(
+            domains,
+            [
+                "download.example.com",
+                "upload.example.com",
+                "chat.example.com"
+            ]
+        );
+
+        assert_eq!(
+            directionality[0].directionality,
+            Directionality::DownloadHeavy
+        );
+        assert_eq!(directionality[0].ratio, Some(300.0));
+        assert_eq!(
+            directionality[1].directionality,
+            Directionality::UploadHeavy
+        );
+        assert_eq!(directionality[1].ratio, Some(0.004));
+        assert_eq!(directionality[2].directionality, Directionality::Balanced);
+        assert_eq!(directionality[2].ratio, Some(2.0));
+
+        // nothing sent to the host
+        assert_eq!(HostDirectionality::new(&Host::default(), 10, 0).ratio, None);
+        assert_eq!(Directionality::from_bytes(0, 0), Directionality::Balanced);
+    }
+
+    #[test]
+    fn test_host_classifications() {
+        let classified_host = |domain: &str, address: IpAddr, is_local, traffic_type| {
+            let (host, mut data_info_host) = host(domain, 100, 100);
+            data_info_host.is_loopback = address.is_loopback();
+            data_info_host.is_local = is_local;
+            data_info_host.is_bogon = is_bogon(&address);
+            data_info_host.traffic_type = traffic_type;
+            (host, data_info_host)
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.hosts = [
+            classified_host(
+                "router.lan",
+                IpAddr::from([192, 168, 1, 1]),
+                true,
+                TrafficType::Unicast,
+            ),
+            classified_host(
+                "localhost",
+                IpAddr::from([127, 0, 0, 1]),
+                true,
+                TrafficType::Unicast,
+            ),
+            classified_host(
+                "dns.google",
+                IpAddr::from([8, 8, 8, 8]),
+                false,
+                TrafficType::Unicast,
+            ),
+            classified_host(
+                "mdns.mcast.net",
+                IpAddr::from([224, 0, 0, 251]),
+                false,
+                TrafficType::Multicast,
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let classifications = analyzer.get_host_classifications();
+        assert_eq!(classifications.len(), 4);
+        let classification = |domain: &str| {
+            classifications
+                .iter()
+                .find(|c| c.host.domain == domain)
+                .unwrap()
+        };
+
+        let router = classification("router.lan");
+        assert!(router.is_local && !router.is_loopback && router.is_bogon);
+        assert_eq!(router.bogon_description, Some("private-use"));
+
+        let localhost = classification("localhost");
+        assert!(localhost.is_local && localhost.is_loopback && localhost.is_bogon);
+        assert_eq!(localhost.bogon_description, Some("loopback"));
+
+        let public = classification("dns.google");
+        assert!(!public.is_local && !public.is_loopback && !public.is_bogon);
+        assert_eq!(public.bogon_description, None);
+        assert_eq!(public.traffic_type, TrafficType::Unicast);
+
+        let multicast = classification("mdns.mcast.net");
+        assert!(!multicast.is_local && multicast.is_bogon);
+        assert_eq!(multicast.bogon_description, Some("multicast"));
+        assert_eq!(multicast.traffic_type, TrafficType::Multicast);
+
+        let json = serde_json::to_value(router).unwrap();
+        assert_eq!(json["domain"], "router.lan");
+        assert_eq!(json["bogon_description"], "private-use");
+        assert_eq!(json["traffic_type"], "Unicast");
+    }
+
+    #[test]
+    fn test_evict_least_recently_active_hosts() {
+        let mut analyzer = TrafficAnalyzer::new(RetentionPolicy {
+            max_hosts: Some(2),
+            ..RetentionPolicy::default()
+        });
+        for (domain, incoming_bytes, outgoing_bytes, final_secs) in [
+            ("old.example.com", 1_000, 100, 101),
+            ("older.example.com", 500, 50, 100),
+            ("recent.example.com", 2_000, 200, 102),
+            ("newest.example.com", 3_000, 300, 103),
+        ] {
+            let (host, mut data_info_host) = host(domain, incoming_bytes, outgoing_bytes);
+            data_info_host.data_info = data_info_host
+                .data_info
+                .with_final_timestamp(Timestamp::new(final_secs, 0));
+            analyzer.add_hosts(vec![HostMessage {
+                host,
+                data_info_host,
+                address_to_lookup: IpAddr::from([203, 0, 113, 1]),
+                rdns: domain.to_string(),
+            }]);
+        }
+
+        let mut evicted: Vec<String> = analyzer
+            .evict_hosts()
+            .into_iter()
+            .map(|h| h.domain)
+            .collect();
+        evicted.sort();
+        assert_eq!(evicted, ["old.example.com", "older.example.com"]);
+        let mut kept: Vec<&str> = analyzer
+            .info_traffic
+            .hosts
+            .keys()
+            .map(|h| h.domain.as_str())
+            .collect();
+        kept.sort_unstable();
+        assert_eq!(kept, ["newest.example.com", "recent.example.com"]);
+
+        let other_hosts = analyzer.get_other_hosts();
+        assert_eq!(other_hosts.hosts, 2);
+        assert_eq!(other_hosts.incoming_packets, 2);
+        assert_eq!(other_hosts.outgoing_packets, 2);
+        assert_eq!(other_hosts.incoming_bytes, 1_500);
+        assert_eq!(other_hosts.outgoing_bytes, 150);
+
+        // nothing else to evict
+        assert!(analyzer.evict_hosts().is_empty());
+        assert_eq!(analyzer.get_other_hosts().hosts, 2);
+    }
+
+    #[test]
+    fn test_retention_policy_limits() {
+        let mut analyzer = TrafficAnalyzer::new(RetentionPolicy {
+            max_age_secs: Some(60),
+            max_hosts: Some(2),
+            max_connections: Some(2),
+        });
+
+        analyzer.info_traffic.last_packet_timestamp = Timestamp::new(200, 0);
+        analyzer.info_traffic.map = [(50000, 100), (50001, 150), (50002, 170), (50003, 190)]
+            .into_iter()
+            .map(|(port, final_secs)| {
+                (
+                    AddressPortPair::new(
+                        IpAddr::from([192, 168, 1, 2]),
+                        Some(port),
+                        IpAddr::from([93, 184, 216, 34]),
+                        Some(443),
+                        Protocol::TCP,
+                    ),
+                    InfoAddressPortPair {
+                        transmitted_packets: 1,
+                        initial_timestamp: Timestamp::new(100, 0),
+                        final_timestamp: Timestamp::new(final_secs, 0),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        // only the stale host has been inactive for more than a minute
+        analyzer.info_traffic.hosts = [
+            host_seen_at("stale.example.com", 120),
+            host_seen_at("old.example.com", 150),
+            host_seen_at("recent.example.com", 160),
+            host_seen_at("newest.example.com", 170),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut evicted: Vec<String> = analyzer
+            .apply_retention()
+            .into_iter()
+            .map(|h| h.domain)
+            .collect();
+        evicted.sort();
+        // aged out, and then exceeding the maximum number of hosts
+        assert_eq!(evicted, ["old.example.com", "stale.example.com"]);
+        assert_eq!(analyzer.info_traffic.hosts.len(), 2);
+        assert_eq!(analyzer.get_other_hosts().hosts, 2);
+
+        // the connection inactive for 100 seconds expired,
+        // and the least recently active of the others exceeded the maximum number of connections
+        let mut ports: Vec<_> = analyzer
+            .info_traffic
+            .map
+            .keys()
+            .filter_map(|key| key.port1)
+            .collect();
+        ports.sort_unstable();
+        assert_eq!(ports, [50002, 50003]);
+
+        // nothing else exceeds the policy
+        assert!(analyzer.apply_retention().is_empty());
+        assert_eq!(analyzer.info_traffic.map.len(), 2);
+    }
+
+    #[test]
+    fn test_top_ports() {
+        // a quarter of the bytes sent in one packet, the rest received in two
+        let exchange = |remote_last_octet: u8,
+                        (local_port, remote_port): (Option<u16>, Option<u16>),
+                        protocol: Protocol,
+                        traffic_direction: TrafficDirection,
+                        bytes: u128| {
+            let (key, info) = connection(
+                ([192, 168, 1, 2], local_port),
+                ([93, 184, 216, remote_last_octet], remote_port),
+                protocol,
+                traffic_direction,
+                (3, bytes),
+            );
+            let info = InfoAddressPortPair {
+                packets_sent: 1,
+                bytes_sent: bytes / 4,
+                packets_received: 2,
+                bytes_received: bytes - bytes / 4,
+                service: match remote_port {
+                    Some(443) => Service::Name("https"),
+                    Some(53) => Service::Name("domain"),
+                    _ => Service::Unknown,
+                },
+                ..info
+            };
+            (key, info)
+        };
+        let ports = |local_port, remote_port| (Some(local_port), Some(remote_port));
+        let (tcp, udp) = (Protocol::TCP, Protocol::UDP);
+        let outgoing = TrafficDirection::Outgoing;
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            exchange(1, ports(50000, 443), tcp, outgoing, 40_000),
+            exchange(2, ports(50001, 443), tcp, outgoing, 25_000),
+            // QUIC
+            exchange(2, ports(50002, 443), udp, outgoing, 15_000),
+            exchange(3, ports(50003, 53), udp, outgoing, 600),
+            exchange(4, ports(50004, 53), udp, outgoing, 400),
+            exchange(5, ports(50005, 8080), tcp, outgoing, 2_000),
+            // no ports: left out
+            exchange(6, (None, None), Protocol::ICMP, outgoing, 100_000),
+            // incoming connection to a local server: the remote port is the one of the client
+            exchange(7, ports(22, 40000), tcp, TrafficDirection::Incoming, 1_200),
+        ]
+        .into_iter()
+        .collect();
+
+        let top_ports = analyzer.get_top_ports(3, DataRepr::Bytes);
+        let ports: Vec<_> = top_ports.iter().map(|p| p.port).collect();
+        assert_eq!(ports, [443, 8080, 40000]);
+        let https = &top_ports[0];
+        assert_eq!(https.service, Service::Name("https"));
+        assert_eq!(https.connections, 3);
+        assert_eq!(https.data.outgoing_bytes, 20_000);
+        assert_eq!(https.data.incoming_bytes, 60_000);
+        assert_eq!(https.data.incoming_packets, 6);
+
+        // DNS exchanged the most packets after HTTPS
+        let top_ports = analyzer.get_top_ports(10, DataRepr::Packets);
+        assert_eq!(top_ports.len(), 4);
+        assert_eq!(top_ports[0].port, 443);
+        assert_eq!(top_ports[1].port, 53);
+        assert_eq!(top_ports[1].service, Service::Name("domain"));
+        assert_eq!(top_ports[1].data.outgoing_packets, 2);
+    }
+
+    #[test]
+    fn test_top_ports_count_bidirectional_connection_once() {
+        let local = IpAddr::from([192, 168, 1, 2]);
+        let remote = IpAddr::from([93, 184, 216, 34]);
+        let outgoing = AddressPortPair::new(local, Some(50000), remote, Some(443), Protocol::TCP);
+        let info = |traffic_direction, packets_sent, packets_received| InfoAddressPortPair {
+            traffic_direction,
+            packets_sent,
+            bytes_sent: packets_sent * 100,
+            packets_received,
+            bytes_received: packets_received * 1000,
+            service: Service::Name("https"),
+            ..Default::default()
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            (outgoing, info(TrafficDirection::Outgoing, 3, 0)),
+            (outgoing.reversed(), info(TrafficDirection::Incoming, 0, 5)),
+        ]
+        .into_iter()
+        .collect();
+        analyzer.info_traffic.merge_directional_counters(&outgoing);
+
+        let top_ports = analyzer.get_top_ports(10, DataRepr::Bytes);
+        assert_eq!(top_ports.len(), 1);
+        assert_eq!(top_ports[0].port, 443);
+        assert_eq!(top_ports[0].connections, 1);
+        assert_eq!(top_ports[0].data.outgoing_packets, 3);
+        assert_eq!(top_ports[0].data.incoming_packets, 5);
+        assert_eq!(top_ports[0].data.incoming_bytes, 5_000);
+    }
+
+    #[test]
+    fn test_app_protocol_breakdown() {
+        let connection = |port: u16, bytes: u128, app_protocol: Option<AppProtocol>| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(port),
+                    IpAddr::from([93, 184, 216, 34]),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_bytes: bytes,
+                    transmitted_packets: 2,
+                    service: Service::Name("https"),
+                    app_protocol,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            // classified from the service
+            connection(50000, 4_000, None),
+            connection(50001, 1_000, Some(AppProtocol::Tls)),
+            // plain HTTP over port 443, detected from the payload
+            connection(50002, 6_000, Some(AppProtocol::Http)),
+        ]
+        .into_iter()
+        .collect();
+
+        let breakdown = analyzer.get_app_protocol_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[0].app_protocol, AppProtocol::Http);
+        assert_eq!(breakdown[0].connections, 1);
+        assert_eq!(breakdown[0].bytes, 6_000);
+        assert_eq!(breakdown[1].app_protocol, AppProtocol::Tls);
+        assert_eq!(breakdown[1].connections, 2);
+        assert_eq!(breakdown[1].packets, 4);
+        assert_eq!(breakdown[1].bytes, 5_000);
+    }
+
+    #[test]
+    fn test_session_info_accounts_dropped_packets() {
+        let interval = |packets: u128, dropped_packets: u32| InfoTraffic {
+            dropped_packets,
+            skipped_writes: packets / 2,
+            capture_volume: CaptureVolume {
+                packets,
+                wire_bytes: packets * 1_000,
+                captured_bytes: packets * 200,
+                snaplen_truncated_packets: packets,
+            },
+            ..Default::default()
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(interval(6, 0), Vec::new());
+        let info = analyzer.get_session_info(3, Some(200));
+        assert_eq!(info.analyzed_bytes, 1_200);
+        assert_eq!(info.wire_bytes, 6_000);
+        assert_eq!(info.estimated_wire_bytes, 6_000);
+        assert_eq!(info.skipped_writes, 3);
+
+        // the dropped packets count is cumulative
+        analyzer.refresh(interval(4, 5), Vec::new());
+        let info = analyzer.get_session_info(3, Some(200));
+        assert_eq!(info.cap_id, 3);
+        assert_eq!(info.snaplen, Some(200));
+        assert_eq!(info.captured_packets, 10);
+        assert_eq!(info.snaplen_truncated_packets, 10);
+        assert_eq!(info.dropped_packets, 5);
+        // skipped writes are counted per interval, like the snaplen-truncated packets
+        assert_eq!(info.skipped_writes, 5);
+        assert_eq!(info.analyzed_bytes, 2_000);
+        assert_eq!(info.wire_bytes, 10_000);
+        // the dropped packets are estimated as large as the average one
+        assert_eq!(info.estimated_wire_bytes, 15_000);
+    }
+
+    #[test]
+    fn test_traffic_by_process() {
+        let process = |pid, name: &str| LocalProcess {
+            pid,
+            name: name.to_string(),
+        };
+        let owners = HashMap::from([
+            (1111, process(4051, "firefox")),
+            (2222, process(977, "spotify")),
+        ]);
+        // TCP sockets bound to the unspecified address, listed as in /proc/net/tcp
+        let socket_table = |sockets: &[(u16, u64)]| {
+            let entries = sockets.iter().map(|(port, inode)| {
+                format!("   0: 00000000:{port:04X} 00000000:0000 0A 0:0 0:0 0 1000 0 {inode} 1")
+            });
+            let proc_net = std::iter::once("  sl  local_address rem_address ... inode".to_string())
+                .chain(entries)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut socket_table = SocketTable::default();
+            socket_table.add_proc_net_entries(Protocol::TCP, &proc_net, &owners);
+            socket_table
+        };
+        let connection = |port| {
+            AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(port),
+                IpAddr::from([151, 101, 1, 140]),
+                Some(443),
+                Protocol::TCP,
+            )
+        };
+        // connections labelled with their process, as done while parsing the packets
+        let interval = |socket_table: &SocketTable, traffic: &[(u16, u128, u128)]| InfoTraffic {
+            map: traffic
+                .iter()
+                .map(|&(port, bytes_sent, bytes_received)| {
+                    let key = connection(port);
+                    let process = socket_table.process_for(&key, TrafficDirection::Outgoing);
+                    let info = InfoAddressPortPair {
+                        bytes_sent,
+                        packets_sent: 1,
+                        bytes_received,
+                        packets_received: 1,
+                        traffic_direction: TrafficDirection::Outgoing,
+                        pid: process.as_ref().map(|p| p.pid),
+                        process: process.map(|p| p.name),
+                        ..Default::default()
+                    };
+                    (key, info)
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut analyzer = TrafficAnalyzer::default();
+        let both_running = socket_table(&[(50000, 1111), (50001, 2222)]);
+        analyzer.refresh(
+            interval(&both_running, &[(50000, 100, 3_000), (50001, 60, 1_500)]),
+            Vec::new(),
+        );
+        // firefox exited, but a packet of its connection was still captured
+        let firefox_exited = socket_table(&[(50001, 2222)]);
+        analyzer.refresh(
+            interval(&firefox_exited, &[(50000, 100, 0), (50001, 0, 1_500)]),
+            Vec::new(),
+        );
+
+        let by_process = analyzer.get_traffic_by_process();
+        let summary: Vec<_> = by_process
+            .iter()
+            .map(|p| {
+                (
+                    p.name.as_str(),
+                    p.pid,
+                    p.data.outgoing_bytes,
+                    p.data.incoming_bytes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("firefox", Some(4051), 100, 3_000),
+                ("spotify", Some(977), 60, 3_000),
+                ("unknown", None, 100, 0),
+            ]
+        );
+        assert_eq!(by_process[1].data.incoming_packets, 2);
+        // the connection itself keeps the process it was attributed to
+        let info = &analyzer.info_traffic.map[&connection(50000)];
+        assert_eq!(info.process.as_deref(), Some("firefox"));
+        assert_eq!(info.pid, Some(4051));
+    }
+
+    #[test]
+    fn test_capture_stats_of_latest_interval() {
+        let interval = |received_packets, dropped_packets, interface_dropped_packets| InfoTraffic {
+            received_packets,
+            dropped_packets,
+            interface_dropped_packets,
+            ..Default::default()
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(interval(120, 0, 0), Vec::new());
+        analyzer.refresh(interval(300, 40, 2), Vec::new());
+
+        // the counters of libpcap are cumulative, so only the latest ones are reported
+        let stats = analyzer.get_capture_stats(3, Duration::from_millis(2_500));
+        assert_eq!(stats.cap_id, 3);
+        assert_eq!(stats.received, 300);
+        assert_eq!(stats.dropped, 40);
+        assert_eq!(stats.if_dropped, 2);
+        assert!((stats.duration_secs - 2.5).abs() < f64::EPSILON);
+
+        // the counters survive snapshots, which only reset the traffic of the interval
+        analyzer.take_traffic_snapshot();
+        let stats = analyzer.get_capture_stats(3, Duration::from_secs(3));
+        assert_eq!(
+            (stats.received, stats.dropped, stats.if_dropped),
+            (300, 40, 2)
+        );
+    }
+
+    #[test]
+    fn test_asymmetric_flows() {
+        use TrafficDirection::{Incoming, Outgoing};
+
+        let flow = |client_port: u16, protocol, traffic_direction, bytes| {
+            connection(
+                ([192, 168, 1, 2], Some(client_port)),
+                ([93, 184, 216, 34], Some(443)),
+                protocol,
+                traffic_direction,
+                (2, bytes),
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            // SYNs never answered
+            flow(50000, Protocol::TCP, Outgoing, 120),
+            // both directions seen
+            flow(50001, Protocol::TCP, Outgoing, 500),
+            flow(50001, Protocol::TCP, Incoming, 3_000),
+            // return traffic only
+            flow(50002, Protocol::TCP, Incoming, 1_000),
+            // one-way UDP is common (e.g., streaming), and isn't flagged
+            flow(50003, Protocol::UDP, Outgoing, 800),
+        ]
+        .into_iter()
+        .collect();
+
+        let flows = analyzer.get_asymmetric_flows();
+        assert_eq!(flows.len(), 2);
+        assert_eq!(flows[0].key.port2, Some(50002));
+        assert_eq!(flows[0].traffic_direction, Incoming);
+        assert_eq!(flows[0].bytes, 1_000);
+        assert_eq!(flows[1].key.port1, Some(50000));
+        assert_eq!(flows[1].traffic_direction, Outgoing);
+        assert_eq!(flows[1].packets, 2);
+    }
+
+    #[test]
+    fn test_app_protocol_average_packet_size() {
+        let query = |port: u16, traffic: (u128, u128), app_protocol: AppProtocol| {
+            let (key, mut info) = connection(
+                ([192, 168, 1, 2], Some(port)),
+                ([8, 8, 8, 8], Some(53)),
+                Protocol::UDP,
+                TrafficDirection::Outgoing,
+                traffic,
+            );
+            info.app_protocol = Some(app_protocol);
+            (key, info)
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            query(50000, (4, 300), AppProtocol::Dns),
+            query(50001, (6, 500), AppProtocol::Dns),
+            // a connection without packets doesn't divide by zero
+            query(50002, (0, 0), AppProtocol::Tls),
+        ]
+        .into_iter()
+        .collect();
+
+        let breakdown = analyzer.get_app_protocol_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[0].app_protocol, AppProtocol::Dns);
+        assert_eq!(breakdown[0].avg_packet_size, 80);
+        assert_eq!(breakdown[1].app_protocol, AppProtocol::Tls);
+        assert_eq!(breakdown[1].avg_packet_size, 0);
+    }
+
+    #[test]
+    fn test_consecutive_snapshots_partition_packets() {
+        let interval = |incoming_packets, outgoing_packets, secs| InfoTraffic {
+            tot_data_info: DataInfo::new_for_tests(
+                incoming_packets,
+                outgoing_packets,
+                incoming_packets * 100,
+                outgoing_packets * 100,
+            ),
+            last_packet_timestamp: Timestamp::new(secs, 0),
+            ..Default::default()
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.refresh(interval(3, 1, 10), Vec::new());
+        analyzer.refresh(interval(2, 2, 11), Vec::new());
+
+        let first = analyzer.take_traffic_snapshot();
+        assert_eq!(first.traffic_data.total_packets, 8);
+        assert_eq!(first.traffic_data.total_bytes, 800);
+        assert_eq!(
+            first.traffic_data.last_packet_timestamp,
+            Timestamp::new(11, 0)
+        );
+
+        analyzer.refresh(interval(5, 0, 12), Vec::new());
+        let second = analyzer.take_traffic_snapshot();
+        assert_eq!(second.traffic_data.total_packets, 5);
+        assert_eq!(second.traffic_data.incoming_packets, 5);
+        assert_eq!(
+            second.traffic_data.last_packet_timestamp,
+            Timestamp::new(12, 0)
+        );
+
+        // nothing left to take
+        let third = analyzer.take_traffic_snapshot();
+        assert_eq!(third.traffic_data.total_packets, 0);
+        assert!(third.connections.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_leave_traffic_data_untouched() {
+        let mut analyzer = TrafficAnalyzer::default();
+        let interval = || InfoTraffic {
+            tot_data_info: DataInfo::new_for_tests(3, 1, 300, 100),
+            ..Default::default()
+        };
+        analyzer.refresh(interval(), Vec::new());
+        let before = analyzer.get_traffic_data();
+        analyzer.take_traffic_snapshot();
+        let after = analyzer.get_traffic_data();
+        assert_eq!(
+            (after.total_packets, after.total_bytes),
+            (before.total_packets, before.total_bytes)
+        );
+        assert_eq!((after.total_packets, after.total_bytes), (4, 400));
+
+        // traffic keeps accumulating across snapshots
+        analyzer.refresh(interval(), Vec::new());
+        analyzer.take_traffic_snapshot();
+        let after = analyzer.get_traffic_data();
+        assert_eq!((after.total_packets, after.total_bytes), (8, 800));
+        assert_eq!(after.incoming_bytes, 600);
+    }
+
+    #[test]
+    fn test_connection_profiles() {
+        let connection = |port: u16, packets: u128, bytes: u128, duration_secs: i64| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(port),
+                    IpAddr::from([93, 184, 216, 34]),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_packets: packets,
+                    transmitted_bytes: bytes,
+                    initial_timestamp: Timestamp::new(100, 0),
+                    final_timestamp: Timestamp::new(100 + duration_secs, 0),
+                    ..Default::default()
+                },
+            )
+        };
+        // 401 medium-sized packets in 4 seconds
+        let medium = |port: u16, bytes: u128, sum_squared_intervals: u128| {
+            let (key, mut info) = connection(port, 401, bytes, 4);
+            info.sum_squared_intervals = sum_squared_intervals;
+            (key, info)
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            // 10_000 full-sized packets in 10 seconds
+            connection(50000, 10_000, 15_000_000, 10),
+            // 60 small packets in a minute
+            connection(50001, 60, 6_000, 60),
+            // a single packet
+            connection(50002, 1, 60, 0),
+            // a packet every 10 milliseconds
+            medium(50003, 200_500, 400 * 10_000 * 10_000),
+            // all the packets at once, apart from one 4 seconds later
+            medium(50004, 200_900, 4_000_000 * 4_000_000),
+        ]
+        .into_iter()
+        .collect();
+
+        let profiles = analyzer.get_connection_profiles();
+        assert_eq!(profiles.len(), 5);
+        assert_eq!(profiles[0].key.port1, Some(50000));
+        assert_eq!(profiles[0].profile, ConnectionProfile::Background);
+        assert_eq!(profiles[0].avg_packet_size, 1_500);
+        assert_eq!(profiles[0].mean_interval_usecs, 1_000);
+        // same size and pace, told apart by their burstiness
+        assert_eq!(profiles[1].key.port1, Some(50004));
+        assert_eq!(profiles[1].profile, ConnectionProfile::Interactive);
+        assert_eq!(profiles[1].mean_interval_usecs, 10_000);
+        assert!(profiles[1].burstiness > 10.0);
+        assert_eq!(profiles[2].key.port1, Some(50003));
+        assert_eq!(profiles[2].profile, ConnectionProfile::Background);
+        assert_eq!(profiles[2].mean_interval_usecs, 10_000);
+        assert!(profiles[2].burstiness < 1e-6);
+        assert_eq!(profiles[3].key.port1, Some(50001));
+        assert_eq!(profiles[3].profile, ConnectionProfile::Interactive);
+        assert_eq!(profiles[3].mean_interval_usecs, 1_016_949);
+        assert_eq!(profiles[4].profile, ConnectionProfile::Undetermined);
+    }
+
+    #[test]
+    fn test_recent_hosts() {
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.hosts = [
+            host_seen_at("old.example.com", 100),
+            host_seen_at("recent.example.com", 150),
+            host_seen_at("newest.example.com", 155),
+        ]
+        .into_iter()
+        .collect();
+
+        // the window includes the latest activity, but not the old host
+        let now = Timestamp::new(160, 0);
+        let recent: Vec<_> = analyzer
+            .recent_hosts(now, Duration::from_secs(30))
+            .into_iter()
+            .map(|h| (h.host.domain, h.last_seen_ms_ago))
+            .collect();
+        assert_eq!(
+            recent,
+            [
+                ("newest.example.com".to_string(), 5_000),
+                ("recent.example.com".to_string(), 10_000)
+            ]
+        );
+
+        // after enough time, all the hosts fall out of the window
+        let later = Timestamp::new(300, 0);
+        assert!(
+            analyzer
+                .recent_hosts(later, Duration::from_secs(60))
+                .is_empty()
+        );
+
+        // measured up to the latest packet
+        analyzer.info_traffic.last_packet_timestamp = now;
+        assert_eq!(analyzer.get_recent_hosts(60).len(), 3);
+        assert_eq!(analyzer.get_recent_hosts(30).len(), 2);
+    }
+
+    #[test]
+    fn test_top_hosts() {
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.hosts = [
+            host("small.example.com", 100, 200),
+            host("large.example.com", 90_000, 10_000),
+            host("medium.example.com", 4_000, 2_000),
+        ]
+        .into_iter()
+        .collect();
+        // the most packets were exchanged with the small host
+        analyzer
+            .info_traffic
+            .hosts
+            .values_mut()
+            .find(|data_info_host| data_info_host.data_info.tot_data(DataRepr::Bytes) == 300)
+            .unwrap()
+            .data_info
+            .add_packets(50, 0, TrafficDirection::Incoming);
+        let top_hosts = |n, sort_type, data_repr| -> Vec<String> {
+            analyzer
+                .get_top_hosts(n, sort_type, data_repr)
+                .into_iter()
+                .map(|h| h.classification.host.domain)
+                .collect()
+        };
+
+        assert_eq!(
+            top_hosts(2, SortType::Descending, DataRepr::Bytes),
+            ["large.example.com", "medium.example.com"]
+        );
+        assert_eq!(
+            top_hosts(2, SortType::Ascending, DataRepr::Bits),
+            ["small.example.com", "medium.example.com"]
+        );
+        assert_eq!(
+            top_hosts(1, SortType::Descending, DataRepr::Packets),
+            ["small.example.com"]
+        );
+        assert_eq!(
+            top_hosts(10, SortType::Descending, DataRepr::Bytes).len(),
+            3
+        );
+        assert!(top_hosts(0, SortType::Descending, DataRepr::Bytes).is_empty());
+
+        let top_host = analyzer.get_top_hosts(1, SortType::Descending, DataRepr::Bytes);
+        let json = serde_json::to_value(top_host).unwrap();
+        assert_eq!(json[0]["domain"], "large.example.com");
+        assert_eq!(json[0]["incoming_bytes"], 90_000);
+        assert_eq!(json[0]["outgoing_bytes"], 10_000);
+    }
+
+    #[test]
+    fn test_take_drop_delta() {
+        let interval = |dropped_packets, interface_dropped_packets| InfoTraffic {
+            dropped_packets,
+            interface_dropped_packets,
+            ..Default::default()
+        };
+        let delta = |kernel_dropped, interface_dropped| DropDelta {
+            kernel_dropped,
+            interface_dropped,
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        assert_eq!(analyzer.take_drop_delta(), delta(0, 0));
+
+        analyzer.refresh(interval(5, 1), Vec::new());
+        assert_eq!(analyzer.take_drop_delta(), delta(5, 1));
+        // no new drops since the previous call
+        assert_eq!(analyzer.take_drop_delta(), delta(0, 0));
+        analyzer.refresh(interval(5, 1), Vec::new());
+        assert_eq!(analyzer.take_drop_delta(), delta(0, 0));
+
+        analyzer.refresh(interval(8, 1), Vec::new());
+        assert_eq!(analyzer.take_drop_delta(), delta(3, 0));
+
+        // the counters wrapped around
+        analyzer.refresh(interval(u32::MAX, 1), Vec::new());
+        analyzer.take_drop_delta();
+        analyzer.refresh(interval(2, 1), Vec::new());
+        assert_eq!(analyzer.take_drop_delta(), delta(3, 0));
+    }
+
+    #[test]
+    fn test_new_flows_since_baseline() {
+        let flow = |port: u16, secs| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(port),
+                    IpAddr::from([1, 1, 1, 1]),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_packets: 1,
+                    initial_timestamp: Timestamp::new(secs, 0),
+                    final_timestamp: Timestamp::new(secs, 0),
+                    ..Default::default()
+                },
+            )
+        };
+        let interval = |flows: Vec<(AddressPortPair, InfoAddressPortPair)>| InfoTraffic {
+            map: flows.into_iter().collect(),
+            ..Default::default()
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        assert_eq!(
+            analyzer.get_new_flows_since_baseline().err().unwrap(),
+            "No baseline taken"
+        );
+
+        analyzer.refresh(interval(vec![flow(50000, 10), flow(50001, 11)]), Vec::new());
+        assert_eq!(analyzer.set_baseline(), 2);
+        assert!(analyzer.get_new_flows_since_baseline().unwrap().is_empty());
+
+        // a flow of the baseline keeps exchanging packets, and a new one appears
+        analyzer.refresh(interval(vec![flow(50001, 12), flow(50002, 12)]), Vec::new());
+        let new_flows = analyzer.get_new_flows_since_baseline().unwrap();
+        assert_eq!(new_flows.len(), 1);
+        assert_eq!(new_flows[0].key.port1, Some(50002));
+
+        // a new baseline includes it
+        assert_eq!(analyzer.set_baseline(), 3);
+        assert!(analyzer.get_new_flows_since_baseline().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_favorite_hosts() {
+        let mut analyzer = TrafficAnalyzer::default();
+        let (small_host, small_data) = host("small.example.com", 100, 200);
+        let (large_host, large_data) = host("large.example.com", 90_000, 10_000);
+        let (unseen_host, _) = host("unseen.example.com", 0, 0);
+        analyzer.info_traffic.hosts = [
+            (small_host.clone(), small_data),
+            (large_host.clone(), large_data),
+        ]
+        .into_iter()
+        .collect();
+
+        analyzer.set_favorite_host(&small_host, true);
+        analyzer.set_favorite_host(&unseen_host, true);
+        assert!(analyzer.info_traffic.hosts[&small_host].is_favorite);
+        assert!(!analyzer.info_traffic.hosts[&large_host].is_favorite);
+
+        // the flag set by the parsing thread in the next intervals is kept
+        let mut msg = InfoTraffic {
+            hosts: HashMap::from([(small_host.clone(), small_data)]),
+            ..Default::default()
+        };
+        msg.hosts.get_mut(&small_host).unwrap().is_favorite = true;
+        analyzer.refresh(msg, Vec::new());
+        assert!(analyzer.info_traffic.hosts[&small_host].is_favorite);
+
+        let favorites = HashSet::from([small_host.clone(), large_host.clone(), unseen_host]);
+        let domains: Vec<_> = analyzer
+            .get_favorite_hosts(&favorites)
+            .into_iter()
+            .map(|h| (h.classification.host.domain, h.data.incoming_bytes))
+            .collect();
+        assert_eq!(
+            domains,
+            [
+                ("large.example.com".to_string(), 90_000),
+                ("small.example.com".to_string(), 200),
+                ("unseen.example.com".to_string(), 0),
+            ]
+        );
+
+        analyzer.set_favorite_host(&small_host, false);
+        assert!(!analyzer.info_traffic.hosts[&small_host].is_favorite);
+    }
+
+    #[test]
+    fn test_top_services() {
+        let mut analyzer = TrafficAnalyzer::default();
+        let data_info = DataInfo::new_for_tests;
+        analyzer.info_traffic.services = HashMap::from([
+            (Service::Name("https"), data_info(40, 30, 90_000, 10_000)),
+            (Service::Name("domain"), data_info(50, 50, 3_000, 2_000)),
+            (Service::Unknown, data_info(2, 1, 800, 400)),
+            (Service::NotApplicable, data_info(500, 0, 150_000, 0)),
+        ]);
+        let top_services = |n, sort_type, data_repr| -> Vec<String> {
+            analyzer
+                .get_top_services(n, sort_type, data_repr)
+                .into_iter()
+                .map(|s| s.service.to_string())
+                .collect()
+        };
+
+        // traffic without a service isn't ranked
+        assert_eq!(
+            top_services(10, SortType::Descending, DataRepr::Bytes),
+            ["https", "domain", "?"]
+        );
+        assert_eq!(
+            top_services(1, SortType::Descending, DataRepr::Packets),
+            ["domain"]
+        );
+        assert_eq!(
+            top_services(2, SortType::Ascending, DataRepr::Bits),
+            ["?", "domain"]
+        );
+
+        // services are serialized by name
+        let top_service = analyzer.get_top_services(1, SortType::Descending, DataRepr::Bytes);
+        let json = serde_json::to_value(top_service).unwrap();
+        assert_eq!(json[0]["service"], "https");
+        assert_eq!(json[0]["incoming_bytes"], 90_000);
+        assert_eq!(json[0]["outgoing_packets"], 30);
+    }
+
+    #[test]
+    fn test_external_connections() {
+        let connection = |remote: [u8; 4], direction, bytes| {
+            let (local, remote) = (IpAddr::from([80, 1, 2, 3]), IpAddr::from(remote));
+            let (address1, address2) = match direction {
+                TrafficDirection::Outgoing => (local, remote),
+                TrafficDirection::Incoming => (remote, local),
+            };
+            (
+                AddressPortPair::new(address1, Some(50000), address2, Some(443), Protocol::TCP),
+                InfoAddressPortPair {
+                    transmitted_bytes: bytes,
+                    traffic_direction: direction,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            connection([8, 8, 8, 8], TrafficDirection::Outgoing, 100),
+            connection([1, 1, 1, 1], TrafficDirection::Incoming, 200),
+            // same subnet of the interface
+            connection([80, 1, 2, 9], TrafficDirection::Outgoing, 300),
+            // bogons
+            connection([192, 168, 1, 1], TrafficDirection::Outgoing, 400),
+            connection([224, 0, 0, 251], TrafficDirection::Outgoing, 500),
+        ]
+        .into_iter()
+        .collect();
+        let my_interface_addresses = [Address {
+            addr: IpAddr::from([80, 1, 2, 3]),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        }];
+
+        let remote_addresses: Vec<IpAddr> = analyzer
+            .get_external_connections(&my_interface_addresses)
+            .into_iter()
+            .map(|c| get_address_to_lookup(&c.key, c.info.traffic_direction))
+            .collect();
+        assert_eq!(
+            remote_addresses,
+            [IpAddr::from([1, 1, 1, 1]), IpAddr::from([8, 8, 8, 8])]
+        );
+    }
+
+    #[test]
+    fn test_gateway_traffic() {
+        use TrafficDirection::{Incoming, Outgoing};
+
+        const GATEWAY_MAC: &str = "aa:bb:cc:00:00:01";
+        const MY_MAC: &str = "aa:bb:cc:00:00:10";
+        let through = |remote: [u8; 4], direction, remote_mac: Option<&str>, bytes| {
+            let (key, info) = connection(
+                ([192, 168, 1, 10], Some(50000)),
+                (remote, Some(443)),
+                Protocol::TCP,
+                direction,
+                (1, bytes),
+            );
+            let (mac_address1, mac_address2) = match direction {
+                Outgoing => (Some(MY_MAC), remote_mac),
+                Incoming => (remote_mac, Some(MY_MAC)),
+            };
+            let mac_flags = |mac: Option<&str>| mac.and_then(MacFlags::from_address);
+            let info = InfoAddressPortPair {
+                mac_address1: mac_address1.map(ToString::to_string),
+                mac_address2: mac_address2.map(ToString::to_string),
+                mac_flags1: mac_flags(mac_address1),
+                mac_flags2: mac_flags(mac_address2),
+                ..info
+            };
+            (key, info)
+        };
+        let my_interface_addresses = [Address {
+            addr: IpAddr::from([192, 168, 1, 10]),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        }];
+        let mut analyzer = TrafficAnalyzer::default();
+        assert!(
+            analyzer
+                .get_gateway_traffic(&my_interface_addresses)
+                .gateway_mac
+                .is_none()
+        );
+
+        analyzer.info_traffic.map = [
+            // most of the remote hosts are reached through the gateway
+            through([8, 8, 8, 8], Outgoing, Some(GATEWAY_MAC), 100),
+            through([1, 1, 1, 1], Outgoing, Some(GATEWAY_MAC), 200),
+            through([140, 82, 112, 3], Incoming, Some(GATEWAY_MAC), 300),
+            // a remote host reached through another router
+            through([9, 9, 9, 9], Outgoing, Some("aa:bb:cc:00:00:02"), 400),
+            // devices of the local network
+            through([192, 168, 1, 20], Outgoing, Some("aa:bb:cc:00:00:20"), 500),
+            through([192, 168, 1, 255], Outgoing, Some("ff:ff:ff:ff:ff:ff"), 600),
+            // not an Ethernet link
+            through([4, 4, 4, 4], Outgoing, None, 700),
+        ]
+        .into_iter()
+        .collect();
+
+        let gateway_traffic = analyzer.get_gateway_traffic(&my_interface_addresses);
+        assert_eq!(gateway_traffic.gateway_mac.as_deref(), Some(GATEWAY_MAC));
+        assert_eq!(gateway_traffic.remote_hosts, 3);
+        assert_eq!(gateway_traffic.through_gateway.connections, 3);
+        assert_eq!(gateway_traffic.through_gateway.packets, 3);
+        assert_eq!(gateway_traffic.through_gateway.bytes, 600);
+        assert_eq!(gateway_traffic.direct.connections, 3);
+        assert_eq!(gateway_traffic.direct.bytes, 1_500);
+    }
+
+    #[test]
+    fn test_focused_host_connections() {
+        let connection = |local_port: u16, remote: [u8; 4], bytes| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(local_port),
+                    IpAddr::from(remote),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_bytes: bytes,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            connection(50000, [8, 8, 8, 8], 100),
+            connection(50001, [1, 1, 1, 1], 200),
+            connection(50002, [8, 8, 8, 8], 300),
+        ]
+        .into_iter()
+        .collect();
+
+        // connections are only streamed while a host is focused
+        assert!(
+            analyzer
+                .traffic_update(0, false)
+                .focused_connections
+                .is_none()
+        );
+
+        analyzer.set_focused_host(Some(IpAddr::from([8, 8, 8, 8])));
+        let ports: Vec<_> = analyzer
+            .traffic_update(0, false)
+            .focused_connections
+            .unwrap()
+            .into_iter()
+            .map(|c| c.key.port1)
+            .collect();
+        assert_eq!(ports, [Some(50002), Some(50000)]);
+
+        // the focused connections keep updating
+        analyzer
+            .info_traffic
+            .map
+            .extend([connection(50003, [8, 8, 8, 8], 400)]);
+        let update = analyzer.traffic_update(0, false);
+        assert_eq!(update.focused_connections.unwrap().len(), 3);
+        // totals still cover the whole capture
+        assert_eq!(update.traffic_data.total_packets, 0);
+
+        analyzer.set_focused_host(None);
+        assert!(
+            analyzer
+                .traffic_update(0, false)
+                .focused_connections
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_stats_for_cidr() {
+        let connection = |remote: &str, local_port: u16, bytes: u128| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from_str("2001:db8::2").unwrap(),
+                    Some(local_port),
+                    IpAddr::from_str(remote).unwrap(),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    transmitted_bytes: bytes,
+                    transmitted_packets: 1,
+                    traffic_direction: TrafficDirection::Outgoing,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            connection("2600:1400::1", 50000, 1000),
+            connection("2600:1400::1", 50001, 2000),
+            connection("2600:1400:ab::7", 50002, 500),
+            // outside the block
+            connection("2600:1401::1", 50003, 4000),
+            connection("2a00:1450::1", 50004, 8000),
+        ]
+        .into_iter()
+        .collect();
+
+        let stats = analyzer.get_stats_for_cidr(" 2600:1400::/32").unwrap();
+        assert_eq!(stats.cidr, "2600:1400::/32");
+        assert_eq!(stats.bytes, 3500);
+        assert_eq!(stats.packets, 3);
+        assert_eq!(stats.hosts, 2);
+        assert_eq!(stats.connections, 3);
+
+        assert_eq!(analyzer.get_stats_for_cidr("10.0.0.0/8").unwrap().bytes, 0);
+        assert!(analyzer.get_stats_for_cidr("2600:1400::/129").is_err());
+        assert!(analyzer.get_stats_for_cidr("").is_err());
+    }
+
+    #[test]
+    fn test_observed_local_addresses() {
+        let connection = |source: [u8; 4], destination: [u8; 4], direction| {
+            (
+                AddressPortPair::new(
+                    IpAddr::from(source),
+                    Some(50000),
+                    IpAddr::from(destination),
+                    Some(443),
+                    Protocol::TCP,
+                ),
+                InfoAddressPortPair {
+                    traffic_direction: direction,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.map = [
+            connection([192, 168, 1, 3], [8, 8, 8, 8], TrafficDirection::Outgoing),
+            connection([192, 168, 1, 2], [1, 1, 1, 1], TrafficDirection::Outgoing),
+            connection([8, 8, 4, 4], [192, 168, 1, 3], TrafficDirection::Incoming),
+            // not local addresses
+            connection(
+                [192, 168, 1, 9],
+                [224, 0, 0, 251],
+                TrafficDirection::Incoming,
+            ),
+            connection(
+                [0, 0, 0, 0],
+                [255, 255, 255, 255],
+                TrafficDirection::Outgoing,
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            analyzer.get_observed_local_addresses(),
+            [
+                IpAddr::from([192, 168, 1, 2]),
+                IpAddr::from([192, 168, 1, 3])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_failure_samples_are_bounded() {
+        let mut analyzer = TrafficAnalyzer::default();
+        for i in 0..MAX_PARSE_FAILURE_SAMPLES + 2 {
+            analyzer.add_parse_failure_sample(ParseFailureSample::new(
+                &[u8::try_from(i).unwrap()],
+                MyLinkType::NotYetAssigned,
+                "truncated".to_string(),
+            ));
+        }
+        let samples = analyzer.get_parse_failure_samples();
+        assert_eq!(samples.len(), MAX_PARSE_FAILURE_SAMPLES);
+        assert_eq!(samples[0].hex, "00");
+        assert_eq!(samples[4].hex, "04");
+    }
+
+    #[test]
+    fn test_notification_summary() {
+        let mut analyzer = TrafficAnalyzer::default();
+        analyzer.info_traffic.tot_data_info =
+            DataInfo::new_for_tests(300, 100, 45_000_000, 2_300_000);
+        for (domain, country) in [
+            ("a.example.com", Country::US),
+            ("b.example.com", Country::US),
+            ("c.example.it", Country::IT),
+            // unknown countries are not counted
+            ("d.example.com", Country::ZZ),
+        ] {
+            let (mut host, data_info_host) = host(domain, 100, 100);
+            host.country = country;
+            analyzer.info_traffic.hosts.insert(host, data_info_host);
+        }
+
+        assert_eq!(
+            analyzer.get_notification_summary(Language::EN, Precision::Auto),
+            "Up 2.3 MB / Down 45 MB to 4 hosts in 2 countries"
+        );
+        assert_eq!(
+            analyzer.get_notification_summary(Language::IT, Precision::Auto),
+            "Su 2.3 MB / Giù 45 MB con 4 host in 2 paesi"
+        );
+        assert_eq!(
+            analyzer.get_notification_summary(Language::EN, Precision::Fixed(2)),
+            "Up 2.30 MB / Down 45.00 MB to 4 hosts in 2 countries"
+        );
+    }
+
+    #[test]
+    fn test_debug_dump_counts() {
+        let mut analyzer = TrafficAnalyzer::default();
+        assert_eq!(analyzer.debug_dump(None).connections, 0);
+
+        let key = |port: u16| {
+            AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(port),
+                IpAddr::from([8, 8, 8, 8]),
+                Some(53),
+                Protocol::UDP,
+            )
+        };
+        analyzer.refresh(
+            InfoTraffic {
+                map: [
+                    (key(50000), InfoAddressPortPair::default()),
+                    (key(50001), InfoAddressPortPair::default()),
+                ]
+                .into_iter()
+                .collect(),
+                services: [(Service::Name("domain"), DataInfo::default())]
+                    .into_iter()
+                    .collect(),
+                hosts: [host("dns.google", 100, 100)].into_iter().collect(),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+
+        let dump = analyzer.debug_dump(None);
+        assert!(dump.resolutions.is_none());
+        assert_eq!(dump.connections, 2);
+        assert_eq!(dump.hosts, 1);
+        assert_eq!(dump.services, 1);
+    }
+
+    #[test]
+    fn test_bandwidth_percentiles_include_idle_seconds() {
+        let mut analyzer = TrafficAnalyzer::default();
+        let second = |bytes: u128| InfoTraffic {
+            tot_data_info: DataInfo::new_for_tests(1, 1, bytes / 2, bytes - bytes / 2),
+            ..Default::default()
+        };
+        for bytes in [300, 100, 200, 400] {
+            analyzer.refresh(second(bytes), Vec::new());
+        }
+        assert_eq!(analyzer.get_bandwidth_percentiles().p50, 200);
+
+        // the gaps of offline captures count as seconds without traffic
+        analyzer.add_idle_seconds(6);
+        let percentiles = analyzer.get_bandwidth_percentiles();
+        assert_eq!(percentiles.seconds, 10);
+        assert_eq!(percentiles.p50, 0);
+        assert_eq!(percentiles.p90, 300);
+        assert_eq!(percentiles.p99, 400);
+    }
+}
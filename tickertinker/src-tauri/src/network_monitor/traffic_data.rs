@@ -0,0 +1,1159 @@
+//! Module defining the payloads sent to the frontend to describe the observed traffic.
+
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::countries::types::country::Country;
+use crate::networking::parse_packets::ResolutionStateDump;
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::app_protocol::AppProtocol;
+use crate::networking::types::data_info::DataInfo;
+use crate::networking::types::data_info_host::DataInfoHost;
+use crate::networking::types::data_representation::DataRepr;
+use crate::networking::types::host::{Host, HostMessage};
+use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+use crate::networking::types::info_traffic::InfoTraffic;
+use crate::networking::types::oui::vendor_for_mac;
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::service::Service;
+use crate::networking::types::traffic_direction::TrafficDirection;
+use crate::networking::types::traffic_type::TrafficType;
+use crate::utils::types::timestamp::Timestamp;
+
+/// Overview of the traffic observed by the current capture
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrafficData {
+    /// Number of packets exchanged
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub total_packets: u128,
+    /// Number of bytes exchanged
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub total_bytes: u128,
+    /// Number of incoming packets
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub incoming_packets: u128,
+    /// Number of outgoing packets
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub outgoing_packets: u128,
+    /// Number of incoming bytes
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub incoming_bytes: u128,
+    /// Number of outgoing bytes
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub outgoing_bytes: u128,
+    /// Number of bytes received per second in the latest interval
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub incoming_bytes_per_second: u128,
+    /// Number of bytes sent per second in the latest interval
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub outgoing_bytes_per_second: u128,
+    /// Number of packets dropped by the capture
+    pub dropped_packets: u32,
+    /// Number of packets accounted without ports because their transport header was malformed
+    /// or cut off (e.g., due to a small snapshot length)
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub malformed_transport_packets: u128,
+    /// Timestamp of the latest parsed packet
+    pub last_packet_timestamp: Timestamp,
+}
+
+impl TrafficData {
+    pub fn new(info_traffic: &InfoTraffic) -> Self {
+        let tot_data_info = &info_traffic.tot_data_info;
+        Self {
+            total_packets: tot_data_info.tot_data(DataRepr::Packets),
+            total_bytes: tot_data_info.tot_data(DataRepr::Bytes),
+            incoming_packets: info_traffic.total_incoming(DataRepr::Packets),
+            outgoing_packets: info_traffic.total_outgoing(DataRepr::Packets),
+            incoming_bytes: info_traffic.total_incoming(DataRepr::Bytes),
+            outgoing_bytes: info_traffic.total_outgoing(DataRepr::Bytes),
+            incoming_bytes_per_second: info_traffic.incoming_rate(DataRepr::Bytes),
+            outgoing_bytes_per_second: info_traffic.outgoing_rate(DataRepr::Bytes),
+            dropped_packets: info_traffic.dropped_packets,
+            malformed_transport_packets: info_traffic.malformed_transport_packets,
+            last_packet_timestamp: info_traffic.last_packet_timestamp,
+        }
+    }
+}
+
+/// Whole traffic observed by the current capture, returned by `get_traffic_data`
+#[derive(Clone, Debug, Serialize)]
+pub struct InfoTrafficData {
+    /// Totals of the exchanged data, dropped packets, and latest packet timestamp
+    #[serde(flatten)]
+    pub traffic_data: TrafficData,
+    /// Connections, sorted by the amount of bytes exchanged
+    pub connections: Vec<ConnectionData>,
+    /// Upper layer services, sorted by the amount of bytes exchanged
+    pub services: Vec<ServiceData>,
+    /// Remote hosts, sorted by the amount of bytes exchanged
+    pub hosts: Vec<HostData>,
+}
+
+impl InfoTrafficData {
+    pub fn new(info_traffic: &InfoTraffic) -> Self {
+        let mut connections: Vec<_> = info_traffic
+            .map
+            .iter()
+            .map(|(key, info)| ConnectionData::new(key, info))
+            .collect();
+        connections.sort_by(|a, b| b.info.transmitted_bytes.cmp(&a.info.transmitted_bytes));
+        let mut services: Vec<_> = info_traffic
+            .services
+            .iter()
+            .map(|(service, data_info)| ServiceData::new(*service, data_info))
+            .collect();
+        services.sort_by(|a, b| b.data.bytes().cmp(&a.data.bytes()));
+        let mut hosts: Vec<_> = info_traffic
+            .hosts
+            .iter()
+            .map(|(host, data_info_host)| HostData::new(host, data_info_host))
+            .collect();
+        hosts.sort_by(|a, b| b.data.bytes().cmp(&a.data.bytes()));
+        Self {
+            traffic_data: TrafficData::new(info_traffic),
+            connections,
+            services,
+            hosts,
+        }
+    }
+}
+
+/// Whole traffic of the capture with its context, written by `export_report_json`
+#[derive(Clone, Debug, Serialize)]
+pub struct TrafficReport {
+    /// Timestamp of the first packet of the capture (`None` if no packet was observed)
+    pub capture_started_at: Option<Timestamp>,
+    /// Name of the captured device (or file), `None` if the capture was stopped
+    pub interface_name: Option<String>,
+    #[serde(flatten)]
+    pub traffic: InfoTrafficData,
+}
+
+/// Packets and bytes exchanged in each direction
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ExchangedData {
+    pub incoming_packets: u128,
+    pub outgoing_packets: u128,
+    pub incoming_bytes: u128,
+    pub outgoing_bytes: u128,
+}
+
+impl ExchangedData {
+    pub fn new(data_info: &DataInfo) -> Self {
+        Self {
+            incoming_packets: data_info.incoming_data(DataRepr::Packets),
+            outgoing_packets: data_info.outgoing_data(DataRepr::Packets),
+            incoming_bytes: data_info.incoming_data(DataRepr::Bytes),
+            outgoing_bytes: data_info.outgoing_data(DataRepr::Bytes),
+        }
+    }
+
+    fn bytes(&self) -> u128 {
+        self.incoming_bytes.saturating_add(self.outgoing_bytes)
+    }
+}
+
+/// An upper layer service with the data exchanged through it
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceData {
+    pub service: Service,
+    #[serde(flatten)]
+    pub data: ExchangedData,
+}
+
+impl ServiceData {
+    pub fn new(service: Service, data_info: &DataInfo) -> Self {
+        Self {
+            service,
+            data: ExchangedData::new(data_info),
+        }
+    }
+}
+
+/// A remote port with the data exchanged with it, returned by `get_top_ports`
+#[derive(Clone, Debug, Serialize)]
+pub struct PortData {
+    pub port: u16,
+    /// Service associated to the port (`Unknown` if none of its connections was identified)
+    pub service: Service,
+    /// Number of connections to the port
+    pub connections: usize,
+    #[serde(flatten)]
+    pub data: ExchangedData,
+}
+
+/// A host with the data exchanged with it
+#[derive(Clone, Debug, Serialize)]
+pub struct HostData {
+    #[serde(flatten)]
+    pub classification: HostClassification,
+    #[serde(flatten)]
+    pub data: ExchangedData,
+}
+
+impl HostData {
+    pub fn new(host: &Host, data_info_host: &DataInfoHost) -> Self {
+        Self {
+            classification: HostClassification::new(host, data_info_host),
+            data: ExchangedData::new(&data_info_host.data_info),
+        }
+    }
+}
+
+/// Packets dropped since the previous call to `take_drop_delta`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DropDelta {
+    /// Packets dropped by the capture handle because its buffer was full
+    /// (i.e., the packets weren't read fast enough)
+    pub kernel_dropped: u32,
+    /// Packets dropped by the network interface or its driver
+    pub interface_dropped: u32,
+}
+
+/// Data exchanged in an interval of the capture, returned by `get_traffic_history`
+#[derive(Clone, Debug, Serialize)]
+pub struct TrafficSample {
+    /// Timestamp of the latest packet of the interval
+    pub timestamp: Timestamp,
+    pub incoming_bytes: u128,
+    pub outgoing_bytes: u128,
+    pub packets: u128,
+}
+
+/// Traffic observed since the previous snapshot, returned by `take_traffic_snapshot`
+#[derive(Clone, Debug, Serialize)]
+pub struct TrafficSnapshot {
+    pub traffic_data: TrafficData,
+    /// Connections that exchanged packets since the previous snapshot
+    pub connections: Vec<ConnectionData>,
+}
+
+/// State of the running capture, returned by `get_capture_status`
+#[derive(Clone, Debug, Serialize)]
+pub struct CaptureStatus {
+    pub cap_id: usize,
+    /// Name of the captured device (or file)
+    pub source: String,
+    /// Whether promiscuous mode was requested when opening the device
+    pub promiscuous_requested: bool,
+    /// Whether the device is actually in promiscuous mode (`None` if the platform can't tell)
+    pub promiscuous_effective: Option<bool>,
+}
+
+/// Details of the running capture session, returned by `get_session_info`
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionInfo {
+    pub cap_id: usize,
+    /// Maximum number of bytes stored per packet (`None` for capture files)
+    pub snaplen: Option<i32>,
+    /// Number of packets cut by the snapshot length: if not zero,
+    /// the snapshot length is too small to inspect the payload of all the packets
+    pub snaplen_truncated_packets: u128,
+    /// Number of packets read (including the ones that couldn't be parsed)
+    pub captured_packets: u128,
+    /// Number of packets dropped by the capture handle (e.g., because the buffer was full)
+    pub dropped_packets: u32,
+    /// Bytes actually analyzed (captured length of the packets read)
+    pub analyzed_bytes: u128,
+    /// Bytes of the packets read, as long as they were on the wire
+    pub wire_bytes: u128,
+    /// Estimated bytes that crossed the interface, including the dropped packets:
+    /// the more it exceeds `wire_bytes`, the more the statistics underestimate the traffic
+    pub estimated_wire_bytes: u128,
+    /// Number of packets not saved because the savefile writer couldn't keep up
+    /// (only when a `savefile_queue_size` is set in the capture options)
+    pub skipped_writes: u128,
+}
+
+impl SessionInfo {
+    pub fn new(cap_id: usize, snaplen: Option<i32>, info_traffic: &InfoTraffic) -> Self {
+        let volume = &info_traffic.capture_volume;
+        Self {
+            cap_id,
+            snaplen,
+            snaplen_truncated_packets: volume.snaplen_truncated_packets,
+            captured_packets: volume.packets,
+            dropped_packets: info_traffic.dropped_packets,
+            analyzed_bytes: volume.captured_bytes,
+            wire_bytes: volume.wire_bytes,
+            estimated_wire_bytes: volume.estimated_wire_bytes(info_traffic.dropped_packets),
+            skipped_writes: info_traffic.skipped_writes,
+        }
+    }
+}
+
+/// Packet counters of the capture handle, returned by `get_capture_stats`
+/// (always zero for capture files)
+#[derive(Clone, Debug, Serialize)]
+pub struct CaptureStats {
+    pub cap_id: usize,
+    /// Number of packets received by the capture handle
+    pub received: u32,
+    /// Number of packets dropped because the capture buffer was full
+    /// (i.e., they arrived faster than they could be read)
+    pub dropped: u32,
+    /// Number of packets dropped by the network interface or its driver
+    pub if_dropped: u32,
+    /// Seconds elapsed since the capture started, on the wall clock
+    pub duration_secs: f64,
+}
+
+/// Sizes of the packets exchanged by a connection, useful to tell bulk transfers
+/// (large packets) from interactive or keep-alive traffic (small packets)
+#[derive(Clone, Debug, Serialize)]
+pub struct PacketSizeData {
+    #[serde(flatten)]
+    pub key: AddressPortPair,
+    /// Size in bytes of the smallest packet
+    pub min: u128,
+    /// Average size in bytes of the packets
+    pub avg: u128,
+    /// Size in bytes of the largest packet
+    pub max: u128,
+}
+
+impl PacketSizeData {
+    pub fn new(key: &AddressPortPair, info: &InfoAddressPortPair) -> Self {
+        Self {
+            key: *key,
+            min: info.min_packet_size,
+            avg: info.avg_packet_size(),
+            max: info.max_packet_size,
+        }
+    }
+}
+
+/// Traffic exchanged with the remote hosts belonging to a CIDR block
+#[derive(Clone, Debug, Serialize)]
+pub struct CidrStats {
+    /// The requested block (e.g., "2600:1400::/32")
+    pub cidr: String,
+    pub bytes: u128,
+    pub packets: u128,
+    /// Number of distinct remote addresses in the block
+    pub hosts: usize,
+    pub connections: usize,
+}
+
+/// A local TCP port accepting connections, inferred from the handshakes observed
+#[derive(Clone, Debug, Serialize)]
+pub struct ListeningPortData {
+    /// Local address the connections were directed to
+    pub address: IpAddr,
+    pub port: u16,
+    /// Service associated to the port
+    pub service: Service,
+    /// Number of distinct remote endpoints (address and port) connecting to the port
+    pub connections: usize,
+    /// Number of distinct remote addresses connecting to the port
+    pub clients: usize,
+}
+
+/// Traffic exchanged with a set of MAC addresses
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct LinkTraffic {
+    pub connections: usize,
+    pub packets: u128,
+    pub bytes: u128,
+}
+
+impl LinkTraffic {
+    pub fn add(&mut self, info: &InfoAddressPortPair) {
+        self.connections += 1;
+        self.packets = self.packets.saturating_add(info.transmitted_packets);
+        self.bytes = self.bytes.saturating_add(info.transmitted_bytes);
+    }
+}
+
+/// Traffic forwarded by the default gateway, compared with the one delivered directly
+/// to other devices of the local network
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GatewayTraffic {
+    /// MAC address of the gateway, if any remote host outside the local network was reached
+    pub gateway_mac: Option<String>,
+    /// Number of distinct remote addresses outside the local network reached through the gateway
+    pub remote_hosts: usize,
+    /// Traffic whose remote end of the link is the gateway
+    pub through_gateway: LinkTraffic,
+    /// Traffic whose remote end of the link is any other MAC address (broadcast included)
+    pub direct: LinkTraffic,
+}
+
+/// Round-trip time of a TCP connection, estimated from the delay between its SYN and SYN-ACK
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionRttData {
+    #[serde(flatten)]
+    pub key: AddressPortPair,
+    pub estimated_rtt_ms: f64,
+}
+
+/// Payload of the `traffic_update` event, emitted once per interval
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrafficUpdateMessage {
+    /// Identifier of the capture this update belongs to
+    pub cap_id: usize,
+    /// Traffic observed since the capture started
+    pub traffic_data: TrafficData,
+    /// Connections involving the focused host, sorted by the amount of bytes exchanged
+    /// (`None` unless a host is focused via `focus_host`)
+    #[serde(skip_deserializing)]
+    pub focused_connections: Option<Vec<ConnectionData>>,
+    /// Whether this is the last update of the capture (only happens in offline captures)
+    pub is_final: bool,
+}
+
+impl TrafficUpdateMessage {
+    /// Encodes the update as MessagePack, with the same field names used in JSON
+    /// (128-bit counters are encoded as 64-bit integers, saturating at `u64::MAX`).
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec_named(self).map_err(|e| e.to_string())
+    }
+}
+
+/// Payload of the `offline_gap` event, emitted when an offline capture has no packets for a while
+#[derive(Clone, Debug, Serialize)]
+pub struct OfflineGapMessage {
+    /// Identifier of the capture this gap belongs to
+    pub cap_id: usize,
+    /// Number of seconds without packets
+    pub gap_secs: u64,
+}
+
+/// A connection (address:port pair) with its statistics, serialized as a single flat record
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionData {
+    #[serde(flatten)]
+    pub key: AddressPortPair,
+    #[serde(flatten)]
+    pub info: InfoAddressPortPair,
+    /// Vendor of the source MAC address (e.g., "Apple, Inc."), if known
+    pub vendor1: Option<&'static str>,
+    /// Vendor of the destination MAC address, if known
+    pub vendor2: Option<&'static str>,
+    /// Average throughput of the connection, from its first to its last packet
+    pub bytes_per_second: f64,
+    /// Names of the ICMP message types exchanged, with the relative count,
+    /// from the most to the least frequent (empty if not ICMP)
+    pub icmp_types: Vec<(String, usize)>,
+    /// Names of the ARP operations exchanged, with the relative count,
+    /// from the most to the least frequent (only serialized for ARP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arp_types: Option<Vec<(String, usize)>>,
+}
+
+impl ConnectionData {
+    pub fn new(key: &AddressPortPair, info: &InfoAddressPortPair) -> Self {
+        Self {
+            key: *key,
+            info: info.clone(),
+            vendor1: info.mac_address1.as_deref().and_then(vendor_for_mac),
+            vendor2: info.mac_address2.as_deref().and_then(vendor_for_mac),
+            bytes_per_second: info.bytes_per_second(DataRepr::Bytes),
+            icmp_types: info.icmp_types_breakdown(),
+            arp_types: (key.protocol == Protocol::ARP).then(|| info.arp_types_breakdown()),
+        }
+    }
+}
+
+/// How many times the bytes flowing in one direction must exceed the others
+/// for the traffic with a host to be considered asymmetric
+const ASYMMETRY_FACTOR: u128 = 10;
+
+/// Balance between the bytes received from and sent to a host
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Directionality {
+    /// Mostly received from the host (e.g., downloads and streaming)
+    DownloadHeavy,
+    /// Comparable amounts in both directions
+    Balanced,
+    /// Mostly sent to the host (e.g., uploads, or possibly data exfiltration)
+    UploadHeavy,
+}
+
+impl Directionality {
+    pub fn from_bytes(incoming_bytes: u128, outgoing_bytes: u128) -> Self {
+        if incoming_bytes > outgoing_bytes.saturating_mul(ASYMMETRY_FACTOR) {
+            Self::DownloadHeavy
+        } else if outgoing_bytes > incoming_bytes.saturating_mul(ASYMMETRY_FACTOR) {
+            Self::UploadHeavy
+        } else {
+            Self::Balanced
+        }
+    }
+}
+
+/// A host with the ratio between the bytes received from and sent to it
+#[derive(Clone, Debug, Serialize)]
+pub struct HostDirectionality {
+    #[serde(flatten)]
+    pub host: Host,
+    pub incoming_bytes: u128,
+    pub outgoing_bytes: u128,
+    /// Incoming bytes divided by outgoing bytes (`None` if nothing was sent to the host)
+    pub ratio: Option<f64>,
+    pub directionality: Directionality,
+}
+
+impl HostDirectionality {
+    pub fn new(host: &Host, incoming_bytes: u128, outgoing_bytes: u128) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = (outgoing_bytes > 0).then(|| incoming_bytes as f64 / outgoing_bytes as f64);
+        Self {
+            host: host.clone(),
+            incoming_bytes,
+            outgoing_bytes,
+            ratio,
+            directionality: Directionality::from_bytes(incoming_bytes, outgoing_bytes),
+        }
+    }
+}
+
+/// A host with the flags describing which kind of address it is
+#[derive(Clone, Debug, Serialize)]
+pub struct HostClassification {
+    #[serde(flatten)]
+    pub host: Host,
+    /// The host is a loopback address
+    pub is_loopback: bool,
+    /// The host is in the local network
+    pub is_local: bool,
+    /// The host is a bogon address
+    pub is_bogon: bool,
+    /// Kind of bogon address (e.g., "private-use"), if any
+    pub bogon_description: Option<&'static str>,
+    /// Whether the traffic with this host is unicast, multicast, or broadcast
+    pub traffic_type: TrafficType,
+}
+
+impl HostClassification {
+    pub fn new(host: &Host, data_info_host: &DataInfoHost) -> Self {
+        Self {
+            host: host.clone(),
+            is_loopback: data_info_host.is_loopback,
+            is_local: data_info_host.is_local,
+            is_bogon: data_info_host.is_bogon.is_some(),
+            bogon_description: data_info_host.is_bogon,
+            traffic_type: data_info_host.traffic_type,
+        }
+    }
+}
+
+/// A host that exchanged packets recently
+#[derive(Clone, Debug, Serialize)]
+pub struct RecentHostData {
+    #[serde(flatten)]
+    pub host: Host,
+    /// Milliseconds elapsed between the latest packet of the host and the one of the capture
+    pub last_seen_ms_ago: u128,
+}
+
+/// Minimum number of packets needed to profile a connection
+const MIN_PROFILED_PACKETS: u128 = 4;
+/// Average packet size (in bytes) from which a connection is considered bulk
+const BULK_AVG_PACKET_SIZE: u128 = 800;
+/// Average packet size (in bytes) from which a steady connection is considered bulk
+const STEADY_AVG_PACKET_SIZE: u128 = 400;
+/// Mean interval between packets (in microseconds) under which a connection is considered steady
+const STEADY_MAX_INTERVAL_USECS: i64 = 20_000;
+/// Burstiness (coefficient of variation of the intervals between packets)
+/// under which a connection is considered steady
+const STEADY_MAX_BURSTINESS: f64 = 1.0;
+
+/// Qualitative profile of a connection, inferred from the size and the pace of its packets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ConnectionProfile {
+    /// Large packets at a steady pace (e.g., downloads, backups, and streaming)
+    Background,
+    /// Small packets exchanged in bursts (e.g., SSH sessions, chats, and browsing)
+    Interactive,
+    /// Too few packets to tell
+    Undetermined,
+}
+
+impl ConnectionProfile {
+    pub fn from_stats(
+        packets: u128,
+        avg_packet_size: u128,
+        mean_interval_usecs: i64,
+        burstiness: f64,
+    ) -> Self {
+        if packets < MIN_PROFILED_PACKETS {
+            Self::Undetermined
+        } else if avg_packet_size >= BULK_AVG_PACKET_SIZE
+            || (avg_packet_size >= STEADY_AVG_PACKET_SIZE
+                && mean_interval_usecs <= STEADY_MAX_INTERVAL_USECS
+                && burstiness <= STEADY_MAX_BURSTINESS)
+        {
+            Self::Background
+        } else {
+            Self::Interactive
+        }
+    }
+}
+
+/// A connection with its profile, and the statistics it was inferred from
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionProfileData {
+    #[serde(flatten)]
+    pub key: AddressPortPair,
+    pub profile: ConnectionProfile,
+    /// Average size in bytes of the packets
+    pub avg_packet_size: u128,
+    /// Mean interval in microseconds between consecutive packets
+    pub mean_interval_usecs: i64,
+    /// Coefficient of variation of the intervals between consecutive packets
+    /// (0 for a steady pace, above 1 for bursts)
+    pub burstiness: f64,
+}
+
+impl ConnectionProfileData {
+    pub fn new(key: &AddressPortPair, info: &InfoAddressPortPair) -> Self {
+        let duration_usecs = info
+            .final_timestamp
+            .to_usecs()
+            .zip(info.initial_timestamp.to_usecs())
+            .map_or(0, |(end, start)| end.saturating_sub(start));
+        let intervals =
+            i64::try_from(info.transmitted_packets.saturating_sub(1)).unwrap_or(i64::MAX);
+        let mean_interval_usecs = duration_usecs.checked_div(intervals).unwrap_or_default();
+        let avg_packet_size = info.avg_packet_size();
+        let burstiness = info.burstiness();
+        Self {
+            key: *key,
+            profile: ConnectionProfile::from_stats(
+                info.transmitted_packets,
+                avg_packet_size,
+                mean_interval_usecs,
+                burstiness,
+            ),
+            avg_packet_size,
+            mean_interval_usecs,
+            burstiness,
+        }
+    }
+}
+
+/// A TCP flow whose packets were only observed in one direction
+#[derive(Clone, Debug, Serialize)]
+pub struct AsymmetricFlowData {
+    #[serde(flatten)]
+    pub key: AddressPortPair,
+    /// Direction of the observed packets
+    pub traffic_direction: TrafficDirection,
+    pub packets: u128,
+    pub bytes: u128,
+}
+
+impl AsymmetricFlowData {
+    pub fn new(key: &AddressPortPair, info: &InfoAddressPortPair) -> Self {
+        Self {
+            key: *key,
+            traffic_direction: info.traffic_direction,
+            packets: info.transmitted_packets,
+            bytes: info.transmitted_bytes,
+        }
+    }
+}
+
+/// Traffic of the hosts evicted to bound the number of tracked hosts, aggregated
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OtherHostsData {
+    /// Number of hosts evicted so far
+    pub hosts: usize,
+    pub incoming_packets: u128,
+    pub outgoing_packets: u128,
+    pub incoming_bytes: u128,
+    pub outgoing_bytes: u128,
+}
+
+/// Payload of the `hosts_evicted` event, emitted when the least recently active hosts are evicted
+#[derive(Clone, Debug, Serialize)]
+pub struct HostsEvictedMessage {
+    /// Identifier of the capture the hosts belong to
+    pub cap_id: usize,
+    /// Hosts just evicted
+    pub evicted: Vec<Host>,
+    /// Aggregate of all the hosts evicted so far
+    pub other_hosts: OtherHostsData,
+}
+
+/// Payload of the `userspace_filtering` event, emitted when a capture starts
+/// (or its filter is updated) if its BPF filter couldn't be compiled
+/// and is applied in userspace instead (less efficiently)
+#[derive(Clone, Debug, Serialize)]
+pub struct UserspaceFilteringMessage {
+    /// Identifier of the capture the filter belongs to
+    pub cap_id: usize,
+    pub bpf_filter: String,
+    /// Error returned by libpcap when compiling the filter
+    pub reason: String,
+}
+
+/// Payload of the `capture_error` event, emitted when a capture stops because of an error
+/// (e.g., the captured interface went down)
+#[derive(Clone, Debug, Serialize)]
+pub struct CaptureErrorMessage {
+    /// Identifier of the capture that stopped
+    pub cap_id: usize,
+    /// Error returned by libpcap
+    pub error: String,
+}
+
+/// Payload of the `local_address_changed` event, emitted when the addresses of the captured
+/// device change (e.g., after a DHCP renewal or connecting to a VPN)
+#[derive(Clone, Debug, Serialize)]
+pub struct LocalAddressChangedMessage {
+    /// Identifier of the capture whose device changed
+    pub cap_id: usize,
+    /// Addresses of the device before the change
+    pub old_addresses: Vec<IpAddr>,
+    /// Addresses of the device after the change, used to tell the direction of new connections
+    pub new_addresses: Vec<IpAddr>,
+}
+
+/// A host just resolved, with the address it was resolved from
+#[derive(Clone, Debug, Serialize)]
+pub struct ResolvedHostData {
+    pub address: IpAddr,
+    /// Result of the rDNS lookup (the address itself if it couldn't be resolved)
+    pub rdns: String,
+    #[serde(flatten)]
+    pub classification: HostClassification,
+}
+
+impl ResolvedHostData {
+    pub fn new(host_msg: &HostMessage) -> Self {
+        Self {
+            address: host_msg.address_to_lookup,
+            rdns: host_msg.rdns.clone(),
+            classification: HostClassification::new(&host_msg.host, &host_msg.data_info_host),
+        }
+    }
+}
+
+/// Payload of the `new_host_resolved` event, emitted for each resolved host
+/// (only if `single_host_events` is set in the capture options)
+#[derive(Clone, Debug, Serialize)]
+pub struct HostResolvedMessage {
+    /// Identifier of the capture the host belongs to
+    pub cap_id: usize,
+    #[serde(flatten)]
+    pub host: ResolvedHostData,
+}
+
+/// Payload of the `hosts_resolved_batch` event, emitted once per interval
+/// with all the hosts resolved in the meantime
+#[derive(Clone, Debug, Serialize)]
+pub struct HostsResolvedBatchMessage {
+    /// Identifier of the capture the hosts belong to
+    pub cap_id: usize,
+    /// Hosts resolved since the previous batch
+    pub hosts: Vec<ResolvedHostData>,
+}
+
+impl HostsResolvedBatchMessage {
+    /// Collects the resolved hosts into a batch (`None` if there's none)
+    pub fn new(cap_id: usize, new_hosts: &[HostMessage]) -> Option<Self> {
+        (!new_hosts.is_empty()).then(|| Self {
+            cap_id,
+            hosts: new_hosts.iter().map(ResolvedHostData::new).collect(),
+        })
+    }
+}
+
+/// Traffic exchanged over an application protocol, aggregated across connections
+#[derive(Clone, Debug, Serialize)]
+pub struct AppProtocolData {
+    pub app_protocol: AppProtocol,
+    /// Number of connections using the protocol
+    pub connections: usize,
+    pub packets: u128,
+    pub bytes: u128,
+    /// Average size in bytes of the packets (0 if no packet was exchanged),
+    /// telling apart protocols exchanging small packets (e.g., DNS) from bulk transfers
+    pub avg_packet_size: u128,
+}
+
+/// Traffic of the connections owned by a local process, returned by `get_traffic_by_process`
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcessTrafficData {
+    /// Name of the process, or "unknown" for the traffic that couldn't be attributed
+    /// (e.g., exchanged after the process exited)
+    pub name: String,
+    /// PID of the process (`None` for the unknown traffic)
+    pub pid: Option<u32>,
+    #[serde(flatten)]
+    pub data: ExchangedData,
+}
+
+/// Traffic marked with a DSCP value, returned by `get_qos_breakdown`
+#[derive(Clone, Debug, Serialize)]
+pub struct QosClassData {
+    /// Name of the QoS class (e.g., "EF"), `None` if the value isn't assigned to a standard class
+    pub class: Option<&'static str>,
+    /// DSCP value of the IP header
+    pub dscp: u8,
+    #[serde(flatten)]
+    pub data: ExchangedData,
+}
+
+/// Sizes of the internal maps of the current capture, returned by `debug_dump_state`
+#[derive(Clone, Debug, Serialize)]
+pub struct DebugStateDump {
+    /// State of the address resolutions (`None` if no capture is running)
+    pub resolutions: Option<ResolutionStateDump>,
+    /// Number of connections (address:port pairs)
+    pub connections: usize,
+    pub hosts: usize,
+    pub services: usize,
+}
+
+/// Percentiles of the bytes exchanged per second over the whole capture,
+/// characterizing how bursty the traffic is (e.g., the 95th percentile used to size links)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BandwidthPercentiles {
+    /// Number of seconds the percentiles are computed on (including those without traffic)
+    pub seconds: usize,
+    /// Median bytes per second
+    pub p50: u128,
+    /// 90th percentile of the bytes per second
+    pub p90: u128,
+    /// 95th percentile of the bytes per second
+    pub p95: u128,
+    /// 99th percentile of the bytes per second
+    pub p99: u128,
+}
+
+/// Traffic exchanged in an hour of the day, summed over all the days of the capture
+#[derive(Clone, Debug, Serialize)]
+pub struct HourlyTrafficData {
+    /// Hour of the day (UTC), from 0 to 23
+    pub hour: usize,
+    pub packets: u128,
+    pub bytes: u128,
+}
+
+impl BandwidthPercentiles {
+    /// Computes the percentiles of the given per-second values with the nearest-rank method,
+    /// each value coming with the number of seconds it was observed in
+    pub fn from_runs(runs: impl IntoIterator<Item = (u128, u64)>) -> Self {
+        let mut sorted: Vec<(u128, u64)> = runs.into_iter().collect();
+        sorted.sort_unstable();
+        let seconds: u128 = sorted.iter().map(|(_, secs)| u128::from(*secs)).sum();
+        let percentile = |p: u128| {
+            // smallest value such that at least p% of the values are less than or equal to it
+            let rank = (p * seconds).div_ceil(100).max(1);
+            let mut cumulative_secs = 0;
+            sorted
+                .iter()
+                .find(|(_, secs)| {
+                    cumulative_secs += u128::from(*secs);
+                    cumulative_secs >= rank
+                })
+                .map(|(bytes, _)| *bytes)
+                .unwrap_or_default()
+        };
+        Self {
+            seconds: usize::try_from(seconds).unwrap_or(usize::MAX),
+            p50: percentile(50),
+            p90: percentile(90),
+            p95: percentile(95),
+            p99: percentile(99),
+        }
+    }
+}
+
+/// Bytes in a (decimal) gigabyte, the unit used by carriers to bill data
+const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
+/// Estimate derived from the bytes exchanged, given a user-supplied rate per gigabyte
+#[derive(Clone, Debug, Serialize)]
+pub struct DataEstimate {
+    /// Number of bytes exchanged
+    pub total_bytes: u128,
+    /// Number of gigabytes exchanged
+    pub gigabytes: f64,
+    /// Rate per gigabyte the estimate is based on
+    pub rate_per_gb: f64,
+    /// Estimated value (i.e., `gigabytes * rate_per_gb`)
+    pub value: f64,
+    /// Estimated value, formatted for display
+    pub formatted: String,
+}
+
+impl DataEstimate {
+    /// Estimates the cost of the bytes exchanged, given the price per gigabyte
+    pub fn cost(total_bytes: u128, price_per_gb: f64) -> Result<Self, String> {
+        Self::new(total_bytes, price_per_gb, |cost| format!("{cost:.2}"))
+    }
+
+    /// Estimates the carbon footprint of the bytes exchanged, given the grams of CO2 per gigabyte
+    pub fn footprint(total_bytes: u128, grams_co2_per_gb: f64) -> Result<Self, String> {
+        Self::new(total_bytes, grams_co2_per_gb, |grams| {
+            if grams >= 1_000.0 {
+                format!("{:.2} kg CO2e", grams / 1_000.0)
+            } else {
+                format!("{grams:.2} g CO2e")
+            }
+        })
+    }
+
+    fn new(
+        total_bytes: u128,
+        rate_per_gb: f64,
+        format: impl Fn(f64) -> String,
+    ) -> Result<Self, String> {
+        if !rate_per_gb.is_finite() || rate_per_gb < 0.0 {
+            return Err(format!("Invalid rate per GB: {rate_per_gb}"));
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let gigabytes = total_bytes as f64 / BYTES_PER_GB;
+        let value = gigabytes * rate_per_gb;
+        Ok(Self {
+            total_bytes,
+            gigabytes,
+            rate_per_gb,
+            value,
+            formatted: format(value),
+        })
+    }
+}
+
+/// Country of an address, returned by `country_lookup`
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CountryLookup {
+    /// ISO 3166-1 alpha-2 code of the country (`ZZ` if unknown)
+    pub country: Country,
+    /// Whether the address was found in the database
+    pub resolved: bool,
+}
+
+impl CountryLookup {
+    pub fn new(country: Country) -> Self {
+        Self {
+            country,
+            resolved: country != Country::ZZ,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::networking::types::arp_type::ArpType;
+    use crate::networking::types::icmp_type::{IcmpType, IcmpTypeV4};
+    use crate::utils::formatted_strings::get_domain_from_r_dns;
+
+    #[test]
+    fn test_bandwidth_percentiles() {
+        // 1..=100 bytes per second, in no particular order
+        let series = (1..=100).map(|i| ((i * 37) % 100 + 1, 1));
+        assert_eq!(
+            BandwidthPercentiles::from_runs(series),
+            BandwidthPercentiles {
+                seconds: 100,
+                p50: 50,
+                p90: 90,
+                p95: 95,
+                p99: 99,
+            }
+        );
+
+        // mostly idle, with a burst: the median ignores it while the top percentiles catch it
+        let series = [(0, 10), (1_000_000, 1), (0, 8), (5_000, 1)];
+        let percentiles = BandwidthPercentiles::from_runs(series);
+        assert_eq!(percentiles.seconds, 20);
+        assert_eq!(percentiles.p50, 0);
+        assert_eq!(percentiles.p90, 0);
+        assert_eq!(percentiles.p95, 5_000);
+        assert_eq!(percentiles.p99, 1_000_000);
+
+        assert_eq!(
+            BandwidthPercentiles::from_runs([]),
+            BandwidthPercentiles::default()
+        );
+    }
+
+    #[test]
+    fn test_traffic_update_msgpack_round_trip() {
+        let update = TrafficUpdateMessage {
+            cap_id: 3,
+            traffic_data: TrafficData {
+                total_packets: 12,
+                total_bytes: 9_000,
+                incoming_packets: 8,
+                outgoing_packets: 4,
+                incoming_bytes: 7_500,
+                outgoing_bytes: 1_500,
+                incoming_bytes_per_second: 2_500,
+                outgoing_bytes_per_second: 500,
+                dropped_packets: 2,
+                malformed_transport_packets: 1,
+                last_packet_timestamp: Timestamp::new(1_700_000_000, 42),
+            },
+            focused_connections: None,
+            is_final: true,
+        };
+
+        let bytes = update.to_msgpack().unwrap();
+        let decoded: TrafficUpdateMessage = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&update).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_traffic_update_msgpack_counters_are_integers() {
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from([8, 8, 8, 8]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let info = InfoAddressPortPair {
+            transmitted_bytes: u128::MAX,
+            transmitted_packets: 3,
+            ..Default::default()
+        };
+        let update = TrafficUpdateMessage {
+            cap_id: 1,
+            traffic_data: TrafficData {
+                total_packets: 3,
+                total_bytes: u128::from(u64::MAX) + 1,
+                ..Default::default()
+            },
+            focused_connections: Some(vec![ConnectionData::new(&key, &info)]),
+            is_final: false,
+        };
+
+        // decoded by a generic MessagePack reader, the counters are plain numbers
+        let bytes = update.to_msgpack().unwrap();
+        let value: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(value["traffic_data"]["total_packets"], 3);
+        assert_eq!(value["traffic_data"]["total_bytes"], u64::MAX);
+        let connection = &value["focused_connections"][0];
+        assert_eq!(connection["transmitted_packets"], 3);
+        assert_eq!(connection["transmitted_bytes"], u64::MAX);
+        assert_eq!(connection["port2"], 443);
+    }
+
+    #[test]
+    fn test_connection_icmp_types_serialized() {
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            None,
+            IpAddr::from([8, 8, 8, 8]),
+            None,
+            Protocol::ICMP,
+        );
+        let info = InfoAddressPortPair {
+            icmp_types: HashMap::from([
+                (IcmpType::V4(IcmpTypeV4::Echo), 1),
+                (IcmpType::V4(IcmpTypeV4::EchoReply), 2),
+            ]),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(ConnectionData::new(&key, &info)).unwrap();
+        assert_eq!(
+            json["icmp_types"],
+            serde_json::json!([["Echo Reply", 2], ["Echo", 1]])
+        );
+        assert!(json.get("arp_types").is_none());
+    }
+
+    #[test]
+    fn test_connection_arp_types_serialized() {
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 1]),
+            None,
+            IpAddr::from([192, 168, 1, 2]),
+            None,
+            Protocol::ARP,
+        );
+        let info = InfoAddressPortPair {
+            arp_types: HashMap::from([(ArpType::Reply, 5), (ArpType::Request, 1)]),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(ConnectionData::new(&key, &info)).unwrap();
+        assert_eq!(
+            json["arp_types"],
+            serde_json::json!([["Reply", 5], ["Request", 1]])
+        );
+    }
+
+    #[test]
+    fn test_connection_vendors_serialized() {
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 20]),
+            Some(50000),
+            IpAddr::from([192, 168, 1, 1]),
+            Some(53),
+            Protocol::UDP,
+        );
+        let info = InfoAddressPortPair {
+            mac_address1: Some("00:0c:29:3e:5a:10".to_string()),
+            mac_address2: Some("a6:2b:8f:00:11:22".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(ConnectionData::new(&key, &info)).unwrap();
+        assert_eq!(json["vendor1"], "VMware, Inc.");
+        // randomized addresses have no vendor
+        assert_eq!(json["vendor2"], serde_json::Value::Null);
+        assert_eq!(json["mac_address1"], "00:0c:29:3e:5a:10");
+    }
+
+    #[test]
+    fn test_resolved_hosts_batched() {
+        let host_msg = |last_octet: u8, rdns: &str| HostMessage {
+            host: Host {
+                domain: get_domain_from_r_dns(rdns.to_string()),
+                ..Default::default()
+            },
+            data_info_host: DataInfoHost::default(),
+            address_to_lookup: IpAddr::from([140, 82, 121, last_octet]),
+            rdns: rdns.to_string(),
+        };
+        let new_hosts = [
+            host_msg(3, "lb-140-82-121-3-fra.github.com"),
+            host_msg(4, "lb-140-82-121-4-fra.github.com"),
+            host_msg(5, "140.82.121.5"),
+        ];
+
+        // all the hosts resolved in the interval are delivered in one event
+        let batch = HostsResolvedBatchMessage::new(7, &new_hosts).unwrap();
+        assert_eq!(batch.cap_id, 7);
+        assert_eq!(batch.hosts.len(), 3);
+        let json = serde_json::to_value(&batch).unwrap();
+        assert_eq!(json["hosts"][0]["address"], "140.82.121.3");
+        assert_eq!(json["hosts"][0]["rdns"], "lb-140-82-121-3-fra.github.com");
+        assert_eq!(json["hosts"][0]["domain"], "github.com");
+        assert_eq!(json["hosts"][1]["address"], "140.82.121.4");
+        assert_eq!(json["hosts"][2]["rdns"], "140.82.121.5");
+
+        // nothing to emit without resolved hosts
+        assert!(HostsResolvedBatchMessage::new(7, &[]).is_none());
+    }
+
+    #[test]
+    fn test_data_estimates() {
+        let cost = DataEstimate::cost(2_500_000_000, 10.0).unwrap();
+        assert!((cost.gigabytes - 2.5).abs() < f64::EPSILON);
+        assert!((cost.value - 25.0).abs() < f64::EPSILON);
+        assert_eq!(cost.formatted, "25.00");
+
+        let footprint = DataEstimate::footprint(2_500_000_000, 60.0).unwrap();
+        assert!((footprint.value - 150.0).abs() < f64::EPSILON);
+        assert_eq!(footprint.formatted, "150.00 g CO2e");
+        let footprint = DataEstimate::footprint(40_000_000_000, 60.0).unwrap();
+        assert_eq!(footprint.formatted, "2.40 kg CO2e");
+
+        assert!(DataEstimate::cost(1_000, -1.0).is_err());
+        assert!(DataEstimate::footprint(1_000, f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Module defining the `TrafficHistory` struct, which keeps the data exchanged in the latest
+//! intervals of the capture, so that the frontend can draw its graphs even if it missed some updates.
+
+use std::collections::VecDeque;
+
+use crate::networking::types::data_info::DataInfo;
+use crate::networking::types::data_representation::DataRepr;
+use crate::utils::types::timestamp::Timestamp;
+
+use super::traffic_data::TrafficSample;
+
+/// Number of samples (one per interval, i.e., per second) kept by default
+pub const DEFAULT_HISTORY_WINDOW: usize = 300;
+
+/// Maximum number of samples that can be kept (one day)
+pub const MAX_HISTORY_WINDOW: usize = 86_400;
+
+/// Ring buffer with the data exchanged in each of the latest intervals, oldest first
+pub struct TrafficHistory {
+    samples: VecDeque<TrafficSample>,
+    /// Maximum number of samples kept
+    window: usize,
+}
+
+impl Default for TrafficHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_WINDOW)
+    }
+}
+
+impl TrafficHistory {
+    /// Creates a history keeping at most `window` samples (up to `MAX_HISTORY_WINDOW`)
+    pub fn new(window: usize) -> Self {
+        let window = window.min(MAX_HISTORY_WINDOW);
+        Self {
+            samples: VecDeque::with_capacity(window.min(DEFAULT_HISTORY_WINDOW)),
+            window,
+        }
+    }
+
+    /// Appends the data exchanged in the latest interval, dropping the oldest sample if needed.
+    ///
+    /// Since the timestamp of the latest packet doesn't advance in intervals without packets,
+    /// those are timestamped one second after the previous sample
+    /// (and skipped if nothing was exchanged yet).
+    pub fn push(&mut self, last_packet_timestamp: Timestamp, data_info: &DataInfo) {
+        let packets = data_info.tot_data(DataRepr::Packets);
+        let timestamp = match self.samples.back() {
+            Some(previous) if packets == 0 => {
+                let mut timestamp = previous.timestamp;
+                timestamp.add_secs(1);
+                timestamp
+            }
+            None if packets == 0 => return,
+            _ => last_packet_timestamp,
+        };
+        self.samples.push_back(TrafficSample {
+            timestamp,
+            incoming_bytes: data_info.incoming_data(DataRepr::Bytes),
+            outgoing_bytes: data_info.outgoing_data(DataRepr::Bytes),
+            packets,
+        });
+        while self.samples.len() > self.window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the samples, oldest first
+    pub fn samples(&self) -> Vec<TrafficSample> {
+        self.samples.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamps(history: &TrafficHistory) -> Vec<i64> {
+        history
+            .samples()
+            .iter()
+            .map(|s| s.timestamp.secs())
+            .collect()
+    }
+
+    #[test]
+    fn test_history_keeps_latest_samples() {
+        let mut history = TrafficHistory::new(3);
+        for secs in 1..=5 {
+            history.push(
+                Timestamp::new(secs, 0),
+                &DataInfo::new_for_tests(1, 1, 100, 50),
+            );
+        }
+        assert_eq!(timestamps(&history), vec![3, 4, 5]);
+
+        let sample = &history.samples()[0];
+        assert_eq!(sample.incoming_bytes, 100);
+        assert_eq!(sample.outgoing_bytes, 50);
+        assert_eq!(sample.packets, 2);
+    }
+
+    #[test]
+    fn test_history_window_is_bounded() {
+        let history = TrafficHistory::new(usize::MAX);
+        assert_eq!(history.window, MAX_HISTORY_WINDOW);
+        assert!(history.samples.capacity() < MAX_HISTORY_WINDOW);
+    }
+
+    #[test]
+    fn test_history_idle_intervals() {
+        let mut history = TrafficHistory::default();
+        // nothing exchanged yet
+        history.push(Timestamp::default(), &DataInfo::default());
+        assert!(history.samples().is_empty());
+
+        history.push(
+            Timestamp::new(10, 500),
+            &DataInfo::new_for_tests(0, 1, 0, 80),
+        );
+        // the timestamp of the latest packet doesn't advance while idle
+        history.push(Timestamp::new(10, 500), &DataInfo::default());
+        history.push(Timestamp::new(10, 500), &DataInfo::default());
+        history.push(Timestamp::new(13, 0), &DataInfo::new_for_tests(1, 0, 40, 0));
+        assert_eq!(timestamps(&history), vec![10, 11, 12, 13]);
+        assert_eq!(history.samples()[1].packets, 0);
+        assert_eq!(history.samples()[1].outgoing_bytes, 0);
+    }
+}
@@ -0,0 +1,114 @@
+//! Module defining the `UpdateCoalescer` struct, which keeps traffic updates from piling up
+//! when the frontend can't keep up with them.
+
+use super::traffic_data::TrafficUpdateMessage;
+
+/// Tracks whether the frontend acknowledged the latest traffic update.
+///
+/// While an update is outstanding, the newer ones are merged into a single pending update,
+/// sent as soon as the outstanding one is acknowledged.
+/// Since updates carry the traffic observed since the start of the capture,
+/// the latest one already includes the data of the ones it replaces.
+#[derive(Default)]
+pub struct UpdateCoalescer {
+    /// Whether an update was sent and not acknowledged yet
+    outstanding: bool,
+    /// Latest update produced while another one was outstanding
+    pending: Option<TrafficUpdateMessage>,
+}
+
+impl UpdateCoalescer {
+    /// Returns the update if it can be sent right away, otherwise keeps it as the pending one
+    pub fn submit(&mut self, mut update: TrafficUpdateMessage) -> Option<TrafficUpdateMessage> {
+        if !self.outstanding {
+            self.outstanding = true;
+            return Some(update);
+        }
+        if let Some(replaced) = self.pending.take() {
+            update.is_final |= replaced.is_final;
+        }
+        self.pending = Some(update);
+        None
+    }
+
+    /// Acknowledges the outstanding update, returning the pending one to send next, if any
+    pub fn ack(&mut self) -> Option<TrafficUpdateMessage> {
+        let pending = self.pending.take();
+        self.outstanding = pending.is_some();
+        pending
+    }
+
+    /// Returns the pending update without waiting for the acknowledgement, if any,
+    /// so that the last one isn't lost when the capture stops
+    pub fn flush(&mut self) -> Option<TrafficUpdateMessage> {
+        self.outstanding = false;
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_monitor::traffic_data::TrafficData;
+
+    fn update(total_packets: u128) -> TrafficUpdateMessage {
+        TrafficUpdateMessage {
+            cap_id: 0,
+            traffic_data: TrafficData {
+                total_packets,
+                ..Default::default()
+            },
+            focused_connections: None,
+            is_final: false,
+        }
+    }
+
+    fn total_packets(update: Option<TrafficUpdateMessage>) -> Option<u128> {
+        update.map(|u| u.traffic_data.total_packets)
+    }
+
+    #[test]
+    fn test_updates_coalesced_while_consumer_is_slow() {
+        let mut coalescer = UpdateCoalescer::default();
+        assert_eq!(total_packets(coalescer.submit(update(10))), Some(10));
+
+        // the consumer is busy with the first update: the next ones are merged
+        assert_eq!(total_packets(coalescer.submit(update(20))), None);
+        assert_eq!(total_packets(coalescer.submit(update(30))), None);
+        assert_eq!(total_packets(coalescer.submit(update(40))), None);
+
+        // once acknowledged, only the latest state is sent
+        assert_eq!(total_packets(coalescer.ack()), Some(40));
+        assert_eq!(total_packets(coalescer.ack()), None);
+
+        // the consumer caught up: updates are sent right away again
+        assert_eq!(total_packets(coalescer.submit(update(50))), Some(50));
+    }
+
+    #[test]
+    fn test_final_update_is_not_lost() {
+        let mut coalescer = UpdateCoalescer::default();
+        coalescer.submit(update(10));
+        coalescer.submit(TrafficUpdateMessage {
+            is_final: true,
+            ..update(20)
+        });
+        // a late update must not hide that the capture ended
+        coalescer.submit(update(20));
+        assert!(coalescer.ack().unwrap().is_final);
+    }
+
+    #[test]
+    fn test_pending_update_flushed_on_stop() {
+        let mut coalescer = UpdateCoalescer::default();
+        assert_eq!(total_packets(coalescer.flush()), None);
+        coalescer.submit(update(10));
+        coalescer.submit(update(20));
+        coalescer.submit(update(30));
+
+        // the latest update is sent even if the outstanding one was never acknowledged
+        assert_eq!(total_packets(coalescer.flush()), Some(30));
+        assert_eq!(total_packets(coalescer.flush()), None);
+        assert_eq!(total_packets(coalescer.ack()), None);
+    }
+}
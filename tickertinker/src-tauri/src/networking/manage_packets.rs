@@ -1,19 +1,25 @@
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-use etherparse::{EtherType, LaxPacketHeaders, LinkHeader, NetHeaders, TransportHeader};
+use etherparse::{
+    EtherType, IpNumber, LaxPacketHeaders, LaxPayloadSlice, LinkHeader, NetHeaders, TransportHeader,
+};
 use pcap::Address;
 
 use crate::networking::types::address_port_pair::AddressPortPair;
 use crate::networking::types::arp_type::ArpType;
-use crate::networking::types::bogon::is_bogon;
 use crate::networking::types::capture_context::CaptureSource;
+use crate::networking::types::capture_filter::CaptureFilter;
+use crate::networking::types::handshake_tracker::HandshakeStep;
+use crate::networking::types::icmp_error::IcmpError;
 use crate::networking::types::icmp_type::{IcmpType, IcmpTypeV4, IcmpTypeV6};
 use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
 use crate::networking::types::info_traffic::InfoTraffic;
+use crate::networking::types::mac_flags::MacFlags;
 use crate::networking::types::packet_filters_fields::PacketFiltersFields;
 use crate::networking::types::service::Service;
 use crate::networking::types::service_query::ServiceQuery;
+use crate::networking::types::services_map_info::{ServiceLookup, ServicesMapInfo};
 use crate::networking::types::traffic_direction::TrafficDirection;
 use crate::networking::types::traffic_type::TrafficType;
 use crate::networking::types::ip_version::IpVersion;
@@ -22,8 +28,22 @@ use std::fmt::Write;
 
 include!(concat!(env!("OUT_DIR"), "/services.rs"));
 
+/// Well-known ports looked up to check that the services map is loaded
+const SERVICES_MAP_SAMPLES: [(u16, Protocol); 4] = [
+    (22, Protocol::TCP),
+    (53, Protocol::UDP),
+    (80, Protocol::TCP),
+    (443, Protocol::TCP),
+];
+
 /// Calls methods to analyze link, network, and transport headers.
 /// Returns the relevant collected information.
+///
+/// Packets without a supported network or transport layer (e.g., Ethernet pause frames or LLDP)
+/// are ignored altogether: `None` is returned and neither their bytes nor their MAC addresses are recorded.
+///
+/// Packets whose transport header was cut off (e.g., by a small snapshot length)
+/// are still accounted, keyed without ports, and `transport_truncated` is set.
 pub fn analyze_headers(
     headers: LaxPacketHeaders,
     mac_addresses: &mut (Option<String>, Option<String>),
@@ -31,19 +51,25 @@ pub fn analyze_headers(
     icmp_type: &mut IcmpType,
     arp_type: &mut ArpType,
     packet_filters_fields: &mut PacketFiltersFields,
+    transport_truncated: &mut bool,
 ) -> Option<AddressPortPair> {
+    // only committed once the packet is known to be accounted
+    let mut packet_macs = (None, None);
+    let mut packet_bytes = 0;
+    let truncated_protocol = truncated_transport_protocol(&headers);
+
     analyze_link_header(
         headers.link,
-        &mut mac_addresses.0,
-        &mut mac_addresses.1,
-        exchanged_bytes,
+        &mut packet_macs.0,
+        &mut packet_macs.1,
+        &mut packet_bytes,
     );
 
     let is_arp = matches!(&headers.net, Some(NetHeaders::Arp(_)));
 
     if !analyze_network_header(
         headers.net,
-        exchanged_bytes,
+        &mut packet_bytes,
         &mut packet_filters_fields.ip_version,
         &mut packet_filters_fields.source,
         &mut packet_filters_fields.dest,
@@ -61,9 +87,16 @@ pub fn analyze_headers(
             icmp_type,
         )
     {
-        return None;
+        let protocol = truncated_protocol?;
+        packet_filters_fields.sport = None;
+        packet_filters_fields.dport = None;
+        packet_filters_fields.protocol = protocol;
+        *transport_truncated = true;
     }
 
+    *mac_addresses = packet_macs;
+    *exchanged_bytes += packet_bytes;
+
     Some(AddressPortPair::new(
         packet_filters_fields.source,
         packet_filters_fields.sport,
@@ -73,6 +106,128 @@ pub fn analyze_headers(
     ))
 }
 
+/// Returns the transport protocol of a packet whose transport header couldn't be parsed
+/// because it was cut off, even though its network header is valid
+fn truncated_transport_protocol(headers: &LaxPacketHeaders) -> Option<Protocol> {
+    if headers.transport.is_some() || headers.stop_err.is_none() {
+        return None;
+    }
+    let LaxPayloadSlice::Ip(ip_payload) = &headers.payload else {
+        return None;
+    };
+    match ip_payload.ip_number {
+        IpNumber::TCP => Some(Protocol::TCP),
+        IpNumber::UDP => Some(Protocol::UDP),
+        IpNumber::ICMP | IpNumber::IPV6_ICMP => Some(Protocol::ICMP),
+        _ => None,
+    }
+}
+
+/// Returns true if the packet is a TCP segment opening or closing a connection
+/// (i.e., it has the SYN, FIN, or RST flag set).
+pub fn is_tcp_handshake_packet(headers: &LaxPacketHeaders) -> bool {
+    matches!(
+        &headers.transport,
+        Some(TransportHeader::Tcp(tcp_header)) if tcp_header.syn || tcp_header.fin || tcp_header.rst
+    )
+}
+
+/// Returns the step of the TCP three-way handshake the packet belongs to, if any
+pub fn tcp_handshake_step(headers: &LaxPacketHeaders) -> Option<HandshakeStep> {
+    match &headers.transport {
+        Some(TransportHeader::Tcp(tcp_header)) if tcp_header.syn && !tcp_header.rst => {
+            Some(if tcp_header.ack {
+                HandshakeStep::SynAck
+            } else {
+                HandshakeStep::Syn
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns the DSCP value (QoS marking) of the IP header of the packet, if any
+pub fn ip_dscp(headers: &LaxPacketHeaders) -> Option<u8> {
+    match &headers.net {
+        Some(NetHeaders::Ipv4(ipv4header, _)) => Some(ipv4header.dscp.value()),
+        // the DSCP value is made of the 6 most significant bits of the traffic class
+        Some(NetHeaders::Ipv6(ipv6header, _)) => Some(ipv6header.traffic_class >> 2),
+        _ => None,
+    }
+}
+
+/// Returns the connection an ICMP error (e.g., destination unreachable or time exceeded)
+/// is about, along with the reported error.
+///
+/// ICMP errors quote the header of the packet triggering them, from which the originating
+/// connection is rebuilt; only the first 8 bytes of its transport header are guaranteed
+/// to be quoted, which is enough for the ports of both TCP and UDP.
+pub fn icmp_error_origin(headers: &LaxPacketHeaders) -> Option<(AddressPortPair, IcmpError)> {
+    let (icmp_error, quoted) = match (&headers.transport, &headers.payload) {
+        (Some(TransportHeader::Icmpv4(icmp_header)), LaxPayloadSlice::Icmpv4 { payload, .. }) => {
+            (IcmpError::from_icmpv4(&icmp_header.icmp_type)?, *payload)
+        }
+        (Some(TransportHeader::Icmpv6(icmp_header)), LaxPayloadSlice::Icmpv6 { payload, .. }) => {
+            (IcmpError::from_icmpv6(&icmp_header.icmp_type)?, *payload)
+        }
+        _ => return None,
+    };
+
+    let quoted_headers = LaxPacketHeaders::from_ip(quoted).ok()?;
+    let (address1, address2) = match &quoted_headers.net {
+        Some(NetHeaders::Ipv4(ipv4header, _)) => (
+            IpAddr::from(ipv4header.source),
+            IpAddr::from(ipv4header.destination),
+        ),
+        Some(NetHeaders::Ipv6(ipv6header, _)) => (
+            IpAddr::from(ipv6header.source),
+            IpAddr::from(ipv6header.destination),
+        ),
+        _ => return None,
+    };
+    let (port1, port2, protocol) = match (&quoted_headers.transport, &quoted_headers.payload) {
+        (Some(TransportHeader::Udp(udp_header)), _) => (
+            Some(udp_header.source_port),
+            Some(udp_header.destination_port),
+            Protocol::UDP,
+        ),
+        (Some(TransportHeader::Tcp(tcp_header)), _) => (
+            Some(tcp_header.source_port),
+            Some(tcp_header.destination_port),
+            Protocol::TCP,
+        ),
+        // the quoted TCP header is usually too short to be parsed: read just its ports
+        (None, LaxPayloadSlice::Ip(ip_payload))
+            if ip_payload.ip_number == IpNumber::TCP && ip_payload.payload.len() >= 4 =>
+        {
+            let ports = ip_payload.payload;
+            (
+                Some(u16::from_be_bytes([ports[0], ports[1]])),
+                Some(u16::from_be_bytes([ports[2], ports[3]])),
+                Protocol::TCP,
+            )
+        }
+        _ => return None,
+    };
+
+    Some((
+        AddressPortPair::new(address1, port1, address2, port2, protocol),
+        icmp_error,
+    ))
+}
+
+/// Returns the payload carried by the TCP or UDP segment, if any
+pub fn transport_payload<'a>(headers: &LaxPacketHeaders<'a>) -> Option<&'a [u8]> {
+    match &headers.payload {
+        LaxPayloadSlice::Udp { payload, .. } | LaxPayloadSlice::Tcp { payload, .. }
+            if !payload.is_empty() =>
+        {
+            Some(*payload)
+        }
+        _ => None,
+    }
+}
+
 /// This function analyzes the data link layer header passed as parameter and updates variables
 /// passed by reference on the basis of the packet header content.
 /// Returns false if packet has to be skipped.
@@ -284,8 +439,11 @@ pub fn modify_or_insert_in_map(
         .map
         .entry(*key)
         .and_modify(|info| {
-            info.transmitted_bytes += exchanged_bytes;
-            info.transmitted_packets += 1;
+            info.transmitted_bytes = info.transmitted_bytes.saturating_add(exchanged_bytes);
+            info.transmitted_packets = info.transmitted_packets.saturating_add(1);
+            info.min_packet_size = info.min_packet_size.min(exchanged_bytes);
+            info.max_packet_size = info.max_packet_size.max(exchanged_bytes);
+            info.add_interval(timestamp);
             info.final_timestamp = timestamp;
             if key.protocol.eq(&Protocol::ICMP) {
                 info.icmp_types
@@ -299,14 +457,24 @@ pub fn modify_or_insert_in_map(
                     .and_modify(|n| *n += 1)
                     .or_insert(1);
             }
+            info.add_capture_source(cs.name());
         })
         .or_insert_with(|| InfoAddressPortPair {
+            mac_flags1: mac_addresses.0.as_deref().and_then(MacFlags::from_address),
+            mac_flags2: mac_addresses.1.as_deref().and_then(MacFlags::from_address),
             mac_address1: mac_addresses.0,
             mac_address2: mac_addresses.1,
             transmitted_bytes: exchanged_bytes,
             transmitted_packets: 1,
+            bytes_sent: 0,
+            bytes_received: 0,
+            packets_sent: 0,
+            packets_received: 0,
+            min_packet_size: exchanged_bytes,
+            max_packet_size: exchanged_bytes,
             initial_timestamp: timestamp,
             final_timestamp: timestamp,
+            sum_squared_intervals: 0,
             service,
             traffic_direction,
             icmp_types: if key.protocol.eq(&Protocol::ICMP) {
@@ -319,13 +487,57 @@ pub fn modify_or_insert_in_map(
             } else {
                 HashMap::new()
             },
+            process: None,
+            pid: None,
+            app_protocol: None,
+            server_name: None,
+            estimated_rtt_ms: None,
+            syn_segments: 0,
+            syn_ack_segments: 0,
+            icmp_error: None,
+            capture_sources: vec![cs.get_name()],
         });
+    let packet_direction = new_info.traffic_direction;
+    new_info.add_directional_packet(exchanged_bytes, packet_direction);
+    let ret_val = (new_info.traffic_direction, new_info.service);
+    info_traffic_msg.merge_directional_counters(key);
 
-    (new_info.traffic_direction, new_info.service)
+    ret_val
+}
+
+/// Returns the size of the services map generated at build time, with a few sample lookups
+pub fn get_services_map_info() -> ServicesMapInfo {
+    ServicesMapInfo {
+        entries: SERVICES.len(),
+        samples: SERVICES_MAP_SAMPLES
+            .iter()
+            .map(|&(port, protocol)| ServiceLookup {
+                port,
+                protocol,
+                service: SERVICES.get(&ServiceQuery(port, protocol)).copied(),
+            })
+            .collect(),
+    }
+}
+
+/// Returns the service associated with a port, given the name of its transport protocol
+/// (`tcp` or `udp`, case-insensitive); services don't apply to any other protocol.
+pub fn lookup_service(port: u16, protocol: &str) -> Service {
+    let protocol = if protocol.eq_ignore_ascii_case("tcp") {
+        Protocol::TCP
+    } else if protocol.eq_ignore_ascii_case("udp") {
+        Protocol::UDP
+    } else {
+        return Service::NotApplicable;
+    };
+    SERVICES
+        .get(&ServiceQuery(port, protocol))
+        .copied()
+        .unwrap_or(Service::Unknown)
 }
 
 /// Returns the traffic direction observed (incoming or outgoing)
-fn get_traffic_direction(
+pub fn get_traffic_direction(
     source_ip: &IpAddr,
     destination_ip: &IpAddr,
     source_port: Option<u16>,
@@ -349,10 +561,10 @@ fn get_traffic_direction(
         };
     }
 
-    // if interface_addresses is empty, check if the IP is a bogon (useful when importing pcap files)
+    // if interface_addresses is empty, check if the IP is a private endpoint (importing pcap files)
     let is_local = |interface_addresses: &Vec<IpAddr>, ip: &IpAddr| -> bool {
         if interface_addresses.is_empty() {
-            is_bogon(ip).is_some()
+            is_private_endpoint(ip)
         } else {
             interface_addresses.contains(ip)
         }
@@ -374,6 +586,39 @@ fn get_traffic_direction(
     }
 }
 
+/// Returns true if the address belongs to a private-use or CGN range (or is an IPv6 ULA)
+fn is_private_endpoint(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ipv4) => {
+            let octets = ipv4.octets();
+            let is_cgn = octets[0] == 100 && (octets[1] & 0xC0) == 64;
+            ipv4.is_private() || is_cgn
+        }
+        IpAddr::V6(ipv6) => ipv6.is_unique_local(),
+    }
+}
+
+/// Returns true if the connection satisfies all the conditions of the capture filter
+pub fn matches_capture_filter(
+    filter: &CaptureFilter,
+    key: &AddressPortPair,
+    my_interface_addresses: &[Address],
+) -> bool {
+    let traffic_direction = get_traffic_direction(
+        &key.address1,
+        &key.address2,
+        key.port1,
+        key.port2,
+        my_interface_addresses,
+    );
+    let traffic_type = get_traffic_type(
+        &get_address_to_lookup(key, traffic_direction),
+        my_interface_addresses,
+        traffic_direction,
+    );
+    filter.matches(key, traffic_direction, traffic_type)
+}
+
 /// Returns the traffic type observed (unicast, multicast or broadcast)
 /// It refers to the remote host
 pub fn get_traffic_type(
@@ -419,10 +664,7 @@ fn is_broadcast_address(address: &IpAddr, my_interface_addresses: &[Address]) ->
 }
 
 /// Determines if the connection is local
-pub fn is_local_connection(
-    address_to_lookup: &IpAddr,
-    my_interface_addresses: &Vec<Address>,
-) -> bool {
+pub fn is_local_connection(address_to_lookup: &IpAddr, my_interface_addresses: &[Address]) -> bool {
     let mut ret_val = false;
 
     for address in my_interface_addresses {
@@ -506,9 +748,21 @@ pub fn get_address_to_lookup(key: &AddressPortPair, traffic_direction: TrafficDi
     }
 }
 
+/// Returns the address of this host in a connection (the counterpart of the address to lookup)
+pub fn get_local_address(key: &AddressPortPair, traffic_direction: TrafficDirection) -> IpAddr {
+    match traffic_direction {
+        TrafficDirection::Outgoing => key.address1,
+        TrafficDirection::Incoming => key.address2,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use pcap::Address;
+    use etherparse::icmpv4::{DestUnreachableHeader, TimeExceededCode};
+    use etherparse::{
+        IcmpEchoHeader, Icmpv4Type, LaxPacketHeaders, PacketBuilder, PacketBuilderStep, TcpHeader,
+    };
+    use pcap::{Address, Device};
     use std::collections::HashSet;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::str::FromStr;
@@ -516,9 +770,17 @@ mod tests {
     use crate::networking::types::protocol::Protocol;
     use crate::networking::types::service::Service;
     use crate::networking::manage_packets::{
-        get_service, get_traffic_direction, get_traffic_type, is_local_connection,
-        mac_from_dec_to_hex,
+        analyze_headers, get_service, get_services_map_info, get_traffic_direction,
+        get_traffic_type, icmp_error_origin, is_local_connection, is_tcp_handshake_packet,
+        lookup_service, mac_from_dec_to_hex, modify_or_insert_in_map,
     };
+    use crate::networking::types::arp_type::ArpType;
+    use crate::networking::types::capture_context::CaptureSource;
+    use crate::networking::types::info_traffic::InfoTraffic;
+    use crate::networking::types::my_device::MyDevice;
+    use crate::networking::types::icmp_error::IcmpError;
+    use crate::networking::types::icmp_type::IcmpType;
+    use crate::networking::types::packet_filters_fields::PacketFiltersFields;
     use crate::networking::types::address_port_pair::AddressPortPair;
     use crate::networking::types::service_query::ServiceQuery;
     use crate::networking::types::traffic_direction::TrafficDirection;
@@ -691,6 +953,41 @@ mod tests {
         assert_eq!(result4, TrafficDirection::Outgoing);
     }
 
+    #[test]
+    fn traffic_direction_offline_test() {
+        // without interface addresses (imported files), only private endpoints are considered local
+        let cases = [
+            // private and CGN sources
+            ("192.168.1.2", "224.0.0.251", TrafficDirection::Outgoing),
+            ("192.168.1.2", "169.254.10.1", TrafficDirection::Outgoing),
+            ("100.64.0.5", "8.8.8.8", TrafficDirection::Outgoing),
+            ("8.8.8.8", "100.127.255.1", TrafficDirection::Incoming),
+            ("fd00::2", "fe80::1", TrafficDirection::Outgoing),
+            // link-local sources
+            ("169.254.10.1", "192.168.1.2", TrafficDirection::Incoming),
+            ("169.254.10.1", "224.0.0.251", TrafficDirection::Incoming),
+            ("fe80::1", "ff02::fb", TrafficDirection::Incoming),
+            ("fe80::1", "fd00::2", TrafficDirection::Incoming),
+            // multicast sources are never local
+            ("224.0.0.251", "10.0.0.1", TrafficDirection::Incoming),
+            // 100.128.0.0 is outside the CGN range
+            ("100.128.0.1", "8.8.8.8", TrafficDirection::Incoming),
+            // local not yet assigned an IP
+            ("0.0.0.0", "255.255.255.255", TrafficDirection::Outgoing),
+            ("0.0.0.0", "10.0.0.1", TrafficDirection::Incoming),
+        ];
+        for (source, destination, expected) in cases {
+            let direction = get_traffic_direction(
+                &IpAddr::from_str(source).unwrap(),
+                &IpAddr::from_str(destination).unwrap(),
+                Some(5353),
+                Some(5353),
+                &[],
+            );
+            assert_eq!(direction, expected, "{source} -> {destination}");
+        }
+    }
+
     #[test]
     fn traffic_type_multicast_ipv4_test() {
         let result1 = get_traffic_type(
@@ -1898,4 +2195,450 @@ mod tests {
             &Service::Name("murmur")
         );
     }
+
+    fn tcp_packet(
+        flags: impl FnOnce(PacketBuilderStep<TcpHeader>) -> PacketBuilderStep<TcpHeader>,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4([192, 168, 1, 2], [93, 184, 216, 34], 64)
+            .tcp(50000, 443, 1, 65535);
+        let builder = flags(builder);
+        let mut packet = Vec::with_capacity(builder.size(payload.len()));
+        builder.write(&mut packet, payload).unwrap();
+        packet
+    }
+
+    #[test]
+    fn is_tcp_handshake_packet_test() {
+        let handshake_packets = [
+            tcp_packet(|b| b.syn(), &[]),
+            tcp_packet(|b| b.syn().ack(1), &[]),
+            tcp_packet(|b| b.rst(), &[]),
+            tcp_packet(|b| b.fin().ack(1), &[]),
+        ];
+        for packet in &handshake_packets {
+            let headers = LaxPacketHeaders::from_ethernet(packet).unwrap();
+            assert!(is_tcp_handshake_packet(&headers));
+        }
+
+        // bulk data is skipped
+        let data_packet = tcp_packet(|b| b.ack(1).psh(), &[0; 1000]);
+        let headers = LaxPacketHeaders::from_ethernet(&data_packet).unwrap();
+        assert!(!is_tcp_handshake_packet(&headers));
+
+        // other protocols are skipped
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4([192, 168, 1, 2], [8, 8, 8, 8], 64)
+            .udp(50000, 53);
+        let mut udp_packet = Vec::new();
+        builder.write(&mut udp_packet, &[0; 40]).unwrap();
+        let headers = LaxPacketHeaders::from_ethernet(&udp_packet).unwrap();
+        assert!(!is_tcp_handshake_packet(&headers));
+    }
+
+    /// Returns an ICMP error about the given IP packet, quoting its first 28 bytes
+    fn icmp_error_packet(icmp_type: Icmpv4Type, original_packet: &[u8]) -> Vec<u8> {
+        let builder = PacketBuilder::ethernet2([7, 8, 9, 10, 11, 12], [1, 2, 3, 4, 5, 6])
+            .ipv4([8, 8, 8, 8], [192, 168, 1, 2], 64)
+            .icmpv4(icmp_type);
+        let quoted = &original_packet[..28];
+        let mut packet = Vec::with_capacity(builder.size(quoted.len()));
+        builder.write(&mut packet, quoted).unwrap();
+        packet
+    }
+
+    #[test]
+    fn icmp_error_origin_port_unreachable_test() {
+        let builder = PacketBuilder::ipv4([192, 168, 1, 2], [8, 8, 8, 8], 64).udp(50000, 53);
+        let mut udp_packet = Vec::new();
+        builder.write(&mut udp_packet, &[0; 40]).unwrap();
+
+        let icmp_packet = icmp_error_packet(
+            Icmpv4Type::DestinationUnreachable(DestUnreachableHeader::Port),
+            &udp_packet,
+        );
+        let headers = LaxPacketHeaders::from_ethernet(&icmp_packet).unwrap();
+        assert_eq!(
+            icmp_error_origin(&headers),
+            Some((
+                AddressPortPair::new(
+                    IpAddr::from([192, 168, 1, 2]),
+                    Some(50000),
+                    IpAddr::from([8, 8, 8, 8]),
+                    Some(53),
+                    Protocol::UDP,
+                ),
+                IcmpError::PortUnreachable
+            ))
+        );
+    }
+
+    #[test]
+    fn icmp_error_origin_truncated_tcp_header_test() {
+        // only the ports and the sequence number of the TCP header are quoted
+        let tcp_frame = tcp_packet(|b| b.syn(), &[]);
+        let icmp_packet = icmp_error_packet(
+            Icmpv4Type::TimeExceeded(TimeExceededCode::TtlExceededInTransit),
+            &tcp_frame[14..],
+        );
+        let headers = LaxPacketHeaders::from_ethernet(&icmp_packet).unwrap();
+        let (key, icmp_error) = icmp_error_origin(&headers).unwrap();
+        assert_eq!(
+            key,
+            AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(50000),
+                IpAddr::from([93, 184, 216, 34]),
+                Some(443),
+                Protocol::TCP,
+            )
+        );
+        assert_eq!(icmp_error, IcmpError::TimeExceeded);
+
+        // echo messages are not errors about other packets
+        let echo_packet = icmp_error_packet(
+            Icmpv4Type::EchoRequest(IcmpEchoHeader { id: 1, seq: 1 }),
+            &tcp_frame[14..],
+        );
+        let headers = LaxPacketHeaders::from_ethernet(&echo_packet).unwrap();
+        assert_eq!(icmp_error_origin(&headers), None);
+    }
+
+    /// Returns the key, bytes, and MAC addresses recorded for the given frame
+    fn analyze_frame(
+        frame: &[u8],
+    ) -> (
+        Option<AddressPortPair>,
+        u128,
+        (Option<String>, Option<String>),
+    ) {
+        let mut mac_addresses = (None, None);
+        let mut exchanged_bytes = 0;
+        let key = analyze_headers(
+            LaxPacketHeaders::from_ethernet(frame).unwrap(),
+            &mut mac_addresses,
+            &mut exchanged_bytes,
+            &mut IcmpType::default(),
+            &mut ArpType::default(),
+            &mut PacketFiltersFields::default(),
+            &mut false,
+        );
+        (key, exchanged_bytes, mac_addresses)
+    }
+
+    #[test]
+    fn analyze_headers_truncated_transport_header_test() {
+        let tcp_frame = tcp_packet(|b| b.ack(1), &[0; 100]);
+        // the capture stopped in the middle of the TCP header
+        let truncated_frame = &tcp_frame[..14 + 20 + 10];
+        let mut exchanged_bytes = 0;
+        let mut transport_truncated = false;
+        let key = analyze_headers(
+            LaxPacketHeaders::from_ethernet(truncated_frame).unwrap(),
+            &mut (None, None),
+            &mut exchanged_bytes,
+            &mut IcmpType::default(),
+            &mut ArpType::default(),
+            &mut PacketFiltersFields::default(),
+            &mut transport_truncated,
+        );
+        assert_eq!(
+            key,
+            Some(AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                None,
+                IpAddr::from([93, 184, 216, 34]),
+                None,
+                Protocol::TCP,
+            ))
+        );
+        assert!(transport_truncated);
+        // the size of the original packet is accounted, as declared by the IP header
+        assert_eq!(exchanged_bytes, tcp_frame.len() as u128);
+
+        // complete packets are not flagged
+        let mut transport_truncated = false;
+        let key = analyze_headers(
+            LaxPacketHeaders::from_ethernet(&tcp_frame).unwrap(),
+            &mut (None, None),
+            &mut 0,
+            &mut IcmpType::default(),
+            &mut ArpType::default(),
+            &mut PacketFiltersFields::default(),
+            &mut transport_truncated,
+        );
+        assert_eq!(key.unwrap().port1, Some(50000));
+        assert!(!transport_truncated);
+    }
+
+    #[test]
+    fn analyze_headers_link_only_frame_test() {
+        // Ethernet pause frame (MAC control, no network layer), padded to the minimum frame size
+        let mut pause_frame = vec![
+            0x01, 0x80, 0xC2, 0x00, 0x00, 0x01, // destination
+            0x00, 0x1B, 0x21, 0x3C, 0x9D, 0xF8, // source
+            0x88, 0x08, // EtherType: MAC control
+            0x00, 0x01, // opcode: pause
+            0xFF, 0xFF, // pause time
+        ];
+        pause_frame.resize(60, 0);
+        let (key, exchanged_bytes, mac_addresses) = analyze_frame(&pause_frame);
+        assert_eq!(key, None);
+        // the link header is not partially accounted
+        assert_eq!(exchanged_bytes, 0);
+        assert_eq!(mac_addresses, (None, None));
+
+        // frames with a network layer are accounted in full
+        let tcp_frame = tcp_packet(|b| b.ack(1), &[0; 100]);
+        let (key, exchanged_bytes, mac_addresses) = analyze_frame(&tcp_frame);
+        assert!(key.is_some());
+        assert_eq!(exchanged_bytes, tcp_frame.len() as u128);
+        assert_eq!(
+            mac_addresses,
+            (
+                Some("01:02:03:04:05:06".to_string()),
+                Some("07:08:09:0a:0b:0c".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn services_map_info_test() {
+        let info = get_services_map_info();
+        assert_ne!(info.entries, 0);
+        assert_eq!(info.entries, 12084);
+
+        let services: Vec<Option<Service>> = info.samples.iter().map(|s| s.service).collect();
+        assert_eq!(
+            services,
+            [
+                Some(Service::Name("ssh")),
+                Some(Service::Name("domain")),
+                Some(Service::Name("http")),
+                Some(Service::Name("https")),
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_service_test() {
+        for (port, protocol, expected) in [
+            (443, "tcp", Service::Name("https")),
+            (53, "UDP", Service::Name("domain")),
+            (22, "Tcp", Service::Name("ssh")),
+            (0, "tcp", Service::Unknown),
+            (443, "icmp", Service::NotApplicable),
+            (443, "", Service::NotApplicable),
+        ] {
+            assert_eq!(lookup_service(port, protocol), expected);
+        }
+    }
+
+    #[test]
+    fn modify_or_insert_in_map_packet_sizes_test() {
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(Device::from("eth0")));
+        cs.set_addresses(vec![Address {
+            addr: IpAddr::from([192, 168, 1, 2]),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        }]);
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from([93, 184, 216, 34]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let add_packets = |info_traffic: &mut InfoTraffic, sizes: &[u128]| {
+            for &size in sizes {
+                modify_or_insert_in_map(
+                    info_traffic,
+                    &key,
+                    &cs,
+                    (None, None),
+                    IcmpType::default(),
+                    ArpType::default(),
+                    size,
+                );
+            }
+        };
+
+        let mut info_traffic = InfoTraffic::default();
+        add_packets(&mut info_traffic, &[1514, 66, 800]);
+        let info = &info_traffic.map[&key];
+        assert_eq!(info.min_packet_size, 66);
+        assert_eq!(info.max_packet_size, 1514);
+        assert_eq!(info.avg_packet_size(), 793);
+
+        // sizes are kept consistent when merging the data of a later interval
+        let mut next_interval = InfoTraffic::default();
+        add_packets(&mut next_interval, &[54, 1200]);
+        let mut info = info.clone();
+        info.refresh(&next_interval.map[&key]);
+        assert_eq!(info.min_packet_size, 54);
+        assert_eq!(info.max_packet_size, 1514);
+        assert_eq!(info.avg_packet_size(), 726);
+    }
+
+    #[test]
+    fn modify_or_insert_in_map_bidirectional_bytes_test() {
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(Device::from("eth0")));
+        cs.set_addresses(vec![Address {
+            addr: IpAddr::from([192, 168, 1, 2]),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        }]);
+        let request = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from([93, 184, 216, 34]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let response = AddressPortPair::new(
+            IpAddr::from([93, 184, 216, 34]),
+            Some(443),
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            Protocol::TCP,
+        );
+
+        let insert = |info_traffic: &mut InfoTraffic, key: &AddressPortPair, size: u128| {
+            modify_or_insert_in_map(
+                info_traffic,
+                key,
+                &cs,
+                (None, None),
+                IcmpType::default(),
+                ArpType::default(),
+                size,
+            );
+        };
+
+        // the response is seen first, but the counters end up on the outgoing entry
+        let mut info_traffic = InfoTraffic::default();
+        for (key, size) in [
+            (&response, 1500),
+            (&request, 100),
+            (&response, 1500),
+            (&request, 60),
+        ] {
+            insert(&mut info_traffic, key, size);
+        }
+        let connection = &info_traffic.map[&request];
+        assert_eq!((connection.bytes_sent, connection.packets_sent), (160, 2));
+        assert_eq!(
+            (connection.bytes_received, connection.packets_received),
+            (3000, 2)
+        );
+        assert_eq!(connection.transmitted_bytes, 160);
+        let reverse = &info_traffic.map[&response];
+        assert_eq!((reverse.bytes_sent, reverse.bytes_received), (0, 0));
+        assert_eq!((reverse.packets_sent, reverse.packets_received), (0, 0));
+        assert_eq!(reverse.transmitted_bytes, 3000);
+
+        // the same holds when the directions are seen in different intervals
+        let mut collected = InfoTraffic::default();
+        for (key, size) in [(&response, 1500), (&request, 100), (&response, 1500)] {
+            let mut msg = InfoTraffic::default();
+            insert(&mut msg, key, size);
+            collected.refresh(&mut msg);
+        }
+        let connection = &collected.map[&request];
+        assert_eq!(
+            (connection.bytes_sent, connection.bytes_received),
+            (100, 3000)
+        );
+        assert_eq!(
+            (connection.packets_sent, connection.packets_received),
+            (1, 2)
+        );
+        let reverse = &collected.map[&response];
+        assert_eq!((reverse.packets_sent, reverse.packets_received), (0, 0));
+    }
+
+    #[test]
+    fn modify_or_insert_in_map_capture_sources_test() {
+        let capture_source = |name: &str| {
+            let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(Device::from(name)));
+            cs.set_addresses(vec![Address {
+                addr: IpAddr::from([192, 168, 1, 2]),
+                netmask: Some(IpAddr::from([255, 255, 255, 0])),
+                broadcast_addr: None,
+                dst_addr: None,
+            }]);
+            cs
+        };
+        let (eth0, eth1) = (capture_source("eth0"), capture_source("eth1"));
+        let key = |port| {
+            AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(port),
+                IpAddr::from([93, 184, 216, 34]),
+                Some(443),
+                Protocol::TCP,
+            )
+        };
+        let (only_eth0, only_eth1, both) = (key(50000), key(50001), key(50002));
+
+        let mut info_traffic = InfoTraffic::default();
+        for (key, cs) in [
+            (&only_eth0, &eth0),
+            (&both, &eth1),
+            (&only_eth1, &eth1),
+            (&both, &eth0),
+            (&both, &eth1),
+            (&only_eth0, &eth0),
+        ] {
+            modify_or_insert_in_map(
+                &mut info_traffic,
+                key,
+                cs,
+                (None, None),
+                IcmpType::default(),
+                ArpType::default(),
+                100,
+            );
+        }
+
+        assert_eq!(info_traffic.map[&only_eth0].capture_sources, ["eth0"]);
+        assert_eq!(info_traffic.map[&only_eth1].capture_sources, ["eth1"]);
+        // in order of first occurrence, without duplicates
+        assert_eq!(info_traffic.map[&both].capture_sources, ["eth1", "eth0"]);
+
+        // the interfaces of a later interval are added when merging
+        let mut next_interval = InfoTraffic::default();
+        modify_or_insert_in_map(
+            &mut next_interval,
+            &only_eth0,
+            &eth1,
+            (None, None),
+            IcmpType::default(),
+            ArpType::default(),
+            100,
+        );
+        let mut info = info_traffic.map[&only_eth0].clone();
+        info.refresh(&next_interval.map[&only_eth0]);
+        assert_eq!(info.capture_sources, ["eth0", "eth1"]);
+        info.refresh(&info_traffic.map[&both]);
+        assert_eq!(info.capture_sources, ["eth0", "eth1"]);
+    }
+
+    /// The map generated by the build script when the services list is missing
+    mod empty_services {
+        use crate::networking::types::protocol::Protocol;
+        use crate::networking::types::service::Service;
+        use crate::networking::types::service_query::ServiceQuery;
+
+        include!(concat!(env!("OUT_DIR"), "/services_empty.rs"));
+
+        #[test]
+        fn test_empty_services_map_compiles() {
+            assert!(SERVICES.is_empty());
+            assert!(SERVICES.get(&ServiceQuery(443, Protocol::TCP)).is_none());
+        }
+    }
 }
@@ -1,3 +1,5 @@
 pub mod manage_packets;
+pub mod parse_dns;
 pub mod parse_packets;
+pub mod parse_tls;
 pub mod types;
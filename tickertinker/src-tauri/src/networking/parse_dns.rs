@@ -0,0 +1,207 @@
+//! Module containing functions to learn the domains requested by the monitored host
+//! from the DNS responses it receives.
+
+use std::net::IpAddr;
+
+use etherparse::{LaxPacketHeaders, LaxPayloadSlice, TransportHeader};
+
+/// Port used by DNS servers
+const DNS_PORT: u16 = 53;
+/// Record types carrying an address
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+/// Maximum number of compression pointers followed while reading a name (avoids loops)
+const MAX_NAME_JUMPS: usize = 16;
+
+/// Returns the DNS message carried by the packet, if it's sent by a DNS server over UDP or TCP
+pub fn dns_response_payload<'a>(headers: &LaxPacketHeaders<'a>) -> Option<&'a [u8]> {
+    match (&headers.transport, &headers.payload) {
+        (Some(TransportHeader::Udp(udp_header)), LaxPayloadSlice::Udp { payload, .. })
+            if udp_header.source_port == DNS_PORT =>
+        {
+            Some(*payload)
+        }
+        // over TCP, messages are prefixed by their length
+        (Some(TransportHeader::Tcp(tcp_header)), LaxPayloadSlice::Tcp { payload, .. })
+            if tcp_header.source_port == DNS_PORT =>
+        {
+            payload.get(2..)
+        }
+        _ => None,
+    }
+}
+
+/// Parses a DNS response, returning the addresses it contains (A and AAAA records)
+/// together with the domain that was originally requested.
+pub fn parse_dns_answers(message: &[u8]) -> Vec<(IpAddr, String)> {
+    let mut answers = Vec::new();
+    let (Some(flags), Some(qd_count), Some(an_count)) = (
+        read_u16(message, 2),
+        read_u16(message, 4),
+        read_u16(message, 6),
+    ) else {
+        return answers;
+    };
+    // only consider successful responses
+    if flags & 0x8000 == 0 || flags & 0x000F != 0 {
+        return answers;
+    }
+
+    let mut offset = 12;
+    let mut requested_domain = None;
+    for _ in 0..qd_count {
+        let Some((name, next)) = read_name(message, offset) else {
+            return answers;
+        };
+        requested_domain.get_or_insert(name);
+        // skip type and class
+        offset = next + 4;
+    }
+
+    for _ in 0..an_count {
+        let Some((name, next)) = read_name(message, offset) else {
+            return answers;
+        };
+        let (Some(record_type), Some(rd_length)) =
+            (read_u16(message, next), read_u16(message, next + 8))
+        else {
+            return answers;
+        };
+        let rdata_start = next + 10;
+        let rdata_end = rdata_start + usize::from(rd_length);
+        let Some(rdata) = message.get(rdata_start..rdata_end) else {
+            return answers;
+        };
+        let address = match record_type {
+            TYPE_A => <[u8; 4]>::try_from(rdata).ok().map(IpAddr::from),
+            TYPE_AAAA => <[u8; 16]>::try_from(rdata).ok().map(IpAddr::from),
+            _ => None,
+        };
+        if let Some(address) = address {
+            // the requested name is preferred over the one of the record (it could be an alias target)
+            answers.push((address, requested_domain.clone().unwrap_or(name)));
+        }
+        offset = rdata_end;
+    }
+
+    answers
+}
+
+/// Reads a (possibly compressed) name, returning it with the offset following it
+fn read_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut next_offset = None;
+    let mut jumps = 0;
+    loop {
+        let len = *message.get(offset)?;
+        match len {
+            0 => break,
+            // compression pointer
+            len if len & 0xC0 == 0xC0 => {
+                jumps += 1;
+                if jumps > MAX_NAME_JUMPS {
+                    return None;
+                }
+                next_offset.get_or_insert(offset + 2);
+                offset = usize::from(read_u16(message, offset)? & 0x3FFF);
+            }
+            len => {
+                let label = message.get(offset + 1..offset + 1 + usize::from(len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + usize::from(len);
+            }
+        }
+    }
+    Some((labels.join("."), next_offset.unwrap_or(offset + 1)))
+}
+
+pub(crate) fn read_u16(message: &[u8], offset: usize) -> Option<u16> {
+    let bytes = message.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use etherparse::PacketBuilder;
+
+    use super::*;
+
+    /// Response to a query for `www.example.com`, answered with a CNAME, an A, and an AAAA record
+    fn example_dns_response() -> Vec<u8> {
+        let mut message = vec![
+            0x12, 0x34, // id
+            0x81, 0x80, // flags: response, no error
+            0x00, 0x01, // questions
+            0x00, 0x03, // answers
+            0x00, 0x00, 0x00, 0x00, // authority and additional records
+        ];
+        // question: www.example.com, type A, class IN
+        message.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+        // CNAME pointing to edge.example.net
+        message.extend_from_slice(&[0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x12]);
+        message.extend_from_slice(b"\x04edge\x07example\x03net\x00");
+        // A and AAAA records of edge.example.net (the name at offset 45)
+        message.extend_from_slice(&[0xC0, 0x2D, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04]);
+        message.extend_from_slice(&[93, 184, 216, 34]);
+        message.extend_from_slice(&[0xC0, 0x2D, 0x00, 0x1C, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x10]);
+        message.extend_from_slice(&Ipv6Addr::new(0x2606, 0x2800, 0, 0, 0, 0, 0, 0x1946).octets());
+        message
+    }
+
+    #[test]
+    fn test_parse_dns_answers() {
+        assert_eq!(
+            parse_dns_answers(&example_dns_response()),
+            vec![
+                (
+                    IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34)),
+                    "www.example.com".to_string()
+                ),
+                (
+                    IpAddr::V6(Ipv6Addr::new(0x2606, 0x2800, 0, 0, 0, 0, 0, 0x1946)),
+                    "www.example.com".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dns_answers_malformed() {
+        let response = example_dns_response();
+        // queries are ignored
+        let mut query = response.clone();
+        query[2] = 0x01;
+        assert!(parse_dns_answers(&query).is_empty());
+        // truncated messages only yield the complete records
+        assert_eq!(parse_dns_answers(&response[..response.len() - 4]).len(), 1);
+        assert!(parse_dns_answers(&response[..10]).is_empty());
+        // compression loops are detected
+        let mut looping = response;
+        looping[12] = 0xC0;
+        looping[13] = 0x0C;
+        assert!(parse_dns_answers(&looping).is_empty());
+    }
+
+    #[test]
+    fn test_dns_response_payload() {
+        let message = example_dns_response();
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4([192, 168, 1, 1], [192, 168, 1, 2], 64)
+            .udp(53, 50000);
+        let mut packet = Vec::new();
+        builder.write(&mut packet, &message).unwrap();
+        let headers = LaxPacketHeaders::from_ethernet(&packet).unwrap();
+        assert_eq!(dns_response_payload(&headers), Some(message.as_slice()));
+
+        // the query sent to the server is not a response
+        let builder = PacketBuilder::ethernet2([7, 8, 9, 10, 11, 12], [1, 2, 3, 4, 5, 6])
+            .ipv4([192, 168, 1, 2], [192, 168, 1, 1], 64)
+            .udp(50000, 53);
+        let mut packet = Vec::new();
+        builder.write(&mut packet, &message).unwrap();
+        let headers = LaxPacketHeaders::from_ethernet(&packet).unwrap();
+        assert_eq!(dns_response_payload(&headers), None);
+    }
+}
@@ -1,63 +1,136 @@
 //! Module containing functions executed by the thread in charge of parsing sniffed packets
 
+use crate::countries::types::country::Country;
 use crate::location;
 use crate::mmdb::asn::get_asn;
 use crate::mmdb::country::get_country;
 use crate::mmdb::types::mmdb_reader::MmdbReaders;
 use crate::networking::manage_packets::{
-    analyze_headers, get_address_to_lookup, get_traffic_type, is_local_connection,
-    modify_or_insert_in_map,
+    analyze_headers, get_address_to_lookup, get_traffic_direction, get_traffic_type,
+    icmp_error_origin, ip_dscp, is_local_connection, is_tcp_handshake_packet,
+    matches_capture_filter, modify_or_insert_in_map, tcp_handshake_step, transport_payload,
 };
+use crate::networking::parse_dns::{dns_response_payload, parse_dns_answers};
+use crate::networking::parse_tls::parse_server_name;
 use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::app_protocol::AppProtocol;
 use crate::networking::types::arp_type::ArpType;
+use crate::networking::types::asn::Asn;
 use crate::networking::types::bogon::is_bogon;
-use crate::networking::types::capture_context::{CaptureContext, CaptureSource};
+use crate::networking::types::capture_context::{CaptureContext, CaptureSource, CaptureType};
+use crate::networking::types::capture_filter::CaptureFilter;
+use crate::networking::types::capture_options::CaptureOptions;
 use crate::networking::types::data_info::DataInfo;
 use crate::networking::types::data_info_host::DataInfoHost;
+use crate::networking::types::handshake_tracker::HandshakeTracker;
 use crate::networking::types::host::{Host, HostMessage};
 use crate::networking::types::icmp_type::IcmpType;
-use crate::networking::types::info_traffic::InfoTraffic;
+use crate::networking::types::info_traffic::{InfoTraffic, TICK_INTERVAL};
 use crate::networking::types::my_link_type::MyLinkType;
 use crate::networking::types::packet_filters_fields::PacketFiltersFields;
+use crate::networking::types::parse_failure_sample::{
+    MAX_PARSE_FAILURE_SAMPLES, ParseFailureSample,
+};
+use crate::networking::types::savefile_queue::SavefileSink;
+use crate::networking::types::service::Service;
+use crate::networking::types::socket_table::{
+    SOCKET_TABLE_REFRESH_INTERVAL, SOCKET_TABLE_SUPPORTED, SocketTableRefresher,
+};
 use crate::networking::types::traffic_direction::TrafficDirection;
 use crate::utils::error_logger::{ErrorLogger, Location};
 use crate::utils::formatted_strings::get_domain_from_r_dns;
 use crate::utils::types::timestamp::Timestamp;
-use async_channel::Sender;
+use async_channel::{Receiver, Sender};
 use dns_lookup::lookup_addr;
 use etherparse::err::ip::{HeaderError, LaxHeaderSliceError};
 use etherparse::err::{Layer, LenError};
 use etherparse::{LaxPacketHeaders, LenSource};
 use pcap::{Address, Device, Packet};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
-/// The calling thread enters a loop in which it waits for network packets
+/// How often a paused file replay checks whether it was resumed
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Maximum number of addresses learned from DNS responses kept until their host is named
+/// (the oldest ones are forgotten first)
+const MAX_DNS_ANSWERS: usize = 4096;
+
+/// The calling thread enters a loop in which it waits for network packets.
+///
+/// In offline captures, the files in `queued_files` are parsed after the first one, in order,
+/// as if their packets were part of the same file.
+#[allow(clippy::too_many_arguments)]
 pub fn parse_packets(
     cap_id: usize,
     mut cs: CaptureSource,
     mmdb_readers: &MmdbReaders,
     capture_context: CaptureContext,
+    mut queued_files: VecDeque<(CaptureSource, CaptureContext)>,
+    options: CaptureOptions,
+    mut capture_filter: CaptureFilter,
+    resolutions_state: &Arc<Mutex<AddressesResolutionState>>,
     tx: &Sender<BackendTrafficMessage>,
+    control_rx: &Receiver<CaptureControlMessage>,
+    stop_signal: &AtomicBool,
+    pause_signal: &AtomicBool,
 ) {
-    let my_link_type = capture_context.my_link_type();
-    let (mut cap, mut savefile) = capture_context.consume();
+    let mut my_link_type = capture_context.my_link_type();
+    let (mut cap, savefiles) = capture_context.consume();
+    let mut savefile =
+        savefiles.map(|savefiles| SavefileSink::new(savefiles, options.savefile_queue_size));
+
+    // sockets of this host, used to label connections with their process (only in live captures)
+    let socket_table = (options.label_processes
+        && SOCKET_TABLE_SUPPORTED
+        && matches!(cs, CaptureSource::Device(_)))
+    .then(|| SocketTableRefresher::start(SOCKET_TABLE_REFRESH_INTERVAL));
 
     let mut info_traffic_msg = InfoTraffic::default();
-    let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::default()));
     // list of newly resolved hosts to be sent (batched to avoid UI updates too often)
     let new_hosts_to_send = Arc::new(Mutex::new(Vec::new()));
 
     // instant of the first parsed packet plus multiples of 1 second (only used in live captures)
     let mut first_packet_ticks = None;
 
+    // whether new addresses are resolved via rDNS (can be toggled while capturing)
+    let mut rdns_enabled = !options.disable_rdns;
+    // number of packets whose headers couldn't be parsed sent as samples
+    let mut parse_failure_samples = 0;
+    // SYN segments waiting for their SYN-ACK, used to estimate the round-trip time of connections
+    let mut handshake_tracker = HandshakeTracker::default();
+
     loop {
+        // act on the running capture if requested (e.g., to replace its filter)
+        while let Ok(control_msg) = control_rx.try_recv() {
+            handle_control_message(
+                &mut cap,
+                &mut capture_filter,
+                &mut rdns_enabled,
+                control_msg,
+            );
+        }
+
+        // file replays are held while paused, since no packet would get lost
+        if pause_signal.load(Ordering::Relaxed) && matches!(cs, CaptureSource::File(_)) {
+            if tx.is_closed() || stop_signal.load(Ordering::Relaxed) {
+                return;
+            }
+            thread::sleep(PAUSE_POLL_INTERVAL);
+            continue;
+        }
+
+        // refreshed even without new packets, to report drops while the buffer is full
+        if matches!(cs, CaptureSource::Device(_)) {
+            update_capture_stats(&mut cap, &mut info_traffic_msg);
+        }
         let packet_res = cap.next_packet();
 
-        if tx.is_closed() {
+        if tx.is_closed() || stop_signal.load(Ordering::Relaxed) {
             return;
         }
 
@@ -75,6 +148,13 @@ pub fn parse_packets(
         match packet_res {
             Err(e) => {
                 if e == pcap::Error::NoMorePackets {
+                    // move on to the next file, if any (each one can have its own link type)
+                    if let Some((next_cs, next_capture_context)) = queued_files.pop_front() {
+                        my_link_type = next_capture_context.my_link_type();
+                        (cap, _) = next_capture_context.consume();
+                        cs = next_cs;
+                        continue;
+                    }
                     // send a message including data from the last interval (only happens in offline captures)
                     let _ = tx.send_blocking(BackendTrafficMessage::TickRun(
                         cap_id,
@@ -83,7 +163,7 @@ pub fn parse_packets(
                         true,
                     ));
                     // wait until there is still some thread doing rdns
-                    while tx.sender_count() > 1 {
+                    while tx.sender_count() > 1 && !stop_signal.load(Ordering::Relaxed) {
                         thread::sleep(Duration::from_millis(1000));
                     }
                     // send one last message including all pending hosts
@@ -92,15 +172,36 @@ pub fn parse_packets(
                         new_hosts_to_send.lock().unwrap().drain(..).collect(),
                     ));
                     return;
+                } else if e != pcap::Error::TimeoutExpired {
+                    // the capture can't go on (e.g., the interface went down)
+                    stop_signal.store(true, Ordering::Relaxed);
+                    let _ = tx
+                        .send_blocking(BackendTrafficMessage::CaptureError(cap_id, e.to_string()));
+                    return;
                 }
             }
+            // live packets are still read while paused, so that the capture buffer doesn't overflow
+            Ok(_) if pause_signal.load(Ordering::Relaxed) => {}
             Ok(packet) => {
-                if let Ok(headers) = get_sniffable_headers(&packet, my_link_type) {
+                info_traffic_msg.capture_volume.add_packet(packet.header);
+                let headers_res = get_sniffable_headers(&packet, my_link_type);
+                if let Err(e) = &headers_res {
+                    if options.sample_parse_failures
+                        && parse_failure_samples < MAX_PARSE_FAILURE_SAMPLES
+                    {
+                        parse_failure_samples += 1;
+                        let sample = ParseFailureSample::new(&packet, my_link_type, e.to_string());
+                        let _ =
+                            tx.send_blocking(BackendTrafficMessage::ParseFailure(cap_id, sample));
+                    }
+                }
+                if let Ok(headers) = headers_res {
                     #[allow(clippy::useless_conversion)]
                     let secs = i64::from(packet.header.ts.tv_sec);
                     #[allow(clippy::useless_conversion)]
-                    let usecs = i64::from(packet.header.ts.tv_usec);
-                    let next_packet_timestamp = Timestamp::new(secs, usecs);
+                    let subsecs = i64::from(packet.header.ts.tv_usec);
+                    let next_packet_timestamp =
+                        Timestamp::from_packet_header(secs, subsecs, cs.timestamp_precision());
 
                     if matches!(cs, CaptureSource::File(_)) {
                         maybe_send_tick_run_offline(
@@ -116,11 +217,29 @@ pub fn parse_packets(
 
                     info_traffic_msg.last_packet_timestamp = next_packet_timestamp;
 
+                    if options.tcp_handshake_only && !is_tcp_handshake_packet(&headers) {
+                        continue;
+                    }
+
+                    let mut app_protocol = None;
+                    let mut server_name = None;
+                    if options.inspect_payloads {
+                        learn_dns_answers(&headers, resolutions_state);
+                        let payload = transport_payload(&headers);
+                        app_protocol = payload.and_then(AppProtocol::from_payload);
+                        server_name = payload.and_then(parse_server_name);
+                    }
+
+                    let handshake_step = tcp_handshake_step(&headers);
+                    let icmp_error = icmp_error_origin(&headers);
+                    let dscp = ip_dscp(&headers);
+
                     let mut exchanged_bytes = 0;
                     let mut mac_addresses = (None, None);
                     let mut icmp_type = IcmpType::default();
                     let mut arp_type = ArpType::default();
                     let mut packet_filters_fields = PacketFiltersFields::default();
+                    let mut transport_truncated = false;
 
                     let key_option = analyze_headers(
                         headers,
@@ -129,30 +248,126 @@ pub fn parse_packets(
                         &mut icmp_type,
                         &mut arp_type,
                         &mut packet_filters_fields,
+                        &mut transport_truncated,
                     );
 
                     let Some(key) = key_option else {
                         continue;
                     };
 
+                    // skip the packets not satisfying the structured capture filter
+                    if !matches_capture_filter(&capture_filter, &key, cs.get_addresses()) {
+                        continue;
+                    }
+
+                    // the packets of the protocols left out by the protocol filter are only counted
+                    let tracked = capture_filter.tracks_protocol(key.protocol);
+
+                    // update the map
+                    let (traffic_direction, service) = if tracked {
+                        modify_or_insert_in_map(
+                            &mut info_traffic_msg,
+                            &key,
+                            &cs,
+                            mac_addresses,
+                            icmp_type,
+                            arp_type,
+                            exchanged_bytes,
+                        )
+                    } else {
+                        let traffic_direction = get_traffic_direction(
+                            &key.address1,
+                            &key.address2,
+                            key.port1,
+                            key.port2,
+                            cs.get_addresses(),
+                        );
+                        (traffic_direction, Service::Unknown)
+                    };
+
                     // save this packet to PCAP file
                     if let Some(file) = savefile.as_mut() {
-                        file.write(&packet);
+                        let saved = file.write(key.protocol, &packet, || {
+                            let address = get_address_to_lookup(&key, traffic_direction);
+                            let resolutions_lock = resolutions_state.lock().unwrap();
+                            let host = resolutions_lock.addresses_resolved.get(&address);
+                            packet_comment(traffic_direction, &service, host)
+                        });
+                        if !saved {
+                            info_traffic_msg.skipped_writes += 1;
+                        }
+                    }
+
+                    if !tracked {
+                        info_traffic_msg
+                            .tot_data_info
+                            .add_packet(exchanged_bytes, traffic_direction);
+                        continue;
+                    }
+
+                    if let Some(app_protocol) = app_protocol {
+                        if let Some(info) = info_traffic_msg
+                            .map
+                            .get_mut(&key)
+                            .filter(|info| info.app_protocol.is_none())
+                        {
+                            info.app_protocol = Some(app_protocol);
+                        }
+                    }
+
+                    // the server of a TLS handshake is named after the domain it was asked for
+                    if let Some(server_name) = server_name {
+                        resolutions_state
+                            .lock()
+                            .unwrap()
+                            .add_server_name(key.address2, server_name.clone());
+                        if let Some(info) = info_traffic_msg.map.get_mut(&key) {
+                            info.server_name = Some(server_name);
+                        }
+                    }
+
+                    // count the handshake segments, which tell the server side of the connections
+                    if let Some(step) = handshake_step
+                        && let Some(info) = info_traffic_msg.map.get_mut(&key)
+                    {
+                        info.add_handshake_segment(step);
+                    }
+
+                    // estimate the round-trip time when the SYN-ACK is matched with its SYN
+                    if let Some((syn_key, rtt_ms)) = handshake_step.and_then(|step| {
+                        handshake_tracker.observe(&key, step, next_packet_timestamp)
+                    }) {
+                        for rtt_key in [key, syn_key] {
+                            if let Some(info) = info_traffic_msg.map.get_mut(&rtt_key) {
+                                info.estimated_rtt_ms = Some(rtt_ms);
+                            }
+                        }
+                    }
+
+                    // link ICMP errors to the connection they're about
+                    if let Some((origin_key, icmp_error)) = icmp_error {
+                        info_traffic_msg.icmp_errors.insert(origin_key, icmp_error);
+                    }
+
+                    // label the connection with its process
+                    if let Some(socket_table) = &socket_table {
+                        if let Some(info) = info_traffic_msg
+                            .map
+                            .get_mut(&key)
+                            .filter(|info| info.process.is_none())
+                            && let Some(process) = socket_table.process_for(&key, traffic_direction)
+                        {
+                            info.process = Some(process.name);
+                            info.pid = Some(process.pid);
+                        }
                     }
-                    // update the map
-                    let (traffic_direction, service) = modify_or_insert_in_map(
-                        &mut info_traffic_msg,
-                        &key,
-                        &cs,
-                        mac_addresses,
-                        icmp_type,
-                        arp_type,
-                        exchanged_bytes,
-                    );
 
                     info_traffic_msg
                         .tot_data_info
                         .add_packet(exchanged_bytes, traffic_direction);
+                    if transport_truncated {
+                        info_traffic_msg.malformed_transport_packets += 1;
+                    }
 
                     // check the rDNS status of this address and act accordingly
                     let address_to_lookup = get_address_to_lookup(&key, traffic_direction);
@@ -175,31 +390,25 @@ pub fn parse_packets(
                             // Useful to NOT perform again a rDNS lookup for this entry
                             resolutions_lock.addresses_waiting_resolution.insert(
                                 address_to_lookup,
-                                DataInfo::new_with_first_packet(exchanged_bytes, traffic_direction),
+                                DataInfo::new_with_first_packet_at(
+                                    exchanged_bytes,
+                                    traffic_direction,
+                                    next_packet_timestamp,
+                                ),
                             );
                             drop(resolutions_lock);
 
-                            // launch new thread to resolve host name
-                            let key2 = key;
-                            let resolutions_state2 = resolutions_state.clone();
-                            let new_hosts_to_send2 = new_hosts_to_send.clone();
-                            let interface_addresses = cs.get_addresses().clone();
-                            let mmdb_readers_2 = mmdb_readers.clone();
-                            let tx2 = tx.clone();
-                            let _ = thread::Builder::new()
-                                .name("thread_reverse_dns_lookup".to_string())
-                                .spawn(move || {
-                                    reverse_dns_lookup(
-                                        &resolutions_state2,
-                                        &new_hosts_to_send2,
-                                        &key2,
-                                        traffic_direction,
-                                        &interface_addresses,
-                                        &mmdb_readers_2,
-                                        &tx2,
-                                    );
-                                })
-                                .log_err(location!());
+                            resolve_host(
+                                rdns_enabled,
+                                resolutions_state,
+                                &new_hosts_to_send,
+                                key,
+                                traffic_direction,
+                                cs.get_addresses(),
+                                mmdb_readers,
+                                options.skip_local_geolocation,
+                                tx,
+                            );
                         }
                         (true, false) => {
                             // waiting for a previously requested rDNS resolution
@@ -208,7 +417,11 @@ pub fn parse_packets(
                                 .addresses_waiting_resolution
                                 .entry(address_to_lookup)
                                 .and_modify(|data_info| {
-                                    data_info.add_packet(exchanged_bytes, traffic_direction);
+                                    data_info.add_packet_at(
+                                        exchanged_bytes,
+                                        traffic_direction,
+                                        next_packet_timestamp,
+                                    );
                                 });
                             drop(resolutions_lock);
                         }
@@ -220,14 +433,17 @@ pub fn parse_packets(
                                 .get(&address_to_lookup)
                                 .unwrap_or(&Host::default())
                                 .clone();
+                            let is_favorite = resolutions_lock.favorite_hosts.contains(&host);
                             drop(resolutions_lock);
                             info_traffic_msg
                                 .hosts
                                 .entry(host)
                                 .and_modify(|data_info_host| {
-                                    data_info_host
-                                        .data_info
-                                        .add_packet(exchanged_bytes, traffic_direction);
+                                    data_info_host.data_info.add_packet_at(
+                                        exchanged_bytes,
+                                        traffic_direction,
+                                        next_packet_timestamp,
+                                    );
                                 })
                                 .or_insert_with(|| {
                                     let my_interface_addresses = cs.get_addresses();
@@ -243,11 +459,12 @@ pub fn parse_packets(
                                     );
                                     let is_bogon = is_bogon(&address_to_lookup);
                                     DataInfoHost {
-                                        data_info: DataInfo::new_with_first_packet(
+                                        data_info: DataInfo::new_with_first_packet_at(
                                             exchanged_bytes,
                                             traffic_direction,
+                                            next_packet_timestamp,
                                         ),
-                                        is_favorite: false,
+                                        is_favorite,
                                         is_loopback,
                                         is_local,
                                         is_bogon,
@@ -268,9 +485,17 @@ pub fn parse_packets(
                             DataInfo::new_with_first_packet(exchanged_bytes, traffic_direction)
                         });
 
-                    // update dropped packets number
-                    if let Ok(stats) = cap.stats() {
-                        info_traffic_msg.dropped_packets = stats.dropped;
+                    // update the QoS markings map
+                    if let Some(dscp) = dscp {
+                        info_traffic_msg
+                            .dscp_data
+                            .entry(dscp)
+                            .and_modify(|data_info| {
+                                data_info.add_packet(exchanged_bytes, traffic_direction);
+                            })
+                            .or_insert_with(|| {
+                                DataInfo::new_with_first_packet(exchanged_bytes, traffic_direction)
+                            });
                     }
                 }
             }
@@ -278,6 +503,156 @@ pub fn parse_packets(
     }
 }
 
+/// Reads packets until `n` of them are accounted, returning the traffic they make up
+/// without resolving the hosts.
+///
+/// Packets whose headers can't be parsed or not satisfying the capture filter aren't accounted.
+/// It stops earlier at the end of the capture (e.g., of a file) or after `timeout`,
+/// so that it can't hang on a quiet device.
+pub fn capture_n_packets(
+    cs: &CaptureSource,
+    capture_context: CaptureContext,
+    n: usize,
+    capture_filter: &CaptureFilter,
+    timeout: Duration,
+) -> Result<InfoTraffic, String> {
+    let my_link_type = capture_context.my_link_type();
+    let (mut cap, _) = capture_context.consume();
+    let mut info_traffic = InfoTraffic::default();
+    let mut packets = 0;
+    let start = Instant::now();
+
+    while packets < n && start.elapsed() < timeout {
+        let packet = match cap.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::TimeoutExpired) => continue,
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(e) => return Err(e.to_string()),
+        };
+        info_traffic.capture_volume.add_packet(packet.header);
+        let Ok(headers) = get_sniffable_headers(&packet, my_link_type) else {
+            continue;
+        };
+
+        let mut exchanged_bytes = 0;
+        let mut mac_addresses = (None, None);
+        let mut icmp_type = IcmpType::default();
+        let mut arp_type = ArpType::default();
+        let mut packet_filters_fields = PacketFiltersFields::default();
+        let mut transport_truncated = false;
+
+        let key_option = analyze_headers(
+            headers,
+            &mut mac_addresses,
+            &mut exchanged_bytes,
+            &mut icmp_type,
+            &mut arp_type,
+            &mut packet_filters_fields,
+            &mut transport_truncated,
+        );
+        let Some(key) = key_option else {
+            continue;
+        };
+        if !matches_capture_filter(capture_filter, &key, cs.get_addresses()) {
+            continue;
+        }
+
+        #[allow(clippy::useless_conversion)]
+        let secs = i64::from(packet.header.ts.tv_sec);
+        #[allow(clippy::useless_conversion)]
+        let subsecs = i64::from(packet.header.ts.tv_usec);
+        info_traffic.last_packet_timestamp =
+            Timestamp::from_packet_header(secs, subsecs, cs.timestamp_precision());
+
+        let (traffic_direction, service) = modify_or_insert_in_map(
+            &mut info_traffic,
+            &key,
+            cs,
+            mac_addresses,
+            icmp_type,
+            arp_type,
+            exchanged_bytes,
+        );
+        info_traffic
+            .tot_data_info
+            .add_packet(exchanged_bytes, traffic_direction);
+        if transport_truncated {
+            info_traffic.malformed_transport_packets += 1;
+        }
+        info_traffic
+            .services
+            .entry(service)
+            .and_modify(|data_info| data_info.add_packet(exchanged_bytes, traffic_direction))
+            .or_insert_with(|| DataInfo::new_with_first_packet(exchanged_bytes, traffic_direction));
+        packets += 1;
+    }
+
+    update_capture_stats(&mut cap, &mut info_traffic);
+    Ok(info_traffic)
+}
+
+/// Copies the packet counters of the capture handle (only available in live captures)
+fn update_capture_stats(cap: &mut CaptureType, info_traffic: &mut InfoTraffic) {
+    if let Ok(stats) = cap.stats() {
+        info_traffic.received_packets = stats.received;
+        info_traffic.dropped_packets = stats.dropped;
+        info_traffic.interface_dropped_packets = stats.if_dropped;
+    }
+}
+
+fn handle_control_message(
+    cap: &mut CaptureType,
+    capture_filter: &mut CaptureFilter,
+    rdns_enabled: &mut bool,
+    control_msg: CaptureControlMessage,
+) {
+    match control_msg {
+        CaptureControlMessage::UpdateFilter(bpf, reply_tx) => {
+            let res = update_bpf_filter(cap, capture_filter, &bpf);
+            let _ = reply_tx.send(res);
+        }
+        CaptureControlMessage::SetRdnsEnabled(enabled) => *rdns_enabled = enabled,
+    }
+}
+
+/// Replaces the BPF filter of the capture, which also replaces the one applied in userspace.
+///
+/// If libpcap can't compile the filter, its structured equivalent is applied in userspace instead,
+/// returning the compilation error as the reason;
+/// if it can't be translated either, the previous filter stays in place.
+fn update_bpf_filter(
+    cap: &mut CaptureType,
+    capture_filter: &mut CaptureFilter,
+    bpf: &str,
+) -> Result<Option<String>, String> {
+    match cap.set_bpf(bpf) {
+        Ok(()) => {
+            capture_filter.userspace_bpf = None;
+            Ok(None)
+        }
+        Err(e) => {
+            let bpf_filter = CaptureFilter::from_bpf(bpf).ok_or_else(|| e.to_string())?;
+            // if not even the empty filter compiles, no previous filter is installed in libpcap
+            let _ = cap.set_bpf("");
+            capture_filter.userspace_bpf = Some(Box::new(bpf_filter));
+            Ok(Some(e.to_string()))
+        }
+    }
+}
+
+/// Describes a saved packet with the outcome of its analysis
+/// (e.g., "outgoing | service: https | host: dns.google")
+fn packet_comment(direction: TrafficDirection, service: &Service, host: Option<&Host>) -> String {
+    let direction = match direction {
+        TrafficDirection::Incoming => "incoming",
+        TrafficDirection::Outgoing => "outgoing",
+    };
+    match host {
+        Some(host) => format!("{direction} | service: {service} | host: {}", host.domain),
+        None => format!("{direction} | service: {service}"),
+    }
+}
+
 fn get_sniffable_headers<'a>(
     packet: &'a Packet,
     my_link_type: MyLinkType,
@@ -330,6 +705,63 @@ fn from_null(packet: &[u8]) -> Result<LaxPacketHeaders<'_>, LaxHeaderSliceError>
     }
 }
 
+/// Resolves the host of an address seen for the first time.
+///
+/// If rDNS is enabled the lookup is performed by a new thread, whose handle is returned;
+/// otherwise the host is named after its IP address right away.
+#[allow(clippy::too_many_arguments)]
+fn resolve_host(
+    rdns_enabled: bool,
+    resolutions_state: &Arc<Mutex<AddressesResolutionState>>,
+    new_hosts_to_send: &Arc<Mutex<Vec<HostMessage>>>,
+    key: AddressPortPair,
+    traffic_direction: TrafficDirection,
+    interface_addresses: &Vec<Address>,
+    mmdb_readers: &MmdbReaders,
+    skip_local_geolocation: bool,
+    tx: &Sender<BackendTrafficMessage>,
+) -> Option<JoinHandle<()>> {
+    if !rdns_enabled {
+        reverse_dns_lookup(
+            resolutions_state,
+            new_hosts_to_send,
+            &key,
+            traffic_direction,
+            interface_addresses,
+            mmdb_readers,
+            skip_local_geolocation,
+            false,
+            tx,
+        );
+        return None;
+    }
+
+    // launch new thread to resolve host name
+    let resolutions_state2 = resolutions_state.clone();
+    let new_hosts_to_send2 = new_hosts_to_send.clone();
+    let interface_addresses = interface_addresses.clone();
+    let mmdb_readers_2 = mmdb_readers.clone();
+    let tx2 = tx.clone();
+    thread::Builder::new()
+        .name("thread_reverse_dns_lookup".to_string())
+        .spawn(move || {
+            reverse_dns_lookup(
+                &resolutions_state2,
+                &new_hosts_to_send2,
+                &key,
+                traffic_direction,
+                &interface_addresses,
+                &mmdb_readers_2,
+                skip_local_geolocation,
+                true,
+                &tx2,
+            );
+        })
+        .log_err(location!())
+        .ok()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn reverse_dns_lookup(
     resolutions_state: &Arc<Mutex<AddressesResolutionState>>,
     new_hosts_to_send: &Arc<Mutex<Vec<HostMessage>>>,
@@ -337,30 +769,50 @@ fn reverse_dns_lookup(
     traffic_direction: TrafficDirection,
     interface_addresses: &Vec<Address>,
     mmdb_readers: &MmdbReaders,
+    skip_local_geolocation: bool,
+    perform_rdns: bool,
     // needed to know that this thread is still running!
     _tx: &Sender<BackendTrafficMessage>,
 ) {
     let address_to_lookup = get_address_to_lookup(key, traffic_direction);
 
-    // perform rDNS lookup
-    let lookup_result = lookup_addr(&address_to_lookup);
+    // the domain requested by the host itself (if observed) is preferred over the rDNS
+    // (hosts are only named once, so the answer isn't needed anymore)
+    let requested_domain = resolutions_state
+        .lock()
+        .unwrap()
+        .dns_answers
+        .remove(&address_to_lookup);
 
     // get new host info and build the new host
     let traffic_type = get_traffic_type(&address_to_lookup, interface_addresses, traffic_direction);
     let is_loopback = address_to_lookup.is_loopback();
     let is_local = is_local_connection(&address_to_lookup, interface_addresses);
     let is_bogon = is_bogon(&address_to_lookup);
-    let country = get_country(&address_to_lookup, &mmdb_readers.country);
-    let asn = get_asn(&address_to_lookup, &mmdb_readers.asn);
-    let rdns = if let Ok(result) = lookup_result {
-        if result.is_empty() {
-            address_to_lookup.to_string()
-        } else {
-            result
+    let (country, asn) = geolocate(
+        &address_to_lookup,
+        is_local,
+        skip_local_geolocation,
+        mmdb_readers,
+    );
+    let resolution_start = Instant::now();
+    let rdns = requested_domain.unwrap_or_else(|| {
+        // perform rDNS lookup
+        let lookup = perform_rdns.then(|| lookup_addr(&address_to_lookup));
+        // the server name of a TLS handshake may have been observed in the meantime
+        let requested_domain = resolutions_state
+            .lock()
+            .unwrap()
+            .dns_answers
+            .remove(&address_to_lookup);
+        match (requested_domain, lookup) {
+            (Some(domain), _) => domain,
+            (None, Some(Ok(result))) if !result.is_empty() => result,
+            _ => address_to_lookup.to_string(),
         }
-    } else {
-        address_to_lookup.to_string()
-    };
+    });
+    let resolution_time = resolution_start.elapsed();
+    let is_resolved = rdns != address_to_lookup.to_string();
     let new_host = Host {
         domain: get_domain_from_r_dns(rdns.clone()),
         asn,
@@ -377,11 +829,18 @@ fn reverse_dns_lookup(
     resolutions_lock
         .addresses_resolved
         .insert(address_to_lookup, new_host.clone());
+    if is_resolved {
+        resolutions_lock.resolved_hosts += 1;
+    } else {
+        resolutions_lock.unresolved_hosts += 1;
+    }
+    resolutions_lock.resolution_time += resolution_time;
+    let is_favorite = resolutions_lock.favorite_hosts.contains(&new_host);
     drop(resolutions_lock);
 
     let data_info_host = DataInfoHost {
         data_info: other_data,
-        is_favorite: false,
+        is_favorite,
         is_local,
         is_bogon,
         is_loopback,
@@ -399,19 +858,180 @@ fn reverse_dns_lookup(
     new_hosts_to_send.lock().unwrap().push(msg_data);
 }
 
+/// Returns the country and ASN of the address
+/// (local addresses are skipped if `skip_local_geolocation` is set)
+fn geolocate(
+    address: &IpAddr,
+    is_local: bool,
+    skip_local_geolocation: bool,
+    mmdb_readers: &MmdbReaders,
+) -> (Country, Asn) {
+    if is_local && skip_local_geolocation {
+        return (Country::ZZ, Asn::default());
+    }
+    (
+        get_country(address, &mmdb_readers.country),
+        get_asn(address, &mmdb_readers.asn),
+    )
+}
+
+/// Records the addresses contained in the packet, if it's a DNS response
+fn learn_dns_answers(
+    headers: &LaxPacketHeaders,
+    resolutions_state: &Mutex<AddressesResolutionState>,
+) {
+    if let Some(message) = dns_response_payload(headers) {
+        let answers = parse_dns_answers(message);
+        if !answers.is_empty() {
+            resolutions_state.lock().unwrap().add_dns_answers(answers);
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AddressesResolutionState {
     /// Map of the addresses waiting for a rDNS resolution; used to NOT send multiple rDNS for the same address
     addresses_waiting_resolution: HashMap<IpAddr, DataInfo>,
     /// Map of the resolved addresses with the corresponding host
     pub addresses_resolved: HashMap<IpAddr, Host>,
+    /// Map of the addresses observed in DNS responses (or as the server of a TLS handshake)
+    /// with the domain that was requested, until the host of the address is named
+    dns_answers: HashMap<IpAddr, String>,
+    /// Addresses recorded in `dns_answers`, oldest first
+    /// (it can still list the ones whose answer was used in the meantime)
+    dns_answers_order: VecDeque<IpAddr>,
+    /// Number of hosts named after a domain
+    resolved_hosts: usize,
+    /// Number of hosts named after their address, since no domain was found
+    unresolved_hosts: usize,
+    /// Total time spent looking for the domains of the hosts
+    resolution_time: Duration,
+    /// Hosts marked as favorites, whose data is flagged with `is_favorite`
+    pub favorite_hosts: HashSet<Host>,
+}
+
+impl AddressesResolutionState {
+    pub fn new(favorite_hosts: HashSet<Host>) -> Self {
+        Self {
+            favorite_hosts,
+            ..Default::default()
+        }
+    }
+
+    /// Records the addresses observed in a DNS response, unless their host was already named,
+    /// forgetting the oldest ones if more than `MAX_DNS_ANSWERS` are kept
+    fn add_dns_answers(&mut self, answers: Vec<(IpAddr, String)>) {
+        for (address, domain) in answers {
+            if self.addresses_resolved.contains_key(&address) {
+                continue;
+            }
+            if self.dns_answers.insert(address, domain).is_none() {
+                self.dns_answers_order.push_back(address);
+            }
+        }
+        while self.dns_answers.len() > MAX_DNS_ANSWERS {
+            let Some(oldest) = self.dns_answers_order.pop_front() else {
+                break;
+            };
+            self.dns_answers.remove(&oldest);
+        }
+        // forget the addresses whose answer was used, once they pile up
+        if self.dns_answers_order.len() > 2 * MAX_DNS_ANSWERS {
+            let dns_answers = &self.dns_answers;
+            self.dns_answers_order
+                .retain(|address| dns_answers.contains_key(address));
+        }
+    }
+
+    /// Records the server name requested in a TLS handshake, unless the server was already named
+    fn add_server_name(&mut self, address: IpAddr, server_name: String) {
+        self.add_dns_answers(vec![(address, server_name)]);
+    }
+
+    /// Returns the number of addresses waiting for a resolution and already resolved,
+    /// with a bounded sample of each (sorted by address)
+    pub fn dump(&self, sample_size: usize) -> ResolutionStateDump {
+        let mut waiting: Vec<IpAddr> = self.addresses_waiting_resolution.keys().copied().collect();
+        waiting.sort_unstable();
+        let mut resolved: Vec<(IpAddr, String)> = self
+            .addresses_resolved
+            .iter()
+            .map(|(address, host)| (*address, host.domain.clone()))
+            .collect();
+        resolved.sort_unstable();
+        ResolutionStateDump {
+            addresses_waiting_resolution: waiting.len(),
+            addresses_waiting_resolution_sample: waiting.into_iter().take(sample_size).collect(),
+            addresses_resolved: resolved.len(),
+            addresses_resolved_sample: resolved.into_iter().take(sample_size).collect(),
+        }
+    }
+
+    /// Returns how many hosts were resolved to a domain, and how long it took on average
+    pub fn stats(&self) -> ResolutionStats {
+        let hosts = self.resolved_hosts + self.unresolved_hosts;
+        #[allow(clippy::cast_precision_loss)]
+        let (resolved_ratio, avg_resolution_ms) = if hosts == 0 {
+            (0.0, 0.0)
+        } else {
+            (
+                self.resolved_hosts as f64 / hosts as f64,
+                self.resolution_time.as_secs_f64() * 1_000.0 / hosts as f64,
+            )
+        };
+        ResolutionStats {
+            resolved_hosts: self.resolved_hosts,
+            unresolved_hosts: self.unresolved_hosts,
+            resolved_ratio,
+            avg_resolution_ms,
+        }
+    }
+}
+
+/// Effectiveness of the resolution of the hosts' domains
+#[derive(Clone, Debug, Serialize)]
+pub struct ResolutionStats {
+    /// Number of hosts named after a domain
+    pub resolved_hosts: usize,
+    /// Number of hosts named after their address
+    pub unresolved_hosts: usize,
+    /// Share of the hosts named after a domain (between 0 and 1)
+    pub resolved_ratio: f64,
+    /// Average time taken to look for the domain of a host, in milliseconds
+    pub avg_resolution_ms: f64,
+}
+
+/// Snapshot of the state of the address resolutions, for debugging purposes
+#[derive(Clone, Debug, Serialize)]
+pub struct ResolutionStateDump {
+    pub addresses_waiting_resolution: usize,
+    pub addresses_waiting_resolution_sample: Vec<IpAddr>,
+    pub addresses_resolved: usize,
+    /// Resolved addresses with the domain of their host
+    pub addresses_resolved_sample: Vec<(IpAddr, String)>,
 }
 
 #[allow(clippy::large_enum_variant)]
 pub enum BackendTrafficMessage {
     TickRun(usize, InfoTraffic, Vec<HostMessage>, bool),
     PendingHosts(usize, Vec<HostMessage>),
-    OfflineGap(usize, u32),
+    OfflineGap(usize, u64),
+    ParseFailure(usize, ParseFailureSample),
+    CaptureError(usize, String),
+    /// The addresses of the captured device changed, from the former to the latter
+    LocalAddressChanged(usize, Vec<IpAddr>, Vec<IpAddr>),
+}
+
+/// Requests to act on a running capture, handled by the thread parsing its packets
+pub enum CaptureControlMessage {
+    /// Replace the BPF filter of the capture, replying with the outcome
+    /// (and with the compilation error if the filter is applied in userspace)
+    UpdateFilter(
+        String,
+        std::sync::mpsc::Sender<Result<Option<String>, String>>,
+    ),
+    /// Enable or disable the rDNS resolution of new addresses
+    SetRdnsEnabled(bool),
 }
 
 fn maybe_send_tick_run_live(
@@ -422,22 +1042,51 @@ fn maybe_send_tick_run_live(
     first_packet_ticks: &mut Option<Instant>,
     tx: &Sender<BackendTrafficMessage>,
 ) {
-    if first_packet_ticks.is_some_and(|i| i.elapsed() >= Duration::from_millis(1000)) {
-        *first_packet_ticks =
-            first_packet_ticks.and_then(|i| i.checked_add(Duration::from_millis(1000)));
+    if first_packet_ticks.is_some_and(|i| i.elapsed() >= TICK_INTERVAL) {
+        *first_packet_ticks = first_packet_ticks.and_then(|i| i.checked_add(TICK_INTERVAL));
         let _ = tx.send_blocking(BackendTrafficMessage::TickRun(
             cap_id,
             info_traffic_msg.take_but_leave_something(),
             new_hosts_to_send.lock().unwrap().drain(..).collect(),
             false,
         ));
-        for dev in Device::list().log_err(location!()).unwrap_or_default() {
-            if dev.name.eq(&cs.get_name()) {
-                cs.set_addresses(dev.addresses);
-                break;
-            }
-        }
+        let devices = Device::list().log_err(location!()).unwrap_or_default();
+        update_local_addresses(cap_id, cs, devices, tx);
+    }
+}
+
+/// Refreshes the addresses of the captured device, reporting if they changed
+/// (e.g., after a DHCP renewal or connecting to a VPN).
+///
+/// The direction of new connections is determined against the new addresses,
+/// while the connections already seen keep their direction.
+fn update_local_addresses(
+    cap_id: usize,
+    cs: &mut CaptureSource,
+    devices: Vec<Device>,
+    tx: &Sender<BackendTrafficMessage>,
+) {
+    let Some(dev) = devices.into_iter().find(|dev| dev.name == cs.name()) else {
+        return;
+    };
+    let old_addresses = ip_addresses(cs.get_addresses());
+    let new_addresses = ip_addresses(&dev.addresses);
+    if old_addresses != new_addresses {
+        let _ = tx.send_blocking(BackendTrafficMessage::LocalAddressChanged(
+            cap_id,
+            old_addresses,
+            new_addresses,
+        ));
     }
+    cs.set_addresses(dev.addresses);
+}
+
+/// Returns the IP addresses of a device, sorted so that they can be compared
+fn ip_addresses(addresses: &[Address]) -> Vec<IpAddr> {
+    let mut ip_addresses: Vec<IpAddr> = addresses.iter().map(|address| address.addr).collect();
+    ip_addresses.sort_unstable();
+    ip_addresses.dedup();
+    ip_addresses
 }
 
 fn maybe_send_tick_run_offline(
@@ -459,12 +1108,495 @@ fn maybe_send_tick_run_offline(
             new_hosts_to_send.lock().unwrap().drain(..).collect(),
             false,
         ));
-        if diff_secs > 1 {
-            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-            let _ = tx.send_blocking(BackendTrafficMessage::OfflineGap(
-                cap_id,
-                diff_secs as u32 - 1,
-            ));
+        if let Ok(gap_secs) = u64::try_from(diff_secs - 1)
+            && gap_secs > 0
+        {
+            let _ = tx.send_blocking(BackendTrafficMessage::OfflineGap(cap_id, gap_secs));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::net::IpAddr;
+    use std::sync::{Arc, Mutex, mpsc};
+
+    use etherparse::{LaxPacketHeaders, PacketBuilder};
+    use pcap::{Address, Capture, Device, Linktype, Packet, PacketHeader};
+
+    use crate::countries::types::country::Country;
+    use crate::mmdb::asn::ASN_MMDB;
+    use crate::mmdb::country::COUNTRY_MMDB;
+    use crate::mmdb::types::mmdb_reader::{MmdbReader, MmdbReaders};
+    use crate::networking::manage_packets::{is_local_connection, modify_or_insert_in_map};
+    use crate::networking::parse_packets::{
+        AddressesResolutionState, BackendTrafficMessage, CaptureControlMessage, MAX_DNS_ANSWERS,
+        geolocate, get_sniffable_headers, handle_control_message, learn_dns_answers,
+        packet_comment, resolve_host, reverse_dns_lookup, update_local_addresses,
+    };
+    use crate::networking::types::address_port_pair::AddressPortPair;
+    use crate::networking::types::arp_type::ArpType;
+    use crate::networking::types::capture_context::{CaptureSource, CaptureType};
+    use crate::networking::types::capture_filter::CaptureFilter;
+    use crate::networking::types::data_info::DataInfo;
+    use crate::networking::types::host::Host;
+    use crate::networking::types::icmp_type::IcmpType;
+    use crate::networking::types::info_traffic::InfoTraffic;
+    use crate::networking::types::my_device::MyDevice;
+    use crate::networking::types::my_link_type::MyLinkType;
+    use crate::networking::types::parse_failure_sample::ParseFailureSample;
+    use crate::networking::types::protocol::Protocol;
+    use crate::networking::types::service::Service;
+    use crate::networking::types::traffic_direction::TrafficDirection;
+    use crate::utils::temp_files::temp_path;
+
+    fn open_empty_offline_capture(file_name: &str) -> CaptureType {
+        let path = temp_path(file_name);
+        let mut savefile = Capture::dead(Linktype::ETHERNET)
+            .unwrap()
+            .savefile(&path)
+            .unwrap();
+        savefile.flush().unwrap();
+        drop(savefile);
+        CaptureType::Offline(Capture::from_file(&path).unwrap())
+    }
+
+    #[test]
+    fn test_update_filter_of_running_capture() {
+        let mut cap = open_empty_offline_capture("tickertinker_update_filter.pcap");
+        let mut capture_filter = CaptureFilter::default()
+            .with_userspace_bpf(CaptureFilter::from_bpf("udp port 53").unwrap());
+        let mut rdns_enabled = true;
+        let (reply_tx, reply_rx) = mpsc::channel();
+
+        // the new filter compiles, so it also replaces the one applied in userspace
+        handle_control_message(
+            &mut cap,
+            &mut capture_filter,
+            &mut rdns_enabled,
+            CaptureControlMessage::UpdateFilter("tcp port 443".to_string(), reply_tx.clone()),
+        );
+        assert_eq!(reply_rx.recv().unwrap(), Ok(None));
+        assert!(capture_filter.userspace_bpf.is_none());
+
+        // an invalid filter is reported back, and the capture keeps running
+        handle_control_message(
+            &mut cap,
+            &mut capture_filter,
+            &mut rdns_enabled,
+            CaptureControlMessage::UpdateFilter("tcp port https port".to_string(), reply_tx),
+        );
+        assert!(reply_rx.recv().unwrap().is_err());
+        assert!(capture_filter.userspace_bpf.is_none());
+        assert!(matches!(cap.next_packet(), Err(pcap::Error::NoMorePackets)));
+    }
+
+    #[test]
+    fn test_local_address_changed() {
+        let address = |ip: [u8; 4]| Address {
+            addr: IpAddr::from(ip),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        };
+        let mut cs = CaptureSource::Device(MyDevice::from_pcap_device(Device::from("eth0")));
+        cs.set_addresses(vec![address([192, 168, 1, 2])]);
+        let (tx, rx) = async_channel::unbounded();
+        let connection = |port| {
+            AddressPortPair::new(
+                IpAddr::from([10, 8, 0, 5]),
+                Some(port),
+                IpAddr::from([93, 184, 216, 34]),
+                Some(443),
+                Protocol::TCP,
+            )
+        };
+        let add_packet = |info_traffic: &mut InfoTraffic, cs: &CaptureSource, key| {
+            modify_or_insert_in_map(
+                info_traffic,
+                key,
+                cs,
+                (None, None),
+                IcmpType::default(),
+                ArpType::default(),
+                100,
+            )
+            .0
+        };
+        let (old_connection, new_connection) = (connection(50000), connection(50001));
+        let mut info_traffic = InfoTraffic::default();
+        assert_eq!(
+            add_packet(&mut info_traffic, &cs, &old_connection),
+            TrafficDirection::Incoming
+        );
+
+        // connecting to a VPN
+        let mut vpn_device = Device::from("eth0");
+        vpn_device.addresses = vec![address([10, 8, 0, 5]), address([192, 168, 1, 2])];
+        let other_device = Device::from("eth1");
+        update_local_addresses(3, &mut cs, vec![other_device, vpn_device.clone()], &tx);
+        match rx.try_recv().unwrap() {
+            BackendTrafficMessage::LocalAddressChanged(cap_id, old, new) => {
+                assert_eq!(cap_id, 3);
+                assert_eq!(old, [IpAddr::from([192, 168, 1, 2])]);
+                assert_eq!(
+                    new,
+                    [IpAddr::from([10, 8, 0, 5]), IpAddr::from([192, 168, 1, 2])]
+                );
+            }
+            _ => panic!("expected a local address change"),
         }
+        // nothing is reported while the addresses stay the same, regardless of their order
+        vpn_device.addresses.reverse();
+        update_local_addresses(3, &mut cs, vec![vpn_device], &tx);
+        assert!(rx.try_recv().is_err());
+
+        // new connections are classified against the new addresses...
+        let mut next_interval = InfoTraffic::default();
+        assert_eq!(
+            add_packet(&mut next_interval, &cs, &new_connection),
+            TrafficDirection::Outgoing
+        );
+        // ...while the ones already seen keep their direction
+        add_packet(&mut next_interval, &cs, &old_connection);
+        info_traffic.refresh(&mut next_interval);
+        let direction = info_traffic.map[&old_connection].traffic_direction;
+        assert_eq!(direction, TrafficDirection::Incoming);
+    }
+
+    #[test]
+    fn test_host_named_after_observed_dns_response() {
+        let mut dns_response = vec![
+            0x00, 0x01, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+        dns_response.extend_from_slice(b"\x03www\x0ctickertinker\x03org\x00\x00\x01\x00\x01");
+        dns_response
+            .extend_from_slice(&[0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04]);
+        dns_response.extend_from_slice(&[203, 0, 113, 80]);
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12])
+            .ipv4([192, 168, 1, 1], [192, 168, 1, 2], 64)
+            .udp(53, 50000);
+        let mut packet = Vec::new();
+        builder.write(&mut packet, &dns_response).unwrap();
+
+        let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::default()));
+        learn_dns_answers(
+            &LaxPacketHeaders::from_ethernet(&packet).unwrap(),
+            &resolutions_state,
+        );
+
+        // a connection to the address contained in the response is named after the requested domain
+        let new_hosts_to_send = Arc::new(Mutex::new(Vec::new()));
+        let (tx, _rx) = async_channel::unbounded();
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50001),
+            IpAddr::from([203, 0, 113, 80]),
+            Some(443),
+            Protocol::TCP,
+        );
+        reverse_dns_lookup(
+            &resolutions_state,
+            &new_hosts_to_send,
+            &key,
+            TrafficDirection::Outgoing,
+            &Vec::new(),
+            &MmdbReaders::default(),
+            false,
+            true,
+            &tx,
+        );
+
+        let new_hosts = new_hosts_to_send.lock().unwrap();
+        assert_eq!(new_hosts.len(), 1);
+        assert_eq!(new_hosts[0].rdns, "www.tickertinker.org");
+        assert_eq!(new_hosts[0].host.domain, "tickertinker.org");
+        assert_eq!(
+            resolutions_state.lock().unwrap().addresses_resolved[&IpAddr::from([203, 0, 113, 80])],
+            new_hosts[0].host
+        );
+        // the answer is forgotten once used, and not recorded again for named hosts
+        let mut resolutions_lock = resolutions_state.lock().unwrap();
+        assert!(resolutions_lock.dns_answers.is_empty());
+        resolutions_lock.add_dns_answers(vec![(
+            IpAddr::from([203, 0, 113, 80]),
+            "www.tickertinker.org".to_string(),
+        )]);
+        assert!(resolutions_lock.dns_answers.is_empty());
+    }
+
+    #[test]
+    fn test_dns_answers_are_bounded() {
+        let mut resolutions_state = AddressesResolutionState::default();
+        let answers = (0..=u32::try_from(MAX_DNS_ANSWERS).unwrap())
+            .map(|n| (IpAddr::from(n.to_be_bytes()), format!("host{n}.example")))
+            .collect();
+        resolutions_state.add_dns_answers(answers);
+        assert_eq!(resolutions_state.dns_answers.len(), MAX_DNS_ANSWERS);
+        // the oldest answer is forgotten first
+        assert!(
+            !resolutions_state
+                .dns_answers
+                .contains_key(&IpAddr::from([0, 0, 0, 0]))
+        );
+        assert_eq!(
+            resolutions_state.dns_answers[&IpAddr::from([0, 0, 0, 1])],
+            "host1.example"
+        );
+
+        // the answers used in the meantime don't count toward the bound
+        resolutions_state.dns_answers.clear();
+        resolutions_state.add_dns_answers(vec![(
+            IpAddr::from([198, 51, 100, 1]),
+            "www.tickertinker.org".to_string(),
+        )]);
+        assert_eq!(resolutions_state.dns_answers.len(), 1);
+        assert!(resolutions_state.dns_answers_order.len() <= 2 * MAX_DNS_ANSWERS);
+    }
+
+    #[test]
+    fn test_favorite_host_flagged_when_resolved() {
+        let (tx, _rx) = async_channel::unbounded();
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50001),
+            IpAddr::from([203, 0, 113, 80]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let resolve = |resolutions_state: AddressesResolutionState| {
+            let new_hosts_to_send = Arc::new(Mutex::new(Vec::new()));
+            reverse_dns_lookup(
+                &Arc::new(Mutex::new(resolutions_state)),
+                &new_hosts_to_send,
+                &key,
+                TrafficDirection::Outgoing,
+                &Vec::new(),
+                &MmdbReaders::default(),
+                false,
+                false,
+                &tx,
+            );
+            new_hosts_to_send.lock().unwrap().pop().unwrap()
+        };
+
+        let host_msg = resolve(AddressesResolutionState::default());
+        assert!(!host_msg.data_info_host.is_favorite);
+
+        let favorite_hosts = HashSet::from([host_msg.host]);
+        let host_msg = resolve(AddressesResolutionState::new(favorite_hosts));
+        assert!(host_msg.data_info_host.is_favorite);
+    }
+
+    #[test]
+    fn test_geolocate_local_but_public_range() {
+        let mmdb_readers = MmdbReaders {
+            country: Arc::new(MmdbReader::from(&String::new(), COUNTRY_MMDB)),
+            asn: Arc::new(MmdbReader::from(&String::new(), ASN_MMDB)),
+        };
+        // the interface is configured on a public range
+        let interface_addresses = vec![Address {
+            addr: IpAddr::from([8, 8, 8, 1]),
+            netmask: Some(IpAddr::from([255, 255, 255, 0])),
+            broadcast_addr: None,
+            dst_addr: None,
+        }];
+        let address = IpAddr::from([8, 8, 8, 8]);
+        let is_local = is_local_connection(&address, &interface_addresses);
+        assert!(is_local);
+
+        let (country, asn) = geolocate(&address, is_local, false, &mmdb_readers);
+        assert_eq!(country, Country::US);
+        assert_eq!(asn.code, "15169");
+
+        let (country, asn) = geolocate(&address, is_local, true, &mmdb_readers);
+        assert_eq!(country, Country::ZZ);
+        assert_eq!(asn.code, "");
+
+        // non-local addresses are always looked up
+        let (country, _) = geolocate(&address, false, true, &mmdb_readers);
+        assert_eq!(country, Country::US);
+    }
+
+    #[test]
+    fn test_toggle_rdns_mid_capture() {
+        let mut cap = open_empty_offline_capture("tickertinker_toggle_rdns.pcap");
+        let mut capture_filter = CaptureFilter::default();
+        let mut rdns_enabled = true;
+        let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::default()));
+        let new_hosts_to_send = Arc::new(Mutex::new(Vec::new()));
+        let (tx, _rx) = async_channel::unbounded();
+        let resolve = |rdns_enabled, last_octet| {
+            let key = AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(50000),
+                IpAddr::from([127, 0, 0, last_octet]),
+                Some(443),
+                Protocol::TCP,
+            );
+            resolve_host(
+                rdns_enabled,
+                &resolutions_state,
+                &new_hosts_to_send,
+                key,
+                TrafficDirection::Outgoing,
+                &Vec::new(),
+                &MmdbReaders::default(),
+                false,
+                &tx,
+            )
+        };
+
+        // while disabled, no resolver thread is spawned and the host is named after its address
+        handle_control_message(
+            &mut cap,
+            &mut capture_filter,
+            &mut rdns_enabled,
+            CaptureControlMessage::SetRdnsEnabled(false),
+        );
+        assert!(!rdns_enabled);
+        assert!(resolve(rdns_enabled, 1).is_none());
+        assert!(resolve(rdns_enabled, 2).is_none());
+        {
+            let new_hosts = new_hosts_to_send.lock().unwrap();
+            assert_eq!(new_hosts.len(), 2);
+            assert_eq!(new_hosts[0].host.domain, "127.0.0.1");
+            assert_eq!(new_hosts[1].host.domain, "127.0.0.2");
+        }
+
+        // once enabled again, new addresses are resolved in background
+        // (named after a known DNS answer, so that the system resolver isn't involved)
+        handle_control_message(
+            &mut cap,
+            &mut capture_filter,
+            &mut rdns_enabled,
+            CaptureControlMessage::SetRdnsEnabled(true),
+        );
+        assert!(rdns_enabled);
+        resolutions_state.lock().unwrap().add_dns_answers(vec![(
+            IpAddr::from([127, 0, 0, 3]),
+            "www.tickertinker.org".to_string(),
+        )]);
+        let resolver = resolve(rdns_enabled, 3).expect("no lookup requested");
+        resolver.join().unwrap();
+        let new_hosts = new_hosts_to_send.lock().unwrap();
+        assert_eq!(new_hosts.len(), 3);
+        assert_eq!(new_hosts[2].host.domain, "tickertinker.org");
+    }
+
+    #[test]
+    fn test_sample_of_packet_failing_to_parse() {
+        // Ethernet frame truncated in the middle of its header
+        let data = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x1A];
+        let header = PacketHeader {
+            ts: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            caplen: 8,
+            len: 8,
+        };
+        let link_type = MyLinkType::Ethernet(Linktype::ETHERNET);
+        let Err(e) = get_sniffable_headers(&Packet::new(&header, &data), link_type) else {
+            panic!("a truncated Ethernet header must not be parsed");
+        };
+
+        let sample = ParseFailureSample::new(&data, link_type, e.to_string());
+        assert_eq!(sample.hex, "ff ff ff ff ff ff 00 1a");
+        assert_eq!(sample.len, 8);
+        assert_eq!(sample.link_type, "EN10MB");
+        assert!(!sample.error.is_empty());
+    }
+
+    #[test]
+    fn test_resolution_stats() {
+        let resolutions_state = Arc::new(Mutex::new(AddressesResolutionState::default()));
+        assert_eq!(resolutions_state.lock().unwrap().stats().resolved_hosts, 0);
+
+        // the domain of an address is known from a previous DNS response
+        resolutions_state.lock().unwrap().dns_answers.insert(
+            IpAddr::from([203, 0, 113, 80]),
+            "www.tickertinker.org".to_string(),
+        );
+
+        let new_hosts_to_send = Arc::new(Mutex::new(Vec::new()));
+        let (tx, _rx) = async_channel::unbounded();
+        for remote_address in [[203, 0, 113, 80], [203, 0, 113, 81]] {
+            let key = AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(50001),
+                IpAddr::from(remote_address),
+                Some(443),
+                Protocol::TCP,
+            );
+            // without rDNS, the other address can't be resolved
+            reverse_dns_lookup(
+                &resolutions_state,
+                &new_hosts_to_send,
+                &key,
+                TrafficDirection::Outgoing,
+                &Vec::new(),
+                &MmdbReaders::default(),
+                false,
+                false,
+                &tx,
+            );
+        }
+
+        let stats = resolutions_state.lock().unwrap().stats();
+        assert_eq!(stats.resolved_hosts, 1);
+        assert_eq!(stats.unresolved_hosts, 1);
+        assert!((stats.resolved_ratio - 0.5).abs() < f64::EPSILON);
+        assert!(stats.avg_resolution_ms >= 0.0);
+    }
+
+    #[test]
+    fn test_dump_resolutions_state() {
+        let mut resolutions_state = AddressesResolutionState::default();
+        for last_octet in 1..=3 {
+            resolutions_state
+                .addresses_waiting_resolution
+                .insert(IpAddr::from([8, 8, 4, last_octet]), DataInfo::default());
+        }
+        resolutions_state.addresses_resolved.insert(
+            IpAddr::from([8, 8, 8, 8]),
+            Host {
+                domain: "dns.google".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let dump = resolutions_state.dump(2);
+        assert_eq!(dump.addresses_waiting_resolution, 3);
+        assert_eq!(
+            dump.addresses_waiting_resolution_sample,
+            [IpAddr::from([8, 8, 4, 1]), IpAddr::from([8, 8, 4, 2])]
+        );
+        assert_eq!(dump.addresses_resolved, 1);
+        assert_eq!(
+            dump.addresses_resolved_sample,
+            [(IpAddr::from([8, 8, 8, 8]), "dns.google".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_packet_comment() {
+        let host = Host {
+            domain: "dns.google".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            packet_comment(
+                TrafficDirection::Outgoing,
+                &Service::Name("https"),
+                Some(&host)
+            ),
+            "outgoing | service: https | host: dns.google"
+        );
+        assert_eq!(
+            packet_comment(TrafficDirection::Incoming, &Service::Unknown, None),
+            "incoming | service: ?"
+        );
     }
 }
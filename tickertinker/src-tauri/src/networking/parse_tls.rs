@@ -0,0 +1,125 @@
+//! Module containing functions to learn the domains contacted by the monitored host
+//! from the TLS handshakes it starts (Server Name Indication).
+
+use crate::networking::parse_dns::read_u16;
+
+/// Content type of the TLS records carrying handshake messages
+const HANDSHAKE_RECORD: u8 = 0x16;
+/// Type of the handshake message starting a TLS session
+const CLIENT_HELLO: u8 = 0x01;
+/// Type of the extension carrying the requested server name
+const SERVER_NAME_EXTENSION: u16 = 0x0000;
+/// Type of the server names that are DNS host names
+const HOST_NAME: u8 = 0x00;
+
+/// Returns the host name requested in a TLS `ClientHello`, if the payload starts with one
+/// (only the part of the handshake carried by this payload is considered)
+pub fn parse_server_name(payload: &[u8]) -> Option<String> {
+    // record header: content type, version, and length
+    if *payload.first()? != HANDSHAKE_RECORD {
+        return None;
+    }
+    let hello = payload.get(5..)?;
+    // handshake header: type and length
+    if *hello.first()? != CLIENT_HELLO {
+        return None;
+    }
+    // skip the handshake header, the client version, and the random bytes
+    let mut offset = 4 + 2 + 32;
+    // session ID, cipher suites, and compression methods
+    offset += 1 + usize::from(*hello.get(offset)?);
+    offset += 2 + usize::from(read_u16(hello, offset)?);
+    offset += 1 + usize::from(*hello.get(offset)?);
+    let extensions_end = offset + 2 + usize::from(read_u16(hello, offset)?);
+    let extensions = hello.get(offset + 2..extensions_end.min(hello.len()))?;
+
+    let mut offset = 0;
+    while let (Some(extension_type), Some(len)) = (
+        read_u16(extensions, offset),
+        read_u16(extensions, offset + 2),
+    ) {
+        let data = extensions.get(offset + 4..offset + 4 + usize::from(len))?;
+        if extension_type == SERVER_NAME_EXTENSION {
+            return read_host_name(data);
+        }
+        offset += 4 + usize::from(len);
+    }
+    None
+}
+
+/// Reads the host name from the list of server names of the extension
+fn read_host_name(server_names: &[u8]) -> Option<String> {
+    // the list is prefixed by its length, and each name by its type and length
+    let mut offset = 2;
+    while let Some(name_type) = server_names.get(offset) {
+        let len = usize::from(read_u16(server_names, offset + 1)?);
+        let name = server_names.get(offset + 3..offset + 3 + len)?;
+        if *name_type == HOST_NAME {
+            return (!name.is_empty() && name.iter().all(u8::is_ascii_graphic))
+                .then(|| String::from_utf8_lossy(name).into_owned());
+        }
+        offset += 3 + len;
+    }
+    None
+}
+
+/// `ClientHello` record requesting the given server name, after a dummy extension
+#[cfg(test)]
+pub fn client_hello_for_tests(server_name: &[u8]) -> Vec<u8> {
+    let name_len = u8::try_from(server_name.len()).unwrap();
+    // extended master secret (empty), then the server name with the lengths of its list and entry
+    let mut extensions = vec![0x00, 0x17, 0x00, 0x00];
+    extensions.extend_from_slice(&[0x00, 0x00, 0x00, name_len + 5, 0x00, name_len + 3]);
+    extensions.extend_from_slice(&[HOST_NAME, 0x00, name_len]);
+    extensions.extend_from_slice(server_name);
+
+    let mut hello = vec![0x03, 0x03]; // client version
+    hello.extend_from_slice(&[0xAB; 32]); // random
+    hello.extend_from_slice(&[0x01, 0xCD]); // session ID
+    hello.extend_from_slice(&[0x00, 0x04, 0x13, 0x01, 0x13, 0x02]); // cipher suites
+    hello.extend_from_slice(&[0x01, 0x00]); // compression methods
+    hello.extend_from_slice(&u16::try_from(extensions.len()).unwrap().to_be_bytes());
+    hello.extend_from_slice(&extensions);
+
+    let hello_len = u16::try_from(hello.len()).unwrap().to_be_bytes();
+    let record_len = u16::try_from(hello.len() + 4).unwrap().to_be_bytes();
+    let mut record = vec![HANDSHAKE_RECORD, 0x03, 0x01, record_len[0], record_len[1]];
+    record.extend_from_slice(&[CLIENT_HELLO, 0x00, hello_len[0], hello_len[1]]);
+    record.extend_from_slice(&hello);
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_name() {
+        assert_eq!(
+            parse_server_name(&client_hello_for_tests(b"www.example.com")),
+            Some("www.example.com".to_string())
+        );
+        // names that aren't printable are ignored
+        assert_eq!(
+            parse_server_name(&client_hello_for_tests(b"bad\nname")),
+            None
+        );
+        assert_eq!(parse_server_name(&client_hello_for_tests(b"")), None);
+    }
+
+    #[test]
+    fn test_parse_server_name_malformed() {
+        let hello = client_hello_for_tests(b"www.example.com");
+        // truncated before the extension
+        assert_eq!(parse_server_name(&hello[..hello.len() - 20]), None);
+        assert_eq!(parse_server_name(&hello[..10]), None);
+        // application data and other handshake messages
+        let mut application_data = hello.clone();
+        application_data[0] = 0x17;
+        assert_eq!(parse_server_name(&application_data), None);
+        let mut server_hello = hello;
+        server_hello[5] = 0x02;
+        assert_eq!(parse_server_name(&server_hello), None);
+        assert_eq!(parse_server_name(&[]), None);
+    }
+}
@@ -1,10 +1,11 @@
 //! Module defining the `AddressPortPair` struct, which represents a network address:port pair.
 
 use crate::networking::types::protocol::Protocol;
+use serde::Serialize;
 use std::net::IpAddr;
 
 /// Struct representing a network address:port pair.
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize)]
 pub struct AddressPortPair {
     /// Network layer IPv4 or IPv6 source address.
     pub address1: IpAddr,
@@ -41,4 +42,15 @@ impl AddressPortPair {
             protocol,
         }
     }
+
+    /// Returns the pair of the packets flowing in the opposite direction
+    pub fn reversed(&self) -> Self {
+        Self::new(
+            self.address2,
+            self.port2,
+            self.address1,
+            self.port1,
+            self.protocol,
+        )
+    }
 }
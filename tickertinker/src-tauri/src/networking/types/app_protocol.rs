@@ -0,0 +1,138 @@
+//! Module defining the `AppProtocol` enum, which represents the application protocol of a connection.
+
+use serde::Serialize;
+
+use crate::networking::types::service::Service;
+
+/// HTTP/1.x request methods, followed by the space preceding the request target
+const HTTP_METHODS: [&[u8]; 9] = [
+    b"GET ",
+    b"POST ",
+    b"PUT ",
+    b"HEAD ",
+    b"DELETE ",
+    b"OPTIONS ",
+    b"PATCH ",
+    b"CONNECT ",
+    b"TRACE ",
+];
+
+/// Application protocol of a connection, classified from its payload or, failing that, its service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+pub enum AppProtocol {
+    Http,
+    Tls,
+    Ssh,
+    Dns,
+    /// Not identified
+    #[default]
+    Unknown,
+}
+
+impl AppProtocol {
+    /// Classifies a transport layer payload by the signature of well-known protocols
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        if HTTP_METHODS
+            .iter()
+            .any(|method| payload.starts_with(method))
+            || payload.starts_with(b"HTTP/1.")
+            || payload.starts_with(b"PRI * HTTP/2.0")
+        {
+            return Some(Self::Http);
+        }
+        if payload.starts_with(b"SSH-") {
+            return Some(Self::Ssh);
+        }
+        // TLS record header: content type (change cipher spec, alert, handshake, or application data)
+        // followed by the protocol version (SSL 3.0 to TLS 1.3)
+        if let [content_type, 0x03, minor_version, ..] = payload {
+            if (0x14..=0x17).contains(content_type) && *minor_version <= 0x04 {
+                return Some(Self::Tls);
+            }
+        }
+        None
+    }
+
+    /// Classifies a connection by the service associated to its ports
+    pub fn from_service(service: Service) -> Self {
+        match service {
+            Service::Name("http" | "http-alt" | "http-proxy" | "www-http") => Self::Http,
+            Service::Name("https" | "imaps" | "pop3s" | "smtps" | "ftps" | "domain-s") => Self::Tls,
+            Service::Name("ssh") => Self::Ssh,
+            Service::Name("domain" | "mdns" | "llmnr") => Self::Dns,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_classification() {
+        assert_eq!(
+            AppProtocol::from_payload(b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Some(AppProtocol::Http)
+        );
+        assert_eq!(
+            AppProtocol::from_payload(b"POST /api HTTP/1.1\r\n"),
+            Some(AppProtocol::Http)
+        );
+        assert_eq!(
+            AppProtocol::from_payload(b"HTTP/1.1 200 OK\r\n"),
+            Some(AppProtocol::Http)
+        );
+        assert_eq!(
+            AppProtocol::from_payload(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"),
+            Some(AppProtocol::Http)
+        );
+        // methods must be followed by a space
+        assert_eq!(AppProtocol::from_payload(b"GETTING"), None);
+    }
+
+    #[test]
+    fn test_tls_classification() {
+        // ClientHello (handshake record, TLS 1.0 record version)
+        assert_eq!(
+            AppProtocol::from_payload(&[0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xFC]),
+            Some(AppProtocol::Tls)
+        );
+        // application data (TLS 1.2 record version)
+        assert_eq!(
+            AppProtocol::from_payload(&[0x17, 0x03, 0x03, 0x00, 0x20]),
+            Some(AppProtocol::Tls)
+        );
+        // not a TLS content type
+        assert_eq!(AppProtocol::from_payload(&[0x18, 0x03, 0x03, 0x00]), None);
+        // not a TLS version
+        assert_eq!(AppProtocol::from_payload(&[0x16, 0x04, 0x01, 0x00]), None);
+        assert_eq!(AppProtocol::from_payload(&[0x16, 0x03]), None);
+    }
+
+    #[test]
+    fn test_other_classifications() {
+        assert_eq!(
+            AppProtocol::from_payload(b"SSH-2.0-OpenSSH_9.6\r\n"),
+            Some(AppProtocol::Ssh)
+        );
+        assert_eq!(AppProtocol::from_payload(&[]), None);
+
+        assert_eq!(
+            AppProtocol::from_service(Service::Name("https")),
+            AppProtocol::Tls
+        );
+        assert_eq!(
+            AppProtocol::from_service(Service::Name("domain")),
+            AppProtocol::Dns
+        );
+        assert_eq!(
+            AppProtocol::from_service(Service::Name("murmur")),
+            AppProtocol::Unknown
+        );
+        assert_eq!(
+            AppProtocol::from_service(Service::NotApplicable),
+            AppProtocol::Unknown
+        );
+    }
+}
@@ -161,7 +161,15 @@ static BOGONS: std::sync::LazyLock<Vec<&'static Bogon>> = std::sync::LazyLock::n
     ]
 });
 
+/// Union of the ranges of all the bogons, to quickly tell addresses that aren't bogons
+static BOGONS_UNION: std::sync::LazyLock<IpCollection> =
+    std::sync::LazyLock::new(|| IpCollection::union(BOGONS.iter().map(|bogon| &bogon.range)));
+
 pub fn is_bogon(address: &IpAddr) -> Option<&'static str> {
+    // most addresses aren't bogons: skip looking for the matching one
+    if !BOGONS_UNION.contains(address) {
+        return None;
+    }
     for bogon in BOGONS.iter() {
         if bogon.range.contains(address) {
             return Some(bogon.description);
@@ -1,10 +1,30 @@
-use crate::gui::types::filters::Filters;
 use crate::networking::types::my_device::MyDevice;
 use crate::networking::types::my_link_type::MyLinkType;
+use crate::networking::types::savefiles::Savefiles;
 use crate::translations::translations::network_adapter_translation;
 use crate::translations::translations_4::capture_file_translation;
 use crate::translations::types::language::Language;
-use pcap::{Active, Address, Capture, Error, Packet, Savefile, Stat};
+use crate::utils::types::timestamp::{Timestamp, TimestampPrecision};
+use pcap::{Active, Address, Capture, Error, Packet, Stat};
+use std::fs::File;
+use std::io::Read;
+
+/// Whether devices are opened in promiscuous mode, unless requested otherwise
+/// (the OS isn't guaranteed to grant it)
+pub const PROMISCUOUS_MODE: bool = true;
+/// Size in bytes of the kernel buffer of live captures, unless set in the capture options
+pub const BUFFER_SIZE: i32 = 2_000_000;
+/// Bytes stored per packet in live captures whose packets are saved to a file
+const SNAPLEN_SAVEFILE: i32 = u16::MAX as i32;
+/// Bytes stored per packet in the other live captures
+/// (limit stored packets slice dimension, to keep more in the buffer)
+const SNAPLEN: i32 = 200;
+/// Bytes read from the beginning of savefiles to detect their timestamp precision
+const SAVEFILE_HEADER_MAX_LEN: u64 = 64 * 1024;
+/// Whether live captures can report nanosecond timestamps on this platform:
+/// pcap ignores the outcome of `pcap_set_tstamp_precision`, so this is checked up front
+/// instead of silently getting microsecond timestamps (only the Linux capture module supports it)
+pub const NANOSECOND_TIMESTAMPS_SUPPORTED: bool = cfg!(target_os = "linux");
 
 pub enum CaptureContext {
     Live(Live),
@@ -14,17 +34,57 @@ pub enum CaptureContext {
 }
 
 impl CaptureContext {
-    pub fn new(source: &CaptureSource, pcap_out_path: Option<&String>, filters: &Filters) -> Self {
-        let mut cap_type = match CaptureType::from_source(source, pcap_out_path) {
+    /// Opens the capture source, optionally saving the packets to `pcap_out_path`
+    /// (split in a file per protocol if `split_savefile_by_protocol` is set,
+    /// and in pcapng format with a comment for each packet if `verbose_save` is set).
+    ///
+    /// Devices are opened with the given kernel buffer size and snapshot length,
+    /// or with the default ones if not set, and in promiscuous mode if `promiscuous` is set;
+    /// these are ignored for files. Devices report timestamps with the precision of the source.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source: &CaptureSource,
+        pcap_out_path: Option<&String>,
+        bpf: Option<&str>,
+        split_savefile_by_protocol: bool,
+        verbose_save: bool,
+        buffer_size: Option<i32>,
+        snaplen: Option<i32>,
+        promiscuous: bool,
+    ) -> Self {
+        if let Some(snaplen) = snaplen
+            && snaplen <= 0
+        {
+            return Self::Error(format!("Invalid snaplen {snaplen}: it must be positive"));
+        }
+        // the whole packets are stored when saved to a file, unless requested otherwise
+        let snaplen = snaplen.unwrap_or(if pcap_out_path.is_some() {
+            SNAPLEN_SAVEFILE
+        } else {
+            SNAPLEN
+        });
+        let buffer_size = buffer_size.unwrap_or(BUFFER_SIZE);
+        if let CaptureSource::Device(device) = source
+            && device.get_timestamp_precision() == TimestampPrecision::Nano
+            && !NANOSECOND_TIMESTAMPS_SUPPORTED
+        {
+            return Self::Error(format!(
+                "Device {} doesn't support nanosecond timestamps",
+                device.get_name()
+            ));
+        }
+
+        let mut cap_type = match CaptureType::from_source(source, buffer_size, snaplen, promiscuous)
+        {
             Ok(c) => c,
             Err(e) => return Self::Error(e.to_string()),
         };
 
         // only apply BPF filter if it is active, and return an error if it fails to apply
-        if filters.is_some_filter_active()
-            && let Err(e) = cap_type.set_bpf(filters.bpf())
+        if let Some(bpf) = bpf
+            && let Err(e) = cap_type.set_bpf(bpf)
         {
-            return Self::Error(e.to_string());
+            return Self::Error(invalid_bpf_message(bpf, &e));
         }
 
         let cap = match cap_type {
@@ -33,25 +93,19 @@ impl CaptureContext {
         };
 
         if let Some(out_path) = pcap_out_path {
-            let savefile_res = cap.savefile(out_path);
+            let savefile_res =
+                Savefiles::new(&cap, out_path, split_savefile_by_protocol, verbose_save);
             match savefile_res {
-                Ok(s) => Self::new_live_with_savefile(cap, s),
+                Ok(s) => Self::new_live_with_savefile(Live::new(cap, snaplen, promiscuous), s),
                 Err(e) => Self::Error(e.to_string()),
             }
         } else {
-            Self::new_live(cap)
+            Self::Live(Live::new(cap, snaplen, promiscuous))
         }
     }
 
-    fn new_live(cap: Capture<Active>) -> Self {
-        Self::Live(Live { cap })
-    }
-
-    fn new_live_with_savefile(cap: Capture<Active>, savefile: Savefile) -> Self {
-        Self::LiveWithSavefile(LiveWithSavefile {
-            live: Live { cap },
-            savefile,
-        })
+    fn new_live_with_savefile(live: Live, savefile: Savefiles) -> Self {
+        Self::LiveWithSavefile(LiveWithSavefile { live, savefile })
     }
 
     fn new_offline(cap: Capture<pcap::Offline>) -> Self {
@@ -65,7 +119,19 @@ impl CaptureContext {
         }
     }
 
-    pub fn consume(self) -> (CaptureType, Option<Savefile>) {
+    /// Compiles and applies a BPF filter to the opened capture,
+    /// returning the compilation error if the filter is invalid
+    pub fn set_bpf(&mut self, bpf: &str) -> Result<(), String> {
+        let res = match self {
+            Self::Live(on) => on.cap.filter(bpf, true),
+            Self::LiveWithSavefile(onws) => onws.live.cap.filter(bpf, true),
+            Self::Offline(off) => off.cap.filter(bpf, true),
+            Self::Error(e) => return Err(e.clone()),
+        };
+        res.map_err(|e| invalid_bpf_message(bpf, &e))
+    }
+
+    pub fn consume(self) -> (CaptureType, Option<Savefiles>) {
         match self {
             Self::Live(on) => (CaptureType::Live(on.cap), None),
             Self::LiveWithSavefile(onws) => (CaptureType::Live(onws.live.cap), Some(onws.savefile)),
@@ -74,6 +140,24 @@ impl CaptureContext {
         }
     }
 
+    /// Returns the maximum number of bytes stored per packet (`None` for files)
+    pub fn snaplen(&self) -> Option<i32> {
+        match self {
+            Self::Live(on) => Some(on.snaplen),
+            Self::LiveWithSavefile(onws) => Some(onws.live.snaplen),
+            Self::Offline(_) | Self::Error(_) => None,
+        }
+    }
+
+    /// Returns whether promiscuous mode was requested when opening the device (`false` for files)
+    pub fn promiscuous(&self) -> bool {
+        match self {
+            Self::Live(on) => on.promiscuous,
+            Self::LiveWithSavefile(onws) => onws.live.promiscuous,
+            Self::Offline(_) | Self::Error(_) => false,
+        }
+    }
+
     pub fn my_link_type(&self) -> MyLinkType {
         match self {
             Self::Live(on) => MyLinkType::from_pcap_link_type(on.cap.get_datalink()),
@@ -86,13 +170,31 @@ impl CaptureContext {
     }
 }
 
+fn invalid_bpf_message(bpf: &str, error: &Error) -> String {
+    format!("Invalid BPF filter \"{bpf}\": {error}")
+}
+
 pub struct Live {
     cap: Capture<Active>,
+    /// Maximum number of bytes stored per packet
+    snaplen: i32,
+    /// Whether promiscuous mode was requested
+    promiscuous: bool,
+}
+
+impl Live {
+    fn new(cap: Capture<Active>, snaplen: i32, promiscuous: bool) -> Self {
+        Self {
+            cap,
+            snaplen,
+            promiscuous,
+        }
+    }
 }
 
 pub struct LiveWithSavefile {
     live: Live,
-    savefile: Savefile,
+    savefile: Savefiles,
 }
 
 pub struct Offline {
@@ -119,28 +221,39 @@ impl CaptureType {
         }
     }
 
-    fn from_source(source: &CaptureSource, pcap_out_path: Option<&String>) -> Result<Self, Error> {
+    fn from_source(
+        source: &CaptureSource,
+        buffer_size: i32,
+        snaplen: i32,
+        promiscuous: bool,
+    ) -> Result<Self, Error> {
         match source {
             CaptureSource::Device(device) => {
+                // all the settings must be applied before the handle is activated by `open`
                 let inactive = Capture::from_device(device.to_pcap_device())?;
                 let cap = inactive
-                    .promisc(true)
-                    .buffer_size(2_000_000) // 2MB buffer
-                    .snaplen(if pcap_out_path.is_some() {
-                        i32::from(u16::MAX)
-                    } else {
-                        200 // limit stored packets slice dimension (to keep more in the buffer)
-                    })
+                    .promisc(promiscuous)
+                    .buffer_size(buffer_size)
+                    .snaplen(snaplen)
+                    .precision(device.get_timestamp_precision().into())
                     .immediate_mode(true) // parse packets ASAP
                     .timeout(150) // ensure UI is updated even if no packets are captured
                     .open()?;
                 Ok(Self::Live(cap))
             }
-            CaptureSource::File(file) => Ok(Self::Offline(Capture::from_file(&file.path)?)),
+            CaptureSource::File(file) => Ok(Self::Offline(Capture::from_file_with_precision(
+                &file.path,
+                file.timestamp_precision.into(),
+            )?)),
         }
     }
 
-    fn set_bpf(&mut self, bpf: &str) -> Result<(), Error> {
+    /// Compiles and applies a BPF filter to the capture handle.
+    ///
+    /// It can also be called on a running capture to replace the filter in place:
+    /// libpcap, WinPcap, and Npcap all support `pcap_setfilter` on activated and offline handles,
+    /// while capture modules not implementing it make this return an error.
+    pub fn set_bpf(&mut self, bpf: &str) -> Result<(), Error> {
         match self {
             Self::Live(cap) => cap.filter(bpf, true),
             Self::Offline(cap) => cap.filter(bpf, true),
@@ -175,6 +288,21 @@ impl CaptureSource {
         }
     }
 
+    /// Returns the timestamp of the first packet of the file (`None` for devices and empty files)
+    pub fn first_packet_timestamp(&self) -> Option<Timestamp> {
+        let Self::File(file) = self else {
+            return None;
+        };
+        let precision = file.timestamp_precision;
+        let mut cap = Capture::from_file_with_precision(&file.path, precision.into()).ok()?;
+        let packet = cap.next_packet().ok()?;
+        #[allow(clippy::useless_conversion)]
+        let secs = i64::from(packet.header.ts.tv_sec);
+        #[allow(clippy::useless_conversion)]
+        let subsecs = i64::from(packet.header.ts.tv_usec);
+        Some(Timestamp::from_packet_header(secs, subsecs, precision))
+    }
+
     pub fn get_link_type(&self) -> MyLinkType {
         match self {
             Self::Device(device) => device.get_link_type(),
@@ -189,6 +317,22 @@ impl CaptureSource {
         }
     }
 
+    /// Precision of the timestamps in the headers of the captured packets
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        match self {
+            Self::Device(device) => device.get_timestamp_precision(),
+            Self::File(file) => file.timestamp_precision,
+        }
+    }
+
+    /// Sets the precision of the timestamps requested when opening the device
+    /// (files are read with the precision of their header)
+    pub fn set_timestamp_precision(&mut self, timestamp_precision: TimestampPrecision) {
+        if let Self::Device(device) = self {
+            device.set_timestamp_precision(timestamp_precision);
+        }
+    }
+
     pub fn get_name(&self) -> String {
         match self {
             Self::Device(device) => device.get_name().clone(),
@@ -196,6 +340,14 @@ impl CaptureSource {
         }
     }
 
+    /// Name of the device, or path of the file, without copying it
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Device(device) => device.get_name(),
+            Self::File(file) => &file.path,
+        }
+    }
+
     #[cfg(target_os = "windows")]
     pub fn get_desc(&self) -> Option<String> {
         match self {
@@ -210,18 +362,33 @@ pub struct MyPcapImport {
     path: String,
     link_type: MyLinkType,
     addresses: Vec<Address>, // this is always empty!
+    /// Resolution of the timestamps stored in the file, to open it without rescaling them
+    timestamp_precision: TimestampPrecision,
 }
 
 impl MyPcapImport {
     pub fn new(path: String) -> Self {
+        let timestamp_precision = read_savefile_header(&path)
+            .map(|header| TimestampPrecision::from_savefile_header(&header))
+            .unwrap_or_default();
         Self {
             path,
             link_type: MyLinkType::default(),
             addresses: vec![],
+            timestamp_precision,
         }
     }
 }
 
+/// Reads the initial bytes of a savefile, enough to include its first blocks
+fn read_savefile_header(path: &str) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(SAVEFILE_HEADER_MAX_LEN)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Copy, Default)]
 pub enum CaptureSourcePicklist {
     #[default]
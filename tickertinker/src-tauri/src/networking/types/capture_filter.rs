@@ -0,0 +1,281 @@
+//! Module defining the `CaptureFilter` struct, a structured alternative to BPF filters.
+
+use std::net::IpAddr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::ip_collection::IpCollection;
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::traffic_direction::TrafficDirection;
+use crate::networking::types::traffic_type::TrafficType;
+
+/// Conditions the packets must satisfy to be aggregated, as requested by the frontend.
+///
+/// All the conditions are ANDed;
+/// an empty list (or an empty `ip_ranges` string) doesn't constrain anything.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CaptureFilter {
+    /// Addresses and ranges at least one of the endpoints must belong to,
+    /// separated by commas (e.g., "8.8.8.8, 192.168.1.0-192.168.1.255")
+    #[serde(deserialize_with = "deserialize_ip_collection")]
+    pub(crate) ip_ranges: IpCollection,
+    /// Ports at least one of the endpoints must use
+    pub ports: Vec<u16>,
+    /// Accepted protocols
+    pub protocols: Vec<Protocol>,
+    /// Accepted traffic directions
+    pub directions: Vec<TrafficDirection>,
+    /// Skip the traffic sent to broadcast addresses
+    pub exclude_broadcast: bool,
+    /// Skip the traffic sent to multicast addresses
+    pub exclude_multicast: bool,
+    /// Equivalent of a BPF filter that couldn't be compiled on this platform,
+    /// applied in userspace in addition to the other conditions
+    #[serde(skip)]
+    pub(crate) userspace_bpf: Option<Box<CaptureFilter>>,
+    /// Protocols whose packets are aggregated into connections and hosts;
+    /// the packets of the other protocols are only counted in the totals.
+    /// `None` or an empty list means all the protocols.
+    #[serde(skip)]
+    pub(crate) protocol_filter: Option<Vec<Protocol>>,
+}
+
+impl CaptureFilter {
+    /// Translates a BPF filter into the equivalent structured filter, if possible.
+    ///
+    /// Only conjunctions (`and` or `&&`) of the most common primitives are supported:
+    /// protocols (`tcp`, `udp`, `icmp`, `arp`), ports (`port 443`, also qualified by protocol
+    /// as in `tcp port 443`), hosts (`host 8.8.8.8`), and networks (`net 192.168.1.0/24`),
+    /// each at most once.
+    pub fn from_bpf(bpf: &str) -> Option<Self> {
+        let mut filter = Self::default();
+        let tokens: Vec<&str> = bpf.split_whitespace().collect();
+        for primitive in tokens.split(|token| matches!(*token, "and" | "&&")) {
+            let (protocol, qualifier) = match primitive {
+                [protocol] => (Some(*protocol), None),
+                [qualifier, value] => (None, Some((*qualifier, *value))),
+                [protocol, "port", port] => (Some(*protocol), Some(("port", *port))),
+                _ => return None,
+            };
+            if let Some(protocol) = protocol {
+                if !filter.protocols.is_empty() {
+                    return None;
+                }
+                filter.protocols.push(bpf_protocol(protocol)?);
+            }
+            match qualifier {
+                None => {}
+                Some(("port", port)) if filter.ports.is_empty() => {
+                    filter.ports.push(port.parse().ok()?);
+                }
+                Some(("host", host)) if filter.ip_ranges == IpCollection::default() => {
+                    let host: IpAddr = host.parse().ok()?;
+                    filter.ip_ranges = IpCollection::new(&host.to_string())?;
+                }
+                Some(("net", net)) if filter.ip_ranges == IpCollection::default() => {
+                    if !net.contains('/') {
+                        return None;
+                    }
+                    filter.ip_ranges = IpCollection::new(net)?;
+                }
+                Some(_) => return None,
+            }
+        }
+        Some(filter)
+    }
+
+    /// Applies the equivalent of a BPF filter in userspace, in addition to the other conditions
+    pub fn with_userspace_bpf(self, bpf_filter: Self) -> Self {
+        Self {
+            userspace_bpf: Some(Box::new(bpf_filter)),
+            ..self
+        }
+    }
+
+    /// Only aggregates the packets of the given protocols, counting the others in the totals
+    pub fn with_protocol_filter(self, protocol_filter: Option<Vec<Protocol>>) -> Self {
+        Self {
+            protocol_filter,
+            ..self
+        }
+    }
+
+    /// Whether the packets of the protocol are aggregated, according to the protocol filter
+    pub fn tracks_protocol(&self, protocol: Protocol) -> bool {
+        self.protocol_filter
+            .as_ref()
+            .is_none_or(|protocols| protocols.is_empty() || protocols.contains(&protocol))
+    }
+
+    pub fn matches(
+        &self,
+        key: &AddressPortPair,
+        traffic_direction: TrafficDirection,
+        traffic_type: TrafficType,
+    ) -> bool {
+        (self.ip_ranges.contains(&key.address1) || self.ip_ranges.contains(&key.address2))
+            && (self.ports.is_empty()
+                || [key.port1, key.port2]
+                    .iter()
+                    .flatten()
+                    .any(|port| self.ports.contains(port)))
+            && (self.protocols.is_empty() || self.protocols.contains(&key.protocol))
+            && (self.directions.is_empty() || self.directions.contains(&traffic_direction))
+            && !(self.exclude_broadcast && traffic_type == TrafficType::Broadcast)
+            && !(self.exclude_multicast && traffic_type == TrafficType::Multicast)
+            && self
+                .userspace_bpf
+                .as_ref()
+                .is_none_or(|bpf_filter| bpf_filter.matches(key, traffic_direction, traffic_type))
+    }
+}
+
+fn bpf_protocol(protocol: &str) -> Option<Protocol> {
+    match protocol {
+        "tcp" => Some(Protocol::TCP),
+        "udp" => Some(Protocol::UDP),
+        "icmp" => Some(Protocol::ICMP),
+        "arp" => Some(Protocol::ARP),
+        _ => None,
+    }
+}
+
+fn deserialize_ip_collection<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<IpCollection, D::Error> {
+    let ip_ranges = String::deserialize(deserializer)?;
+    IpCollection::new(&ip_ranges).ok_or_else(|| {
+        serde::de::Error::custom(format!("Invalid IP addresses or ranges: {ip_ranges}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+
+    fn key(remote: [u8; 4], protocol: Protocol) -> AddressPortPair {
+        AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(50000),
+            IpAddr::from(remote),
+            Some(443),
+            protocol,
+        )
+    }
+
+    #[test]
+    fn test_ip_range_and_protocol_filter() {
+        let filter: CaptureFilter = serde_json::from_str(
+            r#"{"ip_ranges": "8.8.8.0-8.8.8.255", "protocols": ["TCP"], "exclude_multicast": true}"#,
+        )
+        .unwrap();
+        let outgoing = TrafficDirection::Outgoing;
+        let unicast = TrafficType::Unicast;
+
+        assert!(filter.matches(&key([8, 8, 8, 8], Protocol::TCP), outgoing, unicast));
+        // wrong protocol
+        assert!(!filter.matches(&key([8, 8, 8, 8], Protocol::UDP), outgoing, unicast));
+        // out of range
+        assert!(!filter.matches(&key([8, 8, 4, 4], Protocol::TCP), outgoing, unicast));
+        // excluded traffic type
+        assert!(!filter.matches(
+            &key([8, 8, 8, 8], Protocol::TCP),
+            outgoing,
+            TrafficType::Multicast
+        ));
+
+        // the default filter matches everything
+        let filter = CaptureFilter::default();
+        assert!(filter.matches(
+            &key([8, 8, 4, 4], Protocol::UDP),
+            TrafficDirection::Incoming,
+            TrafficType::Broadcast
+        ));
+    }
+
+    #[test]
+    fn test_protocol_filter() {
+        let filter = CaptureFilter::default();
+        assert!(filter.tracks_protocol(Protocol::ARP));
+
+        let filter = filter.with_protocol_filter(Some(Vec::new()));
+        assert!(filter.tracks_protocol(Protocol::ICMP));
+
+        let filter = CaptureFilter::default()
+            .with_protocol_filter(Some(vec![Protocol::TCP, Protocol::UDP]))
+            .with_userspace_bpf(CaptureFilter::default());
+        assert!(filter.tracks_protocol(Protocol::TCP));
+        assert!(filter.tracks_protocol(Protocol::UDP));
+        assert!(!filter.tracks_protocol(Protocol::ICMP));
+        assert!(!filter.tracks_protocol(Protocol::ARP));
+        // untracked packets still satisfy the filter, to be counted in the totals
+        assert!(filter.matches(
+            &key([8, 8, 8, 8], Protocol::ICMP),
+            TrafficDirection::Outgoing,
+            TrafficType::Unicast
+        ));
+    }
+
+    #[test]
+    fn test_filter_from_bpf() {
+        let outgoing = TrafficDirection::Outgoing;
+        let unicast = TrafficType::Unicast;
+
+        let filter = CaptureFilter::from_bpf("tcp port 443 and host 8.8.8.8").unwrap();
+        assert_eq!(filter.protocols, [Protocol::TCP]);
+        assert_eq!(filter.ports, [443]);
+        assert!(filter.matches(&key([8, 8, 8, 8], Protocol::TCP), outgoing, unicast));
+        assert!(!filter.matches(&key([8, 8, 4, 4], Protocol::TCP), outgoing, unicast));
+        assert!(!filter.matches(&key([8, 8, 8, 8], Protocol::UDP), outgoing, unicast));
+
+        let filter = CaptureFilter::from_bpf("net 8.8.0.0/16 && udp").unwrap();
+        assert_eq!(filter.protocols, [Protocol::UDP]);
+        assert!(filter.ports.is_empty());
+        assert!(filter.matches(&key([8, 8, 4, 4], Protocol::UDP), outgoing, unicast));
+        assert!(!filter.matches(&key([8, 9, 4, 4], Protocol::UDP), outgoing, unicast));
+
+        // unsupported or invalid expressions aren't translated
+        for bpf in [
+            "",
+            "tcp or udp",
+            "tcp and udp",
+            "not tcp",
+            "tcp port nope",
+            "src port 53",
+            "port 53 and port 443",
+            "host 8.8.8.8/24",
+            "net 8.8.8.8",
+            "tcp and",
+        ] {
+            assert!(CaptureFilter::from_bpf(bpf).is_none(), "{bpf}");
+        }
+    }
+
+    #[test]
+    fn test_userspace_bpf_restricts_filter() {
+        let outgoing = TrafficDirection::Outgoing;
+        let unicast = TrafficType::Unicast;
+        let filter = CaptureFilter {
+            protocols: vec![Protocol::TCP, Protocol::UDP],
+            ..Default::default()
+        }
+        .with_userspace_bpf(CaptureFilter::from_bpf("port 443").unwrap());
+
+        assert!(filter.matches(&key([8, 8, 8, 8], Protocol::TCP), outgoing, unicast));
+        assert!(!filter.matches(&key([8, 8, 8, 8], Protocol::ICMP), outgoing, unicast));
+        let mut other_port = key([8, 8, 8, 8], Protocol::UDP);
+        other_port.port2 = Some(53);
+        assert!(!filter.matches(&other_port, outgoing, unicast));
+    }
+
+    #[test]
+    fn test_invalid_ip_ranges() {
+        assert!(serde_json::from_str::<CaptureFilter>(r#"{"ip_ranges": "8.8.8.8-"}"#).is_err());
+        let filter: CaptureFilter = serde_json::from_str(r#"{"ip_ranges": ""}"#).unwrap();
+        assert_eq!(filter.ip_ranges, IpCollection::default());
+    }
+}
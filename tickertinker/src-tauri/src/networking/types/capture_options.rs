@@ -0,0 +1,69 @@
+//! Module defining the `CaptureOptions` struct, which groups the optional features of a capture.
+
+use serde::Deserialize;
+
+/// Optional features of a capture, as requested by the frontend
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CaptureOptions {
+    /// Label each connection with the name of the local process owning it.
+    ///
+    /// Only effective in live captures on platforms exposing their socket table (Linux).
+    pub label_processes: bool,
+    /// Only aggregate the TCP packets opening or closing a connection (SYN, FIN, or RST),
+    /// skipping bulk data and other protocols for a lightweight view of who connected to whom.
+    pub tcp_handshake_only: bool,
+    /// Inspect the payload of the packets, to learn the domains requested by the host
+    /// from the observed DNS responses and TLS handshakes (preferred over the ones obtained
+    /// via rDNS), and to detect the application protocol of each connection.
+    pub inspect_payloads: bool,
+    /// Maximum number of hosts to keep track of, overriding the one of the retention policy
+    /// (see `set_retention`) if set; the least recently active hosts are aggregated
+    /// into an "other hosts" entry.
+    pub max_hosts: Option<usize>,
+    /// Don't look up the ASN and country of local addresses, which are usually missing
+    /// from the databases (unless they're publicly routable, e.g., provider CGN ranges);
+    /// rDNS is performed regardless.
+    pub skip_local_geolocation: bool,
+    /// When saving the packets to a PCAP file, write a separate file per protocol
+    /// (TCP, UDP, ICMP, and any other protocol) instead of a single one.
+    pub split_savefile_by_protocol: bool,
+    /// When saving the packets, write pcapng files attaching to each packet a comment
+    /// with its direction, service, and resolved host (displayed by Wireshark),
+    /// instead of plain PCAP files; comments considerably increase the size of the files.
+    pub verbose_save: bool,
+    /// Record the hex dump of the first few packets whose headers couldn't be parsed,
+    /// to help diagnosing captures on exotic interfaces where packets aren't counted.
+    pub sample_parse_failures: bool,
+    /// Wait for the frontend to acknowledge each traffic update (via `ack_traffic_update`)
+    /// before sending the next one, merging the updates produced in the meantime into the latest,
+    /// so that they don't pile up when the frontend can't keep up.
+    pub coalesce_updates: bool,
+    /// Don't resolve the addresses via rDNS from the start of the capture
+    /// (it can be re-enabled with `set_rdns_enabled`), so that no DNS query is sent
+    /// and the contacted hosts aren't disclosed to the resolver; hosts are named after their IP.
+    pub disable_rdns: bool,
+    /// Emit a `new_host_resolved` event for each resolved host, for UIs handling hosts one by one,
+    /// instead of a single `hosts_resolved_batch` event per interval with all the hosts resolved
+    /// in the meantime (which avoids flooding the frontend when many hosts resolve at once).
+    pub single_host_events: bool,
+    /// Number of intervals (seconds) kept in the history returned by `get_traffic_history`,
+    /// between 1 and 86400; 300 if not set.
+    pub history_window: Option<usize>,
+    /// When saving the packets, write them on a background thread queueing at most this many,
+    /// so that a slow disk doesn't hold the capture back: packets arriving while the queue is full
+    /// are still analyzed but not saved, and are counted in the `skipped_writes` of
+    /// `get_session_info`; between 1 and 10000, and if not set,
+    /// each packet is saved before reading the next one.
+    pub savefile_queue_size: Option<usize>,
+    /// Size in bytes of the kernel buffer of live captures (2 MB if not set):
+    /// a larger buffer reduces the dropped packets on high-throughput links.
+    pub buffer_size: Option<i32>,
+    /// Maximum number of bytes stored per packet in live captures, which must be positive
+    /// (200 if not set, or 65535 when saving the packets to a file).
+    pub snaplen: Option<i32>,
+    /// Request nanosecond timestamps in live captures (microseconds if not set);
+    /// the capture fails to start if the device doesn't support them
+    /// (see `nanosecond_timestamps` in `get_platform_capabilities`).
+    pub nanosecond_timestamps: bool,
+}
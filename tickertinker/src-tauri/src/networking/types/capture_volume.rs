@@ -0,0 +1,86 @@
+//! Module defining the `CaptureVolume` struct, which compares the traffic on the wire
+//! with the part of it actually captured.
+
+use pcap::PacketHeader;
+
+/// Packets read from the capture handle, with their length on the wire and the captured one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CaptureVolume {
+    /// Number of packets read (including the ones that couldn't be parsed)
+    pub packets: u128,
+    /// Length in bytes of the packets on the wire
+    pub wire_bytes: u128,
+    /// Length in bytes of the captured part of the packets (the one analyzed)
+    pub captured_bytes: u128,
+    /// Number of packets cut by the snapshot length (captured length shorter than the wire length)
+    pub snaplen_truncated_packets: u128,
+}
+
+impl CaptureVolume {
+    pub fn add_packet(&mut self, header: &PacketHeader) {
+        self.packets += 1;
+        self.wire_bytes += u128::from(header.len);
+        self.captured_bytes += u128::from(header.caplen);
+        if header.caplen < header.len {
+            self.snaplen_truncated_packets += 1;
+        }
+    }
+
+    pub fn refresh(&mut self, other: Self) {
+        self.packets = self.packets.saturating_add(other.packets);
+        self.wire_bytes = self.wire_bytes.saturating_add(other.wire_bytes);
+        self.captured_bytes = self.captured_bytes.saturating_add(other.captured_bytes);
+        self.snaplen_truncated_packets = self
+            .snaplen_truncated_packets
+            .saturating_add(other.snaplen_truncated_packets);
+    }
+
+    /// Estimates the bytes that crossed the interface, including the dropped packets
+    /// (assumed to be as large as the average packet read)
+    pub fn estimated_wire_bytes(&self, dropped_packets: u32) -> u128 {
+        let avg_packet_size = self
+            .wire_bytes
+            .checked_div(self.packets)
+            .unwrap_or_default();
+        self.wire_bytes
+            .saturating_add(u128::from(dropped_packets).saturating_mul(avg_packet_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(caplen: u32, len: u32) -> PacketHeader {
+        PacketHeader {
+            ts: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            caplen,
+            len,
+        }
+    }
+
+    #[test]
+    fn test_estimated_wire_bytes_with_drops() {
+        let mut volume = CaptureVolume::default();
+        volume.add_packet(&header(200, 1_500));
+        volume.add_packet(&header(100, 100));
+        let mut other = CaptureVolume::default();
+        other.add_packet(&header(200, 1_400));
+        volume.refresh(other);
+
+        assert_eq!(volume.packets, 3);
+        assert_eq!(volume.wire_bytes, 3_000);
+        assert_eq!(volume.captured_bytes, 500);
+        assert_eq!(volume.snaplen_truncated_packets, 2);
+
+        // without drops, the estimate is what was read from the wire
+        assert_eq!(volume.estimated_wire_bytes(0), 3_000);
+        // each dropped packet adds the average wire length of the packets read
+        assert_eq!(volume.estimated_wire_bytes(2), 5_000);
+
+        assert_eq!(CaptureVolume::default().estimated_wire_bytes(10), 0);
+    }
+}
@@ -3,6 +3,7 @@
 use crate::networking::types::data_representation::DataRepr;
 use crate::networking::types::traffic_direction::TrafficDirection;
 use crate::report::types::sort_type::SortType;
+use crate::utils::types::timestamp::Timestamp;
 use std::cmp::Ordering;
 use std::time::Instant;
 
@@ -20,6 +21,8 @@ pub struct DataInfo {
     outgoing_bytes: u128,
     /// Latest instant of occurrence
     final_instant: Instant,
+    /// Timestamp of the latest packet, if added with it
+    final_timestamp: Timestamp,
 }
 
 impl DataInfo {
@@ -27,7 +30,7 @@ impl DataInfo {
         match data_repr {
             DataRepr::Packets => self.incoming_packets,
             DataRepr::Bytes => self.incoming_bytes,
-            DataRepr::Bits => self.incoming_bytes * 8,
+            DataRepr::Bits => self.incoming_bytes.saturating_mul(8),
         }
     }
 
@@ -35,32 +38,44 @@ impl DataInfo {
         match data_repr {
             DataRepr::Packets => self.outgoing_packets,
             DataRepr::Bytes => self.outgoing_bytes,
-            DataRepr::Bits => self.outgoing_bytes * 8,
+            DataRepr::Bits => self.outgoing_bytes.saturating_mul(8),
         }
     }
 
     pub fn tot_data(&self, data_repr: DataRepr) -> u128 {
-        self.incoming_data(data_repr) + self.outgoing_data(data_repr)
+        self.incoming_data(data_repr)
+            .saturating_add(self.outgoing_data(data_repr))
     }
 
     pub fn add_packet(&mut self, bytes: u128, traffic_direction: TrafficDirection) {
         if traffic_direction.eq(&TrafficDirection::Outgoing) {
-            self.outgoing_packets += 1;
-            self.outgoing_bytes += bytes;
+            self.outgoing_packets = self.outgoing_packets.saturating_add(1);
+            self.outgoing_bytes = self.outgoing_bytes.saturating_add(bytes);
         } else {
-            self.incoming_packets += 1;
-            self.incoming_bytes += bytes;
+            self.incoming_packets = self.incoming_packets.saturating_add(1);
+            self.incoming_bytes = self.incoming_bytes.saturating_add(bytes);
         }
         self.final_instant = Instant::now();
     }
 
+    /// Adds a packet sent at `timestamp`, according to its header
+    pub fn add_packet_at(
+        &mut self,
+        bytes: u128,
+        traffic_direction: TrafficDirection,
+        timestamp: Timestamp,
+    ) {
+        self.add_packet(bytes, traffic_direction);
+        self.final_timestamp = self.final_timestamp.max(timestamp);
+    }
+
     pub fn add_packets(&mut self, packets: u128, bytes: u128, traffic_direction: TrafficDirection) {
         if traffic_direction.eq(&TrafficDirection::Outgoing) {
-            self.outgoing_packets += packets;
-            self.outgoing_bytes += bytes;
+            self.outgoing_packets = self.outgoing_packets.saturating_add(packets);
+            self.outgoing_bytes = self.outgoing_bytes.saturating_add(bytes);
         } else {
-            self.incoming_packets += packets;
-            self.incoming_bytes += bytes;
+            self.incoming_packets = self.incoming_packets.saturating_add(packets);
+            self.incoming_bytes = self.incoming_bytes.saturating_add(bytes);
         }
     }
 
@@ -72,6 +87,7 @@ impl DataInfo {
                 incoming_bytes: 0,
                 outgoing_bytes: bytes,
                 final_instant: Instant::now(),
+                final_timestamp: Timestamp::default(),
             }
         } else {
             Self {
@@ -80,16 +96,40 @@ impl DataInfo {
                 incoming_bytes: bytes,
                 outgoing_bytes: 0,
                 final_instant: Instant::now(),
+                final_timestamp: Timestamp::default(),
             }
         }
     }
 
+    pub fn new_with_first_packet_at(
+        bytes: u128,
+        traffic_direction: TrafficDirection,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            final_timestamp: timestamp,
+            ..Self::new_with_first_packet(bytes, traffic_direction)
+        }
+    }
+
     pub fn refresh(&mut self, rhs: Self) {
-        self.incoming_packets += rhs.incoming_packets;
-        self.outgoing_packets += rhs.outgoing_packets;
-        self.incoming_bytes += rhs.incoming_bytes;
-        self.outgoing_bytes += rhs.outgoing_bytes;
+        self.incoming_packets = self.incoming_packets.saturating_add(rhs.incoming_packets);
+        self.outgoing_packets = self.outgoing_packets.saturating_add(rhs.outgoing_packets);
+        self.incoming_bytes = self.incoming_bytes.saturating_add(rhs.incoming_bytes);
+        self.outgoing_bytes = self.outgoing_bytes.saturating_add(rhs.outgoing_bytes);
         self.final_instant = rhs.final_instant;
+        self.final_timestamp = self.final_timestamp.max(rhs.final_timestamp);
+    }
+
+    /// Latest instant a packet was exchanged
+    pub fn final_instant(&self) -> Instant {
+        self.final_instant
+    }
+
+    /// Timestamp of the latest packet exchanged, as opposed to the instant it was processed
+    /// (only known for the data added along with the timestamp of its packets)
+    pub fn final_timestamp(&self) -> Timestamp {
+        self.final_timestamp
     }
 
     pub fn compare(&self, other: &Self, sort_type: SortType, data_repr: DataRepr) -> Ordering {
@@ -113,6 +153,15 @@ impl DataInfo {
             incoming_bytes,
             outgoing_bytes,
             final_instant: Instant::now(),
+            final_timestamp: Timestamp::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_final_timestamp(self, final_timestamp: Timestamp) -> Self {
+        Self {
+            final_timestamp,
+            ..self
         }
     }
 }
@@ -125,6 +174,7 @@ impl Default for DataInfo {
             incoming_bytes: 0,
             outgoing_bytes: 0,
             final_instant: Instant::now(),
+            final_timestamp: Timestamp::default(),
         }
     }
 }
@@ -239,4 +289,25 @@ mod tests {
         assert_eq!(data_info_1.outgoing_bytes, 1800);
         assert_eq!(data_info_1.final_instant, data_info_2.final_instant);
     }
+
+    #[test]
+    fn test_data_info_saturates_near_max() {
+        let mut data_info =
+            DataInfo::new_for_tests(u128::MAX - 1, 0, u128::MAX - 10, u128::MAX / 2);
+
+        data_info.add_packet(100, TrafficDirection::Incoming);
+        data_info.add_packets(5, 100, TrafficDirection::Incoming);
+        assert_eq!(data_info.incoming_data(DataRepr::Packets), u128::MAX);
+        assert_eq!(data_info.incoming_data(DataRepr::Bytes), u128::MAX);
+
+        // conversions and totals saturate too
+        assert_eq!(data_info.incoming_data(DataRepr::Bits), u128::MAX);
+        assert_eq!(data_info.outgoing_data(DataRepr::Bits), u128::MAX);
+        assert_eq!(data_info.tot_data(DataRepr::Bytes), u128::MAX);
+
+        let mut other = DataInfo::new_for_tests(1, 1, 1, 1);
+        other.refresh(data_info);
+        assert_eq!(other.incoming_data(DataRepr::Bytes), u128::MAX);
+        assert_eq!(other.outgoing_data(DataRepr::Bytes), u128::MAX / 2 + 1);
+    }
 }
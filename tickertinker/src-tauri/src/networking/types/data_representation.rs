@@ -26,6 +26,12 @@ impl DataRepr {
 
     /// Returns a String representing a quantity of traffic (packets / bytes / bits) with the proper multiple if applicable
     pub fn formatted_string(self, amount: u128) -> String {
+        self.formatted_string_with_precision(amount, Precision::Auto)
+    }
+
+    /// Same as [`DataRepr::formatted_string`], with the given number of decimals
+    /// for the amounts represented with a multiple
+    pub fn formatted_string_with_precision(self, amount: u128, precision: Precision) -> String {
         if self == DataRepr::Packets {
             return amount.to_string();
         }
@@ -42,7 +48,11 @@ impl DataRepr {
             // this allows representing e.g. 999_999 as 999 KB instead of 1000 KB
             n = 999.0;
         }
-        let precision = usize::from(byte_multiple != ByteMultiple::B && n <= 9.95);
+        let precision = match precision {
+            _ if byte_multiple == ByteMultiple::B => 0,
+            Precision::Auto => usize::from(n <= 9.95),
+            Precision::Fixed(decimals) => decimals.min(MAX_DECIMALS),
+        };
         format!("{n:.precision$} {}", byte_multiple.pretty_print(self))
             .trim()
             .to_string()
@@ -57,6 +67,38 @@ impl DataRepr {
     }
 }
 
+/// Maximum number of decimals of a `Precision::Fixed`, to which larger ones are clamped
+pub const MAX_DECIMALS: usize = 6;
+
+/// Number of decimals of the amounts of bytes or bits represented with a multiple (e.g., "1.5 KB");
+/// amounts without a multiple and packets are always integers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RawPrecision")]
+pub enum Precision {
+    /// One decimal for amounts up to 9.95 of a multiple (e.g., "9.9 KB"), none for larger ones
+    #[default]
+    Auto,
+    /// The same number of decimals for any amount (e.g., "1.09 KB" and "71.32 KB" with 2),
+    /// up to `MAX_DECIMALS`
+    Fixed(usize),
+}
+
+/// `Precision` as requested by the frontend, before clamping its decimals
+#[derive(Deserialize)]
+enum RawPrecision {
+    Auto,
+    Fixed(usize),
+}
+
+impl From<RawPrecision> for Precision {
+    fn from(precision: RawPrecision) -> Self {
+        match precision {
+            RawPrecision::Auto => Self::Auto,
+            RawPrecision::Fixed(decimals) => Self::Fixed(decimals.min(MAX_DECIMALS)),
+        }
+    }
+}
+
 /// Represents a Byte or bit multiple for displaying values in a human-readable format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ByteMultiple {
@@ -520,4 +562,52 @@ mod tests {
         assert_eq!(DataRepr::Bytes.formatted_string(u128::MAX), "inf PB");
         assert_eq!(DataRepr::Bits.formatted_string(u128::MAX), "inf Pb");
     }
+
+    #[test]
+    fn test_formatted_string_with_precision() {
+        let formatted =
+            |amount, precision| DataRepr::Bytes.formatted_string_with_precision(amount, precision);
+
+        assert_eq!(formatted(1_090, Precision::Auto), "1.1 KB");
+        assert_eq!(formatted(1_090, Precision::Fixed(0)), "1 KB");
+        assert_eq!(formatted(1_090, Precision::Fixed(2)), "1.09 KB");
+
+        assert_eq!(formatted(71_324, Precision::Auto), "71 KB");
+        assert_eq!(formatted(71_324, Precision::Fixed(0)), "71 KB");
+        assert_eq!(formatted(71_324, Precision::Fixed(2)), "71.32 KB");
+
+        // amounts without a multiple and packets are always integers
+        assert_eq!(formatted(82, Precision::Fixed(2)), "82 B");
+        assert_eq!(
+            DataRepr::Packets.formatted_string_with_precision(1_090, Precision::Fixed(2)),
+            "1090"
+        );
+        assert_eq!(
+            DataRepr::Bits.formatted_string_with_precision(2_500_000, Precision::Fixed(2)),
+            "2.50 Mb"
+        );
+
+        // the default is the same as formatted_string
+        assert_eq!(formatted(9_950, Precision::default()), "9.9 KB");
+        assert_eq!(DataRepr::Bytes.formatted_string(9_950), "9.9 KB");
+
+        // decimals are capped
+        assert_eq!(
+            formatted(1_090, Precision::Fixed(usize::MAX)),
+            "1.090000 KB"
+        );
+    }
+
+    #[test]
+    fn test_precision_decimals_clamped_when_deserialized() {
+        let precision = |json| serde_json::from_str::<Precision>(json).unwrap();
+        assert_eq!(precision(r#""Auto""#), Precision::Auto);
+        assert_eq!(precision(r#"{"Fixed":2}"#), Precision::Fixed(2));
+        assert_eq!(precision(r#"{"Fixed":6}"#), Precision::Fixed(MAX_DECIMALS));
+        assert_eq!(
+            precision(r#"{"Fixed":1000000}"#),
+            Precision::Fixed(MAX_DECIMALS)
+        );
+        assert!(serde_json::from_str::<Precision>(r#"{"Fixed":-1}"#).is_err());
+    }
 }
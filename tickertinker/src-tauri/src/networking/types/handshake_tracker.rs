@@ -0,0 +1,124 @@
+//! Module defining the `HandshakeTracker` struct, which estimates the round-trip time of TCP connections.
+
+use std::collections::HashMap;
+
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::utils::types::timestamp::Timestamp;
+
+/// Maximum number of SYN segments waiting for their SYN-ACK;
+/// when exceeded, the unanswered ones (e.g., port scans) are forgotten
+const MAX_PENDING_SYNS: usize = 10_000;
+
+/// Step of the TCP three-way handshake a segment belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeStep {
+    /// Connection request (SYN flag only)
+    Syn,
+    /// Connection acceptance (SYN and ACK flags)
+    SynAck,
+}
+
+/// Matches the SYN segments with the SYN-ACK answering them,
+/// estimating the round-trip time of the connection from the delta of their timestamps.
+///
+/// The estimate is the whole round trip when capturing on the client,
+/// while it's just the processing time of the server when capturing on the server.
+#[derive(Default)]
+pub struct HandshakeTracker {
+    /// Timestamps of the SYN segments still waiting for a SYN-ACK, by connection
+    pending_syns: HashMap<AddressPortPair, Timestamp>,
+}
+
+impl HandshakeTracker {
+    /// Records a handshake segment, returning the key of the connection opened by the SYN
+    /// and the estimated round-trip time in milliseconds when the segment is the matching SYN-ACK
+    pub fn observe(
+        &mut self,
+        key: &AddressPortPair,
+        step: HandshakeStep,
+        timestamp: Timestamp,
+    ) -> Option<(AddressPortPair, f64)> {
+        match step {
+            HandshakeStep::Syn => {
+                if self.pending_syns.len() >= MAX_PENDING_SYNS {
+                    self.pending_syns.clear();
+                }
+                // retransmitted SYNs replace the original one
+                self.pending_syns.insert(*key, timestamp);
+                None
+            }
+            HandshakeStep::SynAck => {
+                let syn_key = key.reversed();
+                let syn_timestamp = self.pending_syns.remove(&syn_key)?;
+                let delta_usecs = timestamp.to_usecs()? - syn_timestamp.to_usecs()?;
+                #[allow(clippy::cast_precision_loss)]
+                let rtt_ms = delta_usecs as f64 / 1_000.0;
+                (delta_usecs >= 0).then_some((syn_key, rtt_ms))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::networking::types::protocol::Protocol;
+
+    fn key(client_port: u16) -> AddressPortPair {
+        AddressPortPair::new(
+            IpAddr::from([192, 168, 1, 2]),
+            Some(client_port),
+            IpAddr::from([93, 184, 216, 34]),
+            Some(443),
+            Protocol::TCP,
+        )
+    }
+
+    fn reversed(key: AddressPortPair) -> AddressPortPair {
+        AddressPortPair::new(
+            key.address2,
+            key.port2,
+            key.address1,
+            key.port1,
+            key.protocol,
+        )
+    }
+
+    #[test]
+    fn test_rtt_from_syn_and_syn_ack() {
+        let mut tracker = HandshakeTracker::default();
+        let syn = Timestamp::new(100, 999_500);
+        let syn_ack = Timestamp::new(101, 24_000);
+
+        assert_eq!(tracker.observe(&key(50000), HandshakeStep::Syn, syn), None);
+        assert_eq!(
+            tracker.observe(&reversed(key(50000)), HandshakeStep::SynAck, syn_ack),
+            Some((key(50000), 24.5))
+        );
+        // the SYN is matched only once
+        assert_eq!(
+            tracker.observe(&reversed(key(50000)), HandshakeStep::SynAck, syn_ack),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unmatched_syn_ack() {
+        let mut tracker = HandshakeTracker::default();
+        let syn = Timestamp::new(100, 0);
+        tracker.observe(&key(50000), HandshakeStep::Syn, syn);
+
+        // SYN-ACK of another connection
+        assert_eq!(
+            tracker.observe(&reversed(key(50001)), HandshakeStep::SynAck, syn),
+            None
+        );
+        // SYN-ACK in the same direction of the SYN
+        assert_eq!(
+            tracker.observe(&key(50000), HandshakeStep::SynAck, syn),
+            None
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Module defining the `IcmpError` enum, which describes why a connection was rejected along the way.
+
+use etherparse::icmpv4::DestUnreachableHeader;
+use etherparse::icmpv6::DestUnreachableCode;
+use etherparse::{Icmpv4Type, Icmpv6Type};
+use serde::Serialize;
+
+/// Error reported through ICMP (or ICMPv6) about a packet of a connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum IcmpError {
+    /// No route to the destination network
+    NetworkUnreachable,
+    /// The destination host couldn't be reached
+    HostUnreachable,
+    /// The destination host doesn't support the transport protocol
+    ProtocolUnreachable,
+    /// Nothing is listening on the destination port
+    PortUnreachable,
+    /// The packet was too big to be forwarded without fragmentation
+    FragmentationNeeded,
+    /// The packet was dropped by a firewall or an administrative policy
+    AdministrativelyProhibited,
+    /// The destination was unreachable for another reason
+    OtherUnreachable,
+    /// The packet expired in transit (TTL or hop limit exceeded)
+    TimeExceeded,
+}
+
+impl IcmpError {
+    /// Returns the error reported by an ICMPv4 message, if it's an error about another packet
+    pub fn from_icmpv4(icmp_type: &Icmpv4Type) -> Option<Self> {
+        match icmp_type {
+            Icmpv4Type::DestinationUnreachable(header) => Some(match header {
+                DestUnreachableHeader::Network | DestUnreachableHeader::NetworkUnknown => {
+                    Self::NetworkUnreachable
+                }
+                DestUnreachableHeader::Host | DestUnreachableHeader::HostUnknown => {
+                    Self::HostUnreachable
+                }
+                DestUnreachableHeader::Protocol => Self::ProtocolUnreachable,
+                DestUnreachableHeader::Port => Self::PortUnreachable,
+                DestUnreachableHeader::FragmentationNeeded { .. } => Self::FragmentationNeeded,
+                DestUnreachableHeader::Isolated
+                | DestUnreachableHeader::NetworkProhibited
+                | DestUnreachableHeader::HostProhibited
+                | DestUnreachableHeader::FilterProhibited => Self::AdministrativelyProhibited,
+                _ => Self::OtherUnreachable,
+            }),
+            Icmpv4Type::TimeExceeded(_) => Some(Self::TimeExceeded),
+            _ => None,
+        }
+    }
+
+    /// Returns the error reported by an ICMPv6 message, if it's an error about another packet
+    pub fn from_icmpv6(icmp_type: &Icmpv6Type) -> Option<Self> {
+        match icmp_type {
+            Icmpv6Type::DestinationUnreachable(code) => Some(match code {
+                DestUnreachableCode::NoRoute => Self::NetworkUnreachable,
+                DestUnreachableCode::Address => Self::HostUnreachable,
+                DestUnreachableCode::Port => Self::PortUnreachable,
+                DestUnreachableCode::Prohibited
+                | DestUnreachableCode::SourceAddressFailedPolicy
+                | DestUnreachableCode::RejectRoute => Self::AdministrativelyProhibited,
+                _ => Self::OtherUnreachable,
+            }),
+            Icmpv6Type::PacketTooBig { .. } => Some(Self::FragmentationNeeded),
+            Icmpv6Type::TimeExceeded(_) => Some(Self::TimeExceeded),
+            _ => None,
+        }
+    }
+}
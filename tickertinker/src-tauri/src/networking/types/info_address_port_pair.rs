@@ -3,49 +3,161 @@
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt::Display;
+
+use serde::Serialize;
 
 use crate::networking::types::service::Service;
+use crate::networking::types::app_protocol::AppProtocol;
 use crate::networking::types::arp_type::ArpType;
 use crate::networking::types::data_representation::DataRepr;
+use crate::networking::types::handshake_tracker::HandshakeStep;
+use crate::networking::types::icmp_error::IcmpError;
 use crate::networking::types::icmp_type::IcmpType;
+use crate::networking::types::mac_flags::MacFlags;
 use crate::networking::types::traffic_direction::TrafficDirection;
 use crate::report::types::sort_type::SortType;
 use crate::utils::types::timestamp::Timestamp;
 
+/// Shortest interval (in microseconds) rates are computed over, so that the packets of a burst
+/// don't result in an absurdly high rate
+const MIN_RATE_INTERVAL_USECS: i64 = 1_000_000;
+
 /// Struct useful to format the output report file and to keep track of statistics about the sniffed traffic.
 ///
 /// Each `InfoAddressPortPair` struct is associated to a single address:port pair.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub struct InfoAddressPortPair {
     /// Source MAC address
     pub mac_address1: Option<String>,
     /// Destination MAC address
     pub mac_address2: Option<String>,
+    /// Special kinds of the source MAC address (e.g., locally administered)
+    pub mac_flags1: Option<MacFlags>,
+    /// Special kinds of the destination MAC address (e.g., broadcast or multicast)
+    pub mac_flags2: Option<MacFlags>,
     /// Amount of bytes transmitted between the pair.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
     pub transmitted_bytes: u128,
     /// Amount of packets transmitted between the pair.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
     pub transmitted_packets: u128,
+    /// Amount of bytes sent by the local host.
+    /// The sent and received counters of a connection are kept on the entry of one of its
+    /// directions, see `InfoTraffic::merge_directional_counters`.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub bytes_sent: u128,
+    /// Amount of bytes received by the local host.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub bytes_received: u128,
+    /// Amount of packets sent by the local host.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub packets_sent: u128,
+    /// Amount of packets received by the local host.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub packets_received: u128,
+    /// Size in bytes of the smallest packet transmitted between the pair.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub min_packet_size: u128,
+    /// Size in bytes of the largest packet transmitted between the pair.
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub max_packet_size: u128,
     /// First occurrence of information exchange featuring the associate address:port pair as a source or destination.
     pub initial_timestamp: Timestamp,
     /// Last occurrence of information exchange featuring the associate address:port pair as a source or destination.
     pub final_timestamp: Timestamp,
+    /// Sum of the squares of the intervals (in microseconds) between consecutive packets,
+    /// from which the burstiness of the connection is computed
+    #[serde(skip)]
+    pub sum_squared_intervals: u128,
     /// Upper layer service carried by the associated address:port pair.
     pub service: Service,
     /// Determines if the connection is incoming or outgoing
     pub traffic_direction: TrafficDirection,
     /// Types of the ICMP messages exchanged, with the relative count (this is empty if not ICMP)
+    #[serde(skip)]
     pub icmp_types: HashMap<IcmpType, usize>,
     /// Types of the ARP operations, with the relative count (this is empty if not ARP)
+    #[serde(skip)]
     pub arp_types: HashMap<ArpType, usize>,
+    /// Name of the local process owning the connection
+    /// (only known in live captures on supported platforms, when process labelling is enabled)
+    pub process: Option<String>,
+    /// PID of the local process owning the connection (known along with its name)
+    pub pid: Option<u32>,
+    /// Application protocol detected from the payloads exchanged (only known when payloads are inspected)
+    #[serde(skip)]
+    pub app_protocol: Option<AppProtocol>,
+    /// Server name requested in the TLS handshake (SNI), only known when payloads are inspected
+    pub server_name: Option<String>,
+    /// Round-trip time in milliseconds, estimated from the TCP handshake
+    /// (only known for TCP connections whose SYN and SYN-ACK were both captured)
+    pub estimated_rtt_ms: Option<f64>,
+    /// Number of TCP segments requesting a connection (SYN) sent by the source
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub syn_segments: u128,
+    /// Number of TCP segments accepting a connection (SYN-ACK) sent by the source
+    #[serde(serialize_with = "crate::utils::saturating_u64::serialize")]
+    pub syn_ack_segments: u128,
+    /// Latest ICMP error received about the packets of this connection (e.g., port unreachable)
+    pub icmp_error: Option<IcmpError>,
+    /// Interfaces (or files, in offline captures) the connection was observed on,
+    /// in order of first occurrence
+    pub capture_sources: Vec<String>,
 }
 
 impl InfoAddressPortPair {
     pub fn refresh(&mut self, other: &Self) {
-        self.transmitted_bytes += other.transmitted_bytes;
-        self.transmitted_packets += other.transmitted_packets;
+        if self.transmitted_packets > 0 && other.transmitted_packets > 0 {
+            // the interval between the last packet seen so far and the first of the new ones
+            self.add_interval(other.initial_timestamp);
+        }
+        self.sum_squared_intervals = self
+            .sum_squared_intervals
+            .saturating_add(other.sum_squared_intervals);
+        if other.transmitted_packets > 0 {
+            self.min_packet_size = if self.transmitted_packets == 0 {
+                other.min_packet_size
+            } else {
+                self.min_packet_size.min(other.min_packet_size)
+            };
+            self.max_packet_size = self.max_packet_size.max(other.max_packet_size);
+        }
+        self.transmitted_bytes = self
+            .transmitted_bytes
+            .saturating_add(other.transmitted_bytes);
+        self.transmitted_packets = self
+            .transmitted_packets
+            .saturating_add(other.transmitted_packets);
+        self.bytes_sent = self.bytes_sent.saturating_add(other.bytes_sent);
+        self.bytes_received = self.bytes_received.saturating_add(other.bytes_received);
+        self.packets_sent = self.packets_sent.saturating_add(other.packets_sent);
+        self.packets_received = self.packets_received.saturating_add(other.packets_received);
+        self.syn_segments = self.syn_segments.saturating_add(other.syn_segments);
+        self.syn_ack_segments = self.syn_ack_segments.saturating_add(other.syn_ack_segments);
         self.final_timestamp = other.final_timestamp;
         self.service = other.service;
-        self.traffic_direction = other.traffic_direction;
+        // the direction stays the one determined when the connection was first seen,
+        // even if the local addresses changed in the meantime
+        if other.process.is_some() {
+            self.process.clone_from(&other.process);
+            self.pid = other.pid;
+        }
+        if self.app_protocol.is_none() {
+            self.app_protocol = other.app_protocol;
+        }
+        if self.server_name.is_none() {
+            self.server_name.clone_from(&other.server_name);
+        }
+        if other.estimated_rtt_ms.is_some() {
+            self.estimated_rtt_ms = other.estimated_rtt_ms;
+        }
+        if other.icmp_error.is_some() {
+            self.icmp_error = other.icmp_error;
+        }
+        for source in &other.capture_sources {
+            self.add_capture_source(source);
+        }
         for (icmp_type, count) in &other.icmp_types {
             self.icmp_types
                 .entry(*icmp_type)
@@ -60,14 +172,151 @@ impl InfoAddressPortPair {
         }
     }
 
+    /// Accounts for the interval between the last packet and a new one sent at `timestamp`
+    /// (before `final_timestamp` is updated)
+    pub fn add_interval(&mut self, timestamp: Timestamp) {
+        let interval = timestamp
+            .to_usecs()
+            .zip(self.final_timestamp.to_usecs())
+            .and_then(|(new, last)| u128::try_from(new.checked_sub(last)?).ok())
+            .unwrap_or_default();
+        self.sum_squared_intervals = self
+            .sum_squared_intervals
+            .saturating_add(interval.saturating_mul(interval));
+    }
+
+    /// Coefficient of variation of the intervals between consecutive packets:
+    /// close to 0 for packets at a steady pace, around 1 for random arrivals,
+    /// and higher for packets in bursts separated by pauses (0 with fewer than 3 packets)
+    #[allow(clippy::cast_precision_loss)]
+    pub fn burstiness(&self) -> f64 {
+        let intervals = self.transmitted_packets.saturating_sub(1);
+        let duration_usecs = self
+            .final_timestamp
+            .to_usecs()
+            .zip(self.initial_timestamp.to_usecs())
+            .and_then(|(last, first)| last.checked_sub(first))
+            .unwrap_or_default();
+        if intervals < 2 || duration_usecs <= 0 {
+            return 0.0;
+        }
+        let mean = duration_usecs as f64 / intervals as f64;
+        let mean_square = self.sum_squared_intervals as f64 / intervals as f64;
+        (mean_square - mean * mean).max(0.0).sqrt() / mean
+    }
+
+    /// Records that the connection was observed on the given interface (or file)
+    pub fn add_capture_source(&mut self, source: &str) {
+        if !self.capture_sources.iter().any(|s| s == source) {
+            self.capture_sources.push(source.to_string());
+        }
+    }
+
+    /// Counts a packet as sent or received by the local host, depending on its direction
+    pub fn add_directional_packet(&mut self, bytes: u128, traffic_direction: TrafficDirection) {
+        match traffic_direction {
+            TrafficDirection::Outgoing => {
+                self.bytes_sent = self.bytes_sent.saturating_add(bytes);
+                self.packets_sent = self.packets_sent.saturating_add(1);
+            }
+            TrafficDirection::Incoming => {
+                self.bytes_received = self.bytes_received.saturating_add(bytes);
+                self.packets_received = self.packets_received.saturating_add(1);
+            }
+        }
+    }
+
+    /// Takes the bytes and packets sent and received out of the entry, if there are any,
+    /// as `(bytes_sent, bytes_received, packets_sent, packets_received)`
+    pub fn take_directional_counters(&mut self) -> Option<(u128, u128, u128, u128)> {
+        if self.packets_sent == 0 && self.packets_received == 0 {
+            return None;
+        }
+        Some((
+            std::mem::take(&mut self.bytes_sent),
+            std::mem::take(&mut self.bytes_received),
+            std::mem::take(&mut self.packets_sent),
+            std::mem::take(&mut self.packets_received),
+        ))
+    }
+
+    /// Adds the counters taken out of the entry of the opposite direction
+    pub fn add_directional_counters(&mut self, counters: (u128, u128, u128, u128)) {
+        let (bytes_sent, bytes_received, packets_sent, packets_received) = counters;
+        self.bytes_sent = self.bytes_sent.saturating_add(bytes_sent);
+        self.bytes_received = self.bytes_received.saturating_add(bytes_received);
+        self.packets_sent = self.packets_sent.saturating_add(packets_sent);
+        self.packets_received = self.packets_received.saturating_add(packets_received);
+    }
+
+    /// Counts a segment of the TCP three-way handshake sent from the source to the destination
+    pub fn add_handshake_segment(&mut self, step: HandshakeStep) {
+        let segments = match step {
+            HandshakeStep::Syn => &mut self.syn_segments,
+            HandshakeStep::SynAck => &mut self.syn_ack_segments,
+        };
+        *segments = segments.saturating_add(1);
+    }
+
+    /// Names of the ICMP message types exchanged (e.g., "Echo Reply"), with the relative count,
+    /// from the most to the least frequent (empty if not ICMP)
+    pub fn icmp_types_breakdown(&self) -> Vec<(String, usize)> {
+        types_breakdown(&self.icmp_types)
+    }
+
+    /// Names of the ARP operations exchanged ("Request" or "Reply"), with the relative count,
+    /// from the most to the least frequent (empty if not ARP)
+    pub fn arp_types_breakdown(&self) -> Vec<(String, usize)> {
+        types_breakdown(&self.arp_types)
+    }
+
+    /// Average size in bytes of the packets transmitted between the pair
+    pub fn avg_packet_size(&self) -> u128 {
+        self.transmitted_bytes
+            .checked_div(self.transmitted_packets)
+            .unwrap_or_default()
+    }
+
+    /// Application protocol of the connection: the one detected from its payloads if any,
+    /// otherwise the one associated to its service
+    pub fn app_protocol(&self) -> AppProtocol {
+        self.app_protocol
+            .unwrap_or_else(|| AppProtocol::from_service(self.service))
+    }
+
     pub fn transmitted_data(&self, data_repr: DataRepr) -> u128 {
         match data_repr {
             DataRepr::Packets => self.transmitted_packets,
             DataRepr::Bytes => self.transmitted_bytes,
-            DataRepr::Bits => self.transmitted_bytes * 8,
+            DataRepr::Bits => self.transmitted_bytes.saturating_mul(8),
         }
     }
 
+    /// Average amount of data transmitted per second between the first and last packet,
+    /// over an interval of at least one second (the whole amount if they are closer in time)
+    #[allow(clippy::cast_precision_loss)]
+    pub fn bytes_per_second(&self, data_repr: DataRepr) -> f64 {
+        let amount = self.transmitted_data(data_repr) as f64;
+        let interval_usecs = self
+            .final_timestamp
+            .to_usecs()
+            .zip(self.initial_timestamp.to_usecs())
+            .and_then(|(last, first)| last.checked_sub(first))
+            .unwrap_or_default()
+            .max(MIN_RATE_INTERVAL_USECS);
+        amount / (interval_usecs as f64 / 1_000_000.0)
+    }
+
+    /// MAC address of the remote end of the link (i.e., the next hop of outgoing packets
+    /// and the last hop of incoming ones), with its flags
+    pub fn remote_mac(&self) -> Option<(&str, Option<MacFlags>)> {
+        let (mac_address, mac_flags) = match self.traffic_direction {
+            TrafficDirection::Outgoing => (&self.mac_address2, self.mac_flags2),
+            TrafficDirection::Incoming => (&self.mac_address1, self.mac_flags1),
+        };
+        Some((mac_address.as_deref()?, mac_flags))
+    }
+
     pub fn compare(&self, other: &Self, sort_type: SortType, data_repr: DataRepr) -> Ordering {
         match sort_type {
             SortType::Ascending => self
@@ -81,10 +330,23 @@ impl InfoAddressPortPair {
     }
 }
 
+/// Names of the message types, with the relative count, from the most to the least frequent
+fn types_breakdown<T: Display>(types: &HashMap<T, usize>) -> Vec<(String, usize)> {
+    let mut breakdown: Vec<_> = types
+        .iter()
+        .map(|(message_type, count)| (message_type.to_string(), *count))
+        .collect();
+    breakdown.sort_by(|(name_a, count_a), (name_b, count_b)| {
+        count_b.cmp(count_a).then_with(|| name_a.cmp(name_b))
+    });
+    breakdown
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::networking::types::data_representation::DataRepr;
+    use crate::networking::types::icmp_type::IcmpTypeV4;
     use crate::report::types::sort_type::SortType;
 
     #[test]
@@ -149,4 +411,159 @@ mod tests {
             Ordering::Greater
         );
     }
+
+    #[test]
+    fn test_bytes_per_second() {
+        let pair = InfoAddressPortPair {
+            transmitted_bytes: 10_000,
+            transmitted_packets: 20,
+            initial_timestamp: Timestamp::new(100, 250_000),
+            final_timestamp: Timestamp::new(104, 250_000),
+            ..Default::default()
+        };
+        assert_eq!(pair.bytes_per_second(DataRepr::Bytes), 2_500.0);
+        assert_eq!(pair.bytes_per_second(DataRepr::Packets), 5.0);
+        assert_eq!(pair.bytes_per_second(DataRepr::Bits), 20_000.0);
+
+        // sub-second parts are accounted
+        let pair = InfoAddressPortPair {
+            final_timestamp: Timestamp::new(102, 750_000),
+            ..pair
+        };
+        assert_eq!(pair.bytes_per_second(DataRepr::Bytes), 4_000.0);
+    }
+
+    #[test]
+    fn test_bytes_per_second_zero_interval() {
+        let pair = InfoAddressPortPair {
+            transmitted_bytes: 1_500,
+            transmitted_packets: 1,
+            initial_timestamp: Timestamp::new(100, 0),
+            final_timestamp: Timestamp::new(100, 0),
+            ..Default::default()
+        };
+        assert_eq!(pair.bytes_per_second(DataRepr::Bytes), 1_500.0);
+        assert_eq!(pair.bytes_per_second(DataRepr::Packets), 1.0);
+        assert_eq!(
+            InfoAddressPortPair::default().bytes_per_second(DataRepr::Bytes),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_burstiness() {
+        let pair_with_intervals = |intervals: &[i64]| {
+            let mut pair = InfoAddressPortPair {
+                transmitted_packets: 1,
+                initial_timestamp: Timestamp::new(100, 0),
+                final_timestamp: Timestamp::new(100, 0),
+                ..Default::default()
+            };
+            for interval in intervals {
+                let mut timestamp = pair.final_timestamp;
+                timestamp.add_secs(*interval);
+                pair.add_interval(timestamp);
+                pair.transmitted_packets += 1;
+                pair.final_timestamp = timestamp;
+            }
+            pair
+        };
+
+        // a packet per second
+        assert!(pair_with_intervals(&[1; 10]).burstiness().abs() < 1e-9);
+        // bursts of 5 packets a second apart, separated by a minute
+        let bursty = pair_with_intervals(&[1, 1, 1, 1, 60, 1, 1, 1, 1, 60, 1, 1, 1, 1]);
+        assert!(bursty.burstiness() > 1.5);
+        // too few packets
+        assert!(pair_with_intervals(&[1]).burstiness().abs() < f64::EPSILON);
+
+        // refreshing with the packets of the next interval accounts for the gap between them
+        let mut refreshed = pair_with_intervals(&[1, 1, 1, 1]);
+        let mut next = pair_with_intervals(&[1, 1, 1, 1]);
+        next.transmitted_packets = 5;
+        next.initial_timestamp = Timestamp::new(164, 0);
+        next.final_timestamp = Timestamp::new(168, 0);
+        refreshed.refresh(&next);
+        let whole = pair_with_intervals(&[1, 1, 1, 1, 60, 1, 1, 1, 1]);
+        assert_eq!(refreshed.sum_squared_intervals, whole.sum_squared_intervals);
+        assert!((refreshed.burstiness() - whole.burstiness()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_icmp_types_breakdown() {
+        let pair = InfoAddressPortPair {
+            icmp_types: HashMap::from([
+                (IcmpType::V4(IcmpTypeV4::Echo), 4),
+                (IcmpType::V4(IcmpTypeV4::EchoReply), 3),
+                (IcmpType::V4(IcmpTypeV4::TimeExceeded), 7),
+                (IcmpType::V4(IcmpTypeV4::DestinationUnreachable), 3),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            pair.icmp_types_breakdown(),
+            [
+                ("Time Exceeded".to_string(), 7),
+                ("Echo".to_string(), 4),
+                ("Destination Unreachable".to_string(), 3),
+                ("Echo Reply".to_string(), 3),
+            ]
+        );
+        // not ICMP
+        let pair = InfoAddressPortPair::default();
+        assert!(pair.icmp_types_breakdown().is_empty());
+    }
+
+    #[test]
+    fn test_arp_types_breakdown() {
+        let pair = InfoAddressPortPair {
+            arp_types: HashMap::from([
+                (ArpType::Request, 2),
+                (ArpType::Reply, 40),
+                (ArpType::Unknown, 1),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            pair.arp_types_breakdown(),
+            [
+                ("Reply".to_string(), 40),
+                ("Request".to_string(), 2),
+                ("?".to_string(), 1),
+            ]
+        );
+        assert!(pair.icmp_types_breakdown().is_empty());
+    }
+
+    #[test]
+    fn test_bytes_per_second_short_interval() {
+        let pair = InfoAddressPortPair {
+            transmitted_bytes: 3_000,
+            transmitted_packets: 2,
+            initial_timestamp: Timestamp::new(100, 0),
+            final_timestamp: Timestamp::new(100, 1),
+            ..Default::default()
+        };
+        // packets a microsecond apart are accounted as if they were a second apart
+        assert_eq!(pair.bytes_per_second(DataRepr::Bytes), 3_000.0);
+        assert_eq!(pair.bytes_per_second(DataRepr::Bits), 24_000.0);
+
+        // zero-duration flows and out of order timestamps still have a finite rate
+        for final_timestamp in [Timestamp::new(100, 0), Timestamp::new(99, 500_000)] {
+            let pair = InfoAddressPortPair {
+                final_timestamp,
+                ..pair.clone()
+            };
+            let rate = pair.bytes_per_second(DataRepr::Bytes);
+            assert!(rate.is_finite());
+            assert_eq!(rate, 3_000.0);
+        }
+
+        // huge amounts don't overflow
+        let pair = InfoAddressPortPair {
+            transmitted_bytes: u128::MAX,
+            ..pair
+        };
+        assert!(pair.bytes_per_second(DataRepr::Bits).is_finite());
+    }
 }
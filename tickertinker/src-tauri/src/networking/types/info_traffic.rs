@@ -1,20 +1,41 @@
 use crate::networking::types::service::Service;
 use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::capture_volume::CaptureVolume;
 use crate::networking::types::data_info::DataInfo;
 use crate::networking::types::data_info_host::DataInfoHost;
 use crate::networking::types::data_representation::DataRepr;
 use crate::networking::types::host::Host;
+use crate::networking::types::icmp_error::IcmpError;
 use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
+use crate::networking::types::traffic_direction::TrafficDirection;
 use crate::utils::types::timestamp::Timestamp;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Length of the intervals in which the traffic is sent to the frontend
+/// (in offline captures, intervals are measured in packet time)
+pub const TICK_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Struct containing overall traffic statistics and data.
 #[derive(Debug, Default, Clone)]
 pub struct InfoTraffic {
     /// Total amount of exchanged data
     pub tot_data_info: DataInfo,
+    /// Amount of data exchanged in the latest interval merged via `refresh`
+    pub last_interval_data_info: DataInfo,
+    /// Number of packets received by the capture handle, as reported by libpcap
+    pub received_packets: u32,
     /// Number of dropped packets
     pub dropped_packets: u32,
+    /// Number of packets dropped by the network interface or its driver
+    pub interface_dropped_packets: u32,
+    /// Number of packets accounted without ports because their transport header was malformed
+    /// or cut off
+    pub malformed_transport_packets: u128,
+    /// Number of packets not saved to the savefiles because their write queue was full
+    pub skipped_writes: u128,
+    /// Packets read from the capture handle, with their wire and captured lengths
+    pub capture_volume: CaptureVolume,
     /// Timestamp of the latest parsed packet
     pub last_packet_timestamp: Timestamp,
     /// Map of the traffic
@@ -23,13 +44,25 @@ pub struct InfoTraffic {
     pub services: HashMap<Service, DataInfo>,
     /// Map of the hosts with their data info
     pub hosts: HashMap<Host, DataInfoHost>,
+    /// ICMP errors received about the packets of a connection, by the key of that connection
+    pub icmp_errors: HashMap<AddressPortPair, IcmpError>,
+    /// Map of the DSCP values (QoS markings) of the IP packets with their data info
+    pub dscp_data: HashMap<u8, DataInfo>,
 }
 
 impl InfoTraffic {
     pub fn refresh(&mut self, msg: &mut InfoTraffic) {
         self.tot_data_info.refresh(msg.tot_data_info);
+        self.last_interval_data_info = msg.tot_data_info;
 
+        self.received_packets = msg.received_packets;
         self.dropped_packets = msg.dropped_packets;
+        self.interface_dropped_packets = msg.interface_dropped_packets;
+        self.malformed_transport_packets = self
+            .malformed_transport_packets
+            .saturating_add(msg.malformed_transport_packets);
+        self.skipped_writes = self.skipped_writes.saturating_add(msg.skipped_writes);
+        self.capture_volume.refresh(msg.capture_volume);
 
         // it can happen they're equal due to dis-alignments in the PCAP timestamp
         if self.last_packet_timestamp.secs() == msg.last_packet_timestamp.secs() {
@@ -42,6 +75,14 @@ impl InfoTraffic {
                 .entry(*key)
                 .and_modify(|x| x.refresh(value))
                 .or_insert_with(|| value.clone());
+            self.merge_directional_counters(key);
+        }
+
+        // errors about connections never seen (e.g., filtered out) are discarded
+        for (key, icmp_error) in &msg.icmp_errors {
+            if let Some(info) = self.map.get_mut(key) {
+                info.icmp_error = Some(*icmp_error);
+            }
         }
 
         for (key, value) in &msg.services {
@@ -51,6 +92,13 @@ impl InfoTraffic {
                 .or_insert(*value);
         }
 
+        for (key, value) in &msg.dscp_data {
+            self.dscp_data
+                .entry(*key)
+                .and_modify(|x| x.refresh(*value))
+                .or_insert(*value);
+        }
+
         for (key, value) in &msg.hosts {
             self.hosts
                 .entry(key.clone())
@@ -59,6 +107,62 @@ impl InfoTraffic {
         }
     }
 
+    /// Keeps the sent and received counters of a connection on a single entry when both of its
+    /// directions are in the map: the one going out of the local host
+    /// (or, if that can't be told, the one whose source is the lower address and port)
+    pub fn merge_directional_counters(&mut self, key: &AddressPortPair) {
+        let reversed = key.reversed();
+        if reversed == *key || !self.map.contains_key(key) || !self.map.contains_key(&reversed) {
+            return;
+        }
+        let (from, to) = if self.is_canonical_entry(key) {
+            (reversed, *key)
+        } else {
+            (*key, reversed)
+        };
+        if let Some(from_info) = self.map.get_mut(&from)
+            && let Some(counters) = from_info.take_directional_counters()
+            && let Some(to_info) = self.map.get_mut(&to)
+        {
+            to_info.add_directional_counters(counters);
+        }
+    }
+
+    /// Whether the entry is the one holding the counters of its connection
+    /// (see `merge_directional_counters`), which is always the case if the opposite direction
+    /// isn't in the map
+    pub fn is_canonical_entry(&self, key: &AddressPortPair) -> bool {
+        let (Some(info), Some(reversed_info)) = (self.map.get(key), self.map.get(&key.reversed()))
+        else {
+            return true;
+        };
+        match (info.traffic_direction, reversed_info.traffic_direction) {
+            (TrafficDirection::Outgoing, TrafficDirection::Incoming) => true,
+            (TrafficDirection::Incoming, TrafficDirection::Outgoing) => false,
+            _ => (key.address1, key.port1) <= (key.address2, key.port2),
+        }
+    }
+
+    /// Data received since the start of the capture
+    pub fn total_incoming(&self, data_repr: DataRepr) -> u128 {
+        self.tot_data_info.incoming_data(data_repr)
+    }
+
+    /// Data sent since the start of the capture
+    pub fn total_outgoing(&self, data_repr: DataRepr) -> u128 {
+        self.tot_data_info.outgoing_data(data_repr)
+    }
+
+    /// Data received per second in the latest interval
+    pub fn incoming_rate(&self, data_repr: DataRepr) -> u128 {
+        per_second(self.last_interval_data_info.incoming_data(data_repr))
+    }
+
+    /// Data sent per second in the latest interval
+    pub fn outgoing_rate(&self, data_repr: DataRepr) -> u128 {
+        per_second(self.last_interval_data_info.outgoing_data(data_repr))
+    }
+
     pub fn get_thumbnail_data(&self, data_repr: DataRepr) -> (u128, u128, u128) {
         let incoming = self.tot_data_info.incoming_data(data_repr);
         let outgoing = self.tot_data_info.outgoing_data(data_repr);
@@ -75,12 +179,207 @@ impl InfoTraffic {
         (incoming, outgoing, dropped)
     }
 
+    /// Returns the connections that didn't exchange any packet in the last `idle_secs` seconds,
+    /// relative to the timestamp of the latest parsed packet
+    pub fn idle_connections(
+        &self,
+        idle_secs: u64,
+    ) -> Vec<(&AddressPortPair, &InfoAddressPortPair)> {
+        let Some(now_usecs) = self.last_packet_timestamp.to_usecs() else {
+            return Vec::new();
+        };
+        let idle_usecs = i64::try_from(idle_secs)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(1_000_000);
+        let threshold = now_usecs.saturating_sub(idle_usecs);
+        self.map
+            .iter()
+            .filter(|(_, info)| {
+                info.transmitted_packets > 0
+                    && info
+                        .final_timestamp
+                        .to_usecs()
+                        .is_some_and(|usecs| usecs <= threshold)
+            })
+            .collect()
+    }
+
+    /// Returns the traffic accumulated in the interval, leaving an empty one behind.
+    ///
+    /// Only the values that aren't accumulated over the interval are kept
+    /// (the cumulative numbers of dropped packets, and the timestamp of the latest packet),
+    /// so that the traffic sent at each interval is counted exactly once by the receiver.
     pub fn take_but_leave_something(&mut self) -> Self {
         let info_traffic = Self {
             last_packet_timestamp: self.last_packet_timestamp,
+            received_packets: self.received_packets,
             dropped_packets: self.dropped_packets,
+            interface_dropped_packets: self.interface_dropped_packets,
             ..Self::default()
         };
         std::mem::replace(self, info_traffic)
     }
 }
+
+/// Converts the data exchanged in an interval to the data exchanged per second
+fn per_second(interval_data: u128) -> u128 {
+    interval_data * 1_000 / TICK_INTERVAL.as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::networking::manage_packets::get_traffic_direction;
+    use crate::networking::types::protocol::Protocol;
+    use crate::networking::types::traffic_direction::TrafficDirection;
+
+    fn connection(
+        last_octet: u8,
+        initial_secs: i64,
+        final_secs: i64,
+    ) -> (AddressPortPair, InfoAddressPortPair) {
+        (
+            AddressPortPair::new(
+                IpAddr::from([192, 168, 1, 2]),
+                Some(50000),
+                IpAddr::from([8, 8, 8, last_octet]),
+                Some(443),
+                Protocol::TCP,
+            ),
+            InfoAddressPortPair {
+                transmitted_bytes: 500,
+                transmitted_packets: 5,
+                initial_timestamp: Timestamp::new(initial_secs, 0),
+                final_timestamp: Timestamp::new(final_secs, 0),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_idle_connections() {
+        let (stalled_key, stalled_info) = connection(1, 100, 130);
+        let (active_key, active_info) = connection(2, 100, 195);
+        let info_traffic = InfoTraffic {
+            last_packet_timestamp: Timestamp::new(200, 0),
+            map: HashMap::from([(stalled_key, stalled_info), (active_key, active_info)]),
+            ..Default::default()
+        };
+
+        let idle = info_traffic.idle_connections(60);
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].0, &stalled_key);
+
+        // a threshold longer than the silence of both connections returns nothing
+        assert!(info_traffic.idle_connections(100).is_empty());
+        // a threshold shorter than the silence of both connections returns both
+        assert_eq!(info_traffic.idle_connections(2).len(), 2);
+    }
+
+    #[test]
+    fn test_direction_totals_and_rates() {
+        // on the loopback interface both endpoints are the local host:
+        // the direction of each packet is told by its ports, and it must be counted once
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+        let request = get_traffic_direction(&localhost, &localhost, Some(50000), Some(8080), &[]);
+        let reply = get_traffic_direction(&localhost, &localhost, Some(8080), Some(50000), &[]);
+        assert_eq!(request, TrafficDirection::Outgoing);
+        assert_eq!(reply, TrafficDirection::Incoming);
+
+        let mut info_traffic = InfoTraffic::default();
+        for (request_bytes, reply_bytes) in [(300, 1_200), (100, 400)] {
+            let mut interval = InfoTraffic::default();
+            interval.tot_data_info.add_packet(request_bytes, request);
+            interval.tot_data_info.add_packet(reply_bytes, reply);
+            info_traffic.refresh(&mut interval);
+        }
+
+        assert_eq!(info_traffic.total_outgoing(DataRepr::Bytes), 400);
+        assert_eq!(info_traffic.total_incoming(DataRepr::Bytes), 1_600);
+        assert_eq!(info_traffic.total_outgoing(DataRepr::Packets), 2);
+        assert_eq!(info_traffic.total_incoming(DataRepr::Packets), 2);
+        assert_eq!(info_traffic.tot_data_info.tot_data(DataRepr::Packets), 4);
+        // rates only account for the latest interval
+        assert_eq!(info_traffic.outgoing_rate(DataRepr::Bytes), 100);
+        assert_eq!(info_traffic.incoming_rate(DataRepr::Bytes), 400);
+        assert_eq!(info_traffic.incoming_rate(DataRepr::Bits), 3_200);
+    }
+
+    #[test]
+    fn test_take_but_leave_something_resets_interval() {
+        let (key, info) = connection(1, 100, 101);
+        let data_info = DataInfo::new_for_tests(0, 5, 0, 500);
+        let mut info_traffic_msg = InfoTraffic {
+            tot_data_info: data_info,
+            received_packets: 8,
+            dropped_packets: 3,
+            last_packet_timestamp: Timestamp::new(101, 0),
+            map: HashMap::from([(key, info.clone())]),
+            services: HashMap::from([(Service::Unknown, data_info)]),
+            hosts: HashMap::from([(Host::default(), DataInfoHost::default())]),
+            ..Default::default()
+        };
+
+        let mut collected = InfoTraffic::default();
+        collected.refresh(&mut info_traffic_msg.take_but_leave_something());
+
+        // only the values that aren't accumulated over the interval are left behind
+        assert_eq!(
+            info_traffic_msg.tot_data_info.tot_data(DataRepr::Packets),
+            0
+        );
+        assert_eq!(info_traffic_msg.received_packets, 8);
+        assert_eq!(info_traffic_msg.dropped_packets, 3);
+        assert_eq!(
+            info_traffic_msg.last_packet_timestamp,
+            Timestamp::new(101, 0)
+        );
+        assert!(info_traffic_msg.map.is_empty());
+        assert!(info_traffic_msg.services.is_empty());
+        assert!(info_traffic_msg.hosts.is_empty());
+
+        // an interval without packets doesn't count the previous one again
+        collected.refresh(&mut info_traffic_msg.take_but_leave_something());
+        assert_eq!(collected.tot_data_info.tot_data(DataRepr::Bytes), 500);
+        assert_eq!(collected.map[&key].transmitted_bytes, 500);
+        assert_eq!(
+            collected.services[&Service::Unknown].tot_data(DataRepr::Bytes),
+            500
+        );
+
+        // the traffic of the next interval is added on top
+        info_traffic_msg.tot_data_info = data_info;
+        info_traffic_msg.map.insert(key, info);
+        collected.refresh(&mut info_traffic_msg.take_but_leave_something());
+        assert_eq!(collected.tot_data_info.tot_data(DataRepr::Bytes), 1_000);
+        assert_eq!(collected.map[&key].transmitted_bytes, 1_000);
+        assert_eq!(collected.received_packets, 8);
+        assert_eq!(collected.dropped_packets, 3);
+    }
+
+    #[test]
+    fn test_icmp_errors_attached_to_connections() {
+        let (key, info) = connection(1, 100, 100);
+        let (unknown_key, _) = connection(2, 100, 100);
+        let mut info_traffic = InfoTraffic::default();
+        info_traffic.refresh(&mut InfoTraffic {
+            map: HashMap::from([(key, info)]),
+            ..Default::default()
+        });
+
+        info_traffic.refresh(&mut InfoTraffic {
+            icmp_errors: HashMap::from([
+                (key, IcmpError::PortUnreachable),
+                (unknown_key, IcmpError::HostUnreachable),
+            ]),
+            ..Default::default()
+        });
+        assert_eq!(
+            info_traffic.map[&key].icmp_error,
+            Some(IcmpError::PortUnreachable)
+        );
+        assert!(!info_traffic.map.contains_key(&unknown_key));
+    }
+}
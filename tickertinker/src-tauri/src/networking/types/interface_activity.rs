@@ -0,0 +1,156 @@
+//! Module defining the `InterfaceActivity` struct, which tells how busy each device currently is.
+
+use std::cmp::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pcap::{Capture, Device, Error};
+use serde::Serialize;
+
+/// How long each device is sampled for
+pub const SAMPLE_DURATION: Duration = Duration::from_millis(500);
+/// Read timeout of the sampling handles, so that idle devices don't block past the sample duration
+const SAMPLE_READ_TIMEOUT_MS: i32 = 100;
+
+/// Packet rate observed on a device during a short capture,
+/// to help picking the device actually carrying the traffic
+#[derive(Clone, Debug, Serialize)]
+pub struct InterfaceActivity {
+    /// Name of the device
+    pub name: String,
+    /// Human-readable description of the device, if provided by the OS
+    pub description: Option<String>,
+    /// Whether the device could be opened and some packets were observed on it
+    pub active: bool,
+    /// Number of packets observed during the sample
+    pub packets: u128,
+    /// Observed packets per second
+    pub packets_per_second: f64,
+}
+
+impl InterfaceActivity {
+    /// Samples all the given devices concurrently, so that the whole operation takes about
+    /// `duration` regardless of their number, returning them from the busiest to the idlest
+    pub fn sample_all(devices: Vec<Device>, duration: Duration) -> Vec<Self> {
+        let mut activities: Vec<Self> = thread::scope(|scope| {
+            let handles: Vec<_> = devices
+                .into_iter()
+                .map(|device| scope.spawn(move || Self::sample(device, duration)))
+                .collect();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .collect()
+        });
+        sort_by_activity(&mut activities);
+        activities
+    }
+
+    /// Counts the packets seen on the device for `duration`;
+    /// devices that can't be opened are reported as inactive
+    fn sample(device: Device, duration: Duration) -> Self {
+        let name = device.name.clone();
+        let description = device.desc.clone();
+        let packets = match count_packets(device, duration) {
+            Ok(packets) => packets,
+            Err(_) => return Self::inactive(name, description),
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let packets_per_second = packets as f64 / duration.as_secs_f64();
+        Self {
+            name,
+            description,
+            active: packets > 0,
+            packets,
+            packets_per_second,
+        }
+    }
+
+    fn inactive(name: String, description: Option<String>) -> Self {
+        Self {
+            name,
+            description,
+            active: false,
+            packets: 0,
+            packets_per_second: 0.0,
+        }
+    }
+}
+
+fn count_packets(device: Device, duration: Duration) -> Result<u128, Error> {
+    let mut cap = Capture::from_device(device)?
+        .promisc(false)
+        .snaplen(64) // headers aren't even parsed
+        .immediate_mode(true)
+        .timeout(SAMPLE_READ_TIMEOUT_MS)
+        .open()?;
+    let start = Instant::now();
+    let mut packets = 0;
+    while start.elapsed() < duration {
+        match cap.next_packet() {
+            Ok(_) => packets += 1,
+            Err(Error::TimeoutExpired) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(packets)
+}
+
+/// Sorts the devices from the busiest to the idlest, by name when equally busy
+fn sort_by_activity(activities: &mut [InterfaceActivity]) {
+    activities.sort_by(|a, b| {
+        b.packets_per_second
+            .partial_cmp(&a.packets_per_second)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(name: &str, packets: u128) -> InterfaceActivity {
+        #[allow(clippy::cast_precision_loss)]
+        let packets_per_second = packets as f64 / SAMPLE_DURATION.as_secs_f64();
+        InterfaceActivity {
+            name: name.to_string(),
+            description: None,
+            active: packets > 0,
+            packets,
+            packets_per_second,
+        }
+    }
+
+    #[test]
+    fn test_sorted_by_activity() {
+        let mut activities = vec![
+            activity("lo", 3),
+            InterfaceActivity::inactive("any".to_string(), None),
+            activity("wlan0", 250),
+            activity("docker0", 0),
+            activity("eth0", 12),
+        ];
+        sort_by_activity(&mut activities);
+
+        let summary: Vec<_> = activities
+            .iter()
+            .map(|a| (a.name.as_str(), a.packets, a.active))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("wlan0", 250, true),
+                ("eth0", 12, true),
+                ("lo", 3, true),
+                ("any", 0, false),
+                ("docker0", 0, false),
+            ]
+        );
+        assert!(
+            activities
+                .windows(2)
+                .all(|w| w[0].packets_per_second >= w[1].packets_per_second)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Module defining the `InterfaceInfo` struct, which describes a device available for capturing.
+
+use pcap::Device;
+use serde::Serialize;
+
+/// A device with the details needed to tell it from the others
+/// (on Windows, names are GUIDs and only the description is meaningful)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InterfaceInfo {
+    /// Name of the device, to pass to `start_capture`
+    pub name: String,
+    /// Human-readable description of the device, if provided by the OS
+    pub description: Option<String>,
+    /// IP addresses assigned to the device
+    pub addresses: Vec<String>,
+    /// Whether the device is a loopback interface
+    pub is_loopback: bool,
+    /// Whether the device is up
+    pub is_up: bool,
+}
+
+impl InterfaceInfo {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            name: device.name.clone(),
+            description: device.desc.clone(),
+            addresses: device
+                .addresses
+                .iter()
+                .map(|address| address.addr.to_string())
+                .collect(),
+            is_loopback: device.flags.is_loopback(),
+            is_up: device.flags.is_up(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use pcap::{Address, ConnectionStatus, DeviceFlags, IfFlags};
+
+    use super::*;
+
+    #[test]
+    fn test_interface_info_from_device() {
+        let device = Device {
+            name: "lo".to_string(),
+            desc: Some("Loopback".to_string()),
+            addresses: vec![
+                Address {
+                    addr: IpAddr::from([127, 0, 0, 1]),
+                    netmask: Some(IpAddr::from([255, 0, 0, 0])),
+                    broadcast_addr: None,
+                    dst_addr: None,
+                },
+                Address {
+                    addr: "::1".parse().unwrap(),
+                    netmask: None,
+                    broadcast_addr: None,
+                    dst_addr: None,
+                },
+            ],
+            flags: DeviceFlags {
+                if_flags: IfFlags::LOOPBACK | IfFlags::UP | IfFlags::RUNNING,
+                connection_status: ConnectionStatus::NotApplicable,
+            },
+        };
+        assert_eq!(
+            InterfaceInfo::new(&device),
+            InterfaceInfo {
+                name: "lo".to_string(),
+                description: Some("Loopback".to_string()),
+                addresses: vec!["127.0.0.1".to_string(), "::1".to_string()],
+                is_loopback: true,
+                is_up: true,
+            }
+        );
+
+        let down = InterfaceInfo::new(&Device::from("eth1"));
+        assert_eq!(down.description, None);
+        assert!(down.addresses.is_empty());
+        assert!(!down.is_loopback);
+        assert!(!down.is_up);
+    }
+}
@@ -2,6 +2,10 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
+/// Set of addresses, made of individual IPs and ranges.
+///
+/// Both are kept sorted, and overlapping or adjacent ranges are merged,
+/// so that lookups take logarithmic time even for large collections.
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub(crate) struct IpCollection {
     ips: Vec<IpAddr>,
@@ -11,6 +15,7 @@ pub(crate) struct IpCollection {
 impl IpCollection {
     const SEPARATOR: char = ',';
     const RANGE_SEPARATOR: char = '-';
+    const CIDR_SEPARATOR: char = '/';
 
     pub(crate) fn new(str: &str) -> Option<Self> {
         let str = str.replace(' ', "");
@@ -39,6 +44,9 @@ impl IpCollection {
                     return None;
                 }
                 ranges.push(range);
+            } else if object.contains(Self::CIDR_SEPARATOR) {
+                // CIDR block (e.g., 192.168.1.0/24)
+                ranges.push(Self::cidr_range(object)?);
             } else {
                 // individual IP
                 let ip = IpAddr::from_str(object).ok()?;
@@ -46,16 +54,97 @@ impl IpCollection {
             }
         }
 
-        Some(Self { ips, ranges })
+        Some(Self::normalized(ips, ranges))
     }
 
-    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
-        for range in &self.ranges {
-            if range.contains(ip) {
-                return true;
+    /// Returns the union of the collections
+    pub(crate) fn union<'a>(collections: impl IntoIterator<Item = &'a Self>) -> Self {
+        let (mut ips, mut ranges) = (Vec::new(), Vec::new());
+        for collection in collections {
+            ips.extend_from_slice(&collection.ips);
+            ranges.extend_from_slice(&collection.ranges);
+        }
+        Self::normalized(ips, ranges)
+    }
+
+    /// Sorts the IPs removing duplicates, and sorts the ranges merging the overlapping
+    /// or adjacent ones (ranges of different IP versions are never merged)
+    fn normalized(mut ips: Vec<IpAddr>, mut ranges: Vec<RangeInclusive<IpAddr>>) -> Self {
+        ips.sort_unstable();
+        ips.dedup();
+
+        ranges.sort_unstable_by_key(|range| *range.start());
+        let mut merged: Vec<RangeInclusive<IpAddr>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = merged.last_mut()
+                && next_ip(*last.end()).is_some_and(|next| *range.start() <= next)
+                && range.start().is_ipv4() == last.end().is_ipv4()
+            {
+                if range.end() > last.end() {
+                    *last = RangeInclusive::new(*last.start(), *range.end());
+                }
+                continue;
+            }
+            // the last range can't be extended if it ends with the last address of its version
+            if let Some(last) = merged.last()
+                && range.end() <= last.end()
+            {
+                continue;
+            }
+            merged.push(range);
+        }
+
+        Self {
+            ips,
+            ranges: merged,
+        }
+    }
+
+    /// Returns the range of addresses covered by a CIDR block
+    fn cidr_range(cidr: &str) -> Option<RangeInclusive<IpAddr>> {
+        let (ip_str, prefix_str) = cidr.split_once(Self::CIDR_SEPARATOR)?;
+        let prefix = u32::from_str(prefix_str).ok()?;
+        match IpAddr::from_str(ip_str).ok()? {
+            IpAddr::V4(ip) if prefix <= 32 => {
+                // shifting by the whole width leaves no host bits
+                let host_mask = u32::MAX.checked_shr(prefix).unwrap_or(0);
+                let network = u32::from(ip) & !host_mask;
+                Some(RangeInclusive::new(
+                    IpAddr::V4(Ipv4Addr::from(network)),
+                    IpAddr::V4(Ipv4Addr::from(network | host_mask)),
+                ))
             }
+            IpAddr::V6(ip) if prefix <= 128 => {
+                let host_mask = u128::MAX.checked_shr(prefix).unwrap_or(0);
+                let network = u128::from(ip) & !host_mask;
+                Some(RangeInclusive::new(
+                    IpAddr::V6(Ipv6Addr::from(network)),
+                    IpAddr::V6(Ipv6Addr::from(network | host_mask)),
+                ))
+            }
+            _ => None,
         }
-        self.ips.contains(ip)
+    }
+
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        // ranges are disjoint: the only candidate is the last one starting before the address
+        let candidates = self.ranges.partition_point(|range| range.start() <= ip);
+        candidates
+            .checked_sub(1)
+            .is_some_and(|i| self.ranges[i].contains(ip))
+            || self.ips.binary_search(ip).is_ok()
+    }
+}
+
+/// Returns the address following the given one, if any (of the same IP version)
+fn next_ip(ip: IpAddr) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip)
+            .checked_add(1)
+            .map(|next| IpAddr::V4(Ipv4Addr::from(next))),
+        IpAddr::V6(ip) => u128::from(ip)
+            .checked_add(1)
+            .map(|next| IpAddr::V6(Ipv6Addr::from(next))),
     }
 }
 
@@ -208,8 +297,8 @@ mod tests {
             IpCollection::new("aaaa::ffff,bbbb::1-cccc::2,ff::dd").unwrap(),
             IpCollection {
                 ips: vec![
-                    IpAddr::from_str("aaaa::ffff").unwrap(),
-                    IpAddr::from_str("ff::dd").unwrap()
+                    IpAddr::from_str("ff::dd").unwrap(),
+                    IpAddr::from_str("aaaa::ffff").unwrap()
                 ],
                 ranges: vec![RangeInclusive::new(
                     IpAddr::from_str("bbbb::1").unwrap(),
@@ -242,6 +331,48 @@ mod tests {
         assert_eq!(IpCollection::new("1.1.1.1-2.2.2.2-3.3.3.3"), None);
 
         assert_eq!(IpCollection::new("1.1.1.1-2.2.2.2-"), None);
+
+        assert_eq!(IpCollection::new("10.0.0.0/33"), None);
+
+        assert_eq!(IpCollection::new("10.0.0.0/"), None);
+
+        assert_eq!(IpCollection::new("2600:1400::/129"), None);
+    }
+
+    #[test]
+    fn test_new_collections_cidr() {
+        assert_eq!(
+            IpCollection::new("10.1.2.3/8, 1.1.1.1/32").unwrap(),
+            IpCollection {
+                ips: vec![],
+                ranges: vec![
+                    RangeInclusive::new(
+                        IpAddr::from_str("1.1.1.1").unwrap(),
+                        IpAddr::from_str("1.1.1.1").unwrap()
+                    ),
+                    RangeInclusive::new(
+                        IpAddr::from_str("10.0.0.0").unwrap(),
+                        IpAddr::from_str("10.255.255.255").unwrap()
+                    ),
+                ]
+            }
+        );
+
+        // the whole address space absorbs the other blocks
+        assert_eq!(
+            IpCollection::new("10.1.2.3/8, 1.1.1.1/32, 0.0.0.0/0").unwrap(),
+            IpCollection {
+                ips: vec![],
+                ranges: vec![RangeInclusive::new(
+                    IpAddr::from_str("0.0.0.0").unwrap(),
+                    IpAddr::from_str("255.255.255.255").unwrap()
+                )]
+            }
+        );
+
+        let collection = IpCollection::new("2600:1400::/32").unwrap();
+        assert!(collection.contains(&IpAddr::from_str("2600:1400:abcd::1").unwrap()));
+        assert!(!collection.contains(&IpAddr::from_str("2600:1401::1").unwrap()));
     }
 
     #[test]
@@ -321,4 +452,83 @@ mod tests {
         assert!(!collection_3.contains(&IpAddr::from_str("192.168.1.1").unwrap()));
         assert!(!collection_3.contains(&IpAddr::from_str("0.0.0.0").unwrap()));
     }
+
+    #[test]
+    fn test_overlapping_ranges_merged() {
+        let collection = IpCollection::new(
+            "10.0.1.1-10.0.1.10, 10.0.0.50-10.0.1.0, 10.0.0.0-10.0.0.100, 192.168.1.0/24, \
+             192.168.0.0/16, 1.1.1.1, 3.3.3.3, 1.1.1.1, aa::6-aa::9, aa::1-aa::5, aa::3-aa::4",
+        )
+        .unwrap();
+        assert_eq!(
+            collection,
+            IpCollection {
+                ips: vec![
+                    IpAddr::from_str("1.1.1.1").unwrap(),
+                    IpAddr::from_str("3.3.3.3").unwrap()
+                ],
+                ranges: vec![
+                    // overlapping and adjacent
+                    RangeInclusive::new(
+                        IpAddr::from_str("10.0.0.0").unwrap(),
+                        IpAddr::from_str("10.0.1.10").unwrap()
+                    ),
+                    // nested
+                    RangeInclusive::new(
+                        IpAddr::from_str("192.168.0.0").unwrap(),
+                        IpAddr::from_str("192.168.255.255").unwrap()
+                    ),
+                    RangeInclusive::new(
+                        IpAddr::from_str("aa::1").unwrap(),
+                        IpAddr::from_str("aa::9").unwrap()
+                    ),
+                ]
+            }
+        );
+
+        for ip in [
+            "10.0.0.0",
+            "10.0.0.77",
+            "10.0.0.255",
+            "10.0.1.0",
+            "10.0.1.10",
+            "192.168.0.1",
+            "192.168.1.1",
+            "192.168.200.200",
+            "1.1.1.1",
+            "3.3.3.3",
+            "aa::1",
+            "aa::5",
+            "aa::6",
+            "aa::9",
+        ] {
+            assert!(collection.contains(&IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+        for ip in [
+            "9.255.255.255",
+            "10.0.1.11",
+            "192.169.0.0",
+            "2.2.2.2",
+            "aa::",
+            "aa::a",
+            "::10.0.0.1",
+        ] {
+            assert!(!collection.contains(&IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+
+        // ranges of different IP versions are never merged, even if adjacent in the ordering
+        let collection = IpCollection::new("255.255.255.0-255.255.255.255, ::-::ff").unwrap();
+        assert_eq!(collection.ranges.len(), 2);
+        assert!(collection.contains(&IpAddr::from_str("::1").unwrap()));
+        assert!(!collection.contains(&IpAddr::from_str("255.255.254.255").unwrap()));
+
+        let union = IpCollection::union([
+            &IpCollection::new("10.0.0.0-10.0.0.9").unwrap(),
+            &IpCollection::new("10.0.0.10-10.0.0.20, 8.8.8.8").unwrap(),
+        ]);
+        assert_eq!(
+            union,
+            IpCollection::new("8.8.8.8, 10.0.0.0-10.0.0.20").unwrap()
+        );
+    }
 }
@@ -0,0 +1,127 @@
+//! Module defining the `MacFlags` struct, which labels the special kinds of MAC addresses.
+
+use serde::Serialize;
+
+use crate::networking::types::ip_version::IpVersion;
+
+/// Bit of the first octet set in group (multicast and broadcast) addresses
+const GROUP_BIT: u8 = 0x01;
+/// Bit of the first octet set in addresses not assigned by the manufacturer
+const LOCAL_BIT: u8 = 0x02;
+/// Prefix of the MAC addresses IPv4 multicast groups are mapped to (RFC 1112)
+const IPV4_MULTICAST_PREFIX: [u8; 3] = [0x01, 0x00, 0x5e];
+/// Prefix of the MAC addresses IPv6 multicast groups are mapped to (RFC 2464)
+const IPV6_MULTICAST_PREFIX: [u8; 2] = [0x33, 0x33];
+
+/// Flags telling apart the MAC addresses of real devices from the ones of group frames
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MacFlags {
+    /// The address was assigned locally rather than by the manufacturer
+    /// (e.g., randomized or virtual interfaces)
+    pub locally_administered: bool,
+    /// The address identifies a group of devices rather than a single one (broadcast included)
+    pub multicast: bool,
+    /// The address is the broadcast one (ff:ff:ff:ff:ff:ff)
+    pub broadcast: bool,
+    /// IP version of the multicast group mapped to the address, if any
+    pub ip_multicast: Option<IpVersion>,
+}
+
+impl MacFlags {
+    pub fn new(mac: [u8; 6]) -> Self {
+        let ip_multicast = if mac.starts_with(&IPV4_MULTICAST_PREFIX) && mac[3] & 0x80 == 0 {
+            Some(IpVersion::IPv4)
+        } else if mac.starts_with(&IPV6_MULTICAST_PREFIX) {
+            Some(IpVersion::IPv6)
+        } else {
+            None
+        };
+        Self {
+            locally_administered: mac[0] & LOCAL_BIT != 0,
+            multicast: mac[0] & GROUP_BIT != 0,
+            broadcast: mac == [0xff; 6],
+            ip_multicast,
+        }
+    }
+
+    /// Computes the flags of a MAC address in its textual form (see [`normalize_mac`])
+    pub fn from_address(mac: &str) -> Option<Self> {
+        parse_mac(mac).map(Self::new)
+    }
+}
+
+/// Normalizes a MAC address to lowercase octets separated by colons (e.g., "aa:bb:cc:00:11:22"),
+/// accepting both colons and dashes as separators and any case
+pub fn normalize_mac(mac: &str) -> Option<String> {
+    let octets = parse_mac(mac)?;
+    Some(
+        octets
+            .iter()
+            .map(|octet| format!("{octet:02x}"))
+            .collect::<Vec<_>>()
+            .join(":"),
+    )
+}
+
+pub(crate) fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut octets = [0; 6];
+    let mut parts = mac.trim().split([':', '-']);
+    for octet in &mut octets {
+        let part = parts.next()?;
+        if part.len() != 2 || !part.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        *octet = u8::from_str_radix(part, 16).ok()?;
+    }
+    parts.next().is_none().then_some(octets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_mac() {
+        let flags = MacFlags::from_address("ff:ff:ff:ff:ff:ff").unwrap();
+        assert!(flags.broadcast);
+        assert!(flags.multicast);
+        assert_eq!(flags.ip_multicast, None);
+    }
+
+    #[test]
+    fn test_unicast_mac() {
+        let flags = MacFlags::from_address("a4:83:e7:12:34:56").unwrap();
+        assert_eq!(flags, MacFlags::default());
+
+        // randomized address
+        let flags = MacFlags::from_address("da:a1:19:00:11:22").unwrap();
+        assert!(flags.locally_administered);
+        assert!(!flags.multicast);
+    }
+
+    #[test]
+    fn test_ip_multicast_macs() {
+        // mDNS groups
+        let flags = MacFlags::new([0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]);
+        assert!(flags.multicast && !flags.broadcast);
+        assert_eq!(flags.ip_multicast, Some(IpVersion::IPv4));
+        let flags = MacFlags::new([0x33, 0x33, 0x00, 0x00, 0x00, 0xfb]);
+        assert_eq!(flags.ip_multicast, Some(IpVersion::IPv6));
+        // only the lower half of the 01:00:5e block is used for IPv4 groups
+        assert_eq!(
+            MacFlags::new([0x01, 0x00, 0x5e, 0x80, 0x00, 0x01]).ip_multicast,
+            None
+        );
+    }
+
+    #[test]
+    fn test_normalize_mac() {
+        assert_eq!(
+            normalize_mac("A4-83-E7-12-34-56").as_deref(),
+            Some("a4:83:e7:12:34:56")
+        );
+        assert_eq!(normalize_mac("a4:83:e7:12:34"), None);
+        assert_eq!(normalize_mac("a4:83:e7:12:34:56:78"), None);
+        assert_eq!(normalize_mac("a4:83:e7:12:34:zz"), None);
+    }
+}
@@ -1,23 +1,42 @@
 pub mod address_port_pair;
+pub mod app_protocol;
 pub mod arp_type;
 pub mod asn;
 pub mod bogon;
 pub mod capture_context;
+pub mod capture_filter;
+pub mod capture_options;
+pub mod capture_volume;
 pub mod data_info;
 pub mod data_info_host;
 pub mod data_representation;
+pub mod handshake_tracker;
 pub mod host;
 pub mod host_data_states;
+pub mod icmp_error;
 pub mod icmp_type;
 pub mod info_address_port_pair;
 pub mod info_traffic;
+pub mod interface_activity;
+pub mod interface_info;
 pub mod ip_collection;
 pub mod ip_version;
+pub mod mac_flags;
 pub mod my_device;
 pub mod my_link_type;
+pub mod oui;
 pub mod packet_filters_fields;
+pub mod parse_failure_sample;
+pub mod pcapng_writer;
+pub mod platform_capabilities;
 pub mod protocol;
+pub mod qos_class;
+pub mod retention_policy;
+pub mod savefile_queue;
+pub mod savefiles;
 pub mod service;
 pub mod service_query;
+pub mod services_map_info;
+pub mod socket_table;
 pub mod traffic_direction;
 pub mod traffic_type;
@@ -1,6 +1,10 @@
 use pcap::{Address, Device, DeviceFlags};
 
 use crate::networking::types::my_link_type::MyLinkType;
+use crate::utils::types::timestamp::TimestampPrecision;
+
+/// Interface flag set while the device is in promiscuous mode (`IFF_PROMISC`)
+const IFF_PROMISC: u32 = 0x100;
 
 /// Represents the current inspected device.
 /// Used to keep in sync the device addresses in case of changes
@@ -11,6 +15,8 @@ pub struct MyDevice {
     desc: Option<String>,
     addresses: Vec<Address>,
     link_type: MyLinkType,
+    /// Precision of the timestamps requested when opening the device
+    timestamp_precision: TimestampPrecision,
 }
 
 impl MyDevice {
@@ -34,6 +40,7 @@ impl MyDevice {
             desc: device.desc,
             addresses: device.addresses,
             link_type: MyLinkType::default(),
+            timestamp_precision: TimestampPrecision::default(),
         }
     }
 
@@ -60,4 +67,59 @@ impl MyDevice {
     pub fn set_link_type(&mut self, link_type: MyLinkType) {
         self.link_type = link_type;
     }
+
+    pub fn get_timestamp_precision(&self) -> TimestampPrecision {
+        self.timestamp_precision
+    }
+
+    pub fn set_timestamp_precision(&mut self, timestamp_precision: TimestampPrecision) {
+        self.timestamp_precision = timestamp_precision;
+    }
+
+    /// Returns whether the device is currently in promiscuous mode,
+    /// or `None` if the platform doesn't expose it (only Linux does)
+    pub fn is_promiscuous(&self) -> Option<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            let flags =
+                std::fs::read_to_string(format!("/sys/class/net/{}/flags", self.name)).ok()?;
+            parse_promiscuous_flag(&flags)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+}
+
+/// Parses the flags of an interface, as printed in `/sys/class/net/<name>/flags` (e.g., `0x1103`)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_promiscuous_flag(flags: &str) -> Option<bool> {
+    let flags = flags.trim();
+    let flags = u32::from_str_radix(flags.strip_prefix("0x").unwrap_or(flags), 16).ok()?;
+    Some(flags & IFF_PROMISC != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_promiscuous_flag() {
+        assert_eq!(parse_promiscuous_flag("0x1103\n"), Some(true));
+        assert_eq!(parse_promiscuous_flag("0x1003\n"), Some(false));
+        assert_eq!(parse_promiscuous_flag("garbage"), None);
+    }
+
+    #[test]
+    fn test_promiscuous_state_is_populated() {
+        let loopback = MyDevice::from_pcap_device(Device::from("lo"));
+        let missing = MyDevice::from_pcap_device(Device::from("not-a-real-device0"));
+        if cfg!(target_os = "linux") {
+            assert!(loopback.is_promiscuous().is_some());
+        } else {
+            assert_eq!(loopback.is_promiscuous(), None);
+        }
+        assert_eq!(missing.is_promiscuous(), None);
+    }
 }
@@ -34,6 +34,20 @@ impl MyLinkType {
         }
     }
 
+    /// Returns the name of the link type (e.g., "EN10MB"), empty if not yet assigned
+    pub fn get_name(self) -> String {
+        match self {
+            Self::Null(l)
+            | Self::Ethernet(l)
+            | Self::RawIp(l)
+            | Self::Loop(l)
+            | Self::IPv4(l)
+            | Self::IPv6(l)
+            | Self::Unsupported(l) => l.get_name().unwrap_or_else(|_| l.0.to_string()),
+            Self::NotYetAssigned => String::new(),
+        }
+    }
+
     pub fn full_print_on_one_line(self, language: Language) -> String {
         match self {
             Self::Null(l)
@@ -0,0 +1,53 @@
+//! Module resolving the vendor of a MAC address from its Organizationally Unique Identifier,
+//! i.e., the prefix assigned by the IEEE to the manufacturer of the network interface.
+
+use crate::networking::types::mac_flags::{MacFlags, parse_mac};
+
+include!(concat!(env!("OUT_DIR"), "/oui.rs"));
+
+/// Returns the vendor of a MAC address in its textual form (e.g., "VMware, Inc." for
+/// "00:50:56:c0:00:08"), or `None` if unknown.
+///
+/// Addresses not assigned by a manufacturer (locally administered or group ones) have no vendor.
+pub fn vendor_for_mac(mac: &str) -> Option<&'static str> {
+    let octets = parse_mac(mac)?;
+    let flags = MacFlags::new(octets);
+    if flags.locally_administered || flags.multicast {
+        return None;
+    }
+    let oui = u32::from_be_bytes([0, octets[0], octets[1], octets[2]]);
+    OUI_VENDORS.get(&oui).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendor_for_mac() {
+        assert_eq!(vendor_for_mac("00:50:56:c0:00:08"), Some("VMware, Inc."));
+        assert_eq!(
+            vendor_for_mac("B8-27-EB-12-34-56"),
+            Some("Raspberry Pi Foundation")
+        );
+        assert_eq!(vendor_for_mac("00:03:93:aa:bb:cc"), Some("Apple, Inc."));
+        // locally administered (e.g., randomized) and group addresses
+        assert_eq!(vendor_for_mac("02:50:56:c0:00:08"), None);
+        assert_eq!(vendor_for_mac("01:00:5e:00:00:fb"), None);
+        assert_eq!(vendor_for_mac("ff:ff:ff:ff:ff:ff"), None);
+        // not MAC addresses
+        assert_eq!(vendor_for_mac("00:50:56"), None);
+        assert_eq!(vendor_for_mac(""), None);
+    }
+
+    #[test]
+    fn test_vendor_for_mac_outside_bundled_subset() {
+        // only known if the crate was built with the full IEEE registry
+        let vendor = vendor_for_mac("00:00:00:11:22:33");
+        if OUI_REGISTRY_COMPLETE {
+            assert_eq!(vendor, Some("XEROX CORPORATION"));
+        } else {
+            assert_eq!(vendor, None);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Module defining the `ParseFailureSample` struct, which records a packet whose headers couldn't be parsed.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::networking::types::my_link_type::MyLinkType;
+
+/// Maximum number of samples recorded per capture
+pub const MAX_PARSE_FAILURE_SAMPLES: usize = 5;
+/// Maximum number of bytes of each sample included in its hex dump
+const MAX_SAMPLE_BYTES: usize = 256;
+
+/// A packet whose headers couldn't be parsed, useful to diagnose unsupported data links
+#[derive(Clone, Debug, Serialize)]
+pub struct ParseFailureSample {
+    /// Data link type of the capture (e.g., "EN10MB")
+    pub link_type: String,
+    /// Reason why the headers couldn't be parsed
+    pub error: String,
+    /// Length in bytes of the packet
+    pub len: usize,
+    /// Hex dump of the packet (truncated to its first bytes), e.g. "45 00 00 3c"
+    pub hex: String,
+}
+
+impl ParseFailureSample {
+    pub fn new(packet: &[u8], link_type: MyLinkType, error: String) -> Self {
+        let mut hex = String::with_capacity(3 * packet.len().min(MAX_SAMPLE_BYTES));
+        for (i, byte) in packet.iter().take(MAX_SAMPLE_BYTES).enumerate() {
+            if i > 0 {
+                hex.push(' ');
+            }
+            let _ = write!(hex, "{byte:02x}");
+        }
+        Self {
+            link_type: link_type.get_name(),
+            error,
+            len: packet.len(),
+            hex,
+        }
+    }
+}
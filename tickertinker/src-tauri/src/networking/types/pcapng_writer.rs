@@ -0,0 +1,186 @@
+//! Module defining the `PcapngWriter` struct, which saves the captured packets to pcapng files
+//! with a comment attached to each of them (displayed by Wireshark in the packet details).
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use pcap::{Linktype, Packet};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+/// Written in the section header, to let readers detect the byte order of the file
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+
+/// A pcapng file with a single section and interface,
+/// whose timestamps have microsecond resolution (the default of the format)
+pub struct PcapngWriter {
+    writer: BufWriter<File>,
+}
+
+impl PcapngWriter {
+    /// Creates the file, replacing it if it exists, and describes the captured interface
+    pub fn create(path: impl AsRef<Path>, link_type: Linktype) -> io::Result<Self> {
+        let link_type = u16::try_from(link_type.0).map_err(|_| invalid_input("link type"))?;
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let mut section_header = Vec::new();
+        section_header.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        // version 1.0
+        section_header.extend_from_slice(&1_u16.to_le_bytes());
+        section_header.extend_from_slice(&0_u16.to_le_bytes());
+        // length of the section not specified
+        section_header.extend_from_slice(&(-1_i64).to_le_bytes());
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &section_header)?;
+
+        let mut interface_description = Vec::new();
+        interface_description.extend_from_slice(&link_type.to_le_bytes());
+        // reserved
+        interface_description.extend_from_slice(&0_u16.to_le_bytes());
+        // no snapshot length limit
+        interface_description.extend_from_slice(&0_u32.to_le_bytes());
+        write_block(
+            &mut writer,
+            INTERFACE_DESCRIPTION_BLOCK,
+            &interface_description,
+        )?;
+
+        Ok(Self { writer })
+    }
+
+    /// Appends the packet as an Enhanced Packet Block, with the given comment (if not empty)
+    pub fn write(&mut self, packet: &Packet, comment: &str) -> io::Result<()> {
+        #[allow(clippy::useless_conversion)]
+        let usecs = i64::from(packet.header.ts.tv_sec)
+            .checked_mul(1_000_000)
+            .and_then(|usecs| usecs.checked_add(i64::from(packet.header.ts.tv_usec)))
+            .and_then(|usecs| u64::try_from(usecs).ok())
+            .ok_or_else(|| invalid_input("timestamp"))?;
+        #[allow(clippy::cast_possible_truncation)]
+        let (ts_high, ts_low) = ((usecs >> 32) as u32, usecs as u32);
+        let comment_len = u16::try_from(comment.len()).map_err(|_| invalid_input("comment"))?;
+
+        let mut body = Vec::with_capacity(packet.data.len() + comment.len() + 32);
+        // interface identifier
+        body.extend_from_slice(&0_u32.to_le_bytes());
+        body.extend_from_slice(&ts_high.to_le_bytes());
+        body.extend_from_slice(&ts_low.to_le_bytes());
+        body.extend_from_slice(&packet.header.caplen.to_le_bytes());
+        body.extend_from_slice(&packet.header.len.to_le_bytes());
+        body.extend_from_slice(packet.data);
+        pad(&mut body);
+        if comment_len > 0 {
+            body.extend_from_slice(&OPT_COMMENT.to_le_bytes());
+            body.extend_from_slice(&comment_len.to_le_bytes());
+            body.extend_from_slice(comment.as_bytes());
+            pad(&mut body);
+        }
+        body.extend_from_slice(&OPT_ENDOFOPT.to_le_bytes());
+        body.extend_from_slice(&0_u16.to_le_bytes());
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &body)
+    }
+}
+
+/// Writes a block, whose total length is repeated before and after the body
+fn write_block(writer: &mut impl Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let total_len = u32::try_from(body.len() + 12).map_err(|_| invalid_input("block"))?;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&total_len.to_le_bytes())
+}
+
+/// Pads the data to 32 bits, as required for packet data and option values
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+fn invalid_input(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid {what} for a pcapng file"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pcap::{Capture, PacketHeader};
+
+    use super::*;
+    use crate::utils::temp_files::temp_path;
+
+    /// Returns the comments of the Enhanced Packet Blocks of a pcapng file
+    fn read_comments(bytes: &[u8]) -> Vec<String> {
+        let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let mut comments = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let block_type = u32_at(offset);
+            let block_len = u32_at(offset + 4) as usize;
+            assert_eq!(u32_at(offset + block_len - 4) as usize, block_len);
+            if block_type == ENHANCED_PACKET_BLOCK {
+                let captured_len = u32_at(offset + 20) as usize;
+                let mut option = offset + 28 + captured_len.next_multiple_of(4);
+                while u16_at(option) != OPT_ENDOFOPT {
+                    let option_len = usize::from(u16_at(option + 2));
+                    if u16_at(option) == OPT_COMMENT {
+                        let value = &bytes[option + 4..option + 4 + option_len];
+                        comments.push(String::from_utf8(value.to_vec()).unwrap());
+                    }
+                    option += 4 + option_len.next_multiple_of(4);
+                }
+            }
+            offset += block_len;
+        }
+        comments
+    }
+
+    #[test]
+    fn test_pcapng_comments() {
+        let path = temp_path("tickertinker_annotated.pcapng");
+        let packets: [(&[u8], &str); 3] = [
+            (&[1, 2, 3, 4, 5], "outgoing | service: https"),
+            (&[6, 7, 8, 9], "incoming | service: dns | host: dns.google"),
+            (&[10], ""),
+        ];
+        let mut writer = PcapngWriter::create(&path, Linktype::ETHERNET).unwrap();
+        for (secs, (data, comment)) in (1_700_000_000..).zip(packets) {
+            #[allow(clippy::cast_possible_truncation)]
+            let header = PacketHeader {
+                ts: libc::timeval {
+                    tv_sec: secs,
+                    tv_usec: 250_000,
+                },
+                caplen: data.len() as u32,
+                len: data.len() as u32,
+            };
+            writer.write(&Packet::new(&header, data), comment).unwrap();
+        }
+        drop(writer);
+
+        let comments = read_comments(&std::fs::read(&path).unwrap());
+        // packets with an empty comment have no comment option
+        assert_eq!(comments, [packets[0].1, packets[1].1]);
+
+        // the file is readable by libpcap, with the packets unaltered
+        let mut cap = Capture::from_file(&path).unwrap();
+        for (secs, (data, _)) in (1_700_000_000..).zip(packets) {
+            let packet = cap.next_packet().unwrap();
+            assert_eq!(packet.data, data);
+            assert_eq!(packet.header.ts.tv_sec, secs);
+            assert_eq!(packet.header.ts.tv_usec, 250_000);
+        }
+        assert!(cap.next_packet().is_err());
+    }
+}
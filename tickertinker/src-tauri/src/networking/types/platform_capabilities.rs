@@ -0,0 +1,112 @@
+//! Module defining the `PlatformCapabilities` struct, which describes what captures can do on this platform.
+
+use serde::Serialize;
+
+use crate::networking::types::capture_context::{BUFFER_SIZE, NANOSECOND_TIMESTAMPS_SUPPORTED};
+
+/// Linux capability needed to open packet sockets (`CAP_NET_RAW`)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const CAP_NET_RAW: u32 = 13;
+
+/// Capture features available on this platform, so that unsupported options can be disabled up front.
+///
+/// Fields that can't be detected on the current platform are `None`.
+#[derive(Clone, Debug, Serialize)]
+pub struct PlatformCapabilities {
+    /// Whether the capture library (libpcap or Npcap) is available, so that devices can be listed
+    pub live_capture_available: bool,
+    /// Whether this process is allowed to capture live traffic
+    pub capture_permitted: Option<bool>,
+    /// Whether this process is allowed to put devices in promiscuous mode
+    pub promiscuous_permitted: Option<bool>,
+    /// Size in bytes of the kernel buffer requested for live captures,
+    /// unless a `buffer_size` is set in the capture options
+    pub max_buffer_size: i32,
+    /// Formats the captured packets can be saved to (pcapng when `verbose_save` is set)
+    pub save_formats: Vec<&'static str>,
+    /// Whether live captures can report nanosecond timestamps
+    pub nanosecond_timestamps: bool,
+}
+
+impl PlatformCapabilities {
+    pub fn detect() -> Self {
+        let capture_permitted = capture_permitted();
+        Self {
+            live_capture_available: capture_library_available(),
+            capture_permitted,
+            // promiscuous mode doesn't need further privileges than the capture itself
+            promiscuous_permitted: capture_permitted,
+            max_buffer_size: BUFFER_SIZE,
+            save_formats: vec!["pcap", "pcapng"],
+            nanosecond_timestamps: NANOSECOND_TIMESTAMPS_SUPPORTED,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn capture_library_available() -> bool {
+    // Npcap installs its DLLs in a dedicated folder of the system directory
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    std::path::Path::new(&system_root)
+        .join("System32\\Npcap\\wpcap.dll")
+        .exists()
+        && pcap::Device::list().is_ok()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn capture_library_available() -> bool {
+    pcap::Device::list().is_ok()
+}
+
+#[cfg(target_os = "linux")]
+fn capture_permitted() -> Option<bool> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    has_effective_capability(&status, CAP_NET_RAW)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn capture_permitted() -> Option<bool> {
+    None
+}
+
+/// Checks the effective capabilities listed in `/proc/<pid>/status` (e.g., `CapEff: 000001ffffffffff`)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn has_effective_capability(status: &str, capability: u32) -> Option<bool> {
+    let cap_eff = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))?
+        .trim();
+    let cap_eff = u64::from_str_radix(cap_eff, 16).ok()?;
+    Some(cap_eff & (1 << capability) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_platform_capabilities() {
+        let capabilities = PlatformCapabilities::detect();
+        assert_eq!(capabilities.max_buffer_size, BUFFER_SIZE);
+        assert_eq!(capabilities.save_formats, ["pcap", "pcapng"]);
+        assert_eq!(
+            capabilities.nanosecond_timestamps,
+            cfg!(target_os = "linux")
+        );
+        if cfg!(target_os = "linux") {
+            assert!(capabilities.capture_permitted.is_some());
+        }
+        assert_eq!(
+            capabilities.promiscuous_permitted,
+            capabilities.capture_permitted
+        );
+    }
+
+    #[test]
+    fn test_has_effective_capability() {
+        let status = "Name:\ttickertinker\nCapInh:\t0000000000000000\nCapEff:\t0000000000002000\n";
+        assert_eq!(has_effective_capability(status, CAP_NET_RAW), Some(true));
+        assert_eq!(has_effective_capability(status, 12), Some(false));
+        assert_eq!(has_effective_capability("Name:\tbash\n", CAP_NET_RAW), None);
+    }
+}
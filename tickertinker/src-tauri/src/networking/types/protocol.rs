@@ -1,7 +1,7 @@
 // WARNING: this file is imported in build.rs
 
 /// Enum representing the possible observed values of protocol.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum Protocol {
     /// Transmission Control Protocol
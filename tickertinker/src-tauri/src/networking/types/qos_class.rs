@@ -0,0 +1,57 @@
+//! Module defining the QoS classes associated to the DSCP values of the IP header.
+
+/// Standard DSCP values with the name of their class
+/// (RFC 2474, RFC 2597, RFC 3246, RFC 5865, and RFC 8622)
+pub const QOS_CLASSES: &[(u8, &str)] = &[
+    (0, "BE"),
+    (1, "LE"),
+    (8, "CS1"),
+    (10, "AF11"),
+    (12, "AF12"),
+    (14, "AF13"),
+    (16, "CS2"),
+    (18, "AF21"),
+    (20, "AF22"),
+    (22, "AF23"),
+    (24, "CS3"),
+    (26, "AF31"),
+    (28, "AF32"),
+    (30, "AF33"),
+    (32, "CS4"),
+    (34, "AF41"),
+    (36, "AF42"),
+    (38, "AF43"),
+    (40, "CS5"),
+    (44, "VOICE-ADMIT"),
+    (46, "EF"),
+    (48, "CS6"),
+    (56, "CS7"),
+];
+
+/// Returns the name of the QoS class of a DSCP value (e.g., "EF" for 46),
+/// or `None` if the value isn't assigned to a standard class
+pub fn qos_class(dscp: u8) -> Option<&'static str> {
+    QOS_CLASSES
+        .iter()
+        .find(|(value, _)| *value == dscp)
+        .map(|(_, class)| *class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qos_class() {
+        assert_eq!(qos_class(0), Some("BE"));
+        assert_eq!(qos_class(46), Some("EF"));
+        assert_eq!(qos_class(34), Some("AF41"));
+        assert_eq!(qos_class(40), Some("CS5"));
+        assert_eq!(qos_class(5), None);
+        assert_eq!(qos_class(63), None);
+
+        // DSCP values are 6 bits long, and each is assigned to a single class
+        assert!(QOS_CLASSES.iter().all(|(value, _)| *value < 64));
+        assert!(QOS_CLASSES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
@@ -0,0 +1,34 @@
+//! Module defining the `RetentionPolicy` struct, which bounds the traffic data kept in memory.
+
+use serde::Serialize;
+
+/// Number of connections kept by default, to bound the memory of long-running captures
+pub const DEFAULT_MAX_CONNECTIONS: usize = 100_000;
+/// Number of hosts kept by default, to bound the memory of long-running captures
+pub const DEFAULT_MAX_HOSTS: usize = 10_000;
+
+/// Limits applied to the collected traffic at each interval;
+/// what exceeds them is forgotten, from the least recently active entries.
+///
+/// Limits set to `None` are disabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct RetentionPolicy {
+    /// Seconds of inactivity after which connections and hosts are forgotten
+    /// (connections are aged on the packet timestamps, so that it also applies to files)
+    pub max_age_secs: Option<u64>,
+    /// Maximum number of hosts to keep track of;
+    /// the evicted ones are aggregated into the "other hosts" entry
+    pub max_hosts: Option<usize>,
+    /// Maximum number of connections to keep track of
+    pub max_connections: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_secs: None,
+            max_hosts: Some(DEFAULT_MAX_HOSTS),
+            max_connections: Some(DEFAULT_MAX_CONNECTIONS),
+        }
+    }
+}
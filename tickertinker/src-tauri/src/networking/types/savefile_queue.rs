@@ -0,0 +1,238 @@
+//! Module defining the `SavefileQueue` struct, which saves the captured packets on a background
+//! thread, so that a slow disk doesn't hold the capture back.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use pcap::{Packet, PacketHeader};
+
+use crate::location;
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::savefiles::Savefiles;
+use crate::utils::error_logger::{ErrorLogger, Location};
+
+/// Maximum number of packets the queue can hold, bounding the memory it takes with a slow disk
+pub const MAX_SAVEFILE_QUEUE_SIZE: usize = 10_000;
+
+/// A packet waiting to be saved, with its comment (empty if the files aren't annotated)
+struct QueuedPacket {
+    protocol: Protocol,
+    header: PacketHeader,
+    data: Vec<u8>,
+    comment: String,
+}
+
+/// Files the captured packets are saved to by a background thread, fed through a bounded queue:
+/// packets arriving while the queue is full are skipped instead of waiting for the disk
+pub struct SavefileQueue {
+    tx: Option<SyncSender<QueuedPacket>>,
+    handle: Option<JoinHandle<()>>,
+    /// Number of packets sent to the writer and not yet picked up by it
+    pending: Arc<AtomicUsize>,
+    /// Maximum number of packets waiting in the queue
+    capacity: usize,
+    /// Whether the comments of the packets are written
+    annotated: bool,
+}
+
+impl SavefileQueue {
+    /// Starts saving the packets to the files, queueing at most `capacity` of them
+    /// (between 1 and `MAX_SAVEFILE_QUEUE_SIZE`)
+    pub fn new(mut savefiles: Savefiles, capacity: usize) -> Self {
+        let annotated = savefiles.is_annotated();
+        Self::with_writer(capacity, annotated, move |protocol, packet, comment| {
+            savefiles.write(protocol, packet, || comment);
+        })
+    }
+
+    fn with_writer(
+        capacity: usize,
+        annotated: bool,
+        mut write: impl FnMut(Protocol, &Packet, String) + Send + 'static,
+    ) -> Self {
+        // a zero capacity would only let packets through while the writer is idle
+        let capacity = capacity.clamp(1, MAX_SAVEFILE_QUEUE_SIZE);
+        let (tx, rx) = mpsc::sync_channel::<QueuedPacket>(capacity);
+        let pending = Arc::new(AtomicUsize::new(0));
+        let pending_2 = pending.clone();
+        // if the thread can't be spawned, all the packets are skipped
+        let handle = thread::Builder::new()
+            .name("thread_savefile_writer".to_string())
+            .spawn(move || {
+                for queued in rx {
+                    pending_2.fetch_sub(1, Ordering::AcqRel);
+                    let packet = Packet::new(&queued.header, &queued.data);
+                    write(queued.protocol, &packet, queued.comment);
+                }
+            })
+            .log_err(location!())
+            .ok();
+        Self {
+            tx: Some(tx),
+            handle,
+            pending,
+            capacity,
+            annotated,
+        }
+    }
+
+    /// Queues a packet to be saved, returning `false` if it was skipped because the queue is full;
+    /// `comment` is only called if the files are annotated
+    pub fn write(
+        &mut self,
+        protocol: Protocol,
+        packet: &Packet,
+        comment: impl FnOnce() -> String,
+    ) -> bool {
+        let Some(tx) = self.tx.as_ref() else {
+            return false;
+        };
+        // a packet that doesn't fit is skipped before copying it and building its comment
+        if self.pending.load(Ordering::Acquire) >= self.capacity {
+            return false;
+        }
+        // counted before sending, so that the writer never picks up a packet not counted yet
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        let queued = QueuedPacket {
+            protocol,
+            header: *packet.header,
+            data: packet.data.to_vec(),
+            comment: if self.annotated {
+                comment()
+            } else {
+                String::new()
+            },
+        };
+        let sent = tx.try_send(queued).is_ok();
+        if !sent {
+            self.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+        sent
+    }
+}
+
+impl Drop for SavefileQueue {
+    /// Waits for the queued packets to be saved, so that the files are complete
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Files the captured packets are saved to, either directly or through a `SavefileQueue`
+pub enum SavefileSink {
+    Direct(Savefiles),
+    Queued(SavefileQueue),
+}
+
+impl SavefileSink {
+    /// Saves the packets through a queue of `queue_capacity` packets, if set
+    pub fn new(savefiles: Savefiles, queue_capacity: Option<usize>) -> Self {
+        match queue_capacity {
+            Some(capacity) => Self::Queued(SavefileQueue::new(savefiles, capacity)),
+            None => Self::Direct(savefiles),
+        }
+    }
+
+    /// Saves a packet, returning `false` if it was skipped because the queue is full;
+    /// `comment` is only called if the files are annotated
+    pub fn write(
+        &mut self,
+        protocol: Protocol,
+        packet: &Packet,
+        comment: impl FnOnce() -> String,
+    ) -> bool {
+        match self {
+            Self::Direct(savefiles) => {
+                savefiles.write(protocol, packet, comment);
+                true
+            }
+            Self::Queued(queue) => queue.write(protocol, packet, comment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn header(len: usize) -> PacketHeader {
+        PacketHeader {
+            ts: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            caplen: u32::try_from(len).unwrap(),
+            len: u32::try_from(len).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_slow_writer_skips_packets() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let written_2 = written.clone();
+        let mut queue = SavefileQueue::with_writer(2, true, move |_, packet, comment| {
+            // the disk is stuck until released
+            if written_2.lock().unwrap().is_empty() {
+                started_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            }
+            written_2
+                .lock()
+                .unwrap()
+                .push((packet.data.to_vec(), comment));
+        });
+
+        let data: Vec<Vec<u8>> = (1..=5).map(|n| vec![n; usize::from(n)]).collect();
+        let mut write = |bytes: &[u8]| {
+            let header = header(bytes.len());
+            queue.write(Protocol::UDP, &Packet::new(&header, bytes), || {
+                format!("packet {}", bytes.len())
+            })
+        };
+        assert!(write(&data[0]));
+        // the writer is busy with the first packet
+        started_rx.recv().unwrap();
+        // the capture isn't held while the writer is stuck: packets that don't fit are skipped
+        let saved: Vec<bool> = data[1..].iter().map(|d| write(d)).collect();
+        assert_eq!(saved, [true, true, false, false]);
+        // the comments of the skipped packets aren't built
+        let header = header(1);
+        assert!(
+            !queue.write(Protocol::UDP, &Packet::new(&header, &[0]), || {
+                unreachable!("comment built for a skipped packet")
+            })
+        );
+
+        release_tx.send(()).unwrap();
+        drop(queue);
+        // the queued packets are saved before the queue is dropped
+        let written = written.lock().unwrap();
+        assert_eq!(
+            *written,
+            [
+                (data[0].clone(), "packet 1".to_string()),
+                (data[1].clone(), "packet 2".to_string()),
+                (data[2].clone(), "packet 3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_only_built_if_annotated() {
+        let mut queue = SavefileQueue::with_writer(1, false, |_, _, _| {});
+        let header = header(1);
+        assert!(queue.write(Protocol::TCP, &Packet::new(&header, &[0]), || {
+            unreachable!("comment requested without annotations")
+        }));
+    }
+}
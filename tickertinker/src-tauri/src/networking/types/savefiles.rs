@@ -0,0 +1,210 @@
+//! Module defining the `Savefiles` enum, which writes the captured packets to PCAP files.
+
+use std::path::{Path, PathBuf};
+
+use pcap::{Activated, Capture, Error, Packet, Savefile};
+
+use crate::networking::types::pcapng_writer::PcapngWriter;
+use crate::networking::types::protocol::Protocol;
+
+/// A file the captured packets are saved to
+pub enum SavefileWriter {
+    /// PCAP file written by libpcap
+    Pcap(Savefile),
+    /// pcapng file with a comment describing each packet
+    Annotated(PcapngWriter),
+}
+
+impl SavefileWriter {
+    fn new<T: Activated + ?Sized>(
+        cap: &Capture<T>,
+        path: impl AsRef<Path>,
+        annotated: bool,
+    ) -> Result<Self, Error> {
+        if annotated {
+            PcapngWriter::create(path, cap.get_datalink())
+                .map(Self::Annotated)
+                .map_err(|e| Error::IoError(e.kind()))
+        } else {
+            Ok(Self::Pcap(cap.savefile(path)?))
+        }
+    }
+
+    /// Saves a packet, describing it with the comment produced by `comment` if annotated
+    fn write(&mut self, packet: &Packet, comment: impl FnOnce() -> String) {
+        match self {
+            Self::Pcap(savefile) => savefile.write(packet),
+            // write errors are ignored, as libpcap does for PCAP files
+            Self::Annotated(writer) => {
+                let _ = writer.write(packet, &comment());
+            }
+        }
+    }
+}
+
+/// Files the captured packets are saved to
+pub enum Savefiles {
+    /// All the packets are saved to a single file
+    Single(SavefileWriter),
+    /// Packets are saved to a file per protocol, derived from the requested path
+    /// (e.g., `capture.pcap` is split into `capture_tcp.pcap`, `capture_udp.pcap`,
+    /// `capture_icmp.pcap`, and `capture_other.pcap`)
+    PerProtocol {
+        tcp: SavefileWriter,
+        udp: SavefileWriter,
+        icmp: SavefileWriter,
+        /// Packets of any other protocol (e.g., ARP)
+        other: SavefileWriter,
+    },
+}
+
+impl Savefiles {
+    /// Creates the files, in pcapng format with a comment for each packet if `annotated` is set
+    pub fn new<T: Activated + ?Sized>(
+        cap: &Capture<T>,
+        out_path: &str,
+        split_by_protocol: bool,
+        annotated: bool,
+    ) -> Result<Self, Error> {
+        let open = |path| SavefileWriter::new(cap, path, annotated);
+        if !split_by_protocol {
+            return Ok(Self::Single(open(PathBuf::from(out_path))?));
+        }
+        Ok(Self::PerProtocol {
+            tcp: open(protocol_savefile_path(out_path, "tcp"))?,
+            udp: open(protocol_savefile_path(out_path, "udp"))?,
+            icmp: open(protocol_savefile_path(out_path, "icmp"))?,
+            other: open(protocol_savefile_path(out_path, "other"))?,
+        })
+    }
+
+    /// Saves a packet, routing it to the file of its protocol when split;
+    /// `comment` is only called if the files are annotated
+    pub fn write(&mut self, protocol: Protocol, packet: &Packet, comment: impl FnOnce() -> String) {
+        let savefile = match self {
+            Self::Single(savefile) => savefile,
+            Self::PerProtocol {
+                tcp,
+                udp,
+                icmp,
+                other,
+            } => match protocol {
+                Protocol::TCP => tcp,
+                Protocol::UDP => udp,
+                Protocol::ICMP => icmp,
+                Protocol::ARP => other,
+            },
+        };
+        savefile.write(packet, comment);
+    }
+
+    /// Whether the files are in pcapng format, with a comment for each packet
+    pub fn is_annotated(&self) -> bool {
+        let (Self::Single(savefile) | Self::PerProtocol { tcp: savefile, .. }) = self;
+        matches!(savefile, SavefileWriter::Annotated(_))
+    }
+}
+
+/// Returns the path of the file storing the packets of a protocol,
+/// obtained adding a suffix to the name of the requested file
+pub fn protocol_savefile_path(out_path: &str, suffix: &str) -> PathBuf {
+    let path = Path::new(out_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map_or("pcap".into(), |extension| extension.to_string_lossy());
+    path.with_file_name(format!("{stem}_{suffix}.{extension}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use etherparse::PacketBuilder;
+    use pcap::{Linktype, PacketHeader};
+
+    use super::*;
+    use crate::utils::temp_files::temp_path;
+
+    fn packet_bytes(protocol: Protocol) -> Vec<u8> {
+        let builder = PacketBuilder::ethernet2([1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]).ipv4(
+            [192, 168, 1, 2],
+            [8, 8, 8, 8],
+            64,
+        );
+        let mut packet = Vec::new();
+        match protocol {
+            Protocol::TCP => builder.tcp(50000, 443, 1, 64_000).write(&mut packet, &[]),
+            Protocol::UDP => builder.udp(50000, 53).write(&mut packet, &[]),
+            Protocol::ICMP => builder.icmpv4_echo_request(1, 1).write(&mut packet, &[]),
+            Protocol::ARP => {
+                // Ethernet header with the ARP EtherType, followed by a dummy payload
+                packet.extend_from_slice(&[7, 8, 9, 10, 11, 12, 1, 2, 3, 4, 5, 6, 0x08, 0x06]);
+                packet.extend_from_slice(&[0; 28]);
+                Ok(())
+            }
+        }
+        .unwrap();
+        packet
+    }
+
+    fn saved_packets(path: &Path) -> Vec<Vec<u8>> {
+        let mut cap = Capture::from_file(path).unwrap();
+        let mut packets = Vec::new();
+        while let Ok(packet) = cap.next_packet() {
+            packets.push(packet.data.to_vec());
+        }
+        packets
+    }
+
+    #[test]
+    fn test_protocol_savefile_path() {
+        assert_eq!(
+            protocol_savefile_path("/tmp/capture.pcap", "tcp"),
+            PathBuf::from("/tmp/capture_tcp.pcap")
+        );
+        assert_eq!(
+            protocol_savefile_path("/tmp/capture", "other"),
+            PathBuf::from("/tmp/capture_other.pcap")
+        );
+    }
+
+    #[test]
+    fn test_packets_saved_to_the_file_of_their_protocol() {
+        let out_path = temp_path("tickertinker_split_savefile.pcap");
+        let out_path = out_path.to_str().unwrap();
+        let cap = Capture::dead(Linktype::ETHERNET).unwrap();
+        let mut savefiles = Savefiles::new(&cap, out_path, true, false).unwrap();
+
+        let protocols = [
+            Protocol::TCP,
+            Protocol::UDP,
+            Protocol::TCP,
+            Protocol::ICMP,
+            Protocol::ARP,
+        ];
+        for protocol in protocols {
+            let data = packet_bytes(protocol);
+            #[allow(clippy::cast_possible_truncation)]
+            let header = PacketHeader {
+                ts: libc::timeval {
+                    tv_sec: 0,
+                    tv_usec: 0,
+                },
+                caplen: data.len() as u32,
+                len: data.len() as u32,
+            };
+            savefiles.write(protocol, &Packet::new(&header, &data), String::new);
+        }
+        drop(savefiles);
+
+        for (suffix, protocol, count) in [
+            ("tcp", Protocol::TCP, 2),
+            ("udp", Protocol::UDP, 1),
+            ("icmp", Protocol::ICMP, 1),
+            ("other", Protocol::ARP, 1),
+        ] {
+            let packets = saved_packets(&protocol_savefile_path(out_path, suffix));
+            assert_eq!(packets.len(), count, "{suffix}");
+            assert!(packets.iter().all(|p| *p == packet_bytes(protocol)));
+        }
+    }
+}
@@ -19,6 +19,13 @@ impl Service {
     }
 }
 
+// serialized as its displayed name, so that the frontend doesn't deal with enum variants
+impl serde::Serialize for Service {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl std::fmt::Display for Service {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -0,0 +1,24 @@
+//! Module defining the `ServicesMapInfo` struct, which describes the services map generated at build time.
+
+use serde::Serialize;
+
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::service::Service;
+
+/// Statistics about the map used to associate ports to services
+#[derive(Clone, Debug, Serialize)]
+pub struct ServicesMapInfo {
+    /// Number of (port, protocol) pairs with a known service
+    pub entries: usize,
+    /// Lookups of a few well-known ports
+    pub samples: Vec<ServiceLookup>,
+}
+
+/// Result of looking up a (port, protocol) pair in the services map
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceLookup {
+    pub port: u16,
+    pub protocol: Protocol,
+    /// The service found, if any
+    pub service: Option<Service>,
+}
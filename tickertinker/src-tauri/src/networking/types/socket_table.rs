@@ -0,0 +1,372 @@
+//! Module defining the `SocketTable` struct, used to attribute connections to the local processes owning them.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::location;
+use crate::networking::types::address_port_pair::AddressPortPair;
+use crate::networking::types::protocol::Protocol;
+use crate::networking::types::traffic_direction::TrafficDirection;
+use crate::utils::error_logger::{ErrorLogger, Location};
+
+/// Whether the sockets of the capturing host can be inspected on this platform
+pub const SOCKET_TABLE_SUPPORTED: bool = cfg!(target_os = "linux");
+/// How often the socket table is read again, to label the connections opened in the meantime
+pub const SOCKET_TABLE_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A process running on the capturing host
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LocalProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Sockets open on the capturing host, with the process owning them
+#[derive(Default, Debug)]
+pub struct SocketTable {
+    /// Map of the local endpoints (protocol, address, port) to the owning process
+    sockets: HashMap<(Protocol, IpAddr, u16), LocalProcess>,
+}
+
+impl SocketTable {
+    /// Reads the sockets currently open on the host (it's empty on unsupported platforms)
+    pub fn read() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            linux::read_socket_table()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self::default()
+        }
+    }
+
+    /// Adds the sockets listed in a `/proc/net/{tcp,tcp6,udp,udp6}`-formatted table,
+    /// labelling them with the process owning the corresponding inode
+    pub fn add_proc_net_entries(
+        &mut self,
+        protocol: Protocol,
+        proc_net: &str,
+        owners: &HashMap<u64, LocalProcess>,
+    ) {
+        for (address, port, inode) in parse_proc_net(proc_net) {
+            if let Some(process) = owners.get(&inode) {
+                self.sockets
+                    .insert((protocol, address.to_canonical(), port), process.clone());
+            }
+        }
+    }
+
+    /// Returns the process owning the local endpoint of the given connection
+    pub fn process_for(
+        &self,
+        key: &AddressPortPair,
+        traffic_direction: TrafficDirection,
+    ) -> Option<LocalProcess> {
+        let (address, port) = match traffic_direction {
+            TrafficDirection::Outgoing => (key.address1, key.port1?),
+            TrafficDirection::Incoming => (key.address2, key.port2?),
+        };
+        // sockets can also be bound to the unspecified address of either IP version (dual stack)
+        [
+            address.to_canonical(),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        ]
+        .iter()
+        .find_map(|a| self.sockets.get(&(key.protocol, *a, port)))
+        .cloned()
+    }
+}
+
+/// Socket table read again periodically by a background thread, since scanning the descriptors
+/// of every process is too slow to be done by the thread parsing the packets
+pub struct SocketTableRefresher {
+    socket_table: Arc<Mutex<SocketTable>>,
+    /// Dropped to make the refreshing thread return
+    stop_tx: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SocketTableRefresher {
+    /// Starts reading the socket table of the host, right away and then every `interval`
+    pub fn start(interval: Duration) -> Self {
+        Self::with_reader(interval, SocketTable::read)
+    }
+
+    fn with_reader(interval: Duration, read: impl Fn() -> SocketTable + Send + 'static) -> Self {
+        let socket_table = Arc::new(Mutex::new(SocketTable::default()));
+        let socket_table_2 = socket_table.clone();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let handle = thread::Builder::new()
+            .name("thread_socket_table".to_string())
+            .spawn(move || {
+                loop {
+                    // read without holding the lock, so that lookups aren't blocked meanwhile
+                    let new_socket_table = read();
+                    *socket_table_2.lock().unwrap() = new_socket_table;
+                    if let Err(RecvTimeoutError::Disconnected) = stop_rx.recv_timeout(interval) {
+                        return;
+                    }
+                }
+            })
+            .log_err(location!())
+            .ok();
+        Self {
+            socket_table,
+            stop_tx: Some(stop_tx),
+            handle,
+        }
+    }
+
+    /// Returns the process owning the local endpoint of the given connection,
+    /// according to the latest read of the socket table
+    pub fn process_for(
+        &self,
+        key: &AddressPortPair,
+        traffic_direction: TrafficDirection,
+    ) -> Option<LocalProcess> {
+        self.socket_table
+            .lock()
+            .unwrap()
+            .process_for(key, traffic_direction)
+    }
+}
+
+impl Drop for SocketTableRefresher {
+    fn drop(&mut self) {
+        drop(self.stop_tx.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Parses a `/proc/net/{tcp,tcp6,udp,udp6}`-formatted table,
+/// returning the local address, the local port, and the inode of each socket
+fn parse_proc_net(proc_net: &str) -> Vec<(IpAddr, u16, u64)> {
+    proc_net
+        .lines()
+        // skip the header
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (address_hex, port_hex) = fields.get(1)?.split_once(':')?;
+            let address = parse_proc_net_address(address_hex)?;
+            let port = u16::from_str_radix(port_hex, 16).ok()?;
+            let inode = fields.get(9)?.parse().ok()?;
+            Some((address, port, inode))
+        })
+        .collect()
+}
+
+/// Addresses are printed by the kernel as 32-bit words in host byte order
+fn parse_proc_net_address(address_hex: &str) -> Option<IpAddr> {
+    let mut octets = Vec::with_capacity(16);
+    for i in (0..address_hex.len()).step_by(8) {
+        let word = u32::from_str_radix(address_hex.get(i..i + 8)?, 16).ok()?;
+        octets.extend_from_slice(&word.to_ne_bytes());
+    }
+    match octets.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(octets).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(octets).ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use crate::networking::types::protocol::Protocol;
+    use crate::networking::types::socket_table::{LocalProcess, SocketTable};
+
+    pub(super) fn read_socket_table() -> SocketTable {
+        let owners = socket_owners();
+        let mut socket_table = SocketTable::default();
+        for (file, protocol) in [
+            ("tcp", Protocol::TCP),
+            ("tcp6", Protocol::TCP),
+            ("udp", Protocol::UDP),
+            ("udp6", Protocol::UDP),
+        ] {
+            if let Ok(proc_net) = fs::read_to_string(format!("/proc/net/{file}")) {
+                socket_table.add_proc_net_entries(protocol, &proc_net, &owners);
+            }
+        }
+        socket_table
+    }
+
+    /// Returns the map of the socket inodes to the owning process.
+    ///
+    /// Processes of other users are only visible with the needed privileges.
+    fn socket_owners() -> HashMap<u64, LocalProcess> {
+        let mut owners = HashMap::new();
+        let Ok(proc_dir) = fs::read_dir("/proc") else {
+            return owners;
+        };
+        for entry in proc_dir.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok())
+            else {
+                continue;
+            };
+            let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+                continue;
+            };
+            let name = fs::read_to_string(entry.path().join("comm"))
+                .map(|comm| comm.trim().to_string())
+                .unwrap_or_default();
+            for fd in fds.flatten() {
+                let inode = fs::read_link(fd.path()).ok().and_then(|link| {
+                    link.to_str()?
+                        .strip_prefix("socket:[")?
+                        .strip_suffix(']')?
+                        .parse()
+                        .ok()
+                });
+                if let Some(inode) = inode {
+                    let name = name.clone();
+                    owners.insert(inode, LocalProcess { pid, name });
+                }
+            }
+        }
+        owners
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_endian = "little")]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    use super::*;
+
+    const PROC_NET_TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1111 1 0000000000000000 100 0 0 10 0
+   1: 0200A8C0:C350 2E22D8AC:01BB 01 00000000:00000000 02:000A7D6E 00000000  1000        0 2222 2 0000000000000000 20 4 30 10 -1
+   2: 0200A8C0:C351 2E22D8AC:01BB 01 00000000:00000000 02:000A7D6E 00000000  1000        0 3333 2 0000000000000000 20 4 30 10 -1";
+
+    #[test]
+    fn test_parse_proc_net() {
+        assert_eq!(
+            parse_proc_net(PROC_NET_TCP),
+            vec![
+                (IpAddr::from([0, 0, 0, 0]), 22, 1111),
+                (IpAddr::from([192, 168, 0, 2]), 50000, 2222),
+                (IpAddr::from([192, 168, 0, 2]), 50001, 3333),
+            ]
+        );
+        assert_eq!(
+            parse_proc_net_address("00000000000000000000000001000000"),
+            Some(IpAddr::from(Ipv6Addr::LOCALHOST))
+        );
+    }
+
+    #[test]
+    fn test_process_for_known_connection() {
+        let process = |pid, name: &str| LocalProcess {
+            pid,
+            name: name.to_string(),
+        };
+        let owners = HashMap::from([
+            (1111, process(812, "sshd")),
+            (2222, process(4051, "firefox")),
+        ]);
+        let mut socket_table = SocketTable::default();
+        socket_table.add_proc_net_entries(Protocol::TCP, PROC_NET_TCP, &owners);
+
+        let outgoing = AddressPortPair::new(
+            IpAddr::from([192, 168, 0, 2]),
+            Some(50000),
+            IpAddr::from([172, 216, 34, 46]),
+            Some(443),
+            Protocol::TCP,
+        );
+        assert_eq!(
+            socket_table.process_for(&outgoing, TrafficDirection::Outgoing),
+            Some(process(4051, "firefox"))
+        );
+
+        // the server socket is bound to the unspecified address
+        let incoming = AddressPortPair::new(
+            IpAddr::from([192, 168, 0, 7]),
+            Some(40000),
+            IpAddr::from([192, 168, 0, 2]),
+            Some(22),
+            Protocol::TCP,
+        );
+        assert_eq!(
+            socket_table.process_for(&incoming, TrafficDirection::Incoming),
+            Some(process(812, "sshd"))
+        );
+
+        // inode without a known owner
+        let unknown_owner = AddressPortPair {
+            port1: Some(50001),
+            ..outgoing
+        };
+        assert_eq!(
+            socket_table.process_for(&unknown_owner, TrafficDirection::Outgoing),
+            None
+        );
+
+        // same port but different protocol
+        let udp = AddressPortPair {
+            protocol: Protocol::UDP,
+            ..outgoing
+        };
+        assert_eq!(
+            socket_table.process_for(&udp, TrafficDirection::Outgoing),
+            None
+        );
+    }
+
+    #[test]
+    fn test_socket_table_refreshed_in_background() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let reads_2 = reads.clone();
+        let refresher = SocketTableRefresher::with_reader(Duration::from_secs(3600), move || {
+            reads_2.fetch_add(1, Ordering::Relaxed);
+            let owners = HashMap::from([(
+                2222,
+                LocalProcess {
+                    pid: 4051,
+                    name: "firefox".to_string(),
+                },
+            )]);
+            let mut socket_table = SocketTable::default();
+            socket_table.add_proc_net_entries(Protocol::TCP, PROC_NET_TCP, &owners);
+            socket_table
+        });
+
+        // the table is read right away, without blocking the caller
+        let key = AddressPortPair::new(
+            IpAddr::from([192, 168, 0, 2]),
+            Some(50000),
+            IpAddr::from([172, 216, 34, 46]),
+            Some(443),
+            Protocol::TCP,
+        );
+        let started = Instant::now();
+        while refresher
+            .process_for(&key, TrafficDirection::Outgoing)
+            .is_none()
+        {
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // the thread stops without waiting for the next refresh
+        drop(refresher);
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,5 +1,7 @@
+use serde::Serialize;
+
 /// Enum representing the possible traffic type (unicast, multicast or broadcast).
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize)]
 pub enum TrafficType {
     /// Unicast traffic
     Unicast,
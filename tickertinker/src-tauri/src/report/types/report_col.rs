@@ -1,5 +1,5 @@
 use crate::networking::types::address_port_pair::AddressPortPair;
-use crate::networking::types::data_representation::DataRepr;
+use crate::networking::types::data_representation::{DataRepr, Precision};
 use crate::networking::types::info_address_port_pair::InfoAddressPortPair;
 use crate::report::types::search_parameters::FilterInputType;
 use crate::translations::translations::{address_translation, protocol_translation};
@@ -69,6 +69,7 @@ impl ReportCol {
         key: &AddressPortPair,
         val: &InfoAddressPortPair,
         data_repr: DataRepr,
+        precision: Precision,
     ) -> String {
         match self {
             ReportCol::SrcIp => key.address1.to_string(),
@@ -89,7 +90,8 @@ impl ReportCol {
             }
             ReportCol::Proto => key.protocol.to_string(),
             ReportCol::Service => val.service.to_string(),
-            ReportCol::Data => data_repr.formatted_string(val.transmitted_data(data_repr)),
+            ReportCol::Data => data_repr
+                .formatted_string_with_precision(val.transmitted_data(data_repr), precision),
         }
     }
 
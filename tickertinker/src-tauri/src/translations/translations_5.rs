@@ -19,3 +19,22 @@ pub fn traffic_source_translation(language: Language) -> &'static str {
         _ => "Traffic source",
     }
 }
+
+// one-line summary of a capture, e.g., "Up 2.3 MB / Down 45 MB to 37 hosts in 5 countries"
+pub fn notification_summary_translation(
+    language: Language,
+    upload: &str,
+    download: &str,
+    hosts: usize,
+    countries: usize,
+) -> String {
+    match language {
+        Language::EN => {
+            format!("Up {upload} / Down {download} to {hosts} hosts in {countries} countries")
+        }
+        Language::IT => {
+            format!("Su {upload} / Giù {download} con {hosts} host in {countries} paesi")
+        }
+        _ => format!("Up {upload} / Down {download} to {hosts} hosts in {countries} countries"),
+    }
+}
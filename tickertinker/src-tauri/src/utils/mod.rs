@@ -1,4 +1,7 @@
 pub mod check_updates;
 pub mod error_logger;
 pub mod formatted_strings;
+pub mod saturating_u64;
+#[cfg(test)]
+pub mod temp_files;
 pub mod types;
@@ -0,0 +1,8 @@
+//! Serialization of 128-bit counters as unsigned 64-bit integers, saturating at `u64::MAX`,
+//! so that formats without 128-bit integers (e.g., MessagePack) encode them as plain numbers.
+
+use serde::Serializer;
+
+pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(*value).unwrap_or(u64::MAX))
+}
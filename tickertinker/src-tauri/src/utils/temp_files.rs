@@ -0,0 +1,6 @@
+use std::path::PathBuf;
+
+/// Returns a path of the temporary directory that's unique to the running test process
+pub fn temp_path(file_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{file_name}", std::process::id()))
+}
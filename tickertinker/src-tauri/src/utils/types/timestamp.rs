@@ -1,4 +1,118 @@
-#[derive(Clone, Default, Debug, Copy, Eq, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// Magic numbers of PCAP files with microsecond timestamps (in both byte orders)
+const PCAP_MICRO_MAGIC: [u32; 2] = [0xa1b2_c3d4, 0xd4c3_b2a1];
+/// Magic numbers of PCAP files with nanosecond timestamps (in both byte orders)
+const PCAP_NANO_MAGIC: [u32; 2] = [0xa1b2_3c4d, 0x4d3c_b2a1];
+/// Type of the pcapng Section Header Block (the same in both byte orders)
+const PCAPNG_SHB_TYPE: u32 = 0x0a0d_0d0a;
+/// Byte-order magic of the pcapng Section Header Block
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+/// Type of the pcapng Interface Description Block
+const PCAPNG_IDB_TYPE: u32 = 0x0000_0001;
+/// Code of the pcapng option setting the timestamp resolution of an interface
+const PCAPNG_IF_TSRESOL: u16 = 9;
+
+/// Resolution of the sub-second part of the timestamps reported by a capture handle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampPrecision {
+    #[default]
+    Micro,
+    Nano,
+}
+
+impl TimestampPrecision {
+    /// Detects the timestamp resolution of a savefile from its initial bytes:
+    /// the magic number of PCAP files, or the resolution of the first interface of pcapng files.
+    ///
+    /// Anything finer than microseconds is reported as `Nano`; `Micro` is the fallback.
+    pub fn from_savefile_header(header: &[u8]) -> Self {
+        let Some(magic) = read_u32(header, 0, false) else {
+            return Self::Micro;
+        };
+        if PCAP_MICRO_MAGIC.contains(&magic) {
+            Self::Micro
+        } else if PCAP_NANO_MAGIC.contains(&magic) {
+            Self::Nano
+        } else if magic == PCAPNG_SHB_TYPE {
+            pcapng_precision(header).unwrap_or_default()
+        } else {
+            Self::Micro
+        }
+    }
+}
+
+impl From<TimestampPrecision> for pcap::Precision {
+    fn from(precision: TimestampPrecision) -> Self {
+        match precision {
+            TimestampPrecision::Micro => Self::Micro,
+            TimestampPrecision::Nano => Self::Nano,
+        }
+    }
+}
+
+/// Reads the `if_tsresol` option of the first Interface Description Block of a pcapng section
+fn pcapng_precision(header: &[u8]) -> Option<TimestampPrecision> {
+    let big_endian = match read_u32(header, 8, false)? {
+        PCAPNG_BYTE_ORDER_MAGIC => false,
+        _ if read_u32(header, 8, true)? == PCAPNG_BYTE_ORDER_MAGIC => true,
+        _ => return None,
+    };
+    let idb_start = usize::try_from(read_u32(header, 4, big_endian)?).ok()?;
+    if read_u32(header, idb_start, big_endian)? != PCAPNG_IDB_TYPE {
+        return None;
+    }
+    let idb_len = usize::try_from(read_u32(header, idb_start + 4, big_endian)?).ok()?;
+    // options follow link type, reserved field, and snapshot length, up to the trailing length
+    let mut offset = idb_start + 16;
+    let options_end = (idb_start + idb_len).checked_sub(4)?;
+    while offset + 4 <= options_end {
+        let code = read_u16(header, offset, big_endian)?;
+        let len = usize::from(read_u16(header, offset + 2, big_endian)?);
+        if code == PCAPNG_IF_TSRESOL && len == 1 {
+            let tsresol = *header.get(offset + 4)?;
+            // the exponent is a power of 2 if the most significant bit is set, otherwise of 10
+            let finer_than_micro = if tsresol & 0x80 == 0 {
+                tsresol > 6
+            } else {
+                tsresol & 0x7f > 19
+            };
+            return Some(if finer_than_micro {
+                TimestampPrecision::Nano
+            } else {
+                TimestampPrecision::Micro
+            });
+        }
+        // end of options
+        if code == 0 {
+            break;
+        }
+        // values are padded to 32 bits
+        offset += 4 + len.next_multiple_of(4);
+    }
+    // microseconds unless specified otherwise
+    Some(TimestampPrecision::Micro)
+}
+
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+#[derive(Clone, Default, Debug, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Timestamp {
     secs: i64,
     usecs: i64,
@@ -9,6 +123,16 @@ impl Timestamp {
         Self { secs, usecs }
     }
 
+    /// Builds a timestamp from the one of a packet header,
+    /// whose sub-second part is expressed with the precision of the capture handle
+    pub fn from_packet_header(secs: i64, subsecs: i64, precision: TimestampPrecision) -> Self {
+        let usecs = match precision {
+            TimestampPrecision::Micro => subsecs,
+            TimestampPrecision::Nano => subsecs / 1_000,
+        };
+        Self::new(secs, usecs)
+    }
+
     pub fn secs(&self) -> i64 {
         self.secs
     }
@@ -22,6 +146,11 @@ impl Timestamp {
     pub fn add_secs(&mut self, secs: i64) {
         self.secs += secs;
     }
+
+    /// Hour of the day (UTC) the timestamp falls into, from 0 to 23
+    pub fn hour_of_day(self) -> usize {
+        usize::try_from(self.secs.rem_euclid(86_400) / 3_600).unwrap_or_default()
+    }
 }
 
 impl Ord for Timestamp {
@@ -39,6 +168,91 @@ impl PartialOrd for Timestamp {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::temp_files::temp_path;
+
+    /// Builds a little-endian pcapng file with nanosecond timestamps and a single packet
+    fn pcapng_nano(secs: u64, nanos: u64, frame: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        // Section Header Block (version 1.0, unknown section length)
+        for field in [PCAPNG_SHB_TYPE, 28, PCAPNG_BYTE_ORDER_MAGIC, 1] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.extend_from_slice(&(-1_i64).to_le_bytes());
+        file.extend_from_slice(&28_u32.to_le_bytes());
+        // Interface Description Block (Ethernet), with timestamps in units of 10^-9 seconds
+        for field in [PCAPNG_IDB_TYPE, 32, 1, 0xffff] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.extend_from_slice(&PCAPNG_IF_TSRESOL.to_le_bytes());
+        file.extend_from_slice(&1_u16.to_le_bytes());
+        file.extend_from_slice(&[9, 0, 0, 0, 0, 0, 0, 0]);
+        file.extend_from_slice(&32_u32.to_le_bytes());
+        // Enhanced Packet Block
+        let ticks = secs * 1_000_000_000 + nanos;
+        let frame_len = u32::try_from(frame.len()).unwrap();
+        let padded_len = frame.len().next_multiple_of(4);
+        let block_len = u32::try_from(32 + padded_len).unwrap();
+        for field in [
+            6,
+            block_len,
+            0,
+            (ticks >> 32) as u32,
+            ticks as u32,
+            frame_len,
+            frame_len,
+        ] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.extend_from_slice(frame);
+        file.resize(file.len() + padded_len - frame.len(), 0);
+        file.extend_from_slice(&block_len.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn test_pcap_precision_from_magic() {
+        let micro = 0xa1b2_c3d4_u32;
+        let nano = 0xa1b2_3c4d_u32;
+        for (header, precision) in [
+            (micro.to_le_bytes(), TimestampPrecision::Micro),
+            (micro.to_be_bytes(), TimestampPrecision::Micro),
+            (nano.to_le_bytes(), TimestampPrecision::Nano),
+            (nano.to_be_bytes(), TimestampPrecision::Nano),
+        ] {
+            assert_eq!(TimestampPrecision::from_savefile_header(&header), precision);
+        }
+        assert_eq!(
+            TimestampPrecision::from_savefile_header(&[]),
+            TimestampPrecision::Micro
+        );
+    }
+
+    #[test]
+    fn test_nanosecond_pcapng_timestamps() {
+        let file = pcapng_nano(1_700_000_000, 123_456_789, &[0; 60]);
+        let precision = TimestampPrecision::from_savefile_header(&file);
+        assert_eq!(precision, TimestampPrecision::Nano);
+
+        let path = temp_path("tickertinker_nanosecond.pcapng");
+        std::fs::write(&path, &file).unwrap();
+        let mut cap = pcap::Capture::from_file_with_precision(&path, precision.into()).unwrap();
+        let packet = cap.next_packet().unwrap();
+        #[allow(clippy::useless_conversion)]
+        let timestamp = Timestamp::from_packet_header(
+            i64::from(packet.header.ts.tv_sec),
+            i64::from(packet.header.ts.tv_usec),
+            precision,
+        );
+        assert_eq!(timestamp, Timestamp::new(1_700_000_000, 123_456));
+
+        // the default resolution of pcapng interfaces is microseconds
+        let mut file = file;
+        file[28 + 16..28 + 24].copy_from_slice(&[0; 8]);
+        assert_eq!(
+            TimestampPrecision::from_savefile_header(&file),
+            TimestampPrecision::Micro
+        );
+    }
 
     #[test]
     fn test_timestamp_cmp() {
@@ -64,6 +278,17 @@ mod tests {
         assert_eq!(t1.cmp(&t1), std::cmp::Ordering::Equal);
     }
 
+    #[test]
+    fn test_timestamp_hour_of_day() {
+        assert_eq!(Timestamp::new(0, 0).hour_of_day(), 0);
+        assert_eq!(Timestamp::new(3_599, 999_999).hour_of_day(), 0);
+        assert_eq!(Timestamp::new(3_600, 0).hour_of_day(), 1);
+        // 2023-11-14 22:13:20 UTC
+        assert_eq!(Timestamp::new(1_700_000_000, 0).hour_of_day(), 22);
+        // before the epoch, the hour is still the one of the day
+        assert_eq!(Timestamp::new(-1, 0).hour_of_day(), 23);
+    }
+
     #[test]
     fn test_timestamp_to_usecs() {
         let t = Timestamp::new(-1, -700);
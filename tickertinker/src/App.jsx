@@ -53,6 +53,14 @@ function App() {
       });
   };
 
+  useEffect(() => {
+    // Load the bundled country and ASN databases
+    invoke('load_mmdb', { countryPath: null, asnPath: null })
+      .catch((error) => {
+ setError(error);
+      });
+  }, []);
+
   useEffect(() => {
     let unlisten;
     const setupListener = async () => {
@@ -12,8 +12,8 @@ function InterfaceSelector({ onSelectInterface }) {
         const result = await invoke('list_interfaces');
         setInterfaces(result);
         if (result.length > 0) {
-          setSelectedInterface(result[0]);
-          onSelectInterface(result[0]);
+          setSelectedInterface(result[0].name);
+          onSelectInterface(result[0].name);
         }
       } catch (err) {
         setError(err);
@@ -37,8 +37,8 @@ function InterfaceSelector({ onSelectInterface }) {
       <h2>Select Network Interface</h2>
       <select value={selectedInterface} onChange={handleSelectChange}>
         {interfaces.map((iface) => (
-          <option key={iface} value={iface}>
-            {iface}
+          <option key={iface.name} value={iface.name}>
+            {iface.description ?? iface.name}
           </option>
         ))}
       </select>